@@ -0,0 +1,30 @@
+fn main() {
+    // The service is described here rather than generated from proto/presence.proto, so building
+    // does not need `protoc`. Keep the two in sync; the messages live in src/grpc.rs.
+    #[cfg(feature = "grpc")]
+    {
+        use tonic_build::manual::{Builder, Method, Service};
+
+        let method = |name: &str, route: &str, input: &str| {
+            Method::builder()
+                .name(name)
+                .route_name(route)
+                .input_type(format!("crate::grpc::{}", input))
+                .output_type("crate::grpc::Status")
+                .codec_path("tonic::codec::ProstCodec")
+        };
+        let service = Service::builder()
+            .name("PresenceControl")
+            .package("presence.v1")
+            .method(method("get_status", "GetStatus", "Empty").build())
+            .method(method("enable", "Enable", "Presence").build())
+            .method(method("update", "Update", "Presence").build())
+            .method(method("disable", "Disable", "Empty").build())
+            .method(method("pause", "Pause", "Empty").build())
+            .method(method("resume", "Resume", "Empty").build())
+            .method(method("watch_status", "WatchStatus", "Empty").server_streaming().build())
+            .build();
+        Builder::new().build_client(false).compile(&[service]);
+        println!("cargo:rerun-if-changed=build.rs");
+    }
+}
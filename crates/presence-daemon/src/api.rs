@@ -0,0 +1,154 @@
+//! REST control API (`/api/*`): the WebSocket requests as plain HTTP calls, for integrations that
+//! only speak HTTP (Stream Deck, Home Assistant, shell scripts). Its OpenAPI document is served at
+//! `/api/openapi.json`.
+
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use rpc_core::{
+    control::{ControlRequest, StatusSnapshot},
+    managed::ErrorRecord,
+    ratelimit,
+    PresenceCfg,
+};
+use serde::Serialize;
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi, ToSchema,
+};
+
+use crate::AppState;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "presence-daemon control API", description = "Control the Discord Rich Presence published by presence-daemon."),
+    paths(status, enable, update, disable, pause, resume, errors, clear_errors, crate::hooks::run),
+    components(schemas(ApiError, ErrorRecord)),
+    modifiers(&BearerAuth),
+    security(("token" = []))
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("Token printed by `presence-daemon --show-token`."))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Body of every non-2xx response from `/api/*` routes other than authentication failures.
+#[derive(Serialize, ToSchema)]
+pub struct ApiError {
+    pub message: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (status_for(&self.message), Json(self)).into_response()
+    }
+}
+
+/// 429 for requests refused by the rate limit (`--rate-limits`), 400 for anything else `apply` rejects.
+pub(crate) fn status_for(message: &str) -> StatusCode {
+    if message == ratelimit::RATE_LIMITED {
+        StatusCode::TOO_MANY_REQUESTS
+    } else {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(e: JsonRejection) -> Self {
+        ApiError { message: format!("Invalid request: {}", e.body_text()) }
+    }
+}
+
+/// The OpenAPI document describing these routes.
+pub async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Status, last error, client id and the IPC pipe the worker is connected through.
+#[utoipa::path(get, path = "/api/status", responses((status = 200, description = "Status after the request", body = StatusSnapshot), (status = 401, description = "Missing or wrong token")))]
+pub async fn status(State(state): State<AppState>) -> Json<StatusSnapshot> {
+    Json(state.client.snapshot())
+}
+
+/// Starts publishing the presence, or replaces it if already enabled.
+#[utoipa::path(
+    post,
+    path = "/api/enable",
+    request_body = PresenceCfg,
+    responses((status = 200, description = "Status after the request", body = StatusSnapshot), (status = 400, description = "Invalid presence or request body", body = ApiError), (status = 401, description = "Missing or wrong token"), (status = 429, description = "Too soon after the previous request", body = ApiError))
+)]
+pub async fn enable(
+    State(state): State<AppState>,
+    presence: Result<Json<PresenceCfg>, JsonRejection>,
+) -> Result<Json<StatusSnapshot>, ApiError> {
+    let Json(presence) = presence?;
+    run(&state, ControlRequest::Enable { presence })
+}
+
+/// Replaces the presence without changing whether it is enabled.
+#[utoipa::path(
+    post,
+    path = "/api/update",
+    request_body = PresenceCfg,
+    responses((status = 200, description = "Status after the request", body = StatusSnapshot), (status = 400, description = "Invalid presence or request body", body = ApiError), (status = 401, description = "Missing or wrong token"), (status = 429, description = "Too soon after the previous request", body = ApiError))
+)]
+pub async fn update(
+    State(state): State<AppState>,
+    presence: Result<Json<PresenceCfg>, JsonRejection>,
+) -> Result<Json<StatusSnapshot>, ApiError> {
+    let Json(presence) = presence?;
+    run(&state, ControlRequest::Update { presence })
+}
+
+/// Clears the presence and disconnects from Discord.
+#[utoipa::path(post, path = "/api/disable", responses((status = 200, description = "Status after the request", body = StatusSnapshot), (status = 401, description = "Missing or wrong token"), (status = 429, description = "Too soon after the previous request", body = ApiError)))]
+pub async fn disable(State(state): State<AppState>) -> Result<Json<StatusSnapshot>, ApiError> {
+    run(&state, ControlRequest::Disable)
+}
+
+/// Clears the presence from Discord but stays enabled, so `resume` brings it back as it was.
+#[utoipa::path(post, path = "/api/pause", responses((status = 200, description = "Status after the request", body = StatusSnapshot), (status = 400, description = "The presence is not enabled", body = ApiError), (status = 401, description = "Missing or wrong token"), (status = 429, description = "Too soon after the previous request", body = ApiError)))]
+pub async fn pause(State(state): State<AppState>) -> Result<Json<StatusSnapshot>, ApiError> {
+    run(&state, ControlRequest::Pause)
+}
+
+/// Publishes a paused presence again. Does nothing when not paused.
+#[utoipa::path(post, path = "/api/resume", responses((status = 200, description = "Status after the request", body = StatusSnapshot), (status = 401, description = "Missing or wrong token"), (status = 429, description = "Too soon after the previous request", body = ApiError)))]
+pub async fn resume(State(state): State<AppState>) -> Result<Json<StatusSnapshot>, ApiError> {
+    run(&state, ControlRequest::Resume)
+}
+
+/// Every distinct error since the history was last cleared, oldest first, with how often and when
+/// it occurred. `last_error` in the status only holds the latest one.
+#[utoipa::path(get, path = "/api/errors", responses((status = 200, description = "Error history", body = [ErrorRecord]), (status = 401, description = "Missing or wrong token")))]
+pub async fn errors(State(state): State<AppState>) -> Json<Vec<ErrorRecord>> {
+    Json(state.client.error_history())
+}
+
+/// Empties the error history.
+#[utoipa::path(delete, path = "/api/errors", responses((status = 204, description = "Cleared"), (status = 401, description = "Missing or wrong token")))]
+pub async fn clear_errors(State(state): State<AppState>) -> StatusCode {
+    state.client.clear_error_history();
+    StatusCode::NO_CONTENT
+}
+
+fn run(state: &AppState, req: ControlRequest) -> Result<Json<StatusSnapshot>, ApiError> {
+    crate::apply(state, req).map(Json).map_err(|message| ApiError { message })
+}
@@ -0,0 +1,170 @@
+//! Shared-secret authentication for the control API.
+//!
+//! The token is generated on first run and kept in a file only the current user can read. Clients
+//! send it as `Authorization: Bearer <token>`, or as `?token=` where they cannot set headers
+//! (browsers opening a WebSocket).
+
+use std::{
+    fs,
+    io::Write,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use rand::{distributions::Alphanumeric, Rng};
+
+const TOKEN_LEN: usize = 32;
+
+/// The app's config directory, shared with the desktop app.
+pub fn config_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("com", "Watashi", "CustomRichPresence").map(|d| d.config_dir().to_path_buf())
+}
+
+/// Where the token lives unless `--token-file` says otherwise.
+pub fn default_token_file() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("daemon-token"))
+}
+
+/// Reads the token from `path`, creating it with a fresh random token on first run. The second
+/// value is `true` when it was just created.
+pub fn load_or_create(path: &Path) -> anyhow::Result<(String, bool)> {
+    if let Ok(text) = fs::read_to_string(path) {
+        let token = text.trim().to_string();
+        if token.is_empty() {
+            anyhow::bail!("token file {} is empty; delete it to generate a new token", path.display());
+        }
+        return Ok((token, false));
+    }
+
+    let token: String = rand::thread_rng().sample_iter(&Alphanumeric).take(TOKEN_LEN).map(char::from).collect();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut file = opts.open(path).with_context(|| format!("creating {}", path.display()))?;
+    writeln!(file, "{}", token).with_context(|| format!("writing {}", path.display()))?;
+    Ok((token, true))
+}
+
+/// `--no-auth` is refused unless every address the daemon listens on is loopback.
+pub fn check_no_auth(bind: SocketAddr, grpc: Option<SocketAddr>) -> anyhow::Result<()> {
+    if bind.ip().is_loopback() && grpc.is_none_or(|a| a.ip().is_loopback()) {
+        return Ok(());
+    }
+    anyhow::bail!("--no-auth is only allowed on a loopback --bind address; anyone on the network could control your presence")
+}
+
+/// Middleware that rejects requests without the right token. `None` turns authentication off.
+pub async fn require_token(State(token): State<Option<Arc<str>>>, req: Request, next: Next) -> Response {
+    let Some(token) = token else {
+        return next.run(req).await;
+    };
+    let bearer = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let query = req
+        .uri()
+        .query()
+        .and_then(|q| q.split('&').find_map(|kv| kv.strip_prefix("token=")));
+    match bearer.or(query) {
+        Some(given) if token_matches(given, &token) => next.run(req).await,
+        Some(_) => (StatusCode::UNAUTHORIZED, "invalid token").into_response(),
+        None => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "missing token; send `Authorization: Bearer <token>` (see presence-daemon --show-token)",
+        )
+            .into_response(),
+    }
+}
+
+/// Whether `given` is the token. Compares without returning early, so response timing does not
+/// reveal how much of a guess was right.
+pub fn token_matches(given: &str, token: &str) -> bool {
+    let (a, b) = (given.trim().as_bytes(), token.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::HeaderValue, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "s3cret";
+
+    /// Status and `WWW-Authenticate` header of a request to a route guarded by `token`.
+    async fn call(token: Option<&str>, uri: &str, authorization: Option<&str>) -> (StatusCode, Option<HeaderValue>) {
+        let token: Option<Arc<str>> = token.map(Arc::from);
+        let app = Router::new()
+            .route("/status", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn_with_state(token, require_token));
+        let mut req = Request::builder().uri(uri);
+        if let Some(value) = authorization {
+            req = req.header(header::AUTHORIZATION, value);
+        }
+        let res = app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+        (res.status(), res.headers().get(header::WWW_AUTHENTICATE).cloned())
+    }
+
+    #[tokio::test]
+    async fn a_missing_token_asks_for_bearer_auth() {
+        let (status, challenge) = call(Some(TOKEN), "/status", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(challenge.as_ref().and_then(|v| v.to_str().ok()), Some("Bearer"));
+    }
+
+    #[tokio::test]
+    async fn a_wrong_token_is_rejected() {
+        assert_eq!(call(Some(TOKEN), "/status", Some("Bearer nope")).await, (StatusCode::UNAUTHORIZED, None));
+        assert_eq!(call(Some(TOKEN), "/status?token=nope", None).await, (StatusCode::UNAUTHORIZED, None));
+        // Only the Bearer scheme carries the token.
+        assert_eq!(call(Some(TOKEN), "/status", Some("Basic s3cret")).await.0, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn the_token_is_taken_from_the_header_or_the_query() {
+        assert_eq!(call(Some(TOKEN), "/status", Some("Bearer s3cret")).await.0, StatusCode::OK);
+        assert_eq!(call(Some(TOKEN), "/status", Some("Bearer  s3cret ")).await.0, StatusCode::OK);
+        assert_eq!(call(Some(TOKEN), "/status?token=s3cret", None).await.0, StatusCode::OK);
+        assert_eq!(call(Some(TOKEN), "/status?x=1&token=s3cret", None).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn without_a_token_every_request_passes() {
+        assert_eq!(call(None, "/status", None).await.0, StatusCode::OK);
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_ignored() {
+        assert!(token_matches(" s3cret\n", TOKEN));
+        assert!(!token_matches("s3cre", TOKEN));
+        assert!(!token_matches("s3 cret", TOKEN));
+    }
+
+    #[test]
+    fn no_auth_needs_loopback_addresses() {
+        let local: SocketAddr = "127.0.0.1:7117".parse().unwrap();
+        let lan: SocketAddr = "0.0.0.0:7117".parse().unwrap();
+        assert!(check_no_auth(local, None).is_ok());
+        assert!(check_no_auth("[::1]:7117".parse().unwrap(), Some(local)).is_ok());
+        assert!(check_no_auth(lan, None).is_err());
+        assert!(check_no_auth(local, Some(lan)).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! `--bench-ipc`: a repeatable baseline for the IPC path, for work on the worker. Times handshakes
+//! and back-to-back SET_ACTIVITY round trips against the socket from `--ipc-path` (e.g. one opened by
+//! `mock-discord`) or the first Discord found, then prints a report. Left out of `--help` on purpose.
+//!
+//! Against a real Discord, updates refused as too fast are counted and the bench waits as asked
+//! before going on; those waits are not part of the round trip times.
+
+use std::time::{Duration, Instant};
+
+use rpc_core::{DiscordRpcClient, PresenceCfg, RateLimited};
+
+/// Handshakes timed, each on a fresh connection.
+const HANDSHAKES: usize = 10;
+
+pub struct Options {
+    pub ipc_path: Option<String>,
+    pub client_id: String,
+    /// SET_ACTIVITY round trips timed on one connection.
+    pub rounds: usize,
+}
+
+pub fn run(opts: &Options) -> anyhow::Result<()> {
+    let connect = || match &opts.ipc_path {
+        Some(path) => DiscordRpcClient::connect_and_handshake_at(path, &opts.client_id),
+        None => DiscordRpcClient::connect_and_handshake(&opts.client_id),
+    };
+
+    let mut handshakes = Vec::with_capacity(HANDSHAKES);
+    for _ in 0..HANDSHAKES {
+        let started = Instant::now();
+        let (client, _) = connect()?;
+        handshakes.push(started.elapsed());
+        drop(client);
+    }
+
+    let (mut client, ready) = connect()?;
+    println!("IPC benchmark on {} (client {}, user {})", client.pipe(), opts.client_id, ready.user.username);
+    println!("{:<14} {}", "handshake", summary(&mut handshakes));
+
+    let mut updates = Vec::with_capacity(opts.rounds);
+    let mut limited = 0;
+    let mut waited = Duration::ZERO;
+    let started = Instant::now();
+    for n in 0..opts.rounds {
+        let cfg = PresenceCfg {
+            client_id: opts.client_id.clone(),
+            details: format!("Benchmark #{}", n + 1),
+            state: "presence-daemon --bench-ipc".to_string(),
+            ..Default::default()
+        };
+        let sent = Instant::now();
+        match client.set_activity(&cfg, rpc_core::now_unix_ts()) {
+            Ok(()) => updates.push(sent.elapsed()),
+            Err(e) => match e.downcast_ref::<RateLimited>() {
+                Some(limit) => {
+                    limited += 1;
+                    let wait = limit.retry_after.unwrap_or(Duration::from_secs(1));
+                    waited += wait;
+                    std::thread::sleep(wait);
+                }
+                None => return Err(e.context(format!("update {} failed", n + 1))),
+            },
+        }
+    }
+    let busy = started.elapsed().saturating_sub(waited);
+    println!("{:<14} {}", "set_activity", summary(&mut updates));
+    if !updates.is_empty() {
+        println!("{:<14} {:.0} updates/s back to back", "throughput", updates.len() as f64 / busy.as_secs_f64().max(1e-9));
+    }
+    if limited > 0 {
+        println!("{:<14} {} refused as too fast, {:.1} s spent waiting", "rate limited", limited, waited.as_secs_f64());
+    }
+
+    let cleared = Instant::now();
+    client.clear_activity()?;
+    println!("{:<14} {}", "clear", ms(cleared.elapsed()));
+    Ok(())
+}
+
+/// `n`, min, median, p95 and max of `samples`.
+fn summary(samples: &mut [Duration]) -> String {
+    if samples.is_empty() {
+        return "no samples".to_string();
+    }
+    samples.sort();
+    let at = |pct: usize| samples[((samples.len() * pct).div_ceil(100)).saturating_sub(1)];
+    format!(
+        "n={:<5} min {}  median {}  p95 {}  max {}",
+        samples.len(),
+        ms(samples[0]),
+        ms(at(50)),
+        ms(at(95)),
+        ms(samples[samples.len() - 1])
+    )
+}
+
+fn ms(d: Duration) -> String {
+    format!("{:.3} ms", d.as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,278 @@
+//! gRPC variant of the control API, described by `proto/presence.proto`. The messages below are
+//! that file written out for prost; the service traits are generated by `build.rs`.
+
+// tonic's traits and interceptors return `tonic::Status` by value.
+#![allow(clippy::result_large_err)]
+
+use std::{net::SocketAddr, pin::Pin, sync::Arc};
+
+use rpc_core::{control::ControlRequest, cycle::ImageCycle as CycleCfg, marquee::Marquee as MarqueeCfg, ButtonCfg, PresenceCfg, Refresh, RpcStatus, TimerPolicy};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tonic::{Request, Response};
+
+use crate::{AppState, STATUS_POLL};
+
+include!(concat!(env!("OUT_DIR"), "/presence.v1.PresenceControl.rs"));
+
+use presence_control_server::{PresenceControl, PresenceControlServer};
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Empty {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Button {
+    #[prost(string, tag = "1")]
+    pub label: String,
+    #[prost(string, tag = "2")]
+    pub url: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum TimerMode {
+    Continue = 0,
+    Reset = 1,
+    Fixed = 2,
+    End = 3,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Presence {
+    #[prost(string, tag = "1")]
+    pub client_id: String,
+    #[prost(string, tag = "2")]
+    pub details: String,
+    #[prost(string, tag = "3")]
+    pub state: String,
+    #[prost(string, optional, tag = "4")]
+    pub large_image: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub large_text: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub small_image: Option<String>,
+    #[prost(string, optional, tag = "7")]
+    pub small_text: Option<String>,
+    #[prost(message, repeated, tag = "8")]
+    pub buttons: Vec<Button>,
+    #[prost(bool, tag = "9")]
+    pub with_timestamp: bool,
+    #[prost(enumeration = "TimerMode", tag = "10")]
+    pub timer_mode: i32,
+    #[prost(int64, tag = "11")]
+    pub timer_at: i64,
+    #[prost(uint32, tag = "12")]
+    pub keepalive_secs: u32,
+    #[prost(bool, tag = "13")]
+    pub no_burst: bool,
+    #[prost(uint32, tag = "14")]
+    pub template_secs: u32,
+    #[prost(message, optional, tag = "15")]
+    pub marquee: Option<Marquee>,
+    #[prost(message, optional, tag = "16")]
+    pub image_cycle: Option<ImageCycle>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Marquee {
+    #[prost(uint32, tag = "1")]
+    pub width: u32,
+    #[prost(uint32, tag = "2")]
+    pub step: u32,
+    #[prost(uint32, tag = "3")]
+    pub interval_secs: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ImageCycle {
+    #[prost(string, repeated, tag = "1")]
+    pub images: Vec<String>,
+    #[prost(uint32, tag = "2")]
+    pub interval_secs: u32,
+    #[prost(bool, tag = "3")]
+    pub random: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum RunState {
+    Inactive = 0,
+    Connecting = 1,
+    Active = 2,
+    Throttled = 3,
+    Error = 4,
+    WaitingForDiscord = 5,
+    Stabilizing = 6,
+    Degraded = 7,
+    Paused = 8,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Status {
+    #[prost(enumeration = "RunState", tag = "1")]
+    pub status: i32,
+    #[prost(string, optional, tag = "2")]
+    pub last_error: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub client_id: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub pipe: Option<String>,
+    #[prost(string, repeated, tag = "5")]
+    pub warnings: Vec<String>,
+    #[prost(uint32, tag = "6")]
+    pub pending_updates: u32,
+    #[prost(int64, optional, tag = "7")]
+    pub next_send_at: Option<i64>,
+    #[prost(int64, optional, tag = "8")]
+    pub last_sent_at: Option<i64>,
+}
+
+impl From<Presence> for PresenceCfg {
+    fn from(p: Presence) -> Self {
+        let timer = match TimerMode::try_from(p.timer_mode).unwrap_or(TimerMode::Continue) {
+            TimerMode::Continue => TimerPolicy::Continue,
+            TimerMode::Reset => TimerPolicy::Reset,
+            TimerMode::Fixed => TimerPolicy::Fixed { start: p.timer_at },
+            TimerMode::End => TimerPolicy::End { end: p.timer_at },
+        };
+        PresenceCfg {
+            client_id: p.client_id,
+            details: p.details,
+            state: p.state,
+            large_image: p.large_image,
+            large_text: p.large_text,
+            small_image: p.small_image,
+            small_text: p.small_text,
+            buttons: p.buttons.into_iter().map(|b| ButtonCfg { label: b.label, url: b.url }).collect(),
+            with_timestamp: p.with_timestamp,
+            timer,
+            refresh: Refresh {
+                keepalive_secs: if p.keepalive_secs == 0 { Refresh::default().keepalive_secs } else { p.keepalive_secs },
+                burst: !p.no_burst,
+                template_secs: if p.template_secs == 0 { Refresh::default().template_secs } else { p.template_secs },
+            },
+            marquee: p
+                .marquee
+                .map(|m| MarqueeCfg { enabled: true, width: m.width, step: m.step, interval_secs: m.interval_secs })
+                .unwrap_or_default(),
+            image_cycle: p
+                .image_cycle
+                .map(|c| CycleCfg { images: c.images, interval_secs: c.interval_secs, random: c.random })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl From<rpc_core::control::StatusSnapshot> for Status {
+    fn from(s: rpc_core::control::StatusSnapshot) -> Self {
+        let status = match s.status {
+            RpcStatus::Inactive => RunState::Inactive,
+            RpcStatus::Connecting => RunState::Connecting,
+            RpcStatus::Active => RunState::Active,
+            RpcStatus::Throttled => RunState::Throttled,
+            RpcStatus::Error => RunState::Error,
+            RpcStatus::WaitingForDiscord => RunState::WaitingForDiscord,
+            RpcStatus::Stabilizing => RunState::Stabilizing,
+            RpcStatus::Degraded => RunState::Degraded,
+            RpcStatus::Paused => RunState::Paused,
+        };
+        Status {
+            status: status as i32,
+            last_error: s.last_error,
+            client_id: s.client_id,
+            pipe: s.pipe,
+            warnings: s.warnings,
+            pending_updates: s.pending_updates,
+            next_send_at: s.next_send_at,
+            last_sent_at: s.last_sent_at,
+        }
+    }
+}
+
+struct Control {
+    state: AppState,
+}
+
+impl Control {
+    fn run(&self, req: ControlRequest) -> Result<Response<Status>, tonic::Status> {
+        crate::apply(&self.state, req).map(|s| Response::new(s.into())).map_err(|e| match e.as_str() {
+            rpc_core::ratelimit::RATE_LIMITED => tonic::Status::resource_exhausted(e),
+            _ => tonic::Status::invalid_argument(e),
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl PresenceControl for Control {
+    async fn get_status(&self, _: Request<Empty>) -> Result<Response<Status>, tonic::Status> {
+        self.run(ControlRequest::Status)
+    }
+
+    async fn enable(&self, req: Request<Presence>) -> Result<Response<Status>, tonic::Status> {
+        self.run(ControlRequest::Enable { presence: req.into_inner().into() })
+    }
+
+    async fn update(&self, req: Request<Presence>) -> Result<Response<Status>, tonic::Status> {
+        self.run(ControlRequest::Update { presence: req.into_inner().into() })
+    }
+
+    async fn disable(&self, _: Request<Empty>) -> Result<Response<Status>, tonic::Status> {
+        self.run(ControlRequest::Disable)
+    }
+
+    async fn pause(&self, _: Request<Empty>) -> Result<Response<Status>, tonic::Status> {
+        self.run(ControlRequest::Pause)
+    }
+
+    async fn resume(&self, _: Request<Empty>) -> Result<Response<Status>, tonic::Status> {
+        self.run(ControlRequest::Resume)
+    }
+
+    type WatchStatusStream = Pin<Box<dyn Stream<Item = Result<Status, tonic::Status>> + Send>>;
+
+    async fn watch_status(&self, _: Request<Empty>) -> Result<Response<Self::WatchStatusStream>, tonic::Status> {
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        let client = self.state.client.clone();
+        let mut status = self.state.status.clone();
+        let mut stopping = self.state.stopping.clone();
+        tokio::spawn(async move {
+            let mut last = None;
+            let mut tick = tokio::time::interval(STATUS_POLL);
+            loop {
+                tokio::select! {
+                    // Ends the stream, so the server can finish shutting down.
+                    _ = stopping.changed() => return,
+                    Ok(()) = status.changed() => {}
+                    _ = tick.tick() => {}
+                }
+                let now = client.snapshot();
+                if last.as_ref() != Some(&now) {
+                    last = Some(now.clone());
+                    if tx.send(Ok(now.into())).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+/// Serves the gRPC API on `addr` until the daemon shuts down.
+pub(crate) async fn serve(addr: SocketAddr, state: AppState, token: Option<Arc<str>>) -> anyhow::Result<()> {
+    let check = move |req: Request<()>| -> Result<Request<()>, tonic::Status> {
+        let Some(token) = &token else { return Ok(req) };
+        let given = req.metadata().get("authorization").and_then(|v| v.to_str().ok());
+        match given.and_then(|v| v.strip_prefix("Bearer ")) {
+            Some(given) if crate::auth::token_matches(given, token) => Ok(req),
+            _ => Err(tonic::Status::unauthenticated("missing or wrong token (see presence-daemon --show-token)")),
+        }
+    };
+    let mut stopping = state.stopping.clone();
+    tonic::transport::Server::builder()
+        .add_service(PresenceControlServer::with_interceptor(Control { state }, check))
+        .serve_with_shutdown(addr, async move {
+            let _ = stopping.changed().await;
+        })
+        .await?;
+    Ok(())
+}
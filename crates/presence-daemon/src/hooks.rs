@@ -0,0 +1,145 @@
+//! Inbound webhooks: `POST /hooks/<name>` runs the hook `<name>` from the hooks file, so a CI job or
+//! a streaming tool can change the presence with one HTTP call.
+//!
+//! ```json
+//! {
+//!   "live": { "presence": { "client_id": "123456789012345678", "details": "Live: {title}" } },
+//!   "offline": { "disable": true },
+//!   "ci": {}
+//! }
+//! ```
+//!
+//! A JSON object in the request body sets template variables first (`{"title": "Speedrun"}`;
+//! `null` removes one); the worker renders the presence with them on its next template tick. The
+//! file is re-read on every call, so edits apply without a restart.
+
+use std::{collections::BTreeMap, path::Path};
+
+use axum::{
+    body::Bytes,
+    extract::{Path as UrlPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use rpc_core::{control::ControlRequest, progress::Bars, PresenceCfg};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{api::ApiError, AppState};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hook {
+    /// Enable this presence (it may use `{variables}`).
+    presence: Option<PresenceCfg>,
+    /// Clear the presence instead.
+    disable: bool,
+}
+
+/// The presence last sent through any API, as a template, and the variables set by hooks. The
+/// worker has its own copy of both and renders them itself; these fill in recordings.
+#[derive(Debug, Default)]
+pub struct Live {
+    pub template: Option<PresenceCfg>,
+    pub vars: BTreeMap<String, String>,
+    /// Look of `{bar:value/max}` placeholders.
+    pub bars: Bars,
+}
+
+impl Live {
+    /// `presence` with the hook variables and bars filled in. Escapes and the worker's own
+    /// variables (clock, timer) stay, so the result still renders like the template.
+    pub fn fill(&self, presence: &PresenceCfg) -> PresenceCfg {
+        let vars = |name: &str| self.vars.get(name).cloned();
+        let vars = self.bars.vars(&vars);
+        presence.fill(&vars)
+    }
+}
+
+fn load(path: Option<&Path>) -> Result<BTreeMap<String, Hook>, String> {
+    let Some(path) = path else { return Ok(BTreeMap::new()) };
+    match std::fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|e| format!("Invalid hooks file {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(format!("Cannot read hooks file {}: {}", path.display(), e)),
+    }
+}
+
+/// Variable updates from a hook's request body: a flat JSON object, or nothing.
+fn parse_vars(body: &[u8]) -> Result<BTreeMap<String, Option<String>>, String> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(BTreeMap::new());
+    }
+    let obj: serde_json::Map<String, Value> =
+        serde_json::from_slice(body).map_err(|e| format!("Body must be a JSON object of variables: {}", e))?;
+    obj.into_iter()
+        .map(|(k, v)| match v {
+            Value::Null => Ok((k, None)),
+            Value::String(s) => Ok((k, Some(s))),
+            Value::Bool(_) | Value::Number(_) => Ok((k, Some(v.to_string()))),
+            _ => Err(format!("Variable \"{}\" must be a string, number, boolean or null.", k)),
+        })
+        .collect()
+}
+
+/// Runs hook `name`.
+#[utoipa::path(
+    post,
+    path = "/hooks/{name}",
+    params(("name" = String, Path, description = "Hook name from the hooks file")),
+    request_body(content = BTreeMap<String, String>, description = "Template variables to set (optional)"),
+    responses(
+        (status = 200, description = "Status after the hook ran", body = rpc_core::control::StatusSnapshot),
+        (status = 400, description = "Invalid body or hooks file", body = ApiError),
+        (status = 401, description = "Missing or wrong token"),
+        (status = 429, description = "Too soon after the previous request", body = ApiError),
+        (status = 404, description = "No such hook", body = ApiError)
+    )
+)]
+pub async fn run(State(state): State<AppState>, UrlPath(name): UrlPath<String>, body: Bytes) -> Response {
+    let error = |code: StatusCode, message: String| (code, Json(ApiError { message })).into_response();
+    let hooks = match load(state.hooks_file.as_deref()) {
+        Ok(h) => h,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e),
+    };
+    let Some(hook) = hooks.get(&name) else {
+        return error(StatusCode::NOT_FOUND, format!("No hook named \"{}\".", name));
+    };
+    let vars = match parse_vars(&body) {
+        Ok(v) => v,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e),
+    };
+
+    let shown = {
+        let mut live = state.live.lock().unwrap();
+        let changed = !vars.is_empty();
+        for (k, v) in vars {
+            state.client.set_var(&k, v.clone());
+            match v {
+                Some(v) => live.vars.insert(k, v),
+                None => live.vars.remove(&k),
+            };
+        }
+        live.template.as_ref().filter(|_| changed).map(|t| live.fill(t))
+    };
+    // A hook that only sets variables leaves the presence, its timer and the rate limit alone; the
+    // worker shows the new values on its next template tick.
+    let req = if hook.disable {
+        ControlRequest::Disable
+    } else if let Some(presence) = &hook.presence {
+        ControlRequest::Enable { presence: presence.clone() }
+    } else {
+        // Recorded as the update it amounts to, so a replay shows the new values.
+        if let (Some(recorder), Some(presence)) = (&state.recorder, shown) {
+            if let Err(e) = recorder.record(&ControlRequest::Update { presence }) {
+                eprintln!("recording failed: {}", e);
+            }
+        }
+        ControlRequest::Status
+    };
+    match crate::apply(&state, req) {
+        Ok(status) => Json(status).into_response(),
+        Err(e) => error(crate::api::status_for(&e), e),
+    }
+}
@@ -0,0 +1,469 @@
+//! Headless presence worker controlled over a local WebSocket API (`/ws`, see `rpc_core::control`)
+//! or the equivalent REST routes under `/api` (see [`api`]).
+//!
+//! Also serves the browser control UI from `--web <dir>` (default: `web/` next to the executable).
+//!
+//! The API needs the token from `--token-file` (see [`auth`]); the static UI does not.
+//!
+//! `--record <file>` writes every request carried out to a session file (see `rpc_core::session`);
+//! `--replay <file>` applies one again with its original timing (scaled by `--replay-speed`),
+//! bypassing the rate limits, while the APIs stay available.
+
+mod api;
+mod auth;
+mod bench;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod hooks;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use rpc_core::{
+    control::{self, ControlEvent, ControlRequest, StatusSnapshot},
+    managed::{ManagedClient, RpcStatus},
+    progress::{BarStyle, Bars},
+    ratelimit::{Action, RateLimiter, RateLimits},
+    session::{self, Recorder},
+    shutdown::Shutdown,
+};
+use std::{
+    env,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tower_http::services::ServeDir;
+use utoipa::OpenApi;
+
+/// How often each WebSocket or gRPC session checks the worker for changes to push. Status changes
+/// are pushed as they happen, through [`AppState::status`].
+pub(crate) const STATUS_POLL: Duration = Duration::from_millis(500);
+/// How long shutdown waits for the servers to finish their requests, and then for the worker to
+/// clear the activity.
+const SERVER_STOP: Duration = Duration::from_secs(5);
+const WORKER_STOP: Duration = Duration::from_secs(2);
+
+#[derive(Clone)]
+struct AppState {
+    client: ManagedClient,
+    live: Arc<Mutex<hooks::Live>>,
+    hooks_file: Option<Arc<Path>>,
+    /// Shared by every API, so a script cannot get around it by switching protocols.
+    rate: Arc<RateLimiter>,
+    /// Session file from `--record`.
+    recorder: Option<Arc<Recorder>>,
+    /// The worker's status, for sessions to wait on.
+    status: tokio::sync::watch::Receiver<RpcStatus>,
+    /// Changes once, when the daemon starts shutting down; open sessions end then.
+    stopping: tokio::sync::watch::Receiver<bool>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut addr: SocketAddr = control::DEFAULT_ADDR.parse()?;
+    let mut web = default_web_dir();
+    let mut ipc_path = None;
+    let mut token_file = auth::default_token_file();
+    let mut no_auth = false;
+    let mut show_token = false;
+    let mut grpc_addr: Option<SocketAddr> = None;
+    let mut hooks_file = auth::config_dir().map(|d| d.join("daemon-hooks.json"));
+    let mut bars = Bars::default();
+    // Callers here are scripts and integrations rather than clicks.
+    let mut rate_limits = RateLimits::RELAXED;
+    let mut record: Option<PathBuf> = None;
+    let mut replay: Option<PathBuf> = None;
+    let mut replay_speed = 1.0_f64;
+    let mut bench = false;
+    let mut bench_client_id = "123456789012345678".to_string();
+    let mut bench_rounds = 100;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => {
+                let v = args.next().ok_or_else(|| anyhow::anyhow!("--bind needs a value"))?;
+                addr = v.parse().map_err(|_| anyhow::anyhow!("invalid --bind address: {}", v))?;
+            }
+            "--web" => web = PathBuf::from(args.next().ok_or_else(|| anyhow::anyhow!("--web needs a value"))?),
+            "--ipc-path" => ipc_path = Some(args.next().ok_or_else(|| anyhow::anyhow!("--ipc-path needs a value"))?),
+            "--token-file" => {
+                token_file = Some(PathBuf::from(args.next().ok_or_else(|| anyhow::anyhow!("--token-file needs a value"))?))
+            }
+            "--hooks" => hooks_file = Some(PathBuf::from(args.next().ok_or_else(|| anyhow::anyhow!("--hooks needs a value"))?)),
+            "--bar-style" => {
+                let v = args.next().ok_or_else(|| anyhow::anyhow!("--bar-style needs a value"))?;
+                bars.style = BarStyle::from_name(&v).ok_or_else(|| anyhow::anyhow!("unknown --bar-style: {}", v))?;
+            }
+            "--rate-limits" => {
+                let v = args.next().ok_or_else(|| anyhow::anyhow!("--rate-limits needs a value"))?;
+                rate_limits = RateLimits::parse(&v).map_err(|e| anyhow::anyhow!("invalid --rate-limits: {}", e))?;
+            }
+            "--record" => record = Some(PathBuf::from(args.next().ok_or_else(|| anyhow::anyhow!("--record needs a value"))?)),
+            "--replay" => replay = Some(PathBuf::from(args.next().ok_or_else(|| anyhow::anyhow!("--replay needs a value"))?)),
+            "--replay-speed" => {
+                let v = args.next().ok_or_else(|| anyhow::anyhow!("--replay-speed needs a value"))?;
+                replay_speed = v
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|s| s.is_finite() && *s > 0.0)
+                    .ok_or_else(|| anyhow::anyhow!("invalid --replay-speed (expected a number above 0): {}", v))?;
+            }
+            // Contributor tools, not listed in --help; see `bench`.
+            "--bench-ipc" => bench = true,
+            "--bench-client-id" => {
+                bench_client_id = args.next().ok_or_else(|| anyhow::anyhow!("--bench-client-id needs a value"))?
+            }
+            "--bench-rounds" => {
+                let v = args.next().ok_or_else(|| anyhow::anyhow!("--bench-rounds needs a value"))?;
+                bench_rounds = v.parse().map_err(|_| anyhow::anyhow!("invalid --bench-rounds: {}", v))?;
+            }
+            "--no-auth" => no_auth = true,
+            "--show-token" => show_token = true,
+            "--grpc" => {
+                let v = args.next().ok_or_else(|| anyhow::anyhow!("--grpc needs a value"))?;
+                grpc_addr = Some(v.parse().map_err(|_| anyhow::anyhow!("invalid --grpc address: {}", v))?);
+            }
+            "--openapi" => {
+                println!("{}", api::ApiDoc::openapi().to_pretty_json()?);
+                return Ok(());
+            }
+            "-h" | "--help" => {
+                println!(
+                    "usage: presence-daemon [--bind <addr:port>] [--web <dir>] [--ipc-path <socket>] \
+                     [--grpc <addr:port>] [--hooks <file>] [--bar-style <blocks|squares|shades|circles|ascii>] \
+                     [--rate-limits <default|relaxed|off|sync=ms,toggle=ms,update=ms>] [--record <file>] \
+                     [--replay <file>] [--replay-speed <factor>] [--token-file <path>] [--no-auth] [--show-token] [--openapi]"
+                );
+                return Ok(());
+            }
+            other => return Err(anyhow::anyhow!("unknown argument: {}", other)),
+        }
+    }
+
+    if bench {
+        return bench::run(&bench::Options { ipc_path, client_id: bench_client_id, rounds: bench_rounds });
+    }
+
+    if no_auth {
+        auth::check_no_auth(addr, grpc_addr)?;
+    }
+    let token: Option<Arc<str>> = if no_auth {
+        if show_token {
+            anyhow::bail!("--show-token cannot be combined with --no-auth");
+        }
+        None
+    } else {
+        let path = token_file.ok_or_else(|| anyhow::anyhow!("no config directory for the token; pass --token-file"))?;
+        let (token, created) = auth::load_or_create(&path)?;
+        if show_token {
+            println!("{}", token);
+            return Ok(());
+        }
+        if created {
+            println!("Generated an API token in {}", path.display());
+        }
+        println!("API token: {} (stored in {})", token, path.display());
+        Some(token.into())
+    };
+
+    let replay = match replay {
+        Some(path) => Some(session::load(&path).map_err(|e| anyhow::anyhow!("invalid --replay session: {}", e))?),
+        None => None,
+    };
+    let recorder = match record {
+        Some(path) => {
+            let recorder = Recorder::create(&path).map_err(|e| anyhow::anyhow!("cannot record to {}: {}", path.display(), e))?;
+            println!("Recording requests to {}", path.display());
+            Some(Arc::new(recorder))
+        }
+        None => None,
+    };
+
+    let client = ManagedClient::new();
+    client.set_ipc_path(ipc_path);
+    client.set_bars(bars);
+    let mut shutdown = Shutdown::new();
+    let cancel = shutdown.token();
+    let (stop_tx, stopping) = tokio::sync::watch::channel(false);
+
+    let mut watch = client.watch_status();
+    let (status_tx, status) = tokio::sync::watch::channel(watch.current());
+    // Carries the worker's blocking watch over to the runtime until shutdown.
+    let bridge = cancel.clone();
+    std::thread::spawn(move || {
+        while !bridge.is_cancelled() {
+            if let Some(now) = watch.changed_timeout(STATUS_POLL) {
+                status_tx.send_replace(now);
+            }
+        }
+    });
+
+    let state = AppState {
+        client: client.clone(),
+        live: Arc::new(Mutex::new(hooks::Live { bars, ..Default::default() })),
+        hooks_file: hooks_file.map(Arc::from),
+        rate: Arc::new(RateLimiter::new(rate_limits)),
+        recorder,
+        status,
+        stopping,
+    };
+
+    let api = Router::new()
+        .route("/ws", get(ws_handler))
+        .route("/api/status", get(api::status))
+        .route("/api/enable", post(api::enable))
+        .route("/api/update", post(api::update))
+        .route("/api/disable", post(api::disable))
+        .route("/api/pause", post(api::pause))
+        .route("/api/resume", post(api::resume))
+        .route("/api/errors", get(api::errors).delete(api::clear_errors))
+        .route("/hooks/:name", post(hooks::run))
+        .route_layer(middleware::from_fn_with_state(token.clone(), auth::require_token));
+    let app = api
+        .route("/api/openapi.json", get(api::openapi))
+        .fallback_service(ServeDir::new(&web))
+        .with_state(state.clone());
+
+    let mut servers = Vec::new();
+    if let Some(grpc_addr) = grpc_addr {
+        #[cfg(feature = "grpc")]
+        {
+            let (state, token) = (state.clone(), token.clone());
+            servers.push(tokio::spawn(async move {
+                if let Err(e) = grpc::serve(grpc_addr, state, token).await {
+                    eprintln!("gRPC server stopped: {:#}", e);
+                }
+            }));
+            println!("gRPC control API on {} (see proto/presence.proto)", grpc_addr);
+        }
+        #[cfg(not(feature = "grpc"))]
+        anyhow::bail!("cannot serve gRPC on {}: this build has no gRPC support (build with --features grpc)", grpc_addr);
+    }
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    println!("presence-daemon listening on http://{} (web UI from {})", addr, web.display());
+    if let Some(token) = &token {
+        println!("Open http://{}/#token={} to sign the web UI in", addr, token);
+    }
+    let replay = replay.map(|entries| {
+        let (client, cancel) = (client.clone(), cancel.clone());
+        println!("Replaying {} step(s) at {}x", entries.len(), replay_speed);
+        std::thread::spawn(move || {
+            let done = session::replay(&client, &entries, replay_speed, &cancel);
+            if done == entries.len() {
+                println!("Replay finished");
+            }
+        })
+    });
+    let mut stopping = state.stopping.clone();
+    let mut http = tokio::spawn(async move {
+        let served = axum::serve(listener, app).with_graceful_shutdown(async move {
+            let _ = stopping.changed().await;
+        });
+        if let Err(e) = served.await {
+            eprintln!("HTTP server stopped: {}", e);
+        }
+    });
+
+    tokio::select! {
+        _ = stop_signal() => println!("Shutting down"),
+        // Without the HTTP API the daemon is no use; shut the rest down too.
+        _ = &mut http => {}
+    }
+    servers.push(http);
+
+    // What drives the worker stops first (the replay watches the token), then the servers, so no
+    // request can turn the presence back on once the worker has cleared it; files are closed last.
+    if let Some(replay) = replay {
+        shutdown.then("replay", move || {
+            let _ = replay.join();
+        });
+    }
+    let runtime = tokio::runtime::Handle::current();
+    shutdown.then("servers", move || {
+        stop_tx.send_replace(true);
+        runtime.block_on(async {
+            for server in servers.into_iter().filter(|s| !s.is_finished()) {
+                if tokio::time::timeout(SERVER_STOP, server).await.is_err() {
+                    eprintln!("a server did not stop within {} s", SERVER_STOP.as_secs());
+                }
+            }
+        });
+    });
+    shutdown.then("worker", move || {
+        if !client.shutdown(WORKER_STOP) {
+            eprintln!("the presence was not cleared within {} s", WORKER_STOP.as_secs());
+        }
+    });
+    if let Some(recorder) = state.recorder.clone() {
+        shutdown.then("recording", move || {
+            if let Err(e) = recorder.flush() {
+                eprintln!("recording failed: {}", e);
+            }
+        });
+    }
+    shutdown.then("output", || {
+        let _ = std::io::stdout().flush();
+    });
+    for step in tokio::task::spawn_blocking(move || shutdown.run()).await? {
+        eprintln!("shutdown step '{}' failed", step);
+    }
+    Ok(())
+}
+
+/// Ctrl+C, or SIGTERM from a service manager.
+async fn stop_signal() {
+    #[cfg(unix)]
+    if let Ok(mut term) = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = term.recv() => {}
+        }
+        return;
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+fn default_web_dir() -> PathBuf {
+    env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|d| d.join("web")))
+        .unwrap_or_else(|| PathBuf::from("web"))
+}
+
+async fn ws_handler(ws: WebSocketUpgrade, headers: HeaderMap, State(state): State<AppState>) -> Response {
+    // Any web page can open a WebSocket to localhost, so only the UI served from here may connect.
+    if !same_origin(&headers) {
+        return (StatusCode::FORBIDDEN, "cross-origin WebSocket connections are not allowed").into_response();
+    }
+    ws.on_upgrade(move |socket| session(socket, state))
+}
+
+fn same_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok()) else {
+        // Non-browser clients do not send Origin.
+        return true;
+    };
+    let host = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+    origin.split_once("://").map(|(_, h)| h) == host
+}
+
+async fn session(mut socket: WebSocket, state: AppState) {
+    let client = &state.client;
+    let mut last = client.snapshot();
+    if send(&mut socket, &ControlEvent::Status(last.clone())).await.is_err() {
+        return;
+    }
+
+    let mut status = state.status.clone();
+    let mut stopping = state.stopping.clone();
+    let mut tick = tokio::time::interval(STATUS_POLL);
+    loop {
+        tokio::select! {
+            _ = stopping.changed() => {
+                let _ = socket.send(Message::Close(None)).await;
+                break;
+            }
+            Ok(()) = status.changed() => tick.reset_immediately(),
+            msg = socket.recv() => {
+                let text = match msg {
+                    Some(Ok(Message::Text(t))) => t,
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(_)) => continue,
+                };
+                let reply = handle(&state, &text);
+                if let ControlEvent::Status(s) = &reply {
+                    last = s.clone();
+                }
+                if send(&mut socket, &reply).await.is_err() {
+                    break;
+                }
+            }
+            _ = tick.tick() => {
+                let now = client.snapshot();
+                if now != last {
+                    last = now.clone();
+                    if send(&mut socket, &ControlEvent::Status(now)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn handle(state: &AppState, text: &str) -> ControlEvent {
+    let req = match serde_json::from_str::<ControlRequest>(text) {
+        Ok(r) => r,
+        Err(e) => return ControlEvent::Error { message: format!("Invalid request: {}", e) },
+    };
+    match apply(state, req) {
+        Ok(status) => ControlEvent::Status(status),
+        Err(message) => ControlEvent::Error { message },
+    }
+}
+
+/// Carries out one request, shared by the WebSocket, REST, gRPC and webhook APIs. Presences go to
+/// the worker as templates; it renders them with the variables hooks set (see [`hooks`]).
+fn apply(state: &AppState, req: ControlRequest) -> Result<StatusSnapshot, String> {
+    let client = &state.client;
+    if let ControlRequest::Enable { presence } | ControlRequest::Update { presence } = &req {
+        if presence.client_id.trim().is_empty() {
+            return Err("client_id is required.".to_string());
+        }
+    }
+    let action = match req {
+        ControlRequest::Update { .. } => Some(Action::Update),
+        ControlRequest::Status => None,
+        _ => Some(Action::Toggle),
+    };
+    if let Some(action) = action {
+        state.rate.check(action)?;
+    }
+    // Recorded with the hook variables filled in, so a replay shows what Discord showed.
+    let recorded = state.remember(&req);
+    session::apply(client, req)?;
+    if let Some(recorder) = &state.recorder {
+        if let Err(e) = recorder.record(&recorded) {
+            eprintln!("recording failed: {}", e);
+        }
+    }
+    Ok(client.snapshot())
+}
+
+impl AppState {
+    /// Keeps the presence `req` sends, if any, as the template later hooks fill into recordings.
+    /// Returns `req` with the hook variables filled in. The worker gets the template itself and
+    /// renders it on every tick.
+    fn remember(&self, req: &ControlRequest) -> ControlRequest {
+        let mut live = self.live.lock().unwrap();
+        match req {
+            ControlRequest::Enable { presence } => {
+                live.template = Some(presence.clone());
+                ControlRequest::Enable { presence: live.fill(presence) }
+            }
+            ControlRequest::Update { presence } => {
+                live.template = Some(presence.clone());
+                ControlRequest::Update { presence: live.fill(presence) }
+            }
+            other => other.clone(),
+        }
+    }
+}
+
+async fn send(socket: &mut WebSocket, evt: &ControlEvent) -> Result<(), axum::Error> {
+    let json = serde_json::to_string(evt).unwrap_or_default();
+    socket.send(Message::Text(json)).await
+}
@@ -0,0 +1,82 @@
+// Built by: wasm-pack build crates/rpc-control-wasm --target web --out-dir ../presence-daemon/web/pkg
+import init, { ControlClient, defaultUrl } from './pkg/rpc_control_wasm.js'
+
+const $ = (id) => document.getElementById(id)
+const buttons = ['enable', 'update', 'pause', 'resume', 'disable'].map($)
+const STORAGE_KEY = 'crp_remote_form'
+const TOKEN_KEY = 'crp_remote_token'
+let connected = false
+
+// The daemon prints a `#token=` link on startup; keep the token and drop it from the address bar.
+function readToken() {
+  const fromHash = new URLSearchParams(location.hash.slice(1)).get('token')
+  if (fromHash) {
+    localStorage.setItem(TOKEN_KEY, fromHash)
+    history.replaceState(null, '', location.pathname + location.search)
+  }
+  return localStorage.getItem(TOKEN_KEY) || ''
+}
+
+function readForm() {
+  const opt = (id) => $(id).value.trim() || null
+  return {
+    client_id: $('client_id').value.trim(),
+    details: $('details').value,
+    state: $('state').value,
+    large_image: opt('large_image'),
+    with_timestamp: $('with_timestamp').checked,
+  }
+}
+
+function restoreForm() {
+  const saved = JSON.parse(localStorage.getItem(STORAGE_KEY) || '{}')
+  for (const [k, v] of Object.entries(saved)) {
+    const el = $(k)
+    if (!el) continue
+    if (el.type === 'checkbox') el.checked = !!v
+    else el.value = v ?? ''
+  }
+}
+
+function onEvent(evt) {
+  if (evt.type === 'status') {
+    connected = true
+    buttons.forEach((b) => (b.disabled = false))
+    const pipe = evt.pipe ? ` via ${evt.pipe}` : ''
+    const pending = evt.pending_updates ? ` (${evt.pending_updates} update${evt.pending_updates === 1 ? '' : 's'} pending${evt.next_send_at ? `, next send ${new Date(evt.next_send_at * 1000).toLocaleTimeString()}` : ''})` : ''
+    $('status').textContent = `Status: ${evt.status}${pipe}${pending}`
+    $('error').textContent = [evt.last_error, ...(evt.warnings || [])].filter(Boolean).join(' ')
+  } else if (evt.type === 'error') {
+    $('error').textContent = evt.message
+  } else if (evt.type === 'closed') {
+    buttons.forEach((b) => (b.disabled = true))
+    $('status').textContent = connected
+      ? 'Disconnected from daemon. Reload to retry.'
+      : 'Could not connect. Check the API token (presence-daemon --show-token) and reload.'
+  }
+}
+
+function send(fn) {
+  const presence = readForm()
+  localStorage.setItem(STORAGE_KEY, JSON.stringify(presence))
+  try {
+    fn(presence)
+  } catch (e) {
+    $('error').textContent = String(e)
+  }
+}
+
+await init()
+restoreForm()
+const token = readToken()
+$('token').value = token
+$('token').onchange = () => {
+  localStorage.setItem(TOKEN_KEY, $('token').value.trim())
+  location.reload()
+}
+const client = new ControlClient(defaultUrl(location.host, location.protocol === 'https:', token), onEvent)
+$('enable').onclick = () => send((p) => client.enable(p))
+$('update').onclick = () => send((p) => client.update(p))
+$('pause').onclick = () => send(() => client.pause())
+$('resume').onclick = () => send(() => client.resume())
+$('disable').onclick = () => send(() => client.disable())
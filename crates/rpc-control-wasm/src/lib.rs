@@ -0,0 +1,128 @@
+//! Browser client for the presence-daemon control API, built with
+//! `wasm-pack build --target web`.
+//!
+//! ```js
+//! import init, { ControlClient, defaultUrl } from './pkg/rpc_control_wasm.js'
+//!
+//! await init()
+//! const url = defaultUrl(location.host, location.protocol === 'https:', token)
+//! const client = new ControlClient(url, (evt) => {
+//!   if (evt.type === 'status') console.log(evt.status, evt.pipe)
+//! })
+//! // after the first event arrives:
+//! client.enable({ client_id: '123456789012345678', details: 'From the browser' })
+//! ```
+
+use rpc_core::{
+    control::{ControlEvent, ControlRequest},
+    PresenceCfg,
+};
+use serde::Serialize as _;
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::{CloseEvent, MessageEvent, WebSocket};
+
+/// WebSocket URL of the daemon that served a page on `host` (`location.host`), signed in with
+/// the daemon's API `token` unless it runs with `--no-auth`.
+#[wasm_bindgen(js_name = defaultUrl)]
+pub fn default_url(host: &str, secure: bool, token: Option<String>) -> String {
+    let scheme = if secure { "wss" } else { "ws" };
+    match token.filter(|t| !t.is_empty()) {
+        Some(t) => format!("{}://{}/ws?token={}", scheme, host, String::from(js_sys::encode_uri_component(&t))),
+        None => format!("{}://{}/ws", scheme, host),
+    }
+}
+
+/// Connection to the daemon. Requests sent before the first event arrives are dropped by the
+/// browser, so wait for the initial `status` event before calling `enable`/`update`/`disable`.
+#[wasm_bindgen]
+pub struct ControlClient {
+    ws: WebSocket,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(CloseEvent)>,
+}
+
+#[wasm_bindgen]
+impl ControlClient {
+    /// Opens `url`. `on_event` receives every daemon event as a plain object
+    /// (`{type: "status", status, last_error, client_id, pipe}` or `{type: "error", message}`),
+    /// and `{type: "closed"}` once the connection is gone.
+    #[wasm_bindgen(constructor)]
+    pub fn new(url: &str, on_event: js_sys::Function) -> Result<ControlClient, JsValue> {
+        let ws = WebSocket::new(url)?;
+
+        let cb = on_event.clone();
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+            let Some(text) = e.data().as_string() else { return; };
+            let evt = serde_json::from_str::<ControlEvent>(&text).unwrap_or_else(|err| ControlEvent::Error {
+                message: format!("Unexpected message from daemon: {}", err),
+            });
+            if let Ok(v) = to_js(&evt) {
+                let _ = cb.call1(&JsValue::NULL, &v);
+            }
+        });
+        ws.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |_e: CloseEvent| {
+            let v = js_sys::Object::new();
+            let _ = js_sys::Reflect::set(&v, &"type".into(), &"closed".into());
+            let _ = on_event.call1(&JsValue::NULL, &v);
+        });
+        ws.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        Ok(Self { ws, _on_message: on_message, _on_close: on_close })
+    }
+
+    /// Starts publishing `presence` (an object with `PresenceCfg` fields; `client_id` is required).
+    pub fn enable(&self, presence: JsValue) -> Result<(), JsValue> {
+        self.send(&ControlRequest::Enable { presence: from_js(presence)? })
+    }
+
+    pub fn update(&self, presence: JsValue) -> Result<(), JsValue> {
+        self.send(&ControlRequest::Update { presence: from_js(presence)? })
+    }
+
+    pub fn disable(&self) -> Result<(), JsValue> {
+        self.send(&ControlRequest::Disable)
+    }
+
+    /// Clears the presence from Discord but keeps it enabled until `resume`.
+    pub fn pause(&self) -> Result<(), JsValue> {
+        self.send(&ControlRequest::Pause)
+    }
+
+    pub fn resume(&self) -> Result<(), JsValue> {
+        self.send(&ControlRequest::Resume)
+    }
+
+    #[wasm_bindgen(js_name = requestStatus)]
+    pub fn request_status(&self) -> Result<(), JsValue> {
+        self.send(&ControlRequest::Status)
+    }
+
+    pub fn close(&self) -> Result<(), JsValue> {
+        self.ws.close()
+    }
+
+    fn send(&self, req: &ControlRequest) -> Result<(), JsValue> {
+        let json = serde_json::to_string(req).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.ws.send_with_str(&json)
+    }
+}
+
+impl Drop for ControlClient {
+    fn drop(&mut self) {
+        self.ws.set_onmessage(None);
+        self.ws.set_onclose(None);
+        let _ = self.ws.close();
+    }
+}
+
+fn from_js(v: JsValue) -> Result<PresenceCfg, JsValue> {
+    serde_wasm_bindgen::from_value(v).map_err(|e| JsValue::from_str(&format!("Invalid presence: {}", e)))
+}
+
+fn to_js(evt: &ControlEvent) -> Result<JsValue, JsValue> {
+    // Plain objects with `null` for missing values, matching the JSON the daemon sent.
+    evt.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
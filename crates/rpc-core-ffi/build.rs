@@ -0,0 +1,14 @@
+fn main() {
+    let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", dir)).expect("cbindgen.toml");
+    match cbindgen::generate_with_config(&dir, config) {
+        Ok(bindings) => {
+            bindings.write_to_file(format!("{}/include/rpc_core.h", dir));
+        }
+        // Keep the checked-in header rather than failing the build (e.g. mid-edit syntax errors).
+        Err(e) => println!("cargo:warning=rpc_core.h not regenerated: {}", e),
+    }
+}
@@ -0,0 +1,74 @@
+/* Generated by cbindgen from crates/rpc-core-ffi. Do not edit. */
+
+#ifndef RPC_CORE_H
+#define RPC_CORE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result codes returned by the `rpc_*` functions.
+typedef enum RpcResult {
+  RPC_RESULT_OK = 0,
+  // A required pointer was null or a string was not valid UTF-8.
+  RPC_RESULT_INVALID_ARGUMENT = -1,
+  // The activity JSON could not be parsed.
+  RPC_RESULT_INVALID_JSON = -2,
+  // Discord rejected the request or the connection failed.
+  RPC_RESULT_IPC = -3,
+} RpcResult;
+
+// Opaque connection handle.
+typedef struct RpcClient RpcClient;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Connects to the running Discord client and performs the handshake for `client_id`.
+//
+// Returns NULL on failure (see `rpc_last_error`). Free the handle with `rpc_disconnect`.
+//
+// # Safety
+//
+// `client_id` must be NULL or a valid NUL-terminated string.
+struct RpcClient *rpc_connect(const char *client_id);
+
+// Publishes an activity described as JSON, using the same fields as `rpc_core::PresenceCfg`:
+// `details`, `state`, `large_image`, `large_text`, `small_image`, `small_text`,
+// `buttons` (`[{"label", "url"}]`), `with_timestamp` and `timer` (`{"mode": "fixed", "start": ts}`
+// or `{"mode": "end", "end": ts}`). Missing fields are treated as unset; `client_id` is ignored.
+// Otherwise the elapsed timer counts from `rpc_connect`.
+//
+// # Safety
+//
+// `client` must be a handle returned by `rpc_connect` and not yet disconnected.
+// `json` must be NULL or a valid NUL-terminated string.
+enum RpcResult rpc_set_activity(struct RpcClient *client, const char *json);
+
+// Removes the activity but keeps the connection open.
+//
+// # Safety
+//
+// `client` must be a handle returned by `rpc_connect` and not yet disconnected.
+enum RpcResult rpc_clear(struct RpcClient *client);
+
+// Clears the activity if one was set, closes the connection and frees the handle.
+// Passing NULL is a no-op.
+//
+// # Safety
+//
+// `client` must be NULL or a handle returned by `rpc_connect`, and must not be used afterwards.
+void rpc_disconnect(struct RpcClient *client);
+
+// Message for the last failed call on this thread, or NULL if none failed yet.
+//
+// The string stays valid until the next failing call on the same thread.
+const char *rpc_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RPC_CORE_H */
@@ -0,0 +1,148 @@
+//! C API over `rpc_core`. The header is generated into `include/rpc_core.h` on build.
+//!
+//! Every function that can fail returns `RPC_RESULT_OK` (0) or a negative code; the message for the
+//! last failure on the calling thread is available from `rpc_last_error`.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+use rpc_core::{DiscordRpcClient, PresenceCfg};
+
+/// Result codes returned by the `rpc_*` functions.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcResult {
+    Ok = 0,
+    /// A required pointer was null or a string was not valid UTF-8.
+    InvalidArgument = -1,
+    /// The activity JSON could not be parsed.
+    InvalidJson = -2,
+    /// Discord rejected the request or the connection failed.
+    Ipc = -3,
+}
+
+/// Opaque connection handle.
+pub struct RpcClient {
+    inner: DiscordRpcClient,
+    client_id: String,
+    start_ts: i64,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(msg: impl Into<String>) {
+    let msg = msg.into().replace('\0', " ");
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(msg).ok());
+}
+
+unsafe fn str_arg<'a>(p: *const c_char) -> Option<&'a str> {
+    if p.is_null() {
+        return None;
+    }
+    CStr::from_ptr(p).to_str().ok()
+}
+
+/// Connects to the running Discord client and performs the handshake for `client_id`.
+///
+/// Returns NULL on failure (see `rpc_last_error`). Free the handle with `rpc_disconnect`.
+///
+/// # Safety
+///
+/// `client_id` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rpc_connect(client_id: *const c_char) -> *mut RpcClient {
+    let Some(client_id) = str_arg(client_id) else {
+        set_error("client_id must be a non-null UTF-8 string");
+        return ptr::null_mut();
+    };
+    match DiscordRpcClient::connect_and_handshake(client_id) {
+        Ok((inner, _ready)) => Box::into_raw(Box::new(RpcClient {
+            inner,
+            client_id: client_id.to_string(),
+            start_ts: rpc_core::now_unix_ts(),
+        })),
+        Err(e) => {
+            set_error(format!("{:#}", e));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Publishes an activity described as JSON, using the same fields as `rpc_core::PresenceCfg`:
+/// `details`, `state`, `large_image`, `large_text`, `small_image`, `small_text`,
+/// `buttons` (`[{"label", "url"}]`), `with_timestamp` and `timer` (`{"mode": "fixed", "start": ts}`
+/// or `{"mode": "end", "end": ts}`). Missing fields are treated as unset; `client_id` is ignored.
+/// Otherwise the elapsed timer counts from `rpc_connect`.
+///
+/// # Safety
+///
+/// `client` must be a handle returned by `rpc_connect` and not yet disconnected.
+/// `json` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rpc_set_activity(client: *mut RpcClient, json: *const c_char) -> RpcResult {
+    let (Some(client), Some(json)) = (client.as_mut(), str_arg(json)) else {
+        set_error("client and json must be non-null, json must be UTF-8");
+        return RpcResult::InvalidArgument;
+    };
+    let mut cfg: PresenceCfg = match serde_json::from_str(json) {
+        Ok(c) => c,
+        Err(e) => {
+            set_error(format!("Invalid activity JSON: {}", e));
+            return RpcResult::InvalidJson;
+        }
+    };
+    cfg.client_id = client.client_id.clone();
+    match client.inner.set_activity(&cfg, client.start_ts) {
+        Ok(()) => RpcResult::Ok,
+        Err(e) => {
+            set_error(format!("{:#}", e));
+            RpcResult::Ipc
+        }
+    }
+}
+
+/// Removes the activity but keeps the connection open.
+///
+/// # Safety
+///
+/// `client` must be a handle returned by `rpc_connect` and not yet disconnected.
+#[no_mangle]
+pub unsafe extern "C" fn rpc_clear(client: *mut RpcClient) -> RpcResult {
+    let Some(client) = client.as_mut() else {
+        set_error("client must be non-null");
+        return RpcResult::InvalidArgument;
+    };
+    match client.inner.clear_activity() {
+        Ok(()) => RpcResult::Ok,
+        Err(e) => {
+            set_error(format!("{:#}", e));
+            RpcResult::Ipc
+        }
+    }
+}
+
+/// Clears the activity if one was set, closes the connection and frees the handle.
+/// Passing NULL is a no-op.
+///
+/// # Safety
+///
+/// `client` must be NULL or a handle returned by `rpc_connect`, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rpc_disconnect(client: *mut RpcClient) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
+
+/// Message for the last failed call on this thread, or NULL if none failed yet.
+///
+/// The string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn rpc_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
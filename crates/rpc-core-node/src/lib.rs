@@ -0,0 +1,134 @@
+//! Node.js bindings: the background `ManagedClient` worker, driven with plain presence objects.
+//!
+//! ```js
+//! const { ManagedClient } = require('@custom-rich-presence/rpc-core')
+//!
+//! const client = new ManagedClient()
+//! client.enable({ clientId: '123456789012345678', details: 'Streaming', withTimestamp: true })
+//! process.on('exit', () => client.disable())
+//! ```
+
+use napi_derive::napi;
+use rpc_core::{managed, ButtonCfg, PresenceCfg, TimerPolicy};
+
+#[napi(object)]
+pub struct PresenceButton {
+    pub label: String,
+    pub url: String,
+}
+
+/// Activity to publish. Omitted or empty fields are left unset.
+#[napi(object)]
+pub struct Presence {
+    pub client_id: String,
+    pub details: Option<String>,
+    pub state: Option<String>,
+    pub large_image: Option<String>,
+    pub large_text: Option<String>,
+    pub small_image: Option<String>,
+    pub small_text: Option<String>,
+    /// Up to two link buttons.
+    pub buttons: Option<Vec<PresenceButton>>,
+    pub with_timestamp: Option<bool>,
+    /// `"continue"` (default), `"reset"`, `"fixed"` (count up from `timerAt`) or `"end"` (count down to it).
+    pub timer_mode: Option<String>,
+    /// Unix time in seconds for the `"fixed"` and `"end"` modes.
+    pub timer_at: Option<i64>,
+}
+
+impl From<Presence> for PresenceCfg {
+    fn from(p: Presence) -> Self {
+        let opt = |v: Option<String>| v.filter(|s| !s.trim().is_empty());
+        let at = p.timer_at.unwrap_or_else(rpc_core::now_unix_ts);
+        let timer = match p.timer_mode.as_deref() {
+            Some("reset") => TimerPolicy::Reset,
+            Some("fixed") => TimerPolicy::Fixed { start: at },
+            Some("end") => TimerPolicy::End { end: at },
+            _ => TimerPolicy::Continue,
+        };
+        PresenceCfg {
+            client_id: p.client_id.trim().to_string(),
+            details: p.details.unwrap_or_default(),
+            state: p.state.unwrap_or_default(),
+            large_image: opt(p.large_image),
+            large_text: opt(p.large_text),
+            small_image: opt(p.small_image),
+            small_text: opt(p.small_text),
+            buttons: p
+                .buttons
+                .unwrap_or_default()
+                .into_iter()
+                .map(|b| ButtonCfg { label: b.label, url: b.url })
+                .collect(),
+            with_timestamp: p.with_timestamp.unwrap_or(false),
+            timer,
+            refresh: Default::default(),
+            marquee: Default::default(),
+            image_cycle: Default::default(),
+        }
+    }
+}
+
+/// Keeps a presence published from a background thread, reconnecting when Discord restarts.
+/// Calls never block the event loop.
+#[napi]
+pub struct ManagedClient {
+    inner: managed::ManagedClient,
+}
+
+#[napi]
+impl ManagedClient {
+    /// `ipcPath` skips socket discovery (e.g. to target a specific Discord instance or a mock).
+    #[napi(constructor)]
+    pub fn new(ipc_path: Option<String>) -> Self {
+        let inner = managed::ManagedClient::new();
+        inner.set_ipc_path(ipc_path);
+        Self { inner }
+    }
+
+    /// Starts publishing, or applies the presence right away if already enabled.
+    #[napi]
+    pub fn enable(&self, presence: Presence) {
+        self.inner.enable(presence.into());
+    }
+
+    #[napi]
+    pub fn update(&self, presence: Presence) {
+        self.inner.update(presence.into());
+    }
+
+    /// Stops the worker; it clears the activity before exiting.
+    #[napi]
+    pub fn disable(&self) {
+        self.inner.disable();
+    }
+
+    /// Clears the activity but stays enabled until `resume`.
+    #[napi]
+    pub fn pause(&self) {
+        self.inner.pause();
+    }
+
+    #[napi]
+    pub fn resume(&self) {
+        self.inner.resume();
+    }
+
+    /// One of `"inactive"`, `"connecting"`, `"waiting_for_discord"`, `"stabilizing"`, `"active"`,
+    /// `"throttled"`, `"degraded"` (connected, last update rejected), `"paused"`, `"error"`.
+    #[napi(getter)]
+    pub fn status(&self) -> String {
+        self.inner.status().as_str().to_string()
+    }
+
+    #[napi(getter)]
+    pub fn last_error(&self) -> Option<String> {
+        self.inner.last_error()
+    }
+
+    /// Socket/pipe the worker is connected through, if connected.
+    #[napi(getter)]
+    pub fn pipe(&self) -> Option<String> {
+        self.inner.report().pipe
+    }
+}
@@ -0,0 +1,207 @@
+//! Python bindings: `PresenceCfg` and the background `ManagedClient` worker.
+//!
+//! ```python
+//! from rpc_core_py import ManagedClient, PresenceCfg
+//!
+//! cfg = PresenceCfg("123456789012345678", details="In the editor", with_timestamp=True)
+//! cfg.buttons = [("Source", "https://github.com/Watashi00/DiscordCustomPresence")]
+//! with ManagedClient() as client:
+//!     client.enable(cfg)
+//!     ...
+//! ```
+
+use pyo3::prelude::*;
+use rpc_core::{managed, ButtonCfg, TimerPolicy};
+
+/// Activity to publish. Empty strings and `None` leave a field unset.
+#[pyclass(name = "PresenceCfg")]
+#[derive(Clone)]
+struct PyPresenceCfg {
+    #[pyo3(get, set)]
+    client_id: String,
+    #[pyo3(get, set)]
+    details: String,
+    #[pyo3(get, set)]
+    state: String,
+    #[pyo3(get, set)]
+    large_image: Option<String>,
+    #[pyo3(get, set)]
+    large_text: Option<String>,
+    #[pyo3(get, set)]
+    small_image: Option<String>,
+    #[pyo3(get, set)]
+    small_text: Option<String>,
+    /// Up to two `(label, url)` pairs.
+    #[pyo3(get, set)]
+    buttons: Vec<(String, String)>,
+    #[pyo3(get, set)]
+    with_timestamp: bool,
+    /// `"continue"` (default), `"reset"`, `"fixed"` (count up from `timer_at`) or `"end"` (count down to it).
+    #[pyo3(get, set)]
+    timer_mode: String,
+    /// Unix time in seconds for the `"fixed"` and `"end"` modes.
+    #[pyo3(get, set)]
+    timer_at: Option<i64>,
+}
+
+#[pymethods]
+impl PyPresenceCfg {
+    #[new]
+    #[pyo3(signature = (
+        client_id,
+        details = String::new(),
+        state = String::new(),
+        large_image = None,
+        large_text = None,
+        small_image = None,
+        small_text = None,
+        buttons = Vec::new(),
+        with_timestamp = false,
+        timer_mode = "continue".to_string(),
+        timer_at = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        client_id: String,
+        details: String,
+        state: String,
+        large_image: Option<String>,
+        large_text: Option<String>,
+        small_image: Option<String>,
+        small_text: Option<String>,
+        buttons: Vec<(String, String)>,
+        with_timestamp: bool,
+        timer_mode: String,
+        timer_at: Option<i64>,
+    ) -> Self {
+        Self {
+            client_id,
+            details,
+            state,
+            large_image,
+            large_text,
+            small_image,
+            small_text,
+            buttons,
+            with_timestamp,
+            timer_mode,
+            timer_at,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PresenceCfg(client_id={:?}, details={:?}, state={:?})", self.client_id, self.details, self.state)
+    }
+}
+
+impl PyPresenceCfg {
+    fn to_cfg(&self) -> rpc_core::PresenceCfg {
+        let opt = |v: &Option<String>| v.clone().filter(|s| !s.trim().is_empty());
+        let at = self.timer_at.unwrap_or_else(rpc_core::now_unix_ts);
+        let timer = match self.timer_mode.as_str() {
+            "reset" => TimerPolicy::Reset,
+            "fixed" => TimerPolicy::Fixed { start: at },
+            "end" => TimerPolicy::End { end: at },
+            _ => TimerPolicy::Continue,
+        };
+        rpc_core::PresenceCfg {
+            client_id: self.client_id.trim().to_string(),
+            details: self.details.clone(),
+            state: self.state.clone(),
+            large_image: opt(&self.large_image),
+            large_text: opt(&self.large_text),
+            small_image: opt(&self.small_image),
+            small_text: opt(&self.small_text),
+            buttons: self.buttons.iter().map(|(label, url)| ButtonCfg { label: label.clone(), url: url.clone() }).collect(),
+            with_timestamp: self.with_timestamp,
+            timer,
+            refresh: Default::default(),
+            marquee: Default::default(),
+            image_cycle: Default::default(),
+        }
+    }
+}
+
+/// Keeps a presence published from a background thread, reconnecting when Discord restarts.
+///
+/// Used as a context manager, the presence is cleared on exit.
+#[pyclass(name = "ManagedClient")]
+struct PyManagedClient {
+    inner: managed::ManagedClient,
+}
+
+#[pymethods]
+impl PyManagedClient {
+    /// `ipc_path` skips socket discovery (e.g. to target a specific Discord instance or a mock).
+    #[new]
+    #[pyo3(signature = (ipc_path = None))]
+    fn new(ipc_path: Option<String>) -> Self {
+        let inner = managed::ManagedClient::new();
+        inner.set_ipc_path(ipc_path);
+        Self { inner }
+    }
+
+    /// Starts publishing `cfg`, or applies it right away if already enabled.
+    fn enable(&self, cfg: &PyPresenceCfg) {
+        self.inner.enable(cfg.to_cfg());
+    }
+
+    fn update(&self, cfg: &PyPresenceCfg) {
+        self.inner.update(cfg.to_cfg());
+    }
+
+    /// Stops the worker; it clears the activity before exiting.
+    fn disable(&self) {
+        self.inner.disable();
+    }
+
+    /// Clears the activity but stays enabled until `resume`.
+    fn pause(&self) {
+        self.inner.pause();
+    }
+
+    fn resume(&self) {
+        self.inner.resume();
+    }
+
+    /// One of `"inactive"`, `"connecting"`, `"waiting_for_discord"`, `"stabilizing"`, `"active"`,
+    /// `"throttled"`, `"degraded"` (connected, last update rejected), `"paused"`, `"error"`.
+    #[getter]
+    fn status(&self) -> &'static str {
+        self.inner.status().as_str()
+    }
+
+    #[getter]
+    fn last_error(&self) -> Option<String> {
+        self.inner.last_error()
+    }
+
+    /// Socket/pipe the worker is connected through, if connected.
+    #[getter]
+    fn pipe(&self) -> Option<String> {
+        self.inner.report().pipe
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&self, _args: &Bound<'_, pyo3::types::PyTuple>) -> bool {
+        self.inner.disable();
+        false
+    }
+}
+
+impl Drop for PyManagedClient {
+    fn drop(&mut self) {
+        self.inner.disable();
+    }
+}
+
+#[pymodule]
+fn rpc_core_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyPresenceCfg>()?;
+    m.add_class::<PyManagedClient>()?;
+    Ok(())
+}
@@ -0,0 +1,47 @@
+//! Standalone fake Discord IPC server.
+//!
+//! Point the native app at it by setting `"ipc_path"` in its `config.json` to the printed path.
+
+use rpc_core::mock::{self, MockDiscord};
+use std::{env, thread, time::Duration};
+
+fn main() -> anyhow::Result<()> {
+    let mut path = mock::default_path();
+    let mut reject = false;
+    let mut strip_buttons = false;
+    let mut fail_every = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--path" => path = args.next().ok_or_else(|| anyhow::anyhow!("--path needs a value"))?,
+            "--reject-handshake" => reject = true,
+            "--strip-buttons" => strip_buttons = true,
+            "--fail-every" => {
+                let n = args.next().ok_or_else(|| anyhow::anyhow!("--fail-every needs a value"))?;
+                fail_every = Some(n.parse::<u32>()?);
+            }
+            "-h" | "--help" => {
+                println!("usage: mock-discord [--path <socket>] [--reject-handshake] [--strip-buttons] [--fail-every <n>]");
+                return Ok(());
+            }
+            other => return Err(anyhow::anyhow!("unknown argument: {}", other)),
+        }
+    }
+
+    let server = MockDiscord::start(path)?;
+    server.reject_handshakes(reject);
+    server.strip_buttons(strip_buttons);
+    server.fail_every(fail_every);
+    println!("mock Discord IPC listening on {}", server.path());
+
+    let mut seen = 0;
+    loop {
+        thread::sleep(Duration::from_millis(250));
+        let activities = server.activities();
+        for a in activities.iter().skip(seen) {
+            println!("SET_ACTIVITY {}", a);
+        }
+        seen = activities.len();
+    }
+}
@@ -0,0 +1,68 @@
+//! Messages of the daemon's WebSocket control API (`/ws`), one JSON object per text frame.
+//!
+//! Clients send [`ControlRequest`]s; the daemon answers every request and pushes a
+//! [`ControlEvent::Status`] whenever the worker status changes.
+//!
+//! ```json
+//! {"type":"enable","presence":{"client_id":"123456789012345678","details":"Hello"}}
+//! {"type":"status","status":"active","last_error":null,"client_id":"123456789012345678","pipe":"/run/user/1000/discord-ipc-0","last_sent_at":1767225600}
+//! {"type":"status","status":"throttled","last_error":"You are being rate limited.","client_id":"123456789012345678","pipe":"/run/user/1000/discord-ipc-0","pending_updates":3,"next_send_at":1767225610,"last_sent_at":1767225600}
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::{PresenceCfg, RpcStatus};
+
+/// Default address of the daemon's HTTP/WebSocket listener.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:29170";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Start publishing `presence`, or replace it if already enabled.
+    Enable { presence: PresenceCfg },
+    /// Replace the presence without changing whether it is enabled.
+    Update { presence: PresenceCfg },
+    Disable,
+    /// Clear the presence but stay enabled, until `resume`.
+    Pause,
+    Resume,
+    /// Ask for a [`ControlEvent::Status`] right away.
+    Status,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlEvent {
+    Status(StatusSnapshot),
+    /// A request could not be understood or carried out.
+    Error { message: String },
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusSnapshot {
+    pub status: RpcStatus,
+    pub last_error: Option<String>,
+    /// Application whose presence is configured, if any.
+    pub client_id: Option<String>,
+    /// Socket/pipe the daemon is connected to Discord through.
+    pub pipe: Option<String>,
+    /// Fields Discord dropped or changed in the last presence it accepted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// `enable`/`update` requests not sent to Discord yet, e.g. while throttled or reconnecting.
+    /// The newest presence replaces older ones, so they all go out with the next send.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub pending_updates: u32,
+    /// Unix time of the next send, while updates are pending.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_send_at: Option<i64>,
+    /// Unix time Discord last acknowledged the presence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_sent_at: Option<i64>,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
@@ -0,0 +1,112 @@
+//! Image cycling: a presence can list several large images (asset keys or URLs) and show them in
+//! turn or in random order, switching on an interval. Like the [marquee](crate::marquee), the
+//! current image follows the wall clock.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::PresenceCfg;
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageCycle {
+    /// Asset keys or `https://` URLs shown as the large image in turn; empty keeps `large_image`.
+    pub images: Vec<String>,
+    /// Seconds per image, at least [`MIN_INTERVAL_SECS`](Self::MIN_INTERVAL_SECS).
+    pub interval_secs: u32,
+    /// Shuffle instead of going in order. Every image still shows once per round.
+    pub random: bool,
+}
+
+impl Default for ImageCycle {
+    fn default() -> Self {
+        ImageCycle { images: Vec::new(), interval_secs: 30, random: false }
+    }
+}
+
+impl ImageCycle {
+    /// Discord accepts about five activity updates per 20 seconds.
+    pub const MIN_INTERVAL_SECS: u32 = 4;
+    pub const MAX_IMAGES: usize = 20;
+
+    /// Whether there is anything to cycle through.
+    pub fn is_active(&self) -> bool {
+        self.images.len() > 1
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(Self::MIN_INTERVAL_SECS).into())
+    }
+
+    /// Image shown in frame number `frame`.
+    pub fn image(&self, frame: u64) -> Option<&str> {
+        let len = self.images.len() as u64;
+        if len == 0 {
+            return None;
+        }
+        let pos = frame % len;
+        let index = if self.random { shuffled(len, frame / len)[pos as usize] } else { pos };
+        Some(&self.images[index as usize])
+    }
+}
+
+/// `0..len` in an order picked by `round`.
+fn shuffled(len: u64, round: u64) -> Vec<u64> {
+    let mut order: Vec<u64> = (0..len).collect();
+    let mut seed = round;
+    for i in (1..order.len()).rev() {
+        let j = (splitmix(&mut seed) % (i as u64 + 1)) as usize;
+        order.swap(i, j);
+    }
+    order
+}
+
+fn splitmix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl PresenceCfg {
+    /// A copy showing the large image due at Unix time `now`; unchanged without a cycle.
+    pub fn cycled(&self, now: i64) -> PresenceCfg {
+        let mut out = self.clone();
+        if self.image_cycle.is_active() {
+            let frame = now.max(0) as u64 / self.image_cycle.interval().as_secs();
+            out.large_image = self.image_cycle.image(frame).map(str::to_string);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycle(random: bool) -> ImageCycle {
+        ImageCycle { images: ["a", "b", "c"].map(String::from).to_vec(), interval_secs: 10, random }
+    }
+
+    #[test]
+    fn goes_in_order_and_wraps() {
+        let c = cycle(false);
+        let shown: Vec<_> = (0..4).filter_map(|f| c.image(f)).collect();
+        assert_eq!(shown, ["a", "b", "c", "a"]);
+        let cfg = PresenceCfg { image_cycle: c, ..Default::default() };
+        assert_eq!(cfg.cycled(25).large_image.as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn random_order_shows_every_image_each_round() {
+        let c = cycle(true);
+        for round in 0..5 {
+            let mut shown: Vec<_> = (round * 3..round * 3 + 3).filter_map(|f| c.image(f)).collect();
+            shown.sort();
+            assert_eq!(shown, ["a", "b", "c"]);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Checks the activity Discord echoes back in its SET_ACTIVITY response against what was sent.
+//! Discord silently drops or rewrites fields it does not accept (buttons on some accounts, images
+//! it cannot load), so a presence can look different from the form with no error anywhere.
+//!
+//! Only what Discord is known to keep is compared: texts exactly, images and timestamps by
+//! presence (Discord replaces image keys and URLs with its own ids), buttons by label (Discord
+//! echoes only the labels).
+
+use serde_json::Value;
+
+/// Reported when Discord showed none of the buttons.
+pub const BUTTONS_DROPPED: &str = "Discord dropped the buttons.";
+
+/// One sentence per field Discord dropped or changed; empty when the echo matches or when the
+/// response carried no activity to compare.
+pub fn mismatches(sent: &Value, echoed: &Value) -> Vec<String> {
+    if !echoed.is_object() {
+        return Vec::new();
+    }
+    let mut out = Vec::new();
+    for (path, name) in [
+        (&["details"][..], "details"),
+        (&["state"], "state"),
+        (&["assets", "large_text"], "large image text"),
+        (&["assets", "small_text"], "small image text"),
+    ] {
+        match (str_at(sent, path), str_at(echoed, path)) {
+            (Some(_), None) => out.push(format!("Discord dropped the {}.", name)),
+            (Some(a), Some(b)) if a.trim() != b.trim() => out.push(format!("Discord changed the {} to \"{}\".", name, b)),
+            _ => {}
+        }
+    }
+    for (path, name) in [
+        (&["assets", "large_image"][..], "large image"),
+        (&["assets", "small_image"], "small image"),
+        (&["timestamps"], "timer"),
+    ] {
+        if at(sent, path).is_some() && at(echoed, path).is_none() {
+            out.push(format!("Discord dropped the {}.", name));
+        }
+    }
+    let sent_buttons = button_labels(sent);
+    let echoed_buttons = button_labels(echoed);
+    if !sent_buttons.is_empty() && echoed_buttons.is_empty() {
+        out.push(BUTTONS_DROPPED.to_string());
+    } else if sent_buttons.len() > echoed_buttons.len() {
+        out.push(format!("Discord kept {} of {} buttons.", echoed_buttons.len(), sent_buttons.len()));
+    }
+    out
+}
+
+fn at<'a>(v: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter().try_fold(v, |v, key| v.get(key)).filter(|v| !v.is_null())
+}
+
+fn str_at<'a>(v: &'a Value, path: &[&str]) -> Option<&'a str> {
+    at(v, path)?.as_str()
+}
+
+/// Button labels, whether sent as `{label, url}` objects or echoed as bare strings.
+fn button_labels(v: &Value) -> Vec<&str> {
+    let Some(buttons) = v.get("buttons").and_then(Value::as_array) else { return Vec::new() };
+    buttons.iter().filter_map(|b| b.as_str().or_else(|| b.get("label")?.as_str())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sent() -> Value {
+        json!({
+            "details": "Coding",
+            "state": "main.rs",
+            "timestamps": { "start": 1 },
+            "assets": { "large_image": "https://example.com/a.png", "large_text": "Rust" },
+            "buttons": [{ "label": "Repo", "url": "https://example.com" }, { "label": "Site", "url": "https://example.org" }]
+        })
+    }
+
+    #[test]
+    fn a_faithful_echo_has_no_mismatches() {
+        let echoed = json!({
+            "details": "Coding",
+            "state": "main.rs",
+            "timestamps": { "start": 1000 },
+            "assets": { "large_image": "mp:external/abc", "large_text": "Rust" },
+            "buttons": ["Repo", "Site"],
+            "type": 0
+        });
+        assert!(mismatches(&sent(), &echoed).is_empty());
+        assert!(mismatches(&sent(), &sent()).is_empty());
+        assert!(mismatches(&sent(), &Value::Null).is_empty(), "no echo, nothing to compare");
+    }
+
+    #[test]
+    fn dropped_and_changed_fields_are_reported() {
+        let echoed = json!({ "details": "Codin", "state": "main.rs", "buttons": ["Repo"], "assets": {} });
+        assert_eq!(
+            mismatches(&sent(), &echoed),
+            [
+                "Discord changed the details to \"Codin\".",
+                "Discord dropped the large image text.",
+                "Discord dropped the large image.",
+                "Discord dropped the timer.",
+                "Discord kept 1 of 2 buttons.",
+            ]
+        );
+        let no_buttons = json!({ "details": "Coding", "state": "main.rs" });
+        assert!(mismatches(&sent(), &no_buttons).contains(&BUTTONS_DROPPED.to_string()));
+    }
+}
@@ -0,0 +1,323 @@
+//! Helpers for the public Discord HTTP API (no token required, except for [`fetch_user_style`]).
+//!
+//! Every call goes through one shared client, so the proxy chosen with [`set_proxy`] applies to
+//! all of them; apps route their own requests through [`client`] for the same reason.
+//!
+//! App metadata and images are cached on disk once [`set_cache_dir`] is called: repeated lookups
+//! are answered locally, revalidated with their ETag when they expire, and still answered when
+//! Discord cannot be reached.
+
+use anyhow::Context;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+mod cache;
+
+/// How HTTP calls reach the internet.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Proxy {
+    /// `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` from the environment.
+    #[default]
+    System,
+    /// Connect directly, even when the environment names a proxy.
+    Direct,
+    /// An `http://`, `https://`, `socks5://` or `socks5h://` proxy URL; credentials go in as
+    /// `user:password@`.
+    Manual(String),
+}
+
+const PROXY_SCHEMES: [&str; 4] = ["http://", "https://", "socks5://", "socks5h://"];
+
+static CLIENT: RwLock<Option<Client>> = RwLock::new(None);
+static CACHE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+static CACHE_LIMIT: AtomicU64 = AtomicU64::new(DEFAULT_CACHE_LIMIT);
+
+/// Size the cache is trimmed to until [`set_cache_limit`] says otherwise.
+pub const DEFAULT_CACHE_LIMIT: u64 = 50 * 1024 * 1024;
+
+/// How long app metadata is reused before asking Discord again, unless the response says otherwise.
+const APP_META_TTL: i64 = 60 * 60;
+/// Icons and avatars are addressed by content hash, so they can be kept much longer.
+const IMAGE_TTL: i64 = 7 * 24 * 60 * 60;
+
+fn build_client(proxy: &Proxy) -> anyhow::Result<Client> {
+    let builder = Client::builder().user_agent(concat!("rpc-core/", env!("CARGO_PKG_VERSION")));
+    // With both backends compiled in, rustls wins: it behaves the same on every OS.
+    #[cfg(feature = "rustls")]
+    let builder = builder.use_rustls_tls();
+    let builder = match proxy {
+        Proxy::System => builder,
+        Proxy::Direct => builder.no_proxy(),
+        Proxy::Manual(url) => {
+            let url = url.trim();
+            if !PROXY_SCHEMES.iter().any(|s| url.starts_with(s)) {
+                anyhow::bail!("The proxy must be an http://, https://, socks5:// or socks5h:// URL.");
+            }
+            builder.proxy(reqwest::Proxy::all(url).context("Invalid proxy URL")?)
+        }
+    };
+    builder.build().context("Failed to set up the HTTP client")
+}
+
+/// Switches every later HTTP call to `proxy`. An invalid setting is refused and the previous one
+/// stays in effect.
+pub fn set_proxy(proxy: &Proxy) -> anyhow::Result<()> {
+    let client = build_client(proxy)?;
+    *CLIENT.write().unwrap_or_else(|e| e.into_inner()) = Some(client);
+    Ok(())
+}
+
+/// The shared client, set up from the environment until [`set_proxy`] is called. Cheap to clone.
+pub fn client() -> Client {
+    if let Some(c) = CLIENT.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return c.clone();
+    }
+    let mut slot = CLIENT.write().unwrap_or_else(|e| e.into_inner());
+    slot.get_or_insert_with(|| build_client(&Proxy::System).unwrap_or_else(|_| Client::new())).clone()
+}
+
+/// Caches responses in `dir` from now on; `None` turns caching off.
+pub fn set_cache_dir(dir: Option<PathBuf>) {
+    *CACHE_DIR.write().unwrap_or_else(|e| e.into_inner()) = dir;
+}
+
+fn cache_dir() -> Option<PathBuf> {
+    CACHE_DIR.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Caps the cache at `bytes`, deleting the least recently used entries now and after each download.
+pub fn set_cache_limit(bytes: u64) {
+    CACHE_LIMIT.store(bytes, Ordering::Relaxed);
+    if let Some(dir) = cache_dir() {
+        cache::prune(&dir, bytes);
+    }
+}
+
+/// Bytes the cache currently takes on disk.
+pub fn cache_usage() -> u64 {
+    cache_dir().map_or(0, |dir| cache::usage(&dir))
+}
+
+/// Empties the cache; the next lookups go to Discord again.
+pub fn clear_cache() -> anyhow::Result<()> {
+    match cache_dir() {
+        Some(dir) => cache::clear(&dir),
+        None => Ok(()),
+    }
+}
+
+fn cached_get(url: &str, ttl: i64, what: &str) -> anyhow::Result<Vec<u8>> {
+    cache::get(cache_dir().as_deref(), CACHE_LIMIT.load(Ordering::Relaxed), url, ttl, what)
+}
+
+/// Public name and icon of a Discord application.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppMeta {
+    pub name: String,
+    pub icon_hash: Option<String>,
+    pub icon_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcAppResp {
+    name: String,
+    icon: Option<String>,
+}
+
+fn app_meta_url(client_id: &str) -> String {
+    format!("https://discord.com/api/v10/oauth2/applications/{}/rpc", client_id)
+}
+
+/// Looks up an application through `/oauth2/applications/{id}/rpc`, from the cache while it is
+/// fresh. Blocking.
+pub fn fetch_app_meta(client_id: &str) -> anyhow::Result<AppMeta> {
+    let body = cached_get(&app_meta_url(client_id), APP_META_TTL, "app metadata")?;
+    parse_app_meta(client_id, &body)
+}
+
+/// The last metadata fetched for an application, without going to the network.
+pub fn cached_app_meta(client_id: &str) -> Option<AppMeta> {
+    let body = cache::cached(cache_dir().as_deref(), &app_meta_url(client_id))?;
+    parse_app_meta(client_id, &body).ok()
+}
+
+fn parse_app_meta(client_id: &str, body: &[u8]) -> anyhow::Result<AppMeta> {
+    let resp: RpcAppResp = serde_json::from_slice(body).context("Failed to decode response")?;
+    let icon_url = resp.icon.as_ref().map(|h| {
+        format!("https://cdn.discordapp.com/app-icons/{}/{}.png?size=256", client_id, h)
+    });
+    Ok(AppMeta { name: resp.name, icon_hash: resp.icon, icon_url })
+}
+
+#[derive(Debug, Deserialize)]
+struct AssetResp {
+    name: String,
+}
+
+/// Names of the art assets uploaded to an application, the keys `large_image` and `small_image`
+/// can refer to. Blocking.
+pub fn fetch_asset_names(client_id: &str) -> anyhow::Result<Vec<String>> {
+    let url = format!("https://discord.com/api/v10/oauth2/applications/{}/assets", client_id);
+    let assets = client()
+        .get(url)
+        .send()
+        .context("Failed to call Discord API")?
+        .error_for_status()
+        .context("HTTP error while fetching app assets")?
+        .json::<Vec<AssetResp>>()
+        .context("Failed to decode response")?;
+    Ok(assets.into_iter().map(|a| a.name).collect())
+}
+
+/// Profile banner and accent color of the user an OAuth token belongs to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserStyle {
+    /// Animated (`.gif`) when the banner is.
+    pub banner_url: Option<String>,
+    /// `0xRRGGBB`; Discord's fallback banner color when there is no banner image.
+    pub accent_color: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserResp {
+    id: String,
+    banner: Option<String>,
+    accent_color: Option<u32>,
+}
+
+impl UserResp {
+    fn style(self) -> UserStyle {
+        let banner_url = self.banner.map(|hash| {
+            let ext = if hash.starts_with("a_") { "gif" } else { "png" };
+            format!("https://cdn.discordapp.com/banners/{}/{}.{}?size=600", self.id, hash, ext)
+        });
+        UserStyle { banner_url, accent_color: self.accent_color }
+    }
+}
+
+/// Banner and accent color through `/users/@me`, which needs an OAuth2 access token with the
+/// `identify` scope (the RPC handshake does not include them). Not cached. Blocking.
+pub fn fetch_user_style(token: &str) -> anyhow::Result<UserStyle> {
+    let resp = client()
+        .get("https://discord.com/api/v10/users/@me")
+        .bearer_auth(token.trim())
+        .send()
+        .context("Failed to call Discord API")?;
+    if resp.status() == reqwest::StatusCode::UNAUTHORIZED {
+        anyhow::bail!("Discord refused the OAuth token; it may have expired.");
+    }
+    let user = resp
+        .error_for_status()
+        .context("HTTP error while fetching the user profile")?
+        .json::<UserResp>()
+        .context("Failed to decode response")?;
+    Ok(user.style())
+}
+
+/// Downloads an image such as an app icon or avatar, from the cache while it is fresh. Blocking.
+pub fn fetch_image(url: &str) -> anyhow::Result<Vec<u8>> {
+    cached_get(url, IMAGE_TTL, "the image")
+}
+
+/// A previously downloaded image, without going to the network.
+pub fn cached_image(url: &str) -> Option<Vec<u8>> {
+    cache::cached(cache_dir().as_deref(), url)
+}
+
+/// A server invite checked against `/invites/{code}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invite {
+    pub code: String,
+    pub guild_name: Option<String>,
+    /// ISO 8601 time the invite stops working; `None` for permanent invites.
+    pub expires_at: Option<String>,
+}
+
+impl Invite {
+    /// Canonical link for a button.
+    pub fn url(&self) -> String {
+        format!("https://discord.gg/{}", self.code)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct InviteResp {
+    code: String,
+    guild: Option<InviteGuild>,
+    expires_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InviteGuild {
+    name: String,
+}
+
+/// The invite code in a `discord.gg/<code>` or `discord.com/invite/<code>` link, or `input` itself
+/// when it is a bare code.
+pub fn invite_code(input: &str) -> Option<String> {
+    let s = input.trim();
+    let s = s.strip_prefix("https://").or_else(|| s.strip_prefix("http://")).unwrap_or(s);
+    let s = s.strip_prefix("www.").unwrap_or(s);
+    let code = ["discord.gg/", "discord.com/invite/", "discordapp.com/invite/"]
+        .iter()
+        .find_map(|p| s.strip_prefix(p))
+        .unwrap_or(s);
+    let code = code.split(['/', '?', '#']).next().unwrap_or_default();
+    (!code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')).then(|| code.to_string())
+}
+
+/// Checks an invite code or link through `/invites/{code}`, failing for unknown or expired
+/// invites. Blocking.
+pub fn fetch_invite(input: &str) -> anyhow::Result<Invite> {
+    let code = invite_code(input).ok_or_else(|| anyhow::anyhow!("Not a Discord invite code or link."))?;
+    let url = format!("https://discord.com/api/v10/invites/{}?with_expiration=true", code);
+    let resp = client().get(url).send().context("Failed to call Discord API")?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        anyhow::bail!("Invite \"{}\" is invalid or has expired.", code);
+    }
+    let resp = resp
+        .error_for_status()
+        .context("HTTP error while checking the invite")?
+        .json::<InviteResp>()
+        .context("Failed to decode response")?;
+    Ok(Invite { code: resp.code, guild_name: resp.guild.map(|g| g.name), expires_at: resp.expires_at })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_invite_codes_in_links() {
+        for input in ["abc-123", " https://discord.gg/abc-123 ", "discord.com/invite/abc-123?event=1", "https://www.discordapp.com/invite/abc-123/"] {
+            assert_eq!(invite_code(input).as_deref(), Some("abc-123"), "{}", input);
+        }
+        assert_eq!(invite_code("https://example.com/invite/x"), None);
+        assert_eq!(invite_code(""), None);
+    }
+
+    #[test]
+    fn banners_follow_the_avatar_url_scheme() {
+        let animated = UserResp { id: "1".to_string(), banner: Some("a_b".to_string()), accent_color: Some(0x5865f2) };
+        assert_eq!(
+            animated.style(),
+            UserStyle { banner_url: Some("https://cdn.discordapp.com/banners/1/a_b.gif?size=600".to_string()), accent_color: Some(0x5865f2) }
+        );
+        let plain = UserResp { id: "1".to_string(), banner: None, accent_color: None };
+        assert_eq!(plain.style(), UserStyle::default());
+    }
+
+    #[test]
+    fn manual_proxies_need_a_supported_scheme() {
+        for url in ["http://proxy.corp:3128", " https://user:pw@proxy.corp ", "socks5://127.0.0.1:1080", "socks5h://127.0.0.1:9050"] {
+            assert!(build_client(&Proxy::Manual(url.to_string())).is_ok(), "{}", url);
+        }
+        for url in ["proxy.corp:3128", "ftp://proxy.corp", ""] {
+            assert!(build_client(&Proxy::Manual(url.to_string())).is_err(), "{}", url);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! On-disk cache for GET responses, so looking up the same application or image again does not hit
+//! Discord and still works offline.
+//!
+//! Each URL gets `<hash>.json` (ETag, fetch time, lifetime) and `<hash>.body` in the directory set
+//! with [`super::set_cache_dir`]. Fresh entries are served as they are; stale ones are revalidated
+//! with `If-None-Match`, and served anyway when Discord cannot be reached. Once the files add up
+//! to more than the size limit, the least recently used entries are deleted.
+
+use anyhow::Context;
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Meta {
+    /// Guards against two URLs sharing a hash.
+    url: String,
+    etag: Option<String>,
+    /// Unix time of the last download or revalidation.
+    fetched_at: i64,
+    /// Seconds the body is served without asking again.
+    max_age: i64,
+    /// Unix time the entry was last served, for eviction.
+    #[serde(default)]
+    used_at: i64,
+}
+
+impl Meta {
+    fn is_fresh(&self, now: i64) -> bool {
+        now - self.fetched_at < self.max_age
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+/// FNV-1a of the URL, hex encoded: a file name that cannot contain path separators.
+fn key(url: &str) -> String {
+    let h = url.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+    format!("{:016x}", h)
+}
+
+fn paths(dir: &Path, url: &str) -> (PathBuf, PathBuf) {
+    let key = key(url);
+    (dir.join(format!("{}.json", key)), dir.join(format!("{}.body", key)))
+}
+
+fn load(dir: &Path, url: &str) -> Option<(Meta, Vec<u8>)> {
+    let (meta_path, body_path) = paths(dir, url);
+    let meta: Meta = serde_json::from_slice(&fs::read(meta_path).ok()?).ok()?;
+    if meta.url != url {
+        return None;
+    }
+    Some((meta, fs::read(body_path).ok()?))
+}
+
+/// Marks an entry as just served.
+fn touch(dir: &Path, meta: Meta, now: i64) {
+    store(dir, &Meta { used_at: now, ..meta }, None);
+}
+
+/// Best effort: a cache that cannot be written only costs a download next time.
+fn store(dir: &Path, meta: &Meta, body: Option<&[u8]>) {
+    let (meta_path, body_path) = paths(dir, &meta.url);
+    let _ = fs::create_dir_all(dir);
+    if let Some(body) = body {
+        if fs::write(body_path, body).is_err() {
+            return;
+        }
+    }
+    if let Ok(json) = serde_json::to_vec(meta) {
+        let _ = fs::write(meta_path, json);
+    }
+}
+
+/// Entries as `(last used, bytes, meta path, body path)`.
+fn entries(dir: &Path) -> Vec<(i64, u64, PathBuf, PathBuf)> {
+    let Ok(read) = fs::read_dir(dir) else { return Vec::new() };
+    read.flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|x| x == "json"))
+        .map(|meta_path| {
+            let body_path = meta_path.with_extension("body");
+            let size = |p: &Path| fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+            let used = fs::read(&meta_path)
+                .ok()
+                .and_then(|b| serde_json::from_slice::<Meta>(&b).ok())
+                .map_or(0, |m| m.used_at.max(m.fetched_at));
+            (used, size(&meta_path) + size(&body_path), meta_path, body_path)
+        })
+        .collect()
+}
+
+/// Bytes the cache takes on disk.
+pub(super) fn usage(dir: &Path) -> u64 {
+    entries(dir).iter().map(|e| e.1).sum()
+}
+
+/// Deletes the least recently used entries until the cache fits in `limit` bytes.
+pub(super) fn prune(dir: &Path, limit: u64) {
+    let mut entries = entries(dir);
+    let mut total: u64 = entries.iter().map(|e| e.1).sum();
+    entries.sort_by_key(|e| e.0);
+    for (_, size, meta_path, body_path) in entries {
+        if total <= limit {
+            break;
+        }
+        let _ = fs::remove_file(meta_path);
+        let _ = fs::remove_file(body_path);
+        total = total.saturating_sub(size);
+    }
+}
+
+/// Deletes every entry. Other files in the directory are left alone.
+pub(super) fn clear(dir: &Path) -> anyhow::Result<()> {
+    for (_, _, meta_path, body_path) in entries(dir) {
+        fs::remove_file(&meta_path).with_context(|| format!("Failed to delete {}", meta_path.display()))?;
+        let _ = fs::remove_file(body_path);
+    }
+    Ok(())
+}
+
+/// `max-age` from a `Cache-Control` header; `no-store`/`no-cache` count as zero.
+fn max_age(cache_control: &str) -> Option<i64> {
+    cache_control.split(',').map(str::trim).find_map(|d| match d {
+        "no-store" | "no-cache" => Some(0),
+        _ => d.strip_prefix("max-age=")?.parse().ok(),
+    })
+}
+
+/// Body stored for `url`, however old, without going to the network.
+pub(super) fn cached(dir: Option<&Path>, url: &str) -> Option<Vec<u8>> {
+    let dir = dir?;
+    let (meta, body) = load(dir, url)?;
+    touch(dir, meta, now());
+    Some(body)
+}
+
+/// GETs `url` through the cache, then trims the cache to `limit` bytes. `ttl` is the lifetime in
+/// seconds when the response does not set one. `what` names the resource in HTTP errors.
+pub(super) fn get(dir: Option<&Path>, limit: u64, url: &str, ttl: i64, what: &str) -> anyhow::Result<Vec<u8>> {
+    let now = now();
+    let entry = dir.and_then(|d| load(d, url));
+    if let (Some(dir), Some((meta, body))) = (dir, &entry) {
+        if meta.is_fresh(now) {
+            touch(dir, meta.clone(), now);
+            return Ok(body.clone());
+        }
+    }
+
+    let mut req = super::client().get(url);
+    if let Some(etag) = entry.as_ref().and_then(|(m, _)| m.etag.as_deref()) {
+        req = req.header(header::IF_NONE_MATCH, etag);
+    }
+    let resp = match req.send() {
+        Ok(resp) => resp,
+        Err(e) => match (dir, entry) {
+            (Some(dir), Some((meta, body))) => {
+                touch(dir, meta, now);
+                return Ok(body);
+            }
+            _ => return Err(e).context("Failed to call Discord API"),
+        },
+    };
+    let lifetime = resp
+        .headers()
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .and_then(max_age)
+        .unwrap_or(ttl);
+    let etag = resp.headers().get(header::ETAG).and_then(|v| v.to_str().ok()).map(str::to_string);
+
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        if let (Some(dir), Some((meta, body))) = (dir, entry) {
+            store(dir, &Meta { fetched_at: now, used_at: now, max_age: lifetime, etag: etag.or(meta.etag), ..meta }, None);
+            return Ok(body);
+        }
+    }
+    let body = resp
+        .error_for_status()
+        .with_context(|| format!("HTTP error while fetching {}", what))?
+        .bytes()
+        .context("Failed to read the response")?
+        .to_vec();
+    if let Some(dir) = dir {
+        store(dir, &Meta { url: url.to_string(), etag, fetched_at: now, max_age: lifetime, used_at: now }, Some(&body));
+        prune(dir, limit);
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_control_sets_the_lifetime() {
+        assert_eq!(max_age("public, max-age=3600"), Some(3600));
+        assert_eq!(max_age("no-store"), Some(0));
+        assert_eq!(max_age("private"), None);
+    }
+
+    #[test]
+    fn entries_round_trip_and_expire() {
+        let dir = std::env::temp_dir().join(format!("rpc-core-cache-{}", std::process::id()));
+        let url = "https://discord.com/api/v10/oauth2/applications/1/rpc";
+        let meta = Meta { url: url.to_string(), etag: Some("\"abc\"".to_string()), fetched_at: 100, max_age: 60, used_at: 100 };
+        store(&dir, &meta, Some(b"{}"));
+
+        let (loaded, body) = load(&dir, url).expect("stored entry");
+        assert_eq!((loaded.clone(), body), (meta, b"{}".to_vec()));
+        assert!(loaded.is_fresh(159));
+        assert!(!loaded.is_fresh(160));
+        assert_eq!(cached(Some(&dir), url), Some(b"{}".to_vec()));
+        assert!(load(&dir, url).unwrap().0.used_at > 100, "serving an entry marks it used");
+        assert_eq!(cached(Some(&dir), "https://example.com/other"), None);
+        assert_eq!(cached(None, url), None);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn least_recently_used_entries_go_first() {
+        let dir = std::env::temp_dir().join(format!("rpc-core-cache-lru-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (i, used_at) in [30, 10, 20].into_iter().enumerate() {
+            let meta = Meta { url: format!("https://cdn.example/{}", i), etag: None, fetched_at: 0, max_age: 0, used_at };
+            store(&dir, &meta, Some(&[0; 1000]));
+        }
+        let one = usage(&dir) / 3;
+        prune(&dir, one * 2);
+        assert!(load(&dir, "https://cdn.example/1").is_none(), "used longest ago");
+        assert!(load(&dir, "https://cdn.example/0").is_some() && load(&dir, "https://cdn.example/2").is_some());
+
+        clear(&dir).unwrap();
+        assert_eq!(usage(&dir), 0);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
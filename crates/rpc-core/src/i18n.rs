@@ -0,0 +1,166 @@
+//! Localized text for the errors rpc-core reports to users.
+//!
+//! The language is process-wide: apps call [`set_lang`] from their language setting, and every
+//! [`RpcError`] formatted afterwards uses it. Diagnostic details (raw Discord payloads, OS errors)
+//! are appended untranslated.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    En,
+    Pt,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 2] = [Lang::En, Lang::Pt];
+
+    /// Picks the language for a locale tag such as `pt-BR`, `pt_PT.UTF-8` or `en-US`; unknown tags
+    /// fall back to English.
+    pub fn from_tag(tag: &str) -> Self {
+        let primary = tag.split(['-', '_', '.']).next().unwrap_or_default();
+        if primary.eq_ignore_ascii_case("pt") { Lang::Pt } else { Lang::En }
+    }
+
+    /// Name of the language in itself, for language pickers.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Lang::En => "English",
+            Lang::Pt => "Português",
+        }
+    }
+}
+
+static LANG: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::SeqCst);
+}
+
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::SeqCst) {
+        1 => Lang::Pt,
+        _ => Lang::En,
+    }
+}
+
+/// What went wrong while talking to Discord. Displays in the current [`lang`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcError {
+    /// No `discord-ipc-N` socket/pipe could be opened.
+    DiscordNotFound,
+    /// No socket found, and the Flatpak this runs in lacks `--filesystem` permissions for them.
+    SandboxBlocked { app_id: String, missing: Vec<String> },
+    InvalidPath { path: String },
+    ConnectFailed { path: String },
+    /// The socket opened but the handshake could not be sent or answered.
+    HandshakeFailed,
+    /// Discord answered the handshake with an error, usually a wrong Client ID.
+    HandshakeRejected { detail: String },
+    /// Reading or writing the socket failed, or Discord closed it.
+    ConnectionLost,
+    ActivityRejected { detail: String },
+    /// Neither details nor state has at least 2 characters.
+    PresenceTooShort,
+}
+
+impl RpcError {
+    pub fn text(&self, lang: Lang) -> String {
+        use RpcError::*;
+        match (lang, self) {
+            (Lang::En, DiscordNotFound) if !crate::DISCORD_DESKTOP_AVAILABLE => {
+                "Could not find a Discord IPC socket. Discord has no desktop app for this CPU; run a client with arRPC, such as Vesktop, with Rich Presence turned on.".into()
+            }
+            (Lang::Pt, DiscordNotFound) if !crate::DISCORD_DESKTOP_AVAILABLE => {
+                "Não encontrei um socket IPC do Discord. O Discord não tem app desktop para este processador; use um cliente com arRPC, como o Vesktop, com a Rich Presence ativada.".into()
+            }
+            (Lang::En, DiscordNotFound) => "Could not find the Discord IPC socket. Is Discord Desktop running?".into(),
+            (Lang::Pt, DiscordNotFound) => "Não encontrei o socket IPC do Discord. O Discord Desktop está aberto?".into(),
+            (Lang::En, SandboxBlocked { app_id, missing }) => format!(
+                "Could not find the Discord IPC socket. This Flatpak is missing the permission to see it: {}. Grant it with `{}` and restart the app.",
+                missing.join(", "),
+                crate::packaging::flatpak_override_command(app_id, missing)
+            ),
+            (Lang::Pt, SandboxBlocked { app_id, missing }) => format!(
+                "Não encontrei o socket IPC do Discord. Falta a este Flatpak a permissão para vê-lo: {}. Conceda com `{}` e reinicie o app.",
+                missing.join(", "),
+                crate::packaging::flatpak_override_command(app_id, missing)
+            ),
+            (Lang::En, InvalidPath { path }) => format!("Invalid IPC path: {}", path),
+            (Lang::Pt, InvalidPath { path }) => format!("Caminho IPC inválido: {}", path),
+            (Lang::En, ConnectFailed { path }) => format!("Could not connect to IPC socket {}", path),
+            (Lang::Pt, ConnectFailed { path }) => format!("Não consegui conectar ao socket IPC {}", path),
+            (Lang::En, HandshakeFailed) => "The Discord handshake did not complete.".into(),
+            (Lang::Pt, HandshakeFailed) => "O handshake com o Discord não foi concluído.".into(),
+            (Lang::En, HandshakeRejected { detail }) => {
+                format!("Discord rejected the handshake; check the Client ID. ({})", detail)
+            }
+            (Lang::Pt, HandshakeRejected { detail }) => {
+                format!("O Discord recusou o handshake; confira o Client ID. ({})", detail)
+            }
+            (Lang::En, ConnectionLost) => "Lost the connection to Discord.".into(),
+            (Lang::Pt, ConnectionLost) => "A conexão com o Discord caiu.".into(),
+            (Lang::En, ActivityRejected { detail }) => format!("Discord rejected the activity: {}", detail),
+            (Lang::Pt, ActivityRejected { detail }) => format!("O Discord recusou a atividade: {}", detail),
+            (Lang::En, PresenceTooShort) => {
+                "Invalid presence: fill Details or State with at least 2 characters.".into()
+            }
+            (Lang::Pt, PresenceTooShort) => {
+                "Presence inválida: preencha Details ou State com pelo menos 2 caracteres.".into()
+            }
+        }
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text(lang()))
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+/// Prefix for [`RateLimited`](crate::RateLimited) errors.
+#[cfg(feature = "ipc")]
+pub(crate) fn rate_limited_prefix() -> &'static str {
+    match lang() {
+        Lang::En => "Rate limited by Discord",
+        Lang::Pt => "O Discord limitou as atualizações",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locale_tags_pick_the_language() {
+        assert_eq!(Lang::from_tag("pt-BR"), Lang::Pt);
+        assert_eq!(Lang::from_tag("pt_PT.UTF-8"), Lang::Pt);
+        assert_eq!(Lang::from_tag("en-US"), Lang::En);
+        assert_eq!(Lang::from_tag(""), Lang::En);
+        assert_eq!(Lang::from_tag("de"), Lang::En);
+    }
+
+    #[test]
+    fn every_error_is_translated() {
+        let all = [
+            RpcError::DiscordNotFound,
+            RpcError::SandboxBlocked { app_id: "a".into(), missing: vec!["p".into()] },
+            RpcError::InvalidPath { path: "p".into() },
+            RpcError::ConnectFailed { path: "p".into() },
+            RpcError::HandshakeFailed,
+            RpcError::HandshakeRejected { detail: "d".into() },
+            RpcError::ConnectionLost,
+            RpcError::ActivityRejected { detail: "d".into() },
+            RpcError::PresenceTooShort,
+        ];
+        for e in all {
+            assert_ne!(e.text(Lang::En), e.text(Lang::Pt), "{:?}", e);
+        }
+    }
+}
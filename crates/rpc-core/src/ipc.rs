@@ -0,0 +1,749 @@
+//! Discord IPC transport: socket discovery, framing and the [`DiscordRpcClient`].
+//!
+//! Frame headers are little-endian by protocol and encoded as such, so nothing here depends on the
+//! CPU: the same code serves x86_64 and ARM64 on every OS.
+
+use anyhow::Context;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    env, fmt,
+    io::{Read, Write},
+    io, process,
+    sync::RwLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+use interprocess::local_socket::prelude::LocalSocketStream;
+use interprocess::local_socket::traits::Stream;
+use interprocess::local_socket::{GenericFilePath, ToFsName};
+
+#[cfg(unix)]
+use std::path::Path;
+
+use crate::i18n::{self, RpcError};
+use crate::validate::{check_button_url, text_len, truncate, MAX_BUTTON_LABEL};
+use crate::{PresenceCfg, ReadyInfo, TimerPolicy, UserProfile};
+
+pub(crate) type IpcStream = LocalSocketStream;
+
+/// How long [`DiscordRpcClient::ping`] waits for the PONG. A Discord that is running but hung never
+/// closes the socket, so without a limit the ping would never return.
+pub const PING_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long dropping a client waits for Discord to acknowledge clearing the activity, so a hung
+/// Discord cannot hold up the dropping thread (or a panic unwinding through it).
+const DROP_TIMEOUT: Duration = Duration::from_secs(1);
+/// How often a read with a deadline checks for data.
+const POLL_EVERY: Duration = Duration::from_millis(5);
+
+/// Discord refused an activity because updates arrive too fast. The connection is still usable;
+/// wait (at least `retry_after`, when Discord says) before sending again.
+///
+/// Returned inside the `anyhow::Error` from [`DiscordRpcClient::set_activity`]; check with
+/// `err.downcast_ref::<RateLimited>()`.
+#[derive(Debug, Clone)]
+pub struct RateLimited {
+    pub retry_after: Option<Duration>,
+    pub message: String,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", i18n::rate_limited_prefix(), self.message)
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+impl RateLimited {
+    /// Recognizes rate-limit style ERROR payloads (`data.retry_after`, or a message saying so).
+    fn from_error_data(data: &serde_json::Value) -> Option<Self> {
+        let message = data.get("message").and_then(|m| m.as_str()).unwrap_or_default();
+        // Negative, NaN or out-of-range values do not count as a delay.
+        let retry_after = data.get("retry_after").and_then(|v| v.as_f64()).and_then(|s| Duration::try_from_secs_f64(s).ok());
+        let lower = message.to_ascii_lowercase();
+        let by_message = ["rate limit", "ratelimit", "rate-limit", "too many", "too fast"].iter().any(|k| lower.contains(k));
+        if retry_after.is_none() && !by_message {
+            return None;
+        }
+        Some(Self { retry_after, message: message.to_string() })
+    }
+}
+
+fn nonce() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(24)
+        .map(char::from)
+        .collect()
+}
+
+/// Largest frame body accepted in either direction. Real payloads are a few KiB; anything bigger
+/// means the stream is corrupted or not Discord.
+pub const MAX_FRAME_LEN: usize = 1 << 20;
+
+/// Why a frame could not be read. After any of these the stream is out of sync and the
+/// connection should be dropped.
+#[derive(Debug)]
+pub enum FrameError {
+    /// The peer closed the connection before sending a header.
+    Closed,
+    /// The stream ended inside the 8-byte header.
+    TruncatedHeader { got: usize },
+    /// The stream ended before the announced body length.
+    TruncatedBody { expected: usize, got: usize },
+    /// The header announced a negative length.
+    InvalidLength(i32),
+    /// The header announced a body larger than [`MAX_FRAME_LEN`].
+    TooLarge(usize),
+    /// The body is not valid JSON.
+    InvalidJson(serde_json::Error),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Closed => write!(f, "connection closed by Discord"),
+            FrameError::TruncatedHeader { got } => write!(f, "frame header cut off after {} of 8 bytes", got),
+            FrameError::TruncatedBody { expected, got } => {
+                write!(f, "frame body cut off after {} of {} bytes", got, expected)
+            }
+            FrameError::InvalidLength(len) => write!(f, "invalid frame length {}", len),
+            FrameError::TooLarge(len) => write!(f, "frame of {} bytes exceeds the {} byte limit", len, MAX_FRAME_LEN),
+            FrameError::InvalidJson(e) => write!(f, "frame body is not JSON: {}", e),
+            FrameError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FrameError::InvalidJson(e) => Some(e),
+            FrameError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<FrameError> for std::io::Error {
+    fn from(e: FrameError) -> Self {
+        match e {
+            FrameError::Io(e) => e,
+            FrameError::Closed | FrameError::TruncatedHeader { .. } | FrameError::TruncatedBody { .. } => {
+                std::io::Error::new(std::io::ErrorKind::UnexpectedEof, e)
+            }
+            other => std::io::Error::new(std::io::ErrorKind::InvalidData, other),
+        }
+    }
+}
+
+/// Which way a traced frame went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDir {
+    Sent,
+    Received,
+}
+
+/// Receives every frame sent or received by any client in this process: direction, opcode and
+/// JSON body. Bodies are passed unmasked; the tracer is responsible for redacting them.
+pub type FrameTracer = fn(FrameDir, i32, &serde_json::Value);
+
+static TRACER: RwLock<Option<FrameTracer>> = RwLock::new(None);
+
+/// Installs (or with `None` removes) the protocol tracer. Off by default.
+pub fn set_frame_tracer(tracer: Option<FrameTracer>) {
+    *TRACER.write().unwrap_or_else(|e| e.into_inner()) = tracer;
+}
+
+fn tracer() -> Option<FrameTracer> {
+    *TRACER.read().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(crate) fn send_frame(stream: &mut impl Write, opcode: i32, payload: &Value) -> std::io::Result<()> {
+    write_frame(stream, opcode, payload, &mut Vec::new())
+}
+
+/// Sends one frame, built in `buf` so a connection can reuse the same allocation for every frame.
+/// Header and body go out in a single write.
+pub(crate) fn write_frame(
+    stream: &mut impl Write,
+    opcode: i32,
+    payload: &impl Serialize,
+    buf: &mut Vec<u8>,
+) -> std::io::Result<()> {
+    if let Some(t) = tracer() {
+        t(FrameDir::Sent, opcode, &serde_json::to_value(payload)?);
+    }
+    buf.clear();
+    buf.extend_from_slice(&[0; 8]);
+    serde_json::to_writer(&mut *buf, payload)?;
+    let len = buf.len() - 8;
+    if len > MAX_FRAME_LEN {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, FrameError::TooLarge(len)));
+    }
+    buf[..4].copy_from_slice(&opcode.to_le_bytes());
+    buf[4..8].copy_from_slice(&(len as i32).to_le_bytes());
+    stream.write_all(buf)?;
+    stream.flush()
+}
+
+pub(crate) fn read_frame(stream: &mut impl Read) -> Result<(i32, Value), FrameError> {
+    read_frame_into(stream, &mut Vec::new())
+}
+
+/// Reads one frame, using `buf` for the body; see [`write_frame`].
+pub(crate) fn read_frame_into(stream: &mut impl Read, buf: &mut Vec<u8>) -> Result<(i32, Value), FrameError> {
+    let mut header = [0u8; 8];
+    match read_full(stream, &mut header)? {
+        0 => return Err(FrameError::Closed),
+        8 => {}
+        got => return Err(FrameError::TruncatedHeader { got }),
+    }
+
+    let opcode = i32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let len = i32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let len = usize::try_from(len).map_err(|_| FrameError::InvalidLength(len))?;
+    if len > MAX_FRAME_LEN {
+        return Err(FrameError::TooLarge(len));
+    }
+
+    buf.clear();
+    buf.resize(len, 0);
+    let got = read_full(stream, buf)?;
+    if got < len {
+        return Err(FrameError::TruncatedBody { expected: len, got });
+    }
+
+    let v = serde_json::from_slice(buf).map_err(FrameError::InvalidJson)?;
+    if let Some(t) = tracer() {
+        t(FrameDir::Received, opcode, &v);
+    }
+    Ok((opcode, v))
+}
+
+/// Reads from a non-blocking stream, waiting for data until `until`.
+struct Deadline<'a> {
+    stream: &'a mut IpcStream,
+    until: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.stream.read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    if Instant::now() >= self.until {
+                        return Err(io::Error::new(io::ErrorKind::TimedOut, "Discord did not answer in time"));
+                    }
+                    thread::sleep(POLL_EVERY);
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+/// Like `read_exact`, but reports how much was read when the stream ends early.
+fn read_full(stream: &mut impl Read, buf: &mut [u8]) -> Result<usize, FrameError> {
+    let mut got = 0;
+    while got < buf.len() {
+        match stream.read(&mut buf[got..]) {
+            Ok(0) => break,
+            Ok(n) => got += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(FrameError::Io(e)),
+        }
+    }
+    Ok(got)
+}
+
+/// Where sandboxed clients put their socket, under the runtime directory, and what to call them.
+/// Vesktop serves the same protocol through its built-in arRPC, so it needs nothing else.
+#[cfg(unix)]
+const SANDBOXES: &[(&str, &str)] = &[
+    ("app/com.discordapp.Discord", "Discord (Flatpak)"),
+    ("app/com.discordapp.DiscordCanary", "Discord Canary (Flatpak)"),
+    ("snap.discord", "Discord (Snap)"),
+    ("snap.discord-canary", "Discord Canary (Snap)"),
+    (".flatpak/dev.vencord.Vesktop/xdg-run", "Vesktop (Flatpak)"),
+];
+
+#[cfg(unix)]
+fn ipc_candidates() -> Vec<String> {
+    let uid = unsafe { libc::geteuid() };
+
+    // The order Discord and arRPC pick their directory in; macOS only has TMPDIR.
+    let mut bases: Vec<String> = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .map(|dir| dir.trim_end_matches('/').to_string())
+        .filter(|dir| !dir.is_empty())
+        .collect();
+    bases.push(format!("/run/user/{}", uid));
+    bases.push("/tmp".to_string());
+    let mut seen = std::collections::HashSet::new();
+    bases.retain(|b| seen.insert(b.clone()));
+
+    let mut out = Vec::new();
+    for base in &bases {
+        let dirs = std::iter::once(base.clone()).chain(SANDBOXES.iter().map(|(sub, _)| format!("{}/{}", base, sub)));
+        for dir in dirs {
+            for i in 0..10 {
+                let p = format!("{}/discord-ipc-{}", dir, i);
+                if Path::new(&p).exists() {
+                    out.push(p);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Which client a socket found by discovery belongs to, when its location says so, e.g.
+/// `Vesktop (Flatpak)`. Clients outside a sandbox all use the same names and return `None`.
+pub fn ipc_client_label(path: &str) -> Option<&'static str> {
+    #[cfg(unix)]
+    {
+        let dir = Path::new(path).parent()?;
+        SANDBOXES.iter().find(|(sub, _)| dir.ends_with(sub)).map(|(_, label)| *label)
+    }
+    #[cfg(windows)]
+    {
+        let _ = path;
+        None
+    }
+}
+
+#[cfg(windows)]
+fn ipc_candidates() -> Vec<String> {
+    (0..10)
+        .map(|i| format!(r"\\.\pipe\discord-ipc-{}", i))
+        .collect()
+}
+
+/// Environment variable that replaces discovery with the one socket/pipe it names, for setups the
+/// candidate list cannot know (containers with a bind-mounted socket, custom sandboxes).
+pub const IPC_PATH_ENV: &str = "DISCORD_IPC_PATH";
+
+fn candidates() -> Vec<String> {
+    match env::var(IPC_PATH_ENV) {
+        Ok(path) if !path.trim().is_empty() => vec![path.trim().to_string()],
+        _ => ipc_candidates(),
+    }
+}
+
+/// Socket/pipe paths of the Discord clients running right now (Stable, PTB, Canary or separate
+/// installs each get their own `discord-ipc-N`), in the order discovery tries them. Only the path
+/// in [`IPC_PATH_ENV`] when that is set.
+pub fn discover_ipc_paths() -> Vec<String> {
+    candidates().into_iter().filter(|p| std::path::Path::new(p).exists()).collect()
+}
+
+/// Expands `$NAME` and `${NAME}` in a configured socket/pipe path and checks that it has this OS's
+/// form: an absolute path on Linux and macOS, `\\.\pipe\<name>` on Windows.
+pub fn resolve_ipc_path(setting: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = setting.trim();
+    while let Some(at) = rest.find('$') {
+        out.push_str(&rest[..at]);
+        let after = &rest[at + 1..];
+        let (name, tail) = match after.strip_prefix('{') {
+            Some(braced) => braced.split_once('}').ok_or_else(|| format!("Unclosed ${{ in {}", setting.trim()))?,
+            None => after.split_at(after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len())),
+        };
+        if name.is_empty() {
+            return Err("Expected a variable name after $".to_string());
+        }
+        out.push_str(&env::var(name).map_err(|_| format!("${} is not set", name))?);
+        rest = tail;
+    }
+    out.push_str(rest);
+
+    #[cfg(windows)]
+    if !(out.len() > r"\\.\pipe\".len() && out.to_ascii_lowercase().starts_with(r"\\.\pipe\")) {
+        return Err(format!(r"Not a pipe name: {} (expected \\.\pipe\discord-ipc-0 or similar)", out));
+    }
+    #[cfg(unix)]
+    if !Path::new(&out).is_absolute() {
+        return Err(format!("Not an absolute path: {}", out));
+    }
+    Ok(out)
+}
+
+/// Returns the stream together with the socket/pipe path it was opened on.
+fn connect_ipc() -> anyhow::Result<(IpcStream, String)> {
+    for name in candidates() {
+        let Ok(n) = name.as_str().to_fs_name::<GenericFilePath>() else {
+            continue;
+        };
+        if let Ok(s) = LocalSocketStream::connect(n) {
+            return Ok((s, name));
+        }
+    }
+    if let crate::packaging::Packaging::Flatpak { app_id } = crate::packaging::detect() {
+        let missing = crate::packaging::missing_flatpak_permissions();
+        if !missing.is_empty() {
+            return Err(RpcError::SandboxBlocked { app_id, missing: missing.into_iter().map(String::from).collect() }.into());
+        }
+    }
+    Err(RpcError::DiscordNotFound.into())
+}
+
+fn connect_ipc_at(path: &str) -> anyhow::Result<IpcStream> {
+    let name = path
+        .to_fs_name::<GenericFilePath>()
+        .with_context(|| RpcError::InvalidPath { path: path.to_string() })?;
+    LocalSocketStream::connect(name).with_context(|| RpcError::ConnectFailed { path: path.to_string() })
+}
+
+/// A connection to the Discord desktop client that has completed the handshake.
+///
+/// Dropping it clears any activity it published and sends CLOSE (both best effort), so a lost
+/// client never leaves a ghost presence behind.
+pub struct DiscordRpcClient {
+    stream: IpcStream,
+    pid: i64,
+    pipe: String,
+    /// An activity was sent and not cleared since.
+    has_activity: bool,
+    /// What Discord dropped or changed in the last acknowledged activity.
+    echo_mismatches: Vec<String>,
+    /// Frame buffer reused for every request and reply on this connection.
+    frame: Vec<u8>,
+}
+
+/// A command frame. Borrows the activity, so sending it does not copy the activity into a new
+/// JSON tree.
+#[derive(Serialize)]
+struct Command<'a> {
+    cmd: &'static str,
+    args: ActivityArgs<'a>,
+    nonce: String,
+}
+
+#[derive(Serialize)]
+struct ActivityArgs<'a> {
+    pid: i64,
+    activity: &'a Value,
+}
+
+impl<'a> Command<'a> {
+    fn set_activity(pid: i64, activity: &'a Value) -> Self {
+        Command { cmd: "SET_ACTIVITY", args: ActivityArgs { pid, activity }, nonce: nonce() }
+    }
+}
+
+impl DiscordRpcClient {
+    /// Finds the first reachable `discord-ipc-N` socket/pipe and performs the handshake for `client_id`.
+    pub fn connect_and_handshake(client_id: &str) -> anyhow::Result<(Self, ReadyInfo)> {
+        let (stream, pipe) = connect_ipc()?;
+        Self::handshake(stream, pipe, client_id)
+    }
+
+    /// Same as [`connect_and_handshake`](Self::connect_and_handshake), but skips discovery and
+    /// connects to an explicit socket/pipe path (e.g. a mock server).
+    pub fn connect_and_handshake_at(path: &str, client_id: &str) -> anyhow::Result<(Self, ReadyInfo)> {
+        let stream = connect_ipc_at(path)?;
+        Self::handshake(stream, path.to_string(), client_id)
+    }
+
+    fn handshake(mut stream: IpcStream, pipe: String, client_id: &str) -> anyhow::Result<(Self, ReadyInfo)> {
+        let hs = json!({ "v": 1, "client_id": client_id });
+        send_frame(&mut stream, 0, &hs).context(RpcError::HandshakeFailed)?;
+
+        let (op, hs_resp) = read_frame(&mut stream).context(RpcError::HandshakeFailed)?;
+        // Discord answers an invalid handshake with a CLOSE frame (opcode 2) instead of an ERROR event.
+        if op == 2 || hs_resp.get("evt").and_then(|v| v.as_str()) == Some("ERROR") {
+            return Err(RpcError::HandshakeRejected { detail: hs_resp.to_string() }.into());
+        }
+        let ready = ReadyInfo::from_payload(&hs_resp)?;
+
+        Ok((
+            Self {
+                stream,
+                pid: process::id() as i64,
+                pipe,
+                has_activity: false,
+                echo_mismatches: Vec::new(),
+                frame: Vec::new(),
+            },
+            ready,
+        ))
+    }
+
+    /// Process id sent with every activity. Defaults to this process; Discord links the activity to
+    /// that process, e.g. for its "game detected" handling.
+    pub fn pid(&self) -> i64 {
+        self.pid
+    }
+
+    /// Reports the activity on behalf of another process (e.g. a watched game) from now on.
+    pub fn set_pid(&mut self, pid: i64) {
+        self.pid = pid;
+    }
+
+    /// Socket/pipe path this client is connected through.
+    pub fn pipe(&self) -> &str {
+        &self.pipe
+    }
+
+    /// Fields Discord dropped or changed in the last activity it acknowledged, compared against what
+    /// was sent (see [`echo`](crate::echo)). Empty when it showed the activity as sent.
+    pub fn echo_mismatches(&self) -> &[String] {
+        &self.echo_mismatches
+    }
+
+    /// Publishes `cfg` and waits for Discord's acknowledgement.
+    ///
+    /// Fails if neither details nor state has at least 2 characters. Buttons without a label or an
+    /// http(s) URL are dropped, and `http://` links are upgraded to `https://`.
+    pub fn set_activity(&mut self, cfg: &PresenceCfg, start_ts: i64) -> anyhow::Result<()> {
+        let details_ok = text_len(cfg.details.trim()) >= 2;
+        let state_ok = text_len(cfg.state.trim()) >= 2;
+        if !details_ok && !state_ok {
+            return Err(RpcError::PresenceTooShort.into());
+        }
+
+        let mut activity_map = serde_json::Map::new();
+        if details_ok {
+            activity_map.insert("details".into(), json!(cfg.details));
+        }
+        if state_ok {
+            activity_map.insert("state".into(), json!(cfg.state));
+        }
+
+        let mut activity = json!(activity_map);
+
+        if cfg.with_timestamp {
+            activity["timestamps"] = match cfg.timer {
+                TimerPolicy::Fixed { start } => json!({ "start": start }),
+                TimerPolicy::End { end } => json!({ "end": end }),
+                TimerPolicy::Continue | TimerPolicy::Reset => json!({ "start": start_ts }),
+            };
+        }
+
+        let has_assets =
+            cfg.large_image.is_some() || cfg.small_image.is_some() || cfg.large_text.is_some() || cfg.small_text.is_some();
+
+        if has_assets {
+            let mut assets = serde_json::Map::new();
+            if let Some(v) = &cfg.large_image {
+                assets.insert("large_image".into(), json!(v));
+            }
+            if let Some(v) = &cfg.large_text {
+                assets.insert("large_text".into(), json!(v));
+            }
+            if let Some(v) = &cfg.small_image {
+                assets.insert("small_image".into(), json!(v));
+            }
+            if let Some(v) = &cfg.small_text {
+                assets.insert("small_text".into(), json!(v));
+            }
+            activity["assets"] = json!(assets);
+        }
+
+        let mut buttons = Vec::new();
+        for b in cfg.buttons.iter().take(2) {
+            let label = b.label.trim();
+            let mut url = b.url.trim().to_string();
+
+            if label.is_empty() || url.is_empty() {
+                continue;
+            }
+
+            url.retain(|c| !c.is_whitespace());
+
+            if url.starts_with("http://") {
+                url = url.replacen("http://", "https://", 1);
+            }
+
+            // Templates are rendered by now, so this is where an expanded URL that is not a link
+            // gets dropped instead of failing the whole activity.
+            if !url.starts_with("https://") || check_button_url(&url).is_err() {
+                continue;
+            }
+
+            let safe_label = truncate(label, MAX_BUTTON_LABEL);
+            buttons.push(json!({ "label": safe_label, "url": url }));
+        }
+
+        if !buttons.is_empty() {
+            activity["buttons"] = json!(buttons);
+        }
+
+        let command = Command::set_activity(self.pid, &activity);
+        write_frame(&mut self.stream, 1, &command, &mut self.frame).context(RpcError::ConnectionLost)?;
+        // Even a rejected SET_ACTIVITY may have replaced the previous one, so clear on drop either way.
+        self.has_activity = true;
+
+        let (_op2, resp) = read_frame_into(&mut self.stream, &mut self.frame).context(RpcError::ConnectionLost)?;
+        if resp.get("evt").and_then(|v| v.as_str()) == Some("ERROR") {
+            let data = resp.get("data").cloned().unwrap_or_default();
+            if let Some(rl) = RateLimited::from_error_data(&data) {
+                return Err(rl.into());
+            }
+            return Err(RpcError::ActivityRejected { detail: resp.to_string() }.into());
+        }
+        self.echo_mismatches = crate::echo::mismatches(&activity, resp.get("data").unwrap_or(&serde_json::Value::Null));
+
+        Ok(())
+    }
+
+    /// Sends a PING frame and waits for the matching PONG, returning the round-trip time.
+    ///
+    /// A cheap way to tell whether the connection is still alive without touching the activity. No
+    /// PONG within [`PING_TIMEOUT`] counts as a lost connection.
+    pub fn ping(&mut self) -> anyhow::Result<Duration> {
+        let payload = json!({ "nonce": nonce() });
+        let started = Instant::now();
+        write_frame(&mut self.stream, 3, &payload, &mut self.frame).context(RpcError::ConnectionLost)?;
+        let (op, resp) = self.read_within(PING_TIMEOUT).context(RpcError::ConnectionLost)?;
+        match op {
+            4 if resp.get("nonce") == payload.get("nonce") => Ok(started.elapsed()),
+            4 => Err(anyhow::anyhow!("PONG nonce does not match the PING")),
+            2 => Err(anyhow::anyhow!("CLOSE: {}", resp).context(RpcError::ConnectionLost)),
+            _ => Err(anyhow::anyhow!("Expected PONG, got opcode {}: {}", op, resp)),
+        }
+    }
+
+    /// Reads the next frame, failing once `timeout` passes without one. The stream is out of sync
+    /// after a timeout, like after any other read error.
+    fn read_within(&mut self, timeout: Duration) -> Result<(i32, Value), FrameError> {
+        self.stream.set_nonblocking(true).map_err(FrameError::Io)?;
+        let res = read_frame_into(&mut Deadline { stream: &mut self.stream, until: Instant::now() + timeout }, &mut self.frame);
+        self.stream.set_nonblocking(false).map_err(FrameError::Io)?;
+        res
+    }
+
+    /// Removes the activity while keeping the connection open.
+    pub fn clear_activity(&mut self) -> anyhow::Result<()> {
+        self.clear_within(None)
+    }
+
+    /// Sends the clear and waits for its acknowledgement, at most `timeout` if given.
+    fn clear_within(&mut self, timeout: Option<Duration>) -> anyhow::Result<()> {
+        let command = Command::set_activity(self.pid, &Value::Null);
+        write_frame(&mut self.stream, 1, &command, &mut self.frame).context(RpcError::ConnectionLost)?;
+        self.has_activity = false;
+        let _ = match timeout {
+            Some(t) => self.read_within(t),
+            None => read_frame_into(&mut self.stream, &mut self.frame),
+        };
+        Ok(())
+    }
+}
+
+impl Drop for DiscordRpcClient {
+    fn drop(&mut self) {
+        if self.has_activity {
+            let _ = self.clear_within(Some(DROP_TIMEOUT));
+        }
+        let _ = send_frame(&mut self.stream, 2, &json!({}));
+    }
+}
+
+/// Connects just long enough to read the logged-in user from the handshake.
+pub fn get_user_profile_via_handshake(client_id: &str) -> anyhow::Result<UserProfile> {
+    let (_client, ready) = DiscordRpcClient::connect_and_handshake(client_id)?;
+    Ok(ready.user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(op: i32, len: i32, body: &[u8]) -> Vec<u8> {
+        let mut v = op.to_le_bytes().to_vec();
+        v.extend_from_slice(&len.to_le_bytes());
+        v.extend_from_slice(body);
+        v
+    }
+
+    #[test]
+    fn round_trips_a_frame() {
+        let mut buf = Vec::new();
+        send_frame(&mut buf, 1, &json!({ "cmd": "SET_ACTIVITY" })).unwrap();
+        let (op, v) = read_frame(&mut buf.as_slice()).unwrap();
+        assert_eq!(op, 1);
+        assert_eq!(v["cmd"], "SET_ACTIVITY");
+    }
+
+    #[test]
+    fn frames_reuse_the_buffer() {
+        let mut out = Vec::new();
+        let mut buf = Vec::new();
+        let activity = json!({ "details": "x".repeat(200) });
+        write_frame(&mut out, 1, &Command::set_activity(7, &activity), &mut buf).unwrap();
+        let grown = buf.capacity();
+        write_frame(&mut out, 1, &Command::set_activity(7, &Value::Null), &mut buf).unwrap();
+        assert_eq!(buf.capacity(), grown);
+
+        let mut stream = out.as_slice();
+        let (op, first) = read_frame_into(&mut stream, &mut buf).unwrap();
+        assert_eq!(op, 1);
+        assert_eq!(first["cmd"], "SET_ACTIVITY");
+        assert_eq!(first["args"]["pid"], 7);
+        assert_eq!(first["args"]["activity"], activity);
+        let (_, second) = read_frame_into(&mut stream, &mut buf).unwrap();
+        assert!(second["args"]["activity"].is_null());
+        assert_eq!(first["nonce"].as_str().map(str::len), Some(24));
+    }
+
+    #[test]
+    fn reads_rate_limit_delays() {
+        let asked = RateLimited::from_error_data(&json!({ "message": "x", "retry_after": 1.5 })).unwrap();
+        assert_eq!(asked.retry_after, Some(Duration::from_millis(1500)));
+        let huge = RateLimited::from_error_data(&json!({ "message": "rate limited", "retry_after": 1e30 })).unwrap();
+        assert_eq!(huge.retry_after, None);
+        assert!(RateLimited::from_error_data(&json!({ "message": "x", "retry_after": -1.0 })).is_none());
+    }
+
+    #[test]
+    fn rejects_bad_lengths() {
+        let neg = frame(1, -5, b"");
+        assert!(matches!(read_frame(&mut neg.as_slice()), Err(FrameError::InvalidLength(-5))));
+        let huge = frame(1, i32::MAX, b"");
+        assert!(matches!(read_frame(&mut huge.as_slice()), Err(FrameError::TooLarge(_))));
+    }
+
+    #[test]
+    fn reports_truncation() {
+        assert!(matches!(read_frame(&mut &[][..]), Err(FrameError::Closed)));
+        assert!(matches!(read_frame(&mut &[1u8, 0, 0][..]), Err(FrameError::TruncatedHeader { got: 3 })));
+        let short = frame(1, 10, b"{}");
+        assert!(matches!(
+            read_frame(&mut short.as_slice()),
+            Err(FrameError::TruncatedBody { expected: 10, got: 2 })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sandboxed_sockets_are_labelled() {
+        let vesktop = "/run/user/1000/.flatpak/dev.vencord.Vesktop/xdg-run/discord-ipc-0";
+        assert_eq!(ipc_client_label(vesktop), Some("Vesktop (Flatpak)"));
+        assert_eq!(ipc_client_label("/run/user/1000/snap.discord/discord-ipc-1"), Some("Discord (Snap)"));
+        assert_eq!(ipc_client_label("/run/user/1000/discord-ipc-0"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn configured_paths_expand_variables() {
+        std::env::set_var("CRP_TEST_RUNTIME", "/run/user/1000");
+        assert_eq!(resolve_ipc_path(" $CRP_TEST_RUNTIME/discord-ipc-0 ").unwrap(), "/run/user/1000/discord-ipc-0");
+        assert_eq!(resolve_ipc_path("${CRP_TEST_RUNTIME}_x/s").unwrap(), "/run/user/1000_x/s");
+        assert_eq!(resolve_ipc_path("$CRP_TEST_UNSET/s").unwrap_err(), "$CRP_TEST_UNSET is not set");
+        assert!(resolve_ipc_path("${CRP_TEST_RUNTIME/s").unwrap_err().starts_with("Unclosed"));
+        assert!(resolve_ipc_path("discord-ipc-0").unwrap_err().starts_with("Not an absolute path"));
+    }
+
+    #[test]
+    fn rejects_non_json() {
+        let bad = frame(1, 3, b"abc");
+        assert!(matches!(read_frame(&mut bad.as_slice()), Err(FrameError::InvalidJson(_))));
+    }
+}
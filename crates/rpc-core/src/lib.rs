@@ -1,121 +1,87 @@
-use anyhow::Context;
-use rand::{distributions::Alphanumeric, Rng};
+//! Discord Rich Presence over the local IPC socket, without the deprecated discord-rpc SDK.
+//!
+//! # Features
+//!
+//! - `ipc` (default): socket discovery, [`DiscordRpcClient`] and the background
+//!   [`managed::ManagedClient`] worker.
+//! - `http`: helpers that call the public Discord HTTP API, such as `http::fetch_app_meta`. Pick
+//!   the TLS backend with `rustls` (pure Rust, no OpenSSL) or `native-tls` (the OS stack); either
+//!   one turns `http` on.
+//! - `mock-discord`: a fake IPC endpoint (`mock::MockDiscord`) for tests and UI work.
+//!
+//! Error messages follow the language set with [`i18n::set_lang`] (English by default).
+//!
+//! The data types, the [`control`] protocol, [`cycle`], [`i18n`], [`marquee`], [`packaging`], [`progress`], [`ratelimit`], [`session`] files, [`shutdown`], [`template`] and [`validate`] need no features, so they
+//! also build for `wasm32-unknown-unknown` with `default-features = false`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rpc_core::{DiscordRpcClient, PresenceBuilder};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let cfg = PresenceBuilder::new("123456789012345678")
+//!     .details("Editing main.rs")
+//!     .state("Workspace: rpc-core")
+//!     .large_image("logo", Some("Custom Rich Presence"))
+//!     .button("Source", "https://github.com/Watashi00/DiscordCustomPresence")
+//!     .timestamp(true)
+//!     .build();
+//!
+//! let (mut client, ready) = DiscordRpcClient::connect_and_handshake(&cfg.client_id)?;
+//! println!("connected as {}", ready.user.username);
+//! client.set_activity(&cfg, rpc_core::now_unix_ts())?;
+//! # Ok(())
+//! # }
+//! ```
+
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::{
-    io::{Read, Write},
-    process,
-    time::{SystemTime, UNIX_EPOCH},
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod control;
+pub mod cycle;
+pub mod echo;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(all(feature = "http", not(any(feature = "rustls", feature = "native-tls"))))]
+compile_error!("the `http` feature needs a TLS backend: enable `rustls` or `native-tls`");
+pub mod i18n;
+#[cfg(feature = "ipc")]
+mod ipc;
+#[cfg(feature = "ipc")]
+pub mod managed;
+pub mod marquee;
+#[cfg(feature = "mock-discord")]
+pub mod mock;
+pub mod packaging;
+pub mod progress;
+pub mod ratelimit;
+pub mod session;
+pub mod shutdown;
+pub mod template;
+pub mod validate;
+
+#[cfg(feature = "ipc")]
+pub use ipc::{
+    discover_ipc_paths, get_user_profile_via_handshake, ipc_client_label, resolve_ipc_path, set_frame_tracer, DiscordRpcClient,
+    FrameDir, FrameError, FrameTracer, RateLimited, IPC_PATH_ENV, MAX_FRAME_LEN, PING_TIMEOUT,
 };
 
-use interprocess::local_socket::prelude::LocalSocketStream;
-use interprocess::local_socket::traits::Stream;
-use interprocess::local_socket::{GenericFilePath, ToFsName};
-
-#[cfg(unix)]
-use std::env;
-
-#[cfg(unix)]
-use std::path::Path;
-
-#[cfg(unix)]
-use libc;
-
-type IpcStream = LocalSocketStream;
-
-fn now_unix() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64
-}
-
-fn nonce() -> String {
-    rand::thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(24)
-        .map(char::from)
-        .collect()
-}
-
-fn send_frame(stream: &mut IpcStream, opcode: i32, payload: &serde_json::Value) -> std::io::Result<()> {
-    let bytes = payload.to_string().into_bytes();
-    let mut header = Vec::with_capacity(8);
-    header.extend_from_slice(&opcode.to_le_bytes());
-    header.extend_from_slice(&(bytes.len() as i32).to_le_bytes());
-    stream.write_all(&header)?;
-    stream.write_all(&bytes)?;
-    stream.flush()?;
-    Ok(())
-}
-
-fn read_frame(stream: &mut IpcStream) -> std::io::Result<(i32, serde_json::Value)> {
-    let mut header = [0u8; 8];
-    stream.read_exact(&mut header)?;
-
-    let opcode = i32::from_le_bytes([header[0], header[1], header[2], header[3]]);
-    let len = i32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
-
-    let mut buf = vec![0u8; len];
-    stream.read_exact(&mut buf)?;
-
-    let v: serde_json::Value =
-        serde_json::from_slice(&buf).unwrap_or_else(|_| json!({"_raw": String::from_utf8_lossy(&buf)}));
-    Ok((opcode, v))
-}
-
-#[cfg(unix)]
-fn ipc_candidates() -> Vec<String> {
-    let uid = unsafe { libc::geteuid() };
-    let xdg = env::var("XDG_RUNTIME_DIR").ok();
-
-    let mut bases = vec![];
-    if let Some(x) = xdg {
-        bases.push(x);
-    }
-    bases.push(format!("/run/user/{}", uid));
-    bases.push("/tmp".to_string());
-
-    let mut out = Vec::new();
-    for base in bases {
-        for i in 0..10 {
-            let p = format!("{}/discord-ipc-{}", base, i);
-            if Path::new(&p).exists() {
-                out.push(p);
-            }
-        }
-    }
-    out
-}
-
-#[cfg(windows)]
-fn ipc_candidates() -> Vec<String> {
-    (0..10)
-        .map(|i| format!(r"\\.\pipe\discord-ipc-{}", i))
-        .collect()
-}
-
-fn connect_ipc() -> anyhow::Result<IpcStream> {
-    for name in ipc_candidates() {
-        let Ok(n) = name.to_fs_name::<GenericFilePath>() else {
-            continue;
-        };
-        if let Ok(s) = LocalSocketStream::connect(n) {
-            return Ok(s);
-        }
-    }
-    Err(anyhow::anyhow!(
-        "Could not find the Discord IPC socket. Is Discord Desktop running?"
-    ))
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A link button shown under the activity.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ButtonCfg {
     pub label: String,
     pub url: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Everything needed to publish one activity. See [`PresenceBuilder`] for a fluent way to build it.
+///
+/// Missing fields deserialize as empty/unset.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PresenceCfg {
     pub client_id: String,
     pub details: String,
@@ -128,168 +94,341 @@ pub struct PresenceCfg {
 
     pub buttons: Vec<ButtonCfg>,
     pub with_timestamp: bool,
+    /// What the timer shows when `with_timestamp` is on.
+    pub timer: TimerPolicy,
+    /// How often the [`managed::ManagedClient`](crate::managed) worker re-sends this presence.
+    pub refresh: Refresh,
+    /// Scrolls long details and state text; see [`marquee`].
+    pub marquee: marquee::Marquee,
+    /// Large images to rotate through instead of `large_image`; see [`cycle`].
+    pub image_cycle: cycle::ImageCycle,
 }
 
+/// Re-send behaviour of the managed worker. Static presences can refresh rarely to keep IPC
+/// traffic down; ones whose text changes often want the faster end.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Refresh {
+    /// Seconds between re-sends once the presence is active. Clamped to
+    /// [`MIN_KEEPALIVE_SECS`](Self::MIN_KEEPALIVE_SECS)..=[`MAX_KEEPALIVE_SECS`](Self::MAX_KEEPALIVE_SECS).
+    pub keepalive_secs: u32,
+    /// Re-send a few times right after connecting, since Discord sometimes drops the first
+    /// activity of a session. Off sends once and goes straight to the keepalive.
+    pub burst: bool,
+    /// Seconds between re-renders of built-in template variables such as `{time}`, independent of
+    /// the keepalive. The presence is only re-sent when the rendered text changed, and no more
+    /// often than Discord's rate limit allows. At least 1.
+    pub template_secs: u32,
+}
+
+impl Refresh {
+    /// Discord allows about five activity updates per 20 seconds.
+    pub const MIN_KEEPALIVE_SECS: u32 = 5;
+    pub const MAX_KEEPALIVE_SECS: u32 = 300;
+
+    /// The keepalive interval, within bounds.
+    pub fn keepalive(&self) -> std::time::Duration {
+        let secs = self.keepalive_secs.clamp(Self::MIN_KEEPALIVE_SECS, Self::MAX_KEEPALIVE_SECS);
+        std::time::Duration::from_secs(secs.into())
+    }
+
+    /// The template re-render interval, at least a second.
+    pub fn template_every(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.template_secs.max(1).into())
+    }
+}
+
+impl Default for Refresh {
+    fn default() -> Self {
+        Refresh { keepalive_secs: 10, burst: true, template_secs: 1 }
+    }
+}
+
+/// How applying a presence affects its timer.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum TimerPolicy {
+    /// Keep counting from when the presence was first enabled.
+    #[default]
+    Continue,
+    /// Restart the elapsed timer every time this presence is applied.
+    Reset,
+    /// Count up from a fixed Unix time.
+    Fixed { start: i64 },
+    /// Count down to a Unix time.
+    End { end: i64 },
+}
+
+/// The Discord user the desktop client is logged in as.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserProfile {
     pub id: String,
     pub username: String,
     pub global_name: Option<String>,
     pub avatar_hash: Option<String>,
+    /// Animated (`.gif`) when the avatar is, like Discord shows it.
     pub avatar_url: Option<String>,
+    /// Frame drawn around the avatar, when the user has one equipped.
+    #[serde(default)]
+    pub avatar_decoration: Option<AvatarDecoration>,
 }
 
-pub struct DiscordRpcClient {
-    stream: IpcStream,
-    pid: i64,
+/// An avatar decoration from the user's `avatar_decoration_data`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AvatarDecoration {
+    pub asset: String,
+    pub sku_id: Option<String>,
+    /// Animated PNG, drawn over the avatar and slightly larger than it (about 1.2×).
+    pub url: String,
 }
 
-impl DiscordRpcClient {
-    pub fn connect_and_handshake(client_id: &str) -> anyhow::Result<(Self, serde_json::Value)> {
-        let mut stream = connect_ipc().context("Failed to connect to discord-ipc")?;
-
-        let hs = json!({ "v": 1, "client_id": client_id });
-        send_frame(&mut stream, 0, &hs).context("Failed to send handshake")?;
+/// Parsed handshake READY payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadyInfo {
+    pub user: UserProfile,
+    pub cdn_host: Option<String>,
+    pub api_endpoint: Option<String>,
+    pub environment: Option<String>,
+    /// Payload as received, kept for diagnostics.
+    pub raw: serde_json::Value,
+}
 
-        let (_op, hs_resp) = read_frame(&mut stream).context("Failed to read handshake response")?;
-        if hs_resp.get("evt").and_then(|v| v.as_str()) == Some("ERROR") {
-            return Err(anyhow::anyhow!("Handshake error: {}", hs_resp));
-        }
+/// User id of the placeholder account arRPC (the RPC server in Vesktop, and the one run next to
+/// WebCord and other web clients) answers the handshake with. Presences work; the account is not
+/// shared.
+pub const ARRPC_USER_ID: &str = "1045800378228281345";
 
-        Ok((
-            Self {
-                stream,
-                pid: process::id() as i64,
-            },
-            hs_resp,
-        ))
+impl ReadyInfo {
+    /// Whether the handshake was answered by arRPC rather than the Discord desktop app.
+    pub fn is_arrpc(&self) -> bool {
+        self.user.id == ARRPC_USER_ID
     }
 
-    pub fn set_activity(&mut self, cfg: &PresenceCfg, start_ts: i64) -> anyhow::Result<()> {
-        let details_ok = cfg.details.trim().len() >= 2;
-        let state_ok = cfg.state.trim().len() >= 2;
-        if !details_ok && !state_ok {
-            return Err(anyhow::anyhow!(
-                "Invalid presence: fill Details or State with at least 2 characters."
-            ));
-        }
+    /// Parses the READY frame Discord sends in reply to the handshake.
+    pub fn from_payload(hs_resp: &serde_json::Value) -> anyhow::Result<Self> {
+        let data = hs_resp
+            .get("data")
+            .ok_or_else(|| anyhow::anyhow!("Handshake did not return data: {}", hs_resp))?;
+        let config = data.get("config");
+        let config_str = |key: &str| config.and_then(|c| c.get(key)).and_then(|v| v.as_str()).map(|s| s.to_string());
+        let cdn_host = config_str("cdn_host");
+
+        let user = data
+            .get("user")
+            .ok_or_else(|| anyhow::anyhow!("Handshake did not return data.user: {}", hs_resp))?;
+        let id = user.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
+        let username = user.get("username").and_then(|v| v.as_str()).unwrap_or("user").to_string();
+        let global_name = user.get("global_name").and_then(|v| v.as_str()).map(|s| s.to_string());
+        let avatar_hash = user.get("avatar").and_then(|v| v.as_str()).map(|s| s.to_string());
+
+        let host = cdn_host.as_deref().unwrap_or("cdn.discordapp.com");
+        let avatar_url = avatar_hash.as_ref().map(|hash| {
+            let ext = if hash.starts_with("a_") { "gif" } else { "png" };
+            format!("https://{}/avatars/{}/{}.{}?size=128", host, id, hash, ext)
+        });
+        let decoration = user.get("avatar_decoration_data").filter(|d| !d.is_null());
+        let avatar_decoration = decoration.and_then(|d| d.get("asset")?.as_str()).map(|asset| AvatarDecoration {
+            asset: asset.to_string(),
+            sku_id: decoration.and_then(|d| d.get("sku_id")?.as_str()).map(str::to_string),
+            // `passthrough=true` keeps the animation instead of the first frame.
+            url: format!("https://{}/avatar-decoration-presets/{}.png?size=160&passthrough=true", host, asset),
+        });
 
-        let mut activity_map = serde_json::Map::new();
-        if details_ok {
-            activity_map.insert("details".into(), json!(cfg.details));
-        }
-        if state_ok {
-            activity_map.insert("state".into(), json!(cfg.state));
-        }
+        Ok(Self {
+            user: UserProfile { id, username, global_name, avatar_hash, avatar_url, avatar_decoration },
+            cdn_host,
+            api_endpoint: config_str("api_endpoint"),
+            environment: config_str("environment"),
+            raw: hs_resp.clone(),
+        })
+    }
+}
 
-        let mut activity = json!(activity_map);
+/// State of the [`managed::ManagedClient`](crate::managed) worker.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcStatus {
+    Inactive,
+    Connecting,
+    /// No Discord socket was found; the worker keeps looking.
+    WaitingForDiscord,
+    /// Connected and re-sending the presence until Discord shows it reliably.
+    Stabilizing,
+    Active,
+    /// Connected, but Discord is rate limiting updates; the worker is backing off.
+    Throttled,
+    /// Connected, but Discord rejected the last activity; the worker retries on the next update or
+    /// keepalive.
+    Degraded,
+    /// Enabled, but the presence is cleared until resumed.
+    Paused,
+    Error,
+}
 
-        if cfg.with_timestamp {
-            activity["timestamps"] = json!({ "start": start_ts });
+impl RpcStatus {
+    /// Name on the wire, as serialized.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RpcStatus::Inactive => "inactive",
+            RpcStatus::Connecting => "connecting",
+            RpcStatus::WaitingForDiscord => "waiting_for_discord",
+            RpcStatus::Stabilizing => "stabilizing",
+            RpcStatus::Active => "active",
+            RpcStatus::Throttled => "throttled",
+            RpcStatus::Degraded => "degraded",
+            RpcStatus::Paused => "paused",
+            RpcStatus::Error => "error",
         }
+    }
 
-        let has_assets =
-            cfg.large_image.is_some() || cfg.small_image.is_some() || cfg.large_text.is_some() || cfg.small_text.is_some();
-
-        if has_assets {
-            let mut assets = serde_json::Map::new();
-            if let Some(v) = &cfg.large_image {
-                assets.insert("large_image".into(), json!(v));
-            }
-            if let Some(v) = &cfg.large_text {
-                assets.insert("large_text".into(), json!(v));
-            }
-            if let Some(v) = &cfg.small_image {
-                assets.insert("small_image".into(), json!(v));
-            }
-            if let Some(v) = &cfg.small_text {
-                assets.insert("small_text".into(), json!(v));
-            }
-            activity["assets"] = json!(assets);
+    /// Short lowercase text for people.
+    pub fn label(&self) -> &'static str {
+        match self {
+            RpcStatus::WaitingForDiscord => "waiting for Discord",
+            RpcStatus::Degraded => "connected, update rejected",
+            other => other.as_str(),
         }
+    }
 
-        let mut buttons = Vec::new();
-        for b in cfg.buttons.iter().take(2) {
-            let label = b.label.trim();
-            let mut url = b.url.trim().to_string();
+    /// Whether a connection to Discord is open.
+    pub fn is_connected(&self) -> bool {
+        matches!(self, RpcStatus::Stabilizing | RpcStatus::Active | RpcStatus::Throttled | RpcStatus::Degraded)
+    }
+}
 
-            if label.is_empty() || url.is_empty() {
-                continue;
-            }
+/// Fluent constructor for [`PresenceCfg`]. Empty strings are treated as unset.
+#[derive(Debug, Clone)]
+pub struct PresenceBuilder {
+    cfg: PresenceCfg,
+}
 
-            url.retain(|c| !c.is_whitespace());
+impl PresenceBuilder {
+    pub fn new(client_id: impl Into<String>) -> Self {
+        Self { cfg: PresenceCfg { client_id: client_id.into(), ..Default::default() } }
+    }
 
-            if url.starts_with("http://") {
-                url = url.replacen("http://", "https://", 1);
-            }
+    /// First line under the app name.
+    pub fn details(mut self, details: impl Into<String>) -> Self {
+        self.cfg.details = details.into();
+        self
+    }
 
-            if !url.starts_with("https://") {
-                continue;
-            }
+    /// Second line under the app name.
+    pub fn state(mut self, state: impl Into<String>) -> Self {
+        self.cfg.state = state.into();
+        self
+    }
 
-            let safe_label = if label.chars().count() > 32 {
-                label.chars().take(32).collect::<String>()
-            } else {
-                label.to_string()
-            };
+    /// Large image asset key or `https://` URL, with an optional hover text.
+    pub fn large_image(mut self, key: impl Into<String>, text: Option<&str>) -> Self {
+        self.cfg.large_image = non_empty(key.into());
+        self.cfg.large_text = text.and_then(|t| non_empty(t.to_string()));
+        self
+    }
 
-            buttons.push(json!({ "label": safe_label, "url": url }));
-        }
+    /// Small (badge) image asset key or `https://` URL, with an optional hover text.
+    pub fn small_image(mut self, key: impl Into<String>, text: Option<&str>) -> Self {
+        self.cfg.small_image = non_empty(key.into());
+        self.cfg.small_text = text.and_then(|t| non_empty(t.to_string()));
+        self
+    }
 
-        if !buttons.is_empty() {
-            activity["buttons"] = json!(buttons);
-        }
+    /// Adds a link button. Discord shows at most two; extra buttons are ignored when sending.
+    pub fn button(mut self, label: impl Into<String>, url: impl Into<String>) -> Self {
+        self.cfg.buttons.push(ButtonCfg { label: label.into(), url: url.into() });
+        self
+    }
 
-        let payload = json!({
-            "cmd": "SET_ACTIVITY",
-            "args": { "pid": self.pid, "activity": activity },
-            "nonce": nonce()
-        });
+    /// Shows an "elapsed" timer starting at the timestamp passed to `set_activity`.
+    pub fn timestamp(mut self, on: bool) -> Self {
+        self.cfg.with_timestamp = on;
+        self
+    }
 
-        send_frame(&mut self.stream, 1, &payload).context("Failed to send SET_ACTIVITY")?;
+    /// Shows a timer following `policy` (implies [`timestamp(true)`](Self::timestamp)).
+    pub fn timer(mut self, policy: TimerPolicy) -> Self {
+        self.cfg.with_timestamp = true;
+        self.cfg.timer = policy;
+        self
+    }
 
-        let (_op2, resp) = read_frame(&mut self.stream).context("Failed to read SET_ACTIVITY ACK")?;
-        if resp.get("evt").and_then(|v| v.as_str()) == Some("ERROR") {
-            return Err(anyhow::anyhow!("SET_ACTIVITY error: {}", resp));
-        }
+    /// Re-send behaviour of the managed worker; see [`Refresh`].
+    pub fn refresh(mut self, refresh: Refresh) -> Self {
+        self.cfg.refresh = refresh;
+        self
+    }
 
-        Ok(())
+    /// Rotates the large image through `cycle.images`.
+    pub fn image_cycle(mut self, cycle: cycle::ImageCycle) -> Self {
+        self.cfg.image_cycle = cycle;
+        self
     }
 
-    pub fn clear_activity(&mut self) -> anyhow::Result<()> {
-        let payload = json!({
-            "cmd": "SET_ACTIVITY",
-            "args": { "pid": self.pid, "activity": serde_json::Value::Null },
-            "nonce": nonce()
-        });
+    /// Scrolls details and state text longer than the marquee width (implies `enabled`).
+    pub fn marquee(mut self, marquee: marquee::Marquee) -> Self {
+        self.cfg.marquee = marquee::Marquee { enabled: true, ..marquee };
+        self
+    }
 
-        send_frame(&mut self.stream, 1, &payload).context("Failed to send CLEAR SET_ACTIVITY")?;
-        let _ = read_frame(&mut self.stream);
-        Ok(())
+    pub fn build(self) -> PresenceCfg {
+        self.cfg
     }
 }
 
-pub fn get_user_profile_via_handshake(client_id: &str) -> anyhow::Result<UserProfile> {
-    let (_client, hs_resp) = DiscordRpcClient::connect_and_handshake(client_id)?;
-
-    let user = hs_resp
-        .get("data")
-        .and_then(|d| d.get("user"))
-        .ok_or_else(|| anyhow::anyhow!("Handshake did not return data.user: {}", hs_resp))?;
+fn non_empty(s: String) -> Option<String> {
+    if s.trim().is_empty() { None } else { Some(s) }
+}
 
-    let id = user.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
-    let username = user.get("username").and_then(|v| v.as_str()).unwrap_or("user").to_string();
-    let global_name = user.get("global_name").and_then(|v| v.as_str()).map(|s| s.to_string());
-    let avatar_hash = user.get("avatar").and_then(|v| v.as_str()).map(|s| s.to_string());
+/// OS and CPU architecture of this build, e.g. `linux aarch64` or `windows aarch64`, for diagnostics.
+pub fn platform() -> String {
+    format!("{} {}", std::env::consts::OS, std::env::consts::ARCH)
+}
 
-    let avatar_url = avatar_hash.as_ref().map(|hash| {
-        let ext = if hash.starts_with("a_") { "gif" } else { "png" };
-        format!("https://cdn.discordapp.com/avatars/{}/{}.{}?size=128", id, hash, ext)
-    });
+/// Whether Discord makes a desktop app for this OS and CPU. It does not for Linux on ARM (Raspberry
+/// Pi and other ARM boards); there the presence goes through a client with an arRPC server, such as
+/// Vesktop. Windows on ARM runs the x64 app, which uses the same pipes.
+pub const DISCORD_DESKTOP_AVAILABLE: bool =
+    !cfg!(all(target_os = "linux", any(target_arch = "aarch64", target_arch = "arm")));
 
-    Ok(UserProfile { id, username, global_name, avatar_hash, avatar_url })
+/// Current Unix time in seconds, suitable as the `start_ts` of an activity.
+pub fn now_unix_ts() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
 }
 
-pub fn now_unix_ts() -> i64 {
-    now_unix()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ready_carries_animated_avatars_and_decorations() {
+        let ready = json!({ "data": { "config": { "cdn_host": "cdn.discordapp.com" }, "user": {
+            "id": "1", "username": "me", "avatar": "a_abc",
+            "avatar_decoration_data": { "asset": "a_deco", "sku_id": "99" }
+        } } });
+        let user = ReadyInfo::from_payload(&ready).unwrap().user;
+        assert_eq!(user.avatar_url.as_deref(), Some("https://cdn.discordapp.com/avatars/1/a_abc.gif?size=128"));
+        let deco = user.avatar_decoration.unwrap();
+        assert_eq!((deco.asset.as_str(), deco.sku_id.as_deref()), ("a_deco", Some("99")));
+        assert!(deco.url.ends_with("/avatar-decoration-presets/a_deco.png?size=160&passthrough=true"));
+
+        let plain = json!({ "data": { "user": { "id": "1", "username": "me", "avatar": "abc", "avatar_decoration_data": null } } });
+        let user = ReadyInfo::from_payload(&plain).unwrap().user;
+        assert!(user.avatar_url.unwrap().ends_with("abc.png?size=128"));
+        assert_eq!(user.avatar_decoration, None);
+    }
+
+    #[test]
+    fn arrpc_handshakes_are_recognised() {
+        let arrpc = json!({ "data": { "user": { "id": ARRPC_USER_ID, "username": "arrpc", "global_name": "arRPC" } } });
+        assert!(ReadyInfo::from_payload(&arrpc).unwrap().is_arrpc());
+        let discord = json!({ "data": { "user": { "id": "1", "username": "me" } } });
+        assert!(!ReadyInfo::from_payload(&discord).unwrap().is_arrpc());
+    }
 }
@@ -0,0 +1,71 @@
+//! Built-in template variables the worker fills in on every render, and the wall-clock ticks it
+//! re-renders them on.
+//!
+//! | Name | Example |
+//! |---|---|
+//! | `{time}` | `14:05` |
+//! | `{time_s}` | `14:05:09` |
+//! | `{date}` | `2024-03-01` |
+//! | `{weekday}` | `Friday` |
+//! | `{elapsed}` | seconds since the timer started, e.g. for `{bar:elapsed/1500}` |
+//! | `{remaining}` | seconds left on a countdown timer |
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{PresenceCfg, TimerPolicy};
+
+/// Value of a built-in variable in local time, or `None` for names this module does not know.
+pub(super) fn var(name: &str) -> Option<String> {
+    let fmt = match name {
+        "time" => "%H:%M",
+        "time_s" => "%H:%M:%S",
+        "date" => "%Y-%m-%d",
+        "weekday" => "%A",
+        _ => return None,
+    };
+    Some(chrono::Local::now().format(fmt).to_string())
+}
+
+/// Value of a timer variable, given the session start and the current Unix time.
+pub(super) fn timer_var(name: &str, cfg: &PresenceCfg, start_ts: Option<i64>, now: i64) -> Option<String> {
+    let secs = match (name, cfg.timer) {
+        ("elapsed", TimerPolicy::Fixed { start }) => now - start,
+        ("elapsed", _) => now - start_ts?,
+        ("remaining", TimerPolicy::End { end }) => end - now,
+        _ => return None,
+    };
+    Some(secs.max(0).to_string())
+}
+
+/// Time until the next multiple of `every` since the Unix epoch, so a one-minute interval renders
+/// right as the minute turns rather than up to a minute late.
+pub(super) fn until_next_tick(every: Duration) -> Duration {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    align(now, every)
+}
+
+fn align(now: Duration, every: Duration) -> Duration {
+    let every_ms = every.as_millis().max(1);
+    let into = now.as_millis() % every_ms;
+    Duration::from_millis((every_ms - into) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_land_on_interval_boundaries() {
+        let minute = Duration::from_secs(60);
+        assert_eq!(align(Duration::from_millis(120_250), minute), Duration::from_millis(59_750));
+        assert_eq!(align(Duration::from_secs(180), minute), minute, "on a boundary waits a full interval");
+        assert_eq!(align(Duration::from_millis(7_400), Duration::from_secs(1)), Duration::from_millis(600));
+    }
+
+    #[test]
+    fn only_known_names_have_values() {
+        assert_eq!(var("time").map(|t| t.len()), Some(5));
+        assert_eq!(var("time_s").map(|t| t.len()), Some(8));
+        assert_eq!(var("clock"), None);
+    }
+}
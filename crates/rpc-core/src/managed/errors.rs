@@ -0,0 +1,83 @@
+//! Deduplicated history of worker errors. `last_error` only holds the latest one, so a burst of
+//! reconnect failures hides whatever went wrong first; this keeps each distinct message with how
+//! often and when it happened.
+
+use serde::{Deserialize, Serialize};
+
+/// Distinct messages kept; the least recently seen one goes first.
+pub const MAX_ERRORS: usize = 20;
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorRecord {
+    pub message: String,
+    /// Times this message occurred.
+    pub count: u32,
+    /// Unix time of the first occurrence.
+    pub first_seen: i64,
+    /// Unix time of the latest occurrence.
+    pub last_seen: i64,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ErrorLog {
+    records: Vec<ErrorRecord>,
+}
+
+impl ErrorLog {
+    pub fn record(&mut self, message: &str, now: i64) {
+        if let Some(r) = self.records.iter_mut().find(|r| r.message == message) {
+            r.count = r.count.saturating_add(1);
+            r.last_seen = now;
+            return;
+        }
+        if self.records.len() >= MAX_ERRORS {
+            if let Some(oldest) = self.records.iter().enumerate().min_by_key(|(_, r)| r.last_seen).map(|(i, _)| i) {
+                self.records.remove(oldest);
+            }
+        }
+        self.records.push(ErrorRecord { message: message.to_string(), count: 1, first_seen: now, last_seen: now });
+    }
+
+    /// Oldest first, so the root cause of a cascade leads.
+    pub fn list(&self) -> Vec<ErrorRecord> {
+        let mut out = self.records.clone();
+        out.sort_by_key(|r| r.first_seen);
+        out
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_are_counted_once() {
+        let mut log = ErrorLog::default();
+        log.record("rejected", 10);
+        log.record("socket closed", 11);
+        log.record("socket closed", 15);
+        let list = log.list();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].message, "rejected");
+        assert_eq!((list[1].count, list[1].first_seen, list[1].last_seen), (2, 11, 15));
+    }
+
+    #[test]
+    fn the_least_recently_seen_message_is_dropped_when_full() {
+        let mut log = ErrorLog::default();
+        for i in 0..MAX_ERRORS as i64 {
+            log.record(&format!("e{}", i), i);
+        }
+        log.record("e0", 100);
+        log.record("new", 101);
+        let list = log.list();
+        assert_eq!(list.len(), MAX_ERRORS);
+        assert!(list.iter().any(|r| r.message == "e0"));
+        assert!(!list.iter().any(|r| r.message == "e1"));
+    }
+}
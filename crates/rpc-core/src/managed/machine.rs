@@ -0,0 +1,357 @@
+//! Connection state machine behind the worker. Pure: it decides what to do next and how long to
+//! wait, and the worker in `mod.rs` performs the IPC calls and feeds back what happened.
+//!
+//! ```text
+//! Disconnected ──Dial──▶ Handshaking ──Connected──▶ Stabilizing ──Sent×2──▶ Active
+//!                              │                        │                     │  ▲
+//!                  ConnectFailed / DiscordMissing Failed / RateLimited  Rejected  Sent
+//!                              ▼                        ▼                     ▼  │
+//!                        Backoff(Reconnect) ◀──────── Backoff(Throttled)    Degraded
+//! ```
+//!
+//! A `Degraded` connection fails and rate limits like an active one.
+
+use std::time::Duration;
+
+use crate::RpcStatus;
+
+/// Re-sends right after connecting; Discord sometimes drops the first activity of a session.
+pub(super) const BURST_SCHEDULE: [Duration; 5] = [
+    Duration::from_secs(0),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(4),
+    Duration::from_secs(8),
+];
+/// Acknowledged sends in a row before the presence counts as active.
+pub(super) const STABLE_AFTER: u8 = 2;
+/// Default keepalive; each presence can choose its own (see `Refresh`).
+pub(super) const KEEPALIVE_TICK: Duration = Duration::from_secs(10);
+pub(super) const RETRY_DELAY: Duration = Duration::from_secs(2);
+/// Rate-limit backoff: starts here and doubles while Discord keeps refusing, up to `THROTTLE_MAX`.
+pub(super) const THROTTLE_MIN: Duration = Duration::from_secs(5);
+/// Also the longest a `retry_after` from Discord is honored.
+pub(super) const THROTTLE_MAX: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Backoff {
+    /// No connection; dial again once the delay is over.
+    Reconnect,
+    /// Connected but rate limited. `delay` is the current backoff, `left` what remains of it.
+    Throttled { delay: Duration, left: Duration },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum State {
+    #[default]
+    Disconnected,
+    Handshaking,
+    /// Connected; `sent` activities acknowledged so far in the burst.
+    Stabilizing { sent: u8 },
+    Active,
+    /// Connected, but Discord rejected the last activity.
+    Degraded,
+    Backoff(Backoff),
+}
+
+/// What happened since the last step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Event {
+    /// The worker starts connecting.
+    Dial,
+    Connected,
+    ConnectFailed,
+    /// No Discord socket to connect to.
+    DiscordMissing,
+    /// An activity was acknowledged.
+    Sent,
+    /// Discord refused an activity but kept the connection.
+    Rejected,
+    RateLimited { retry_after: Option<Duration> },
+    /// A send or ping failed; the connection is gone.
+    Failed,
+    /// A ping during a throttle backoff succeeded.
+    Alive,
+    /// A reconnect delay ran out.
+    Elapsed,
+}
+
+/// What the worker should do once the wait is over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Step {
+    Connect,
+    /// Send the activity.
+    Send,
+    /// Ping; only report `Alive` (or `Failed`).
+    Ping,
+    /// Ping, then send if the connection is alive.
+    PingSend,
+    /// Nothing to do but report `Elapsed`.
+    Retry,
+}
+
+/// Which signals end a wait early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Wake {
+    /// `update` and `disable` both cut the wait short.
+    Any,
+    /// Only `disable` does; used where sending early would defeat the purpose of waiting.
+    DisableOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Plan {
+    pub wait: Duration,
+    pub wake: Wake,
+    pub step: Step,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Machine {
+    pub state: State,
+    /// Wait between re-sends while active, and between pings during long throttles.
+    pub keepalive: Duration,
+    /// Whether to run the burst schedule after connecting, or send once.
+    pub burst: bool,
+    /// The last dial found no Discord socket; reconnect attempts report as waiting for Discord.
+    pub missing: bool,
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Machine { state: State::default(), keepalive: KEEPALIVE_TICK, burst: true, missing: false }
+    }
+}
+
+impl Machine {
+    /// Status to publish for the current state.
+    pub fn status(&self) -> RpcStatus {
+        match self.state {
+            State::Disconnected | State::Handshaking | State::Backoff(Backoff::Reconnect) if self.missing => {
+                RpcStatus::WaitingForDiscord
+            }
+            State::Disconnected | State::Handshaking => RpcStatus::Connecting,
+            State::Stabilizing { .. } => RpcStatus::Stabilizing,
+            State::Active => RpcStatus::Active,
+            State::Degraded => RpcStatus::Degraded,
+            State::Backoff(Backoff::Reconnect) => RpcStatus::Error,
+            State::Backoff(Backoff::Throttled { .. }) => RpcStatus::Throttled,
+        }
+    }
+
+    /// Whether the state implies an open connection.
+    pub fn connected(&self) -> bool {
+        matches!(
+            self.state,
+            State::Stabilizing { .. } | State::Active | State::Degraded | State::Backoff(Backoff::Throttled { .. })
+        )
+    }
+
+    /// Applies a presence's refresh settings; takes effect from the next plan.
+    pub fn configure(&mut self, refresh: &crate::Refresh) {
+        self.keepalive = refresh.keepalive();
+        self.burst = refresh.burst;
+    }
+
+    pub fn plan(&self) -> Plan {
+        let plan = |wait, wake, step| Plan { wait, wake, step };
+        match self.state {
+            State::Disconnected | State::Handshaking => plan(Duration::ZERO, Wake::Any, Step::Connect),
+            State::Stabilizing { sent } => {
+                let wait = BURST_SCHEDULE.get(sent as usize).copied().unwrap_or(self.keepalive);
+                plan(wait, Wake::DisableOnly, Step::Send)
+            }
+            // A corrected presence from `update` is sent right away; otherwise retry on the tick.
+            State::Active | State::Degraded => plan(self.keepalive, Wake::Any, Step::PingSend),
+            State::Backoff(Backoff::Reconnect) => plan(RETRY_DELAY, Wake::Any, Step::Retry),
+            // Long backoffs still ping every keepalive tick so a dead connection is noticed early.
+            State::Backoff(Backoff::Throttled { left, .. }) if left > self.keepalive => {
+                plan(self.keepalive, Wake::DisableOnly, Step::Ping)
+            }
+            State::Backoff(Backoff::Throttled { left, .. }) => plan(left, Wake::DisableOnly, Step::PingSend),
+        }
+    }
+
+    pub fn handle(&mut self, event: Event) {
+        match event {
+            Event::DiscordMissing => self.missing = true,
+            Event::Connected | Event::ConnectFailed => self.missing = false,
+            _ => {}
+        }
+        self.state = self.next(event);
+    }
+
+    fn next(&self, event: Event) -> State {
+        use State::*;
+        match (self.state, event) {
+            (Disconnected, Event::Dial) => Handshaking,
+            (Handshaking, Event::Connected) => Stabilizing { sent: 0 },
+            (Handshaking, Event::ConnectFailed | Event::DiscordMissing) => Backoff(self::Backoff::Reconnect),
+            (Backoff(self::Backoff::Reconnect), Event::Elapsed) => Disconnected,
+
+            (Stabilizing { .. }, Event::Sent) if !self.burst => Active,
+            (Stabilizing { sent }, Event::Sent) if sent + 1 >= STABLE_AFTER => Active,
+            (Stabilizing { sent }, Event::Sent) => Stabilizing { sent: sent + 1 },
+            (Active | Degraded, Event::Sent) => Active,
+            (Backoff(self::Backoff::Throttled { .. }), Event::Sent) => Active,
+            (_, Event::Rejected) if self.connected() => Degraded,
+
+            (Backoff(self::Backoff::Throttled { delay, left }), Event::Alive) => {
+                Backoff(self::Backoff::Throttled { delay, left: left.saturating_sub(self.keepalive) })
+            }
+
+            (s, Event::RateLimited { retry_after }) if self.connected() => {
+                let prev = match s {
+                    Backoff(self::Backoff::Throttled { delay, .. }) => Some(delay),
+                    _ => None,
+                };
+                let delay = throttle_delay(prev, retry_after);
+                Backoff(self::Backoff::Throttled { delay, left: delay })
+            }
+            (_, Event::Failed) if self.connected() => Backoff(self::Backoff::Reconnect),
+
+            // Anything else does not apply to the current state.
+            (s, _) => s,
+        }
+    }
+}
+
+/// Doubles the previous backoff (starting at `THROTTLE_MIN`), but waits at least as long as Discord
+/// asked. Never more than `THROTTLE_MAX`, whatever a malformed `retry_after` says.
+fn throttle_delay(prev: Option<Duration>, retry_after: Option<Duration>) -> Duration {
+    let next = prev.map_or(THROTTLE_MIN, |t| t.saturating_mul(2)).clamp(THROTTLE_MIN, THROTTLE_MAX);
+    retry_after.map_or(next, |r| r.max(next)).min(THROTTLE_MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(state: State) -> Machine {
+        Machine { state, ..Machine::default() }
+    }
+
+    fn after(state: State, event: Event) -> State {
+        let mut m = at(state);
+        m.handle(event);
+        m.state
+    }
+
+    fn throttled(secs: u64) -> State {
+        let d = Duration::from_secs(secs);
+        State::Backoff(Backoff::Throttled { delay: d, left: d })
+    }
+
+    #[test]
+    fn connects_then_stabilizes() {
+        assert_eq!(after(State::Disconnected, Event::Dial), State::Handshaking);
+        assert_eq!(after(State::Handshaking, Event::Connected), State::Stabilizing { sent: 0 });
+        assert_eq!(after(State::Stabilizing { sent: 0 }, Event::Sent), State::Stabilizing { sent: 1 });
+        assert_eq!(after(State::Stabilizing { sent: 1 }, Event::Sent), State::Active);
+        assert_eq!(after(State::Active, Event::Sent), State::Active);
+    }
+
+    #[test]
+    fn failed_handshake_retries_after_a_delay() {
+        let s = after(State::Handshaking, Event::ConnectFailed);
+        assert_eq!(s, State::Backoff(Backoff::Reconnect));
+        assert_eq!(at(s).status(), RpcStatus::Error);
+        assert_eq!(at(s).plan(), Plan { wait: RETRY_DELAY, wake: Wake::Any, step: Step::Retry });
+        assert_eq!(after(s, Event::Elapsed), State::Disconnected);
+    }
+
+    #[test]
+    fn missing_discord_reports_waiting_until_a_dial_gets_through() {
+        let mut m = at(State::Handshaking);
+        m.handle(Event::DiscordMissing);
+        assert_eq!(m.status(), RpcStatus::WaitingForDiscord);
+        m.handle(Event::Elapsed);
+        m.handle(Event::Dial);
+        assert_eq!(m.status(), RpcStatus::WaitingForDiscord, "no flicker to connecting between attempts");
+        m.handle(Event::Connected);
+        assert_eq!(m.status(), RpcStatus::Stabilizing);
+        assert_eq!(after(State::Handshaking, Event::ConnectFailed), State::Backoff(Backoff::Reconnect));
+    }
+
+    #[test]
+    fn rejected_activities_keep_the_connection() {
+        for s in [State::Stabilizing { sent: 1 }, State::Active, throttled(5)] {
+            assert_eq!(after(s, Event::Rejected), State::Degraded, "from {:?}", s);
+        }
+        assert_eq!(at(State::Degraded).status(), RpcStatus::Degraded);
+        assert_eq!(at(State::Degraded).plan(), Plan { wait: KEEPALIVE_TICK, wake: Wake::Any, step: Step::PingSend });
+        assert_eq!(after(State::Degraded, Event::Sent), State::Active);
+        assert_eq!(after(State::Degraded, Event::Failed), State::Backoff(Backoff::Reconnect));
+        assert_eq!(after(State::Handshaking, Event::Rejected), State::Handshaking);
+    }
+
+    #[test]
+    fn failures_drop_the_connection() {
+        for s in [State::Stabilizing { sent: 1 }, State::Active, throttled(5)] {
+            assert_eq!(after(s, Event::Failed), State::Backoff(Backoff::Reconnect), "from {:?}", s);
+        }
+    }
+
+    #[test]
+    fn a_failed_burst_send_reconnects_instead_of_waiting_out_the_burst() {
+        let mut m = at(State::Handshaking);
+        m.handle(Event::Connected);
+        m.handle(Event::Failed);
+        assert_eq!(m.state, State::Backoff(Backoff::Reconnect));
+        assert_eq!(m.plan(), Plan { wait: RETRY_DELAY, wake: Wake::Any, step: Step::Retry });
+        assert!(!m.connected());
+    }
+
+    #[test]
+    fn rate_limits_back_off_and_double() {
+        let s = after(State::Active, Event::RateLimited { retry_after: None });
+        assert_eq!(s, throttled(5));
+        assert_eq!(at(s).status(), RpcStatus::Throttled);
+        assert_eq!(after(s, Event::RateLimited { retry_after: None }), throttled(10));
+        assert_eq!(after(throttled(100), Event::RateLimited { retry_after: None }), throttled(120));
+        let asked = Event::RateLimited { retry_after: Some(Duration::from_secs(30)) };
+        assert_eq!(after(State::Stabilizing { sent: 0 }, asked), throttled(30));
+        let absurd = Event::RateLimited { retry_after: Some(Duration::MAX) };
+        assert_eq!(after(State::Active, absurd), throttled(120));
+    }
+
+    #[test]
+    fn throttle_pings_until_the_backoff_is_over() {
+        let s = throttled(25);
+        assert_eq!(at(s).plan().step, Step::Ping);
+        assert_eq!(at(s).plan().wake, Wake::DisableOnly);
+        let s = after(s, Event::Alive);
+        let s = after(s, Event::Alive);
+        assert_eq!(
+            at(s).plan(),
+            Plan { wait: Duration::from_secs(5), wake: Wake::DisableOnly, step: Step::PingSend }
+        );
+        assert_eq!(after(s, Event::Sent), State::Active);
+    }
+
+    #[test]
+    fn burst_waits_follow_the_schedule() {
+        assert_eq!(at(State::Stabilizing { sent: 0 }).plan().wait, BURST_SCHEDULE[0]);
+        assert_eq!(at(State::Stabilizing { sent: 1 }).plan().wait, BURST_SCHEDULE[1]);
+        assert_eq!(at(State::Active).plan(), Plan { wait: KEEPALIVE_TICK, wake: Wake::Any, step: Step::PingSend });
+    }
+
+    #[test]
+    fn refresh_settings_change_the_keepalive_and_skip_the_burst() {
+        let mut m = at(State::Stabilizing { sent: 0 });
+        m.configure(&crate::Refresh { keepalive_secs: 60, burst: false, ..Default::default() });
+        m.handle(Event::Sent);
+        assert_eq!(m.state, State::Active);
+        assert_eq!(m.plan().wait, Duration::from_secs(60));
+
+        m.configure(&crate::Refresh { keepalive_secs: 1, ..Default::default() });
+        assert_eq!(m.plan().wait, Duration::from_secs(5), "clamped to the minimum");
+    }
+
+    #[test]
+    fn unrelated_events_are_ignored() {
+        assert_eq!(after(State::Disconnected, Event::Sent), State::Disconnected);
+        assert_eq!(after(State::Active, Event::Connected), State::Active);
+        assert_eq!(after(State::Backoff(Backoff::Reconnect), Event::RateLimited { retry_after: None }), State::Backoff(Backoff::Reconnect));
+    }
+}
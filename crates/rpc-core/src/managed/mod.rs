@@ -0,0 +1,636 @@
+//! Background worker that keeps a presence published: connects, re-sends on a burst schedule so
+//! the Discord client latches onto it, then refreshes on a keepalive tick and reconnects on errors.
+//! The transitions live in [`machine`]; this module runs them against a real connection.
+//!
+//! Presences with `{placeholders}` are rendered right before each send. Built-in variables such as
+//! `{time}` are also re-rendered between keepalives, every [`Refresh::template_secs`](crate::Refresh),
+//! and sent as soon as the text changes and the rate limit allows. A [marquee](crate::marquee) and
+//! an [image cycle](crate::cycle) advance the same way, one frame per interval.
+//!
+//! [`pause`](ManagedClient::pause) clears the presence and closes the connection without stopping
+//! the worker; [`resume`](ManagedClient::resume) reconnects and publishes it again.
+
+use std::{
+    collections::BTreeMap,
+    process,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    control::StatusSnapshot, i18n::RpcError, progress::Bars, DiscordRpcClient, PresenceCfg, RateLimited, ReadyInfo,
+    TimerPolicy,
+};
+
+pub use crate::RpcStatus;
+
+mod clock;
+mod errors;
+mod machine;
+mod watch;
+
+pub use errors::{ErrorRecord, MAX_ERRORS};
+use errors::ErrorLog;
+use machine::{Event, Machine, Step, Wake};
+pub use watch::StatusWatch;
+use watch::StatusCell;
+
+/// Shortest gap between template re-sends, so a `{time_s}` presence stays within Discord's limit
+/// of about five activity updates per 20 seconds.
+const TEMPLATE_MIN_GAP: Duration = Duration::from_secs(4);
+
+/// Connection details published by the worker.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionReport {
+    /// Socket/pipe the worker is connected through.
+    pub pipe: Option<String>,
+    /// When the last SET_ACTIVITY was acknowledged.
+    pub last_ok: Option<Instant>,
+    /// `last_ok` as Unix time, for the control API.
+    pub last_ok_at: Option<i64>,
+    /// When the worker will send next (burst step, keepalive or reconnect).
+    pub next_send: Option<Instant>,
+    /// `next_send` as Unix time.
+    pub next_send_at: Option<i64>,
+    /// READY info from the most recent handshake.
+    pub ready: Option<ReadyInfo>,
+    /// Round-trip time of the last PING.
+    pub rtt: Option<Duration>,
+    /// When the current connection was established.
+    pub connected_at: Option<Instant>,
+    /// What Discord dropped or changed in the last acknowledged activity; see [`crate::echo`].
+    pub echo_mismatches: Vec<String>,
+}
+
+#[derive(Default)]
+struct Signal {
+    cv: Condvar,
+    flag: Mutex<bool>,
+}
+
+impl Signal {
+    fn poke(&self) {
+        let mut f = self.flag.lock().unwrap();
+        *f = true;
+        self.cv.notify_all();
+    }
+
+    /// Waits until `poke` or until `dur` expires, consuming a pending poke. Returns whether it was
+    /// poked.
+    fn wait_or_timeout(&self, dur: Duration) -> bool {
+        let mut f = self.flag.lock().unwrap();
+        if *f {
+            *f = false;
+            return true;
+        }
+        let (mut f2, _) = self.cv.wait_timeout(f, dur).unwrap();
+        std::mem::take(&mut *f2)
+    }
+}
+
+/// Presences and variables are kept as `Arc` snapshots, replaced whole on every change: readers
+/// (UI, API handlers, the worker's render loop) hold a lock only long enough to copy the pointer,
+/// never while cloning, rendering or sending, and always see one complete version.
+struct Shared {
+    running: AtomicBool,
+    paused: AtomicBool,
+    thread_alive: AtomicBool,
+    status: Arc<StatusCell>,
+    report: Mutex<ConnectionReport>,
+    last_error: Mutex<Option<String>>,
+    errors: Mutex<ErrorLog>,
+    cfg: Mutex<Option<Arc<PresenceCfg>>>,
+    /// The rendered presence Discord last acknowledged.
+    sent: Mutex<Option<Arc<PresenceCfg>>>,
+    bars: Mutex<Bars>,
+    /// Template variables set through `set_var`, on top of the built-in ones.
+    vars: Mutex<Arc<BTreeMap<String, String>>>,
+    start_ts: Mutex<Option<i64>>,
+    ipc_path: Mutex<Option<String>>,
+    pid: Mutex<Option<u32>>,
+    /// `enable`/`update` calls so far, and how many of them the last acknowledged send covered.
+    queued: AtomicU64,
+    covered: AtomicU64,
+    signal: Signal,
+}
+
+/// Handle to the presence worker. Cheap to clone; all clones drive the same worker.
+///
+/// ```no_run
+/// use rpc_core::{managed::ManagedClient, PresenceBuilder};
+///
+/// let client = ManagedClient::new();
+/// client.enable(PresenceBuilder::new("123456789012345678").details("Hello").build());
+/// // ... later
+/// client.disable();
+/// ```
+#[derive(Clone)]
+pub struct ManagedClient {
+    shared: Arc<Shared>,
+}
+
+impl Default for ManagedClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManagedClient {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                running: AtomicBool::new(false),
+                paused: AtomicBool::new(false),
+                thread_alive: AtomicBool::new(false),
+                status: StatusCell::new(),
+                report: Mutex::new(ConnectionReport::default()),
+                last_error: Mutex::new(None),
+                errors: Mutex::new(ErrorLog::default()),
+                cfg: Mutex::new(None),
+                sent: Mutex::new(None),
+                bars: Mutex::new(Bars::default()),
+                vars: Mutex::new(Arc::new(BTreeMap::new())),
+                start_ts: Mutex::new(None),
+                ipc_path: Mutex::new(None),
+                pid: Mutex::new(None),
+                queued: AtomicU64::new(0),
+                covered: AtomicU64::new(0),
+                signal: Signal::default(),
+            }),
+        }
+    }
+
+    pub fn status(&self) -> RpcStatus {
+        self.shared.status.get()
+    }
+
+    /// A watch on [`status`](Self::status), for waiting on changes instead of polling. Every
+    /// consumer (UI, tray, API sessions, notifications) takes its own.
+    pub fn watch_status(&self) -> StatusWatch {
+        self.shared.status.watch()
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.shared.last_error.lock().unwrap().clone()
+    }
+
+    /// Every distinct error since the history was last cleared, oldest first, with how often it
+    /// occurred. Unlike [`last_error`](Self::last_error) it survives reconnects and recovery.
+    pub fn error_history(&self) -> Vec<ErrorRecord> {
+        self.shared.errors.lock().unwrap().list()
+    }
+
+    pub fn clear_error_history(&self) {
+        self.shared.errors.lock().unwrap().clear();
+    }
+
+    /// Presence currently configured, if any.
+    pub fn presence(&self) -> Option<PresenceCfg> {
+        self.presence_snapshot().map(|c| (*c).clone())
+    }
+
+    /// Presence currently configured, shared instead of copied. It never changes; `enable` and
+    /// `update` replace it with a new one.
+    pub fn presence_snapshot(&self) -> Option<Arc<PresenceCfg>> {
+        self.shared.cfg.lock().unwrap().clone()
+    }
+
+    /// Client id of the presence currently configured, if any.
+    pub fn client_id(&self) -> Option<String> {
+        self.presence_snapshot().map(|c| c.client_id.clone())
+    }
+
+    pub fn report(&self) -> ConnectionReport {
+        self.shared.report.lock().unwrap().clone()
+    }
+
+    /// `enable`/`update` calls Discord has not acknowledged yet. Only the newest presence is sent,
+    /// so any number of them goes out with the next send; they pile up while connecting, throttled
+    /// or paused.
+    pub fn pending_updates(&self) -> u32 {
+        let w = &self.shared;
+        let pending = w.queued.load(Ordering::SeqCst).saturating_sub(w.covered.load(Ordering::SeqCst));
+        pending.try_into().unwrap_or(u32::MAX)
+    }
+
+    /// Status in the shape the control API sends it.
+    pub fn snapshot(&self) -> StatusSnapshot {
+        let report = self.report();
+        let pending_updates = self.pending_updates();
+        StatusSnapshot {
+            status: self.status(),
+            last_error: self.last_error(),
+            client_id: self.client_id(),
+            pipe: report.pipe,
+            warnings: report.echo_mismatches,
+            pending_updates,
+            next_send_at: report.next_send_at.filter(|_| pending_updates > 0),
+            last_sent_at: report.last_ok_at,
+        }
+    }
+
+    /// Start of the elapsed timer for the current session, if enabled.
+    pub fn start_ts(&self) -> Option<i64> {
+        *self.shared.start_ts.lock().unwrap()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.shared.running.load(Ordering::SeqCst)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.shared.paused.load(Ordering::SeqCst)
+    }
+
+    /// Clears the presence and disconnects, but keeps the worker enabled with its presence until
+    /// [`resume`](Self::resume). Does nothing while disabled.
+    pub fn pause(&self) {
+        if self.is_running() && !self.shared.paused.swap(true, Ordering::SeqCst) {
+            self.shared.signal.poke();
+        }
+    }
+
+    /// Reconnects after [`pause`](Self::pause) and publishes the presence again.
+    pub fn resume(&self) {
+        if self.shared.paused.swap(false, Ordering::SeqCst) {
+            self.shared.signal.poke();
+        }
+    }
+
+    /// Connects to this socket/pipe instead of discovering one. Applies on the next (re)connect.
+    pub fn set_ipc_path(&self, path: Option<String>) {
+        *self.shared.ipc_path.lock().unwrap() = path;
+    }
+
+    /// Reports activities as coming from `pid` instead of this process. Applies on the next send.
+    pub fn set_pid_override(&self, pid: Option<u32>) {
+        *self.shared.pid.lock().unwrap() = pid;
+    }
+
+    /// How `{bar:…}` placeholders look. Applies on the next render.
+    pub fn set_bars(&self, bars: Bars) {
+        *self.shared.bars.lock().unwrap() = bars;
+    }
+
+    /// Sets template variable `name`, or removes it with `None`. It takes precedence over a
+    /// built-in variable of the same name and shows up on the next template tick.
+    pub fn set_var(&self, name: &str, value: Option<String>) {
+        let mut vars = self.shared.vars.lock().unwrap();
+        // Copies the map only while a render still holds the previous version.
+        let vars = Arc::make_mut(&mut vars);
+        match value {
+            Some(v) => vars.insert(name.to_string(), v),
+            None => vars.remove(name),
+        };
+    }
+
+    /// Starts the worker thread, or applies `cfg` right away if it is already running. Ends a
+    /// [`pause`](Self::pause).
+    pub fn enable(&self, cfg: PresenceCfg) {
+        let w = &self.shared;
+        w.paused.store(false, Ordering::SeqCst);
+        w.queued.fetch_add(1, Ordering::SeqCst);
+        w.apply_timer(&cfg);
+        *w.cfg.lock().unwrap() = Some(Arc::new(cfg));
+        w.start_ts.lock().unwrap().get_or_insert_with(crate::now_unix_ts);
+        w.running.store(true, Ordering::SeqCst);
+
+        if w.thread_alive.swap(true, Ordering::SeqCst) {
+            w.signal.poke();
+            return;
+        }
+
+        let w = Arc::clone(w);
+        thread::spawn(move || run(&w));
+    }
+
+    /// Replaces the presence. If the worker is running it is sent immediately.
+    pub fn update(&self, cfg: PresenceCfg) {
+        if self.is_running() {
+            self.shared.queued.fetch_add(1, Ordering::SeqCst);
+            self.shared.apply_timer(&cfg);
+        }
+        *self.shared.cfg.lock().unwrap() = Some(Arc::new(cfg));
+        if self.is_running() {
+            self.shared.signal.poke();
+        }
+    }
+
+    /// Stops the worker; it clears the activity (best effort) before exiting.
+    pub fn disable(&self) {
+        self.shared.running.store(false, Ordering::SeqCst);
+        self.shared.signal.poke();
+    }
+
+    /// Stops the worker and waits up to `timeout` for it to clear the activity and exit, for
+    /// shutting down before the process does. Returns whether it exited in time.
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        self.disable();
+        let deadline = Instant::now() + timeout;
+        while self.shared.thread_alive.load(Ordering::SeqCst) {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        true
+    }
+}
+
+impl Shared {
+    fn set_status(&self, st: RpcStatus) {
+        self.status.set(st);
+    }
+
+    fn set_error(&self, msg: Option<String>) {
+        if let Some(m) = &msg {
+            self.errors.lock().unwrap().record(m, crate::now_unix_ts());
+        }
+        *self.last_error.lock().unwrap() = msg;
+    }
+
+    fn set_next_send(&self, after: Duration) {
+        let mut report = self.report.lock().unwrap();
+        report.next_send = Some(Instant::now() + after);
+        report.next_send_at = Some(crate::now_unix_ts() + after.as_secs_f64().ceil() as i64);
+    }
+
+    /// Records an acknowledged send of the presence as it was after `queued` calls.
+    fn mark_sent(&self, queued: u64) {
+        let mut report = self.report.lock().unwrap();
+        report.last_ok = Some(Instant::now());
+        report.last_ok_at = Some(crate::now_unix_ts());
+        self.covered.fetch_max(queued, Ordering::SeqCst);
+    }
+
+    /// Restarts the session timer for presences that ask for it.
+    fn apply_timer(&self, cfg: &PresenceCfg) {
+        if cfg.with_timestamp && cfg.timer == TimerPolicy::Reset {
+            *self.start_ts.lock().unwrap() = Some(crate::now_unix_ts());
+        }
+    }
+
+    /// Blocks while paused and running.
+    fn wait_paused(&self) {
+        while self.paused.load(Ordering::SeqCst) && self.running.load(Ordering::SeqCst) {
+            self.signal.wait_or_timeout(Duration::from_secs(60));
+        }
+    }
+
+    /// Sleeps for `dur`, ignoring pokes from `update` but returning early on `disable` or `pause`.
+    fn wait_while_running(&self, dur: Duration) {
+        let deadline = Instant::now() + dur;
+        while self.running.load(Ordering::SeqCst) && !self.paused.load(Ordering::SeqCst) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            self.signal.wait_or_timeout(left);
+        }
+    }
+
+    /// Waits like `wait_or_timeout`, re-rendering the built-in template variables and the marquee
+    /// on every tick in between and sending whenever that changes the presence. A send that does
+    /// not go through ends the wait with its event.
+    fn wait_rendering(
+        &self,
+        dur: Duration,
+        client: &mut Option<DiscordRpcClient>,
+        fallback: &Arc<PresenceCfg>,
+    ) -> Option<Event> {
+        let deadline = Instant::now() + dur;
+        let every = tick_every(fallback).unwrap_or(dur);
+        loop {
+            let now = Instant::now();
+            let left = deadline.saturating_duration_since(now);
+            if left.is_zero() {
+                return None;
+            }
+            let rendered = self.rendered(fallback);
+            let sent = self.sent.lock().unwrap().clone();
+            let changed = sent.as_deref() != Some(&rendered);
+            let wait = if changed {
+                let last_ok = self.report.lock().unwrap().last_ok;
+                last_ok.map_or(Duration::ZERO, |t| (t + TEMPLATE_MIN_GAP).saturating_duration_since(now))
+            } else {
+                clock::until_next_tick(every)
+            };
+            if changed && wait.is_zero() {
+                match self.send(client, fallback) {
+                    Event::Sent => continue,
+                    other => return Some(other),
+                }
+            }
+            if self.signal.wait_or_timeout(wait.min(left)) {
+                return None;
+            }
+        }
+    }
+
+    fn current_cfg(&self, fallback: &Arc<PresenceCfg>) -> Arc<PresenceCfg> {
+        self.cfg.lock().unwrap().clone().unwrap_or_else(|| Arc::clone(fallback))
+    }
+
+    /// The current presence with the image cycle and marquee at their current frames and the
+    /// variables and progress bars filled in.
+    fn rendered(&self, fallback: &Arc<PresenceCfg>) -> PresenceCfg {
+        let cfg = self.current_cfg(fallback);
+        let start_ts = *self.start_ts.lock().unwrap();
+        let bars = *self.bars.lock().unwrap();
+        let now = crate::now_unix_ts();
+        let set = Arc::clone(&self.vars.lock().unwrap());
+        let lookup = |name: &str| {
+            set.get(name).cloned().or_else(|| clock::var(name)).or_else(|| clock::timer_var(name, &cfg, start_ts, now))
+        };
+        let vars = bars.vars(&lookup);
+        cfg.cycled(now).render(&vars).scrolled(now)
+    }
+
+    fn connect(&self, client: &mut Option<DiscordRpcClient>, cfg: &PresenceCfg) -> Event {
+        let ipc_path = { self.ipc_path.lock().unwrap().clone() };
+        let conn = match ipc_path {
+            Some(p) => DiscordRpcClient::connect_and_handshake_at(&p, &cfg.client_id),
+            None => DiscordRpcClient::connect_and_handshake(&cfg.client_id),
+        };
+        match conn {
+            Ok((c, ready)) => {
+                let mut report = self.report.lock().unwrap();
+                report.pipe = Some(c.pipe().to_string());
+                report.ready = Some(ready);
+                report.connected_at = Some(Instant::now());
+                drop(report);
+                *client = Some(c);
+                self.set_error(None);
+                Event::Connected
+            }
+            Err(e) => {
+                self.set_error(Some(e.to_string()));
+                match e.downcast_ref::<RpcError>() {
+                    Some(RpcError::DiscordNotFound | RpcError::SandboxBlocked { .. } | RpcError::ConnectFailed { .. }) => {
+                        Event::DiscordMissing
+                    }
+                    _ => Event::ConnectFailed,
+                }
+            }
+        }
+    }
+
+    fn send(&self, client: &mut Option<DiscordRpcClient>, fallback: &Arc<PresenceCfg>) -> Event {
+        let Some(c) = client.as_mut() else {
+            return Event::Failed;
+        };
+        // Read on every send: `enable`/`update` may have reset it since the loop started.
+        let start_ts = *self.start_ts.lock().unwrap().get_or_insert_with(crate::now_unix_ts);
+        let pid = self.pid.lock().unwrap().unwrap_or_else(process::id);
+        c.set_pid(pid as i64);
+        // Counted before reading the presence, so an update that lands in between stays pending.
+        let queued = self.queued.load(Ordering::SeqCst);
+        let cfg = self.rendered(fallback);
+        match c.set_activity(&cfg, start_ts) {
+            Ok(()) => {
+                self.report.lock().unwrap().echo_mismatches = c.echo_mismatches().to_vec();
+                self.mark_sent(queued);
+                *self.sent.lock().unwrap() = Some(Arc::new(cfg));
+                self.set_error(None);
+                Event::Sent
+            }
+            Err(e) => {
+                self.set_error(Some(e.to_string()));
+                if let Some(rl) = e.downcast_ref::<RateLimited>() {
+                    return Event::RateLimited { retry_after: rl.retry_after };
+                }
+                match e.downcast_ref::<RpcError>() {
+                    Some(RpcError::ActivityRejected { .. } | RpcError::PresenceTooShort) => Event::Rejected,
+                    _ => Event::Failed,
+                }
+            }
+        }
+    }
+
+    /// Pings the connection and records the RTT.
+    fn ping(&self, client: &mut Option<DiscordRpcClient>) -> Event {
+        let Some(c) = client.as_mut() else {
+            return Event::Failed;
+        };
+        match c.ping() {
+            Ok(rtt) => {
+                self.report.lock().unwrap().rtt = Some(rtt);
+                Event::Alive
+            }
+            Err(e) => {
+                self.set_error(Some(e.to_string()));
+                self.report.lock().unwrap().rtt = None;
+                Event::Failed
+            }
+        }
+    }
+}
+
+/// How often the rendered presence can change between keepalives, or `None` when it cannot.
+fn tick_every(cfg: &PresenceCfg) -> Option<Duration> {
+    [
+        cfg.is_template().then(|| cfg.refresh.template_every()),
+        cfg.marquee.enabled.then(|| cfg.marquee.interval()),
+        cfg.image_cycle.is_active().then(|| cfg.image_cycle.interval()),
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+}
+
+fn run(w: &Shared) {
+    w.set_status(RpcStatus::Connecting);
+    w.set_error(None);
+
+    let mut machine = Machine::default();
+    let mut client: Option<DiscordRpcClient> = None;
+
+    while w.running.load(Ordering::SeqCst) {
+        if w.paused.load(Ordering::SeqCst) {
+            // Dropping the client clears the activity and closes the connection.
+            client = None;
+            machine.state = Default::default();
+            *w.sent.lock().unwrap() = None;
+            let mut report = w.report.lock().unwrap();
+            report.pipe = None;
+            report.connected_at = None;
+            report.next_send = None;
+            report.next_send_at = None;
+            drop(report);
+            w.set_status(RpcStatus::Paused);
+            w.set_error(None);
+            w.wait_paused();
+            continue;
+        }
+        let cfg_opt = { w.cfg.lock().unwrap().clone() };
+        let Some(cfg) = cfg_opt else {
+            break;
+        };
+
+        machine.configure(&cfg.refresh);
+        let plan = machine.plan();
+        let mut early = None;
+        if !plan.wait.is_zero() {
+            w.set_next_send(plan.wait);
+            match plan.wake {
+                Wake::Any if machine.status() == RpcStatus::Active && tick_every(&cfg).is_some() => {
+                    early = w.wait_rendering(plan.wait, &mut client, &cfg);
+                }
+                Wake::Any => {
+                    w.signal.wait_or_timeout(plan.wait);
+                }
+                Wake::DisableOnly => w.wait_while_running(plan.wait),
+            }
+            if !w.running.load(Ordering::SeqCst) {
+                break;
+            }
+            if w.paused.load(Ordering::SeqCst) {
+                continue;
+            }
+        }
+
+        let event = match early {
+            Some(event) => event,
+            None => match plan.step {
+                Step::Connect => {
+                    machine.handle(Event::Dial);
+                    w.set_status(machine.status());
+                    w.connect(&mut client, &cfg)
+                }
+                Step::Send => w.send(&mut client, &cfg),
+                Step::Ping => w.ping(&mut client),
+                Step::PingSend => match w.ping(&mut client) {
+                    Event::Alive => w.send(&mut client, &cfg),
+                    failed => failed,
+                },
+                Step::Retry => Event::Elapsed,
+            },
+        };
+        machine.handle(event);
+        if !machine.connected() {
+            client = None;
+            let mut report = w.report.lock().unwrap();
+            report.pipe = None;
+            report.connected_at = None;
+        }
+        w.set_status(machine.status());
+    }
+
+    if let Some(mut c) = client {
+        let _ = c.clear_activity();
+    }
+
+    *w.start_ts.lock().unwrap() = None;
+    *w.sent.lock().unwrap() = None;
+    w.paused.store(false, Ordering::SeqCst);
+    // Nothing is waiting to go out once disabled.
+    w.covered.store(w.queued.load(Ordering::SeqCst), Ordering::SeqCst);
+    *w.report.lock().unwrap() = ConnectionReport::default();
+    w.set_status(RpcStatus::Inactive);
+    w.set_error(None);
+    w.thread_alive.store(false, Ordering::SeqCst);
+}
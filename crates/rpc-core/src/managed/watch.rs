@@ -0,0 +1,91 @@
+//! The worker's status as a watch: one writer (the worker), any number of [`StatusWatch`]es that
+//! block until it changes instead of polling. Each watch sees the latest status, not every
+//! transition; a watch that falls behind skips straight to the current value.
+
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::RpcStatus;
+
+/// The status and a version bumped on every change.
+pub(super) struct StatusCell {
+    state: Mutex<(RpcStatus, u64)>,
+    cv: Condvar,
+}
+
+impl StatusCell {
+    pub(super) fn new() -> Arc<StatusCell> {
+        Arc::new(StatusCell { state: Mutex::new((RpcStatus::Inactive, 0)), cv: Condvar::new() })
+    }
+
+    pub(super) fn get(&self) -> RpcStatus {
+        self.state.lock().unwrap().0
+    }
+
+    /// Stores `status` and wakes the watches, unless it is the status already stored.
+    pub(super) fn set(&self, status: RpcStatus) {
+        let mut state = self.state.lock().unwrap();
+        if state.0 != status {
+            *state = (status, state.1 + 1);
+            self.cv.notify_all();
+        }
+    }
+
+    pub(super) fn watch(self: &Arc<Self>) -> StatusWatch {
+        let seen = self.state.lock().unwrap().1;
+        StatusWatch { cell: self.clone(), seen }
+    }
+}
+
+/// Observes the worker's status; from [`ManagedClient::watch_status`](super::ManagedClient::watch_status).
+/// Cheap to clone, and clones keep their own place.
+///
+/// ```no_run
+/// use rpc_core::managed::ManagedClient;
+///
+/// let client = ManagedClient::new();
+/// let mut watch = client.watch_status();
+/// std::thread::spawn(move || loop {
+///     let status = watch.changed();
+///     println!("now {}", status.as_str());
+/// });
+/// ```
+#[derive(Clone)]
+pub struct StatusWatch {
+    cell: Arc<StatusCell>,
+    seen: u64,
+}
+
+impl StatusWatch {
+    /// The current status, without marking it seen.
+    pub fn current(&self) -> RpcStatus {
+        self.cell.get()
+    }
+
+    /// Whether the status changed since this watch last returned it.
+    pub fn has_changed(&self) -> bool {
+        self.cell.state.lock().unwrap().1 != self.seen
+    }
+
+    /// Blocks until the status changes, then returns it.
+    pub fn changed(&mut self) -> RpcStatus {
+        let state = self.cell.state.lock().unwrap();
+        let state = self.cell.cv.wait_while(state, |s| s.1 == self.seen).unwrap();
+        self.seen = state.1;
+        state.0
+    }
+
+    /// Like [`changed`](Self::changed), but gives up after `timeout` and returns `None`.
+    pub fn changed_timeout(&mut self, timeout: Duration) -> Option<RpcStatus> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.cell.state.lock().unwrap();
+        while state.1 == self.seen {
+            let left = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero())?;
+            state = self.cell.cv.wait_timeout(state, left).unwrap().0;
+        }
+        self.seen = state.1;
+        Some(state.0)
+    }
+}
@@ -0,0 +1,100 @@
+//! Marquee mode: details and state text longer than the visible width scroll through it across
+//! successive updates, like a now-playing ticker. Which part is shown follows the wall clock, so
+//! every sender of the same presence shows the same frame.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{validate::MAX_TEXT, PresenceCfg};
+
+/// Shown between the end of the text and its start coming round again.
+const GAP: &str = "   ";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Marquee {
+    pub enabled: bool,
+    /// Characters visible at once; text up to this long does not scroll. Clamped to
+    /// [`MIN_WIDTH`](Self::MIN_WIDTH)..=128.
+    pub width: u32,
+    /// Characters the text moves per frame.
+    pub step: u32,
+    /// Seconds per frame, at least [`MIN_INTERVAL_SECS`](Self::MIN_INTERVAL_SECS).
+    pub interval_secs: u32,
+}
+
+impl Marquee {
+    pub const MIN_WIDTH: u32 = 8;
+    /// Discord accepts about five activity updates per 20 seconds.
+    pub const MIN_INTERVAL_SECS: u32 = 4;
+
+    pub fn width(&self) -> usize {
+        (self.width as usize).clamp(Self::MIN_WIDTH as usize, MAX_TEXT)
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(Self::MIN_INTERVAL_SECS).into())
+    }
+
+    /// The `width` characters of `text` visible in frame number `frame`, or `text` itself when it
+    /// fits.
+    pub fn frame(&self, text: &str, frame: u64) -> String {
+        let width = self.width();
+        let chars: Vec<&str> = text.graphemes(true).collect();
+        if chars.len() <= width {
+            return text.to_string();
+        }
+        let looped: Vec<&str> = chars.into_iter().chain(GAP.graphemes(true)).collect();
+        let start = (frame.wrapping_mul(self.step.max(1).into()) % looped.len() as u64) as usize;
+        looped.iter().cycle().skip(start).take(width).copied().collect()
+    }
+}
+
+impl Default for Marquee {
+    fn default() -> Self {
+        Marquee { enabled: false, width: 32, step: 3, interval_secs: 5 }
+    }
+}
+
+impl PresenceCfg {
+    /// A copy with details and state cut to the marquee frame for Unix time `now`; unchanged when
+    /// the marquee is off.
+    pub fn scrolled(&self, now: i64) -> PresenceCfg {
+        let mut out = self.clone();
+        if self.marquee.enabled {
+            let frame = now.max(0) as u64 / self.marquee.interval().as_secs();
+            out.details = self.marquee.frame(&self.details, frame);
+            out.state = self.marquee.frame(&self.state, frame);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_text_scrolls_and_wraps_around() {
+        let m = Marquee { enabled: true, width: 8, step: 4, interval_secs: 5 };
+        let text = "Never Gonna Give You Up";
+        assert_eq!(m.frame(text, 0), "Never Go");
+        assert_eq!(m.frame(text, 1), "r Gonna ");
+        assert_eq!(m.frame(text, 5), " Up   Ne");
+        assert_eq!(m.frame(text, 0), m.frame(text, 26 / 2), "one full loop is 26 characters");
+        assert_eq!(m.frame("Short", 3), "Short");
+    }
+
+    #[test]
+    fn frames_follow_the_clock_only_when_enabled() {
+        let mut cfg = crate::PresenceBuilder::new("1").details("a".repeat(20) + &"b".repeat(20)).state("idle").build();
+        assert_eq!(cfg.scrolled(100).details, cfg.details);
+        cfg.marquee = Marquee { enabled: true, width: 20, step: 20, interval_secs: 1 };
+        assert_eq!(cfg.scrolled(3).details, "a".repeat(20), "one frame per 4 seconds at most");
+        assert_eq!(cfg.scrolled(4).details, "b".repeat(20));
+        assert_eq!(cfg.scrolled(4).state, "idle");
+    }
+}
@@ -0,0 +1,245 @@
+//! Fake Discord IPC endpoint.
+//!
+//! Speaks enough of the RPC protocol (handshake/READY, SET_ACTIVITY acks, PING/PONG) for UI work
+//! and integration tests to run without a Discord client. Failures can be injected at runtime.
+
+use serde_json::json;
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use interprocess::local_socket::traits::{Listener as _, Stream as _};
+use interprocess::local_socket::{GenericFilePath, ListenerOptions, ToFsName};
+
+use crate::ipc::{read_frame, send_frame, IpcStream};
+
+#[derive(Default)]
+struct MockState {
+    stop: AtomicBool,
+    reject_handshake: AtomicBool,
+    drop_next: AtomicBool,
+    strip_buttons: AtomicBool,
+    hang: AtomicBool,
+    fail_every: Mutex<Option<u32>>,
+    pending_errors: Mutex<VecDeque<(i64, String)>>,
+    activity_count: Mutex<u32>,
+    activities: Mutex<Vec<serde_json::Value>>,
+}
+
+/// A running mock server. The listener stops when this is dropped.
+pub struct MockDiscord {
+    path: String,
+    state: Arc<MockState>,
+}
+
+/// Path the mock binary listens on when none is given. Deliberately not a `discord-ipc-N`
+/// name, so it never shadows a real client during discovery.
+pub fn default_path() -> String {
+    #[cfg(unix)]
+    {
+        std::env::temp_dir().join("discord-ipc-mock").to_string_lossy().into_owned()
+    }
+    #[cfg(windows)]
+    {
+        r"\\.\pipe\discord-ipc-mock".to_string()
+    }
+}
+
+impl MockDiscord {
+    pub fn start(path: impl Into<String>) -> io::Result<Self> {
+        let path = path.into();
+
+        #[cfg(unix)]
+        {
+            // A previous run that was killed leaves the socket file behind.
+            let _ = std::fs::remove_file(&path);
+        }
+
+        let name = path.clone().to_fs_name::<GenericFilePath>()?;
+        let listener = ListenerOptions::new().name(name).create_sync()?;
+        let state = Arc::new(MockState::default());
+
+        let st = Arc::clone(&state);
+        thread::spawn(move || loop {
+            let conn = listener.accept();
+            if st.stop.load(Ordering::SeqCst) {
+                break;
+            }
+            if let Ok(stream) = conn {
+                let st2 = Arc::clone(&st);
+                thread::spawn(move || {
+                    let _ = serve(stream, &st2);
+                });
+            }
+        });
+
+        Ok(Self { path, state })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The next SET_ACTIVITY is answered with an ERROR event carrying `code`/`message`.
+    pub fn fail_next(&self, code: i64, message: impl Into<String>) {
+        self.state.pending_errors.lock().unwrap().push_back((code, message.into()));
+    }
+
+    /// Every `n`th SET_ACTIVITY fails (`None` disables it).
+    pub fn fail_every(&self, n: Option<u32>) {
+        *self.state.fail_every.lock().unwrap() = n.filter(|n| *n > 0);
+    }
+
+    /// Answer handshakes with a CLOSE frame, like Discord does for an invalid client id.
+    pub fn reject_handshakes(&self, reject: bool) {
+        self.state.reject_handshake.store(reject, Ordering::SeqCst);
+    }
+
+    /// Hang up on the next SET_ACTIVITY without answering, as if Discord quit.
+    pub fn drop_next(&self) {
+        self.state.drop_next.store(true, Ordering::SeqCst);
+    }
+
+    /// Echo activities back without their buttons, the way Discord does when it will not show them.
+    pub fn strip_buttons(&self, strip: bool) {
+        self.state.strip_buttons.store(strip, Ordering::SeqCst);
+    }
+
+    /// Keep connections open but stop answering, as if Discord froze. Activities are still recorded.
+    pub fn hang(&self, hang: bool) {
+        self.state.hang.store(hang, Ordering::SeqCst);
+    }
+
+    /// Every activity received so far (`null` for clears), oldest first.
+    pub fn activities(&self) -> Vec<serde_json::Value> {
+        self.state.activities.lock().unwrap().clone()
+    }
+
+    pub fn last_activity(&self) -> Option<serde_json::Value> {
+        self.state.activities.lock().unwrap().last().cloned()
+    }
+}
+
+impl Drop for MockDiscord {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::SeqCst);
+        // Unblock the accept loop so it can observe the stop flag.
+        if let Ok(name) = self.path.as_str().to_fs_name::<GenericFilePath>() {
+            let _ = IpcStream::connect(name);
+        }
+    }
+}
+
+fn serve(mut stream: IpcStream, st: &MockState) -> io::Result<()> {
+    let (op, hs) = read_frame(&mut stream)?;
+    if op != 0 {
+        return Ok(());
+    }
+    if st.reject_handshake.load(Ordering::SeqCst) {
+        send_frame(&mut stream, 2, &json!({ "code": 4000, "message": "Invalid Client ID" }))?;
+        return Ok(());
+    }
+
+    let ready = json!({
+        "cmd": "DISPATCH",
+        "evt": "READY",
+        "data": {
+            "v": 1,
+            "config": {
+                "cdn_host": "cdn.discordapp.com",
+                "api_endpoint": "//discord.com/api",
+                "environment": "mock"
+            },
+            "user": {
+                "id": "100000000000000001",
+                "username": "mock-user",
+                "discriminator": "0",
+                "global_name": "Mock User",
+                "avatar": null
+            },
+            "client_id": hs.get("client_id").cloned().unwrap_or(serde_json::Value::Null)
+        },
+        "nonce": null
+    });
+    send_frame(&mut stream, 1, &ready)?;
+
+    while !st.stop.load(Ordering::SeqCst) {
+        let (op, msg) = read_frame(&mut stream)?;
+        match op {
+            1 => {}
+            2 => return Ok(()),
+            3 => {
+                if !st.hang.load(Ordering::SeqCst) {
+                    send_frame(&mut stream, 4, &msg)?;
+                }
+                continue;
+            }
+            _ => continue,
+        }
+
+        let cmd = msg.get("cmd").and_then(|v| v.as_str()).unwrap_or("").to_string();
+        let nonce = msg.get("nonce").cloned().unwrap_or(serde_json::Value::Null);
+
+        if cmd != "SET_ACTIVITY" {
+            let err = json!({
+                "cmd": cmd,
+                "evt": "ERROR",
+                "data": { "code": 4000, "message": format!("Unknown command {}", cmd) },
+                "nonce": nonce
+            });
+            send_frame(&mut stream, 1, &err)?;
+            continue;
+        }
+
+        if st.drop_next.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let count = {
+            let mut c = st.activity_count.lock().unwrap();
+            *c += 1;
+            *c
+        };
+        let periodic = st.fail_every.lock().unwrap().is_some_and(|n| count % n == 0);
+        let injected = st.pending_errors.lock().unwrap().pop_front();
+        let failure = injected.or_else(|| periodic.then(|| (4000, "Mock failure".to_string())));
+
+        if let Some((code, message)) = failure {
+            let err = json!({
+                "cmd": "SET_ACTIVITY",
+                "evt": "ERROR",
+                "data": { "code": code, "message": message },
+                "nonce": nonce
+            });
+            send_frame(&mut stream, 1, &err)?;
+            continue;
+        }
+
+        let activity = msg
+            .get("args")
+            .and_then(|a| a.get("activity"))
+            .cloned()
+            .unwrap_or(serde_json::Value::Null);
+        st.activities.lock().unwrap().push(activity.clone());
+        if st.hang.load(Ordering::SeqCst) {
+            continue;
+        }
+
+        let mut echoed = activity;
+        if st.strip_buttons.load(Ordering::SeqCst) {
+            if let Some(a) = echoed.as_object_mut() {
+                a.remove("buttons");
+            }
+        }
+        let ack = json!({ "cmd": "SET_ACTIVITY", "evt": null, "data": echoed, "nonce": nonce });
+        send_frame(&mut stream, 1, &ack)?;
+    }
+
+    Ok(())
+}
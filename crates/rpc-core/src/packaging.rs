@@ -0,0 +1,111 @@
+//! How this app was packaged, where that changes what it can reach.
+//!
+//! Inside a Flatpak, settings and caches live in the sandbox (`~/.var/app/<id>/…`). Flatpak points
+//! `XDG_CONFIG_HOME` and friends there, and directory lookups follow those variables. What a
+//! Flatpak does not get by default is Discord's socket: the runtime directory is private, and each
+//! socket has to be granted with a `--filesystem` permission. [`missing_flatpak_permissions`]
+//! reads the sandbox's own permissions, so a failed discovery can name exactly what to grant.
+//!
+//! An AppImage is not sandboxed. In portable mode its runtime points `HOME` and `XDG_CONFIG_HOME`
+//! at directories next to the image, which directory lookups follow as well.
+
+use std::path::PathBuf;
+
+/// Describes the sandbox of a running Flatpak: app id and granted permissions.
+const FLATPAK_INFO: &str = "/.flatpak-info";
+
+/// `--filesystem` permissions a Flatpak of this app needs to see the sockets of the Discord app,
+/// Discord's own Flatpak and Vesktop's Flatpak.
+pub const FLATPAK_PERMISSIONS: &[&str] =
+    &["xdg-run/discord-ipc-0", "xdg-run/app/com.discordapp.Discord:create", "xdg-run/.flatpak/dev.vencord.Vesktop:create"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packaging {
+    /// Installed or run from a build; nothing special.
+    Plain,
+    Flatpak { app_id: String },
+    /// The `.AppImage` file itself.
+    AppImage { path: PathBuf },
+}
+
+/// How the running process was packaged.
+pub fn detect() -> Packaging {
+    if let Ok(app_id) = std::env::var("FLATPAK_ID") {
+        return Packaging::Flatpak { app_id };
+    }
+    if let Some(app_id) = std::fs::read_to_string(FLATPAK_INFO).ok().and_then(|info| ini_value(&info, "Application", "name")) {
+        return Packaging::Flatpak { app_id };
+    }
+    match std::env::var_os("APPIMAGE") {
+        Some(path) => Packaging::AppImage { path: path.into() },
+        None => Packaging::Plain,
+    }
+}
+
+/// The [`FLATPAK_PERMISSIONS`] the running Flatpak lacks. Empty outside a Flatpak.
+pub fn missing_flatpak_permissions() -> Vec<&'static str> {
+    match std::fs::read_to_string(FLATPAK_INFO) {
+        Ok(info) => missing_from(&info),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// `flatpak override` command that grants `missing` to `app_id`.
+pub fn flatpak_override_command<S: AsRef<str>>(app_id: &str, missing: &[S]) -> String {
+    let flags: Vec<String> = missing.iter().map(|p| format!("--filesystem={}", p.as_ref())).collect();
+    format!("flatpak override --user {} {}", flags.join(" "), app_id)
+}
+
+fn missing_from(info: &str) -> Vec<&'static str> {
+    let granted = ini_value(info, "Context", "filesystems").unwrap_or_default();
+    let granted: Vec<&str> = granted.split(';').map(str::trim).filter(|s| !s.is_empty()).map(strip_mode).collect();
+    FLATPAK_PERMISSIONS
+        .iter()
+        .copied()
+        .filter(|need| {
+            let need = strip_mode(need);
+            !granted.iter().any(|g| need == *g || need.strip_prefix(g).is_some_and(|rest| rest.starts_with('/')))
+        })
+        .collect()
+}
+
+/// A permission without its `:ro`, `:rw` or `:create` suffix.
+fn strip_mode(permission: &str) -> &str {
+    permission.rsplit_once(':').map_or(permission, |(path, _)| path)
+}
+
+fn ini_value(text: &str, section: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_section = name == section;
+        } else if in_section {
+            if let Some((k, v)) = line.split_once('=') {
+                if k.trim() == key {
+                    return Some(v.trim().to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn granted_permissions_are_not_reported() {
+        let none = "[Application]\nname=com.example.App\n\n[Context]\nshared=network;ipc;\n";
+        assert_eq!(missing_from(none), FLATPAK_PERMISSIONS);
+        assert_eq!(ini_value(none, "Application", "name").as_deref(), Some("com.example.App"));
+
+        let some = "[Context]\nfilesystems=xdg-run/discord-ipc-0;xdg-run/app/com.discordapp.Discord:ro;\n";
+        assert_eq!(missing_from(some), ["xdg-run/.flatpak/dev.vencord.Vesktop:create"]);
+        assert!(missing_from("[Context]\nfilesystems=xdg-run;\n").is_empty());
+        assert_eq!(
+            flatpak_override_command("com.example.App", &["xdg-run/discord-ipc-0"]),
+            "flatpak override --user --filesystem=xdg-run/discord-ipc-0 com.example.App"
+        );
+    }
+}
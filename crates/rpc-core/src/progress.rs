@@ -0,0 +1,130 @@
+//! Unicode progress bars like `▰▰▰▱▱ 60%` for presence text, from a value and a maximum such as a
+//! media position and its duration, or the minutes into a pomodoro.
+//!
+//! As a [template](crate::template) placeholder, `{bar:<value>/<max>}` renders one, where each side
+//! is a number or the name of another variable: `{bar:position/duration}`, `{bar:elapsed/1500}`.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarStyle {
+    /// `▰▰▰▱▱`
+    #[default]
+    Blocks,
+    /// `■■■□□`
+    Squares,
+    /// `███░░`
+    Shades,
+    /// `●●●○○`
+    Circles,
+    /// `###--`, for fonts without the others.
+    Ascii,
+}
+
+impl BarStyle {
+    pub const ALL: [BarStyle; 5] = [BarStyle::Blocks, BarStyle::Squares, BarStyle::Shades, BarStyle::Circles, BarStyle::Ascii];
+
+    /// Name used in config files and on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BarStyle::Blocks => "blocks",
+            BarStyle::Squares => "squares",
+            BarStyle::Shades => "shades",
+            BarStyle::Circles => "circles",
+            BarStyle::Ascii => "ascii",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<BarStyle> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    fn glyphs(&self) -> (&'static str, &'static str) {
+        match self {
+            BarStyle::Blocks => ("▰", "▱"),
+            BarStyle::Squares => ("■", "□"),
+            BarStyle::Shades => ("█", "░"),
+            BarStyle::Circles => ("●", "○"),
+            BarStyle::Ascii => ("#", "-"),
+        }
+    }
+}
+
+/// How `{bar:…}` placeholders look.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bars {
+    pub style: BarStyle,
+    /// Cells in the bar, 1 to [`MAX_WIDTH`](Self::MAX_WIDTH).
+    pub width: u32,
+    /// Append the percentage.
+    pub percent: bool,
+}
+
+impl Default for Bars {
+    fn default() -> Self {
+        Bars { style: BarStyle::Blocks, width: 10, percent: true }
+    }
+}
+
+impl Bars {
+    pub const MAX_WIDTH: u32 = 40;
+
+    /// The bar for `value` out of `max`; values outside `0..=max` are clamped and a `max` of zero
+    /// counts as empty.
+    pub fn render(&self, value: f64, max: f64) -> String {
+        let ratio = if max > 0.0 && value.is_finite() { (value / max).clamp(0.0, 1.0) } else { 0.0 };
+        let width = self.width.clamp(1, Self::MAX_WIDTH) as usize;
+        let filled = (ratio * width as f64).round() as usize;
+        let (on, off) = self.style.glyphs();
+        let mut out = on.repeat(filled) + &off.repeat(width - filled);
+        if self.percent {
+            out.push_str(&format!(" {}%", (ratio * 100.0).round()));
+        }
+        out
+    }
+
+    /// `vars` extended with `bar:<value>/<max>` names. A bar whose sides are not numbers (directly
+    /// or through `vars`) stays unresolved, so a later render with more variables can fill it in.
+    pub fn vars<'a>(&'a self, vars: &'a dyn Fn(&str) -> Option<String>) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name: &str| {
+            let Some(args) = name.strip_prefix("bar:") else { return vars(name) };
+            let (value, max) = args.split_once('/')?;
+            let number = |s: &str| s.trim().parse::<f64>().ok().or_else(|| vars(s)?.trim().parse().ok());
+            Some(self.render(number(value)?, number(max)?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_each_style_and_clamps() {
+        let bars = Bars { width: 5, ..Bars::default() };
+        assert_eq!(bars.render(3.0, 5.0), "▰▰▰▱▱ 60%");
+        assert_eq!(bars.render(9.0, 5.0), "▰▰▰▰▰ 100%");
+        assert_eq!(bars.render(1.0, 0.0), "▱▱▱▱▱ 0%");
+        let ascii = Bars { style: BarStyle::Ascii, width: 4, percent: false };
+        assert_eq!(ascii.render(1.0, 4.0), "#---");
+        assert_eq!(BarStyle::from_name("circles"), Some(BarStyle::Circles));
+    }
+
+    #[test]
+    fn bar_placeholders_read_numbers_and_variables() {
+        let bars = Bars { width: 4, percent: false, ..Bars::default() };
+        let vars = |name: &str| match name {
+            "pos" => Some("30".to_string()),
+            "title" => Some("Song".to_string()),
+            _ => None,
+        };
+        let render = |text: &str| crate::template::render(text, &bars.vars(&vars));
+        assert_eq!(render("{title} {bar:pos/60}"), "Song ▰▰▱▱");
+        assert_eq!(render("{bar:3/4}"), "▰▰▰▱");
+        assert_eq!(render("{bar:elapsed/60}"), "{bar:elapsed/60}");
+    }
+}
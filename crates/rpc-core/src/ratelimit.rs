@@ -0,0 +1,174 @@
+//! Minimum delays between user actions, so a double click or a looping script does not flood
+//! Discord. Each front end keeps its own [`RateLimiter`]; the delays come from a [`RateLimits`]
+//! table that can be one of the presets or set by hand.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// What a caller is about to do; each kind has its own delay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Lookups against the Discord HTTP API: app metadata, invites, the signed-in user.
+    Sync,
+    /// Enabling or disabling the presence, which opens or closes the IPC connection.
+    Toggle,
+    /// Pushing a new activity over an open connection.
+    Update,
+}
+
+/// Minimum delay in milliseconds since the previous action, per kind of action.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimits {
+    pub sync_ms: u64,
+    pub toggle_ms: u64,
+    pub update_ms: u64,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl RateLimits {
+    /// Paced for clicks in a UI.
+    pub const DEFAULT: RateLimits = RateLimits { sync_ms: 650, toggle_ms: 900, update_ms: 350 };
+    /// For local automation (REST, CLI, hooks) that sends bursts on purpose. Updates still stay
+    /// above what Discord drops silently.
+    pub const RELAXED: RateLimits = RateLimits { sync_ms: 200, toggle_ms: 250, update_ms: 100 };
+    /// No delays at all.
+    pub const OFF: RateLimits = RateLimits { sync_ms: 0, toggle_ms: 0, update_ms: 0 };
+
+    pub fn delay(&self, action: Action) -> Duration {
+        Duration::from_millis(match action {
+            Action::Sync => self.sync_ms,
+            Action::Toggle => self.toggle_ms,
+            Action::Update => self.update_ms,
+        })
+    }
+
+    /// Reads a preset name (`default`, `relaxed`, `off`) or a list such as
+    /// `sync=500,toggle=800,update=300`; kinds left out keep their default delay.
+    pub fn parse(s: &str) -> Result<RateLimits, String> {
+        match s.trim() {
+            "default" => return Ok(Self::DEFAULT),
+            "relaxed" => return Ok(Self::RELAXED),
+            "off" => return Ok(Self::OFF),
+            _ => {}
+        }
+        let mut limits = Self::DEFAULT;
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, ms) = part.split_once('=').ok_or_else(|| format!("expected name=ms, got \"{}\"", part))?;
+            let ms: u64 = ms.trim().parse().map_err(|_| format!("invalid delay for {}: \"{}\"", name.trim(), ms.trim()))?;
+            match name.trim() {
+                "sync" => limits.sync_ms = ms,
+                "toggle" => limits.toggle_ms = ms,
+                "update" => limits.update_ms = ms,
+                other => return Err(format!("unknown rate limit \"{}\" (expected sync, toggle or update)", other)),
+            }
+        }
+        Ok(limits)
+    }
+}
+
+/// Named choices for a settings screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RatePreset {
+    #[default]
+    Default,
+    Relaxed,
+    Custom,
+}
+
+impl RatePreset {
+    pub const ALL: [RatePreset; 3] = [RatePreset::Default, RatePreset::Relaxed, RatePreset::Custom];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RatePreset::Default => "Default",
+            RatePreset::Relaxed => "Relaxed",
+            RatePreset::Custom => "Custom",
+        }
+    }
+
+    /// The table this preset stands for; `custom` is used as is for [`RatePreset::Custom`].
+    pub fn limits(self, custom: RateLimits) -> RateLimits {
+        match self {
+            RatePreset::Default => RateLimits::DEFAULT,
+            RatePreset::Relaxed => RateLimits::RELAXED,
+            RatePreset::Custom => custom,
+        }
+    }
+}
+
+/// Error returned while an action is still inside its delay.
+pub const RATE_LIMITED: &str = "Rate limit: please wait a moment before repeating the action.";
+
+/// Tracks the last allowed action. Actions share one clock, so an update right after a toggle
+/// waits for the update delay.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    limits: Mutex<RateLimits>,
+    last: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        RateLimiter { limits: Mutex::new(limits), last: Mutex::new(None) }
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        *self.limits.lock().unwrap()
+    }
+
+    pub fn set_limits(&self, limits: RateLimits) {
+        *self.limits.lock().unwrap() = limits;
+    }
+
+    /// Records the action, or fails with [`RATE_LIMITED`] if the previous one was too recent.
+    pub fn check(&self, action: Action) -> Result<(), String> {
+        let delay = self.limits().delay(action);
+        let mut last = self.last.lock().unwrap();
+        if last.is_some_and(|t| t.elapsed() < delay) {
+            return Err(RATE_LIMITED.to_string());
+        }
+        *last = Some(Instant::now());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_presets_and_custom_tables() {
+        assert_eq!(RateLimits::parse("relaxed"), Ok(RateLimits::RELAXED));
+        assert_eq!(RateLimits::parse("off"), Ok(RateLimits::OFF));
+        assert_eq!(
+            RateLimits::parse("sync=100, update=50"),
+            Ok(RateLimits { sync_ms: 100, toggle_ms: 900, update_ms: 50 })
+        );
+        assert!(RateLimits::parse("sync=fast").is_err());
+        assert!(RateLimits::parse("clicks=10").is_err());
+    }
+
+    #[test]
+    fn repeats_inside_the_delay_are_refused() {
+        let limiter = RateLimiter::new(RateLimits { sync_ms: 60_000, toggle_ms: 60_000, update_ms: 0 });
+        assert!(limiter.check(Action::Sync).is_ok());
+        assert_eq!(limiter.check(Action::Toggle), Err(RATE_LIMITED.to_string()));
+        assert!(limiter.check(Action::Update).is_ok());
+
+        limiter.set_limits(RateLimits::OFF);
+        assert!(limiter.check(Action::Sync).is_ok());
+        assert!(limiter.check(Action::Sync).is_ok());
+    }
+}
@@ -0,0 +1,152 @@
+//! Recorded sessions: the [`ControlRequest`]s applied to a worker, each with its offset from the
+//! start of the recording, one JSON object per line. Replaying one re-applies the requests with the
+//! original timing, for demo videos and for running the worker against real usage patterns.
+//!
+//! ```json
+//! {"at_ms":0,"type":"enable","presence":{"client_id":"123456789012345678","details":"Editing"}}
+//! {"at_ms":95000,"type":"update","presence":{"client_id":"123456789012345678","details":"Testing"}}
+//! {"at_ms":180250,"type":"disable"}
+//! ```
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::control::ControlRequest;
+
+/// One step of a session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Milliseconds since the recording started.
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub request: ControlRequest,
+}
+
+/// Appends requests to a session file as they are applied. Status requests change nothing and are
+/// not recorded.
+#[derive(Debug)]
+pub struct Recorder {
+    start: Instant,
+    out: Mutex<File>,
+}
+
+impl Recorder {
+    /// Starts a new recording at `path`, replacing the file if it exists.
+    pub fn create(path: &Path) -> io::Result<Recorder> {
+        let out = OpenOptions::new().create(true).write(true).truncate(true).open(path)?;
+        Ok(Recorder { start: Instant::now(), out: Mutex::new(out) })
+    }
+
+    pub fn record(&self, request: &ControlRequest) -> io::Result<()> {
+        if matches!(request, ControlRequest::Status) {
+            return Ok(());
+        }
+        let entry = Entry { at_ms: self.start.elapsed().as_millis() as u64, request: request.clone() };
+        let mut line = serde_json::to_string(&entry).map_err(io::Error::other)?;
+        line.push('\n');
+        // One write per line, so a crash leaves at most the last line cut off.
+        self.out.lock().unwrap().write_all(line.as_bytes())
+    }
+
+    /// Makes sure what was recorded is on disk.
+    pub fn flush(&self) -> io::Result<()> {
+        self.out.lock().unwrap().sync_all()
+    }
+}
+
+/// Reads a session. Blank lines are skipped; steps must be in time order.
+pub fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries: Vec<Entry> = Vec::new();
+    for (n, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let entry: Entry = serde_json::from_str(line).map_err(|e| format!("line {}: {}", n + 1, e))?;
+        if entries.last().is_some_and(|last| entry.at_ms < last.at_ms) {
+            return Err(format!("line {}: at_ms goes back in time", n + 1));
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+pub fn load(path: &Path) -> Result<Vec<Entry>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Carries out one request on `client`. Only pausing needs the presence to be enabled.
+#[cfg(feature = "ipc")]
+pub fn apply(client: &crate::managed::ManagedClient, request: ControlRequest) -> Result<(), String> {
+    match request {
+        ControlRequest::Enable { presence } => client.enable(presence),
+        ControlRequest::Update { presence } => client.update(presence),
+        ControlRequest::Disable => client.disable(),
+        ControlRequest::Pause if !client.is_running() => return Err("The presence is not enabled.".to_string()),
+        ControlRequest::Pause => client.pause(),
+        ControlRequest::Resume => client.resume(),
+        ControlRequest::Status => {}
+    }
+    Ok(())
+}
+
+/// Re-applies `entries` to `client` with their original spacing divided by `speed`, until done or
+/// `stop` is cancelled. Returns how many steps were applied; steps that fail (pausing while disabled)
+/// count too, as they would have failed the same way when recorded.
+#[cfg(feature = "ipc")]
+pub fn replay(
+    client: &crate::managed::ManagedClient,
+    entries: &[Entry],
+    speed: f64,
+    stop: &crate::shutdown::CancelToken,
+) -> usize {
+    use std::time::Duration;
+
+    let speed = if speed.is_finite() && speed > 0.0 { speed } else { 1.0 };
+    let start = Instant::now();
+    for (done, entry) in entries.iter().enumerate() {
+        let due = Duration::from_secs_f64(entry.at_ms as f64 / 1000.0 / speed);
+        let left = due.saturating_sub(start.elapsed());
+        if stop.wait_timeout(left) {
+            return done;
+        }
+        let _ = apply(client, entry.request.clone());
+    }
+    entries.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PresenceCfg;
+
+    #[test]
+    fn recorded_sessions_read_back() {
+        let path = std::env::temp_dir().join(format!("crp-session-{}.jsonl", std::process::id()));
+        let recorder = Recorder::create(&path).unwrap();
+        let presence = PresenceCfg { client_id: "123".to_string(), details: "Editing".to_string(), ..Default::default() };
+        recorder.record(&ControlRequest::Enable { presence: presence.clone() }).unwrap();
+        recorder.record(&ControlRequest::Status).unwrap();
+        recorder.record(&ControlRequest::Disable).unwrap();
+
+        let entries = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(&entries[0].request, ControlRequest::Enable { presence: p } if *p == presence));
+        assert!(matches!(entries[1].request, ControlRequest::Disable));
+        assert!(entries[0].at_ms <= entries[1].at_ms);
+    }
+
+    #[test]
+    fn parse_rejects_bad_lines_and_time_travel() {
+        let ok = "{\"at_ms\":0,\"type\":\"pause\"}\n\n{\"at_ms\":10,\"type\":\"resume\"}\n";
+        assert_eq!(parse(ok).unwrap().len(), 2);
+        assert_eq!(parse("{\"at_ms\":0,\"type\":\"jump\"}").unwrap_err().split(':').next(), Some("line 1"));
+        let back = "{\"at_ms\":10,\"type\":\"pause\"}\n{\"at_ms\":5,\"type\":\"resume\"}";
+        assert_eq!(parse(back).unwrap_err(), "line 2: at_ms goes back in time");
+    }
+}
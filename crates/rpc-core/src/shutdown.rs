@@ -0,0 +1,134 @@
+//! Ordered shutdown for apps built around the worker. Background loops watch a [`CancelToken`]
+//! instead of running until the process dies; [`Shutdown::run`] cancels it, then runs the steps
+//! registered with [`then`](Shutdown::then) in order: typically stop the inputs that feed the
+//! presence, then the servers, then the worker (which clears the activity), and flush files last.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use rpc_core::{managed::ManagedClient, shutdown::Shutdown};
+//!
+//! let client = ManagedClient::new();
+//! let mut shutdown = Shutdown::new();
+//! let token = shutdown.token();
+//! std::thread::spawn(move || {
+//!     while !token.wait_timeout(Duration::from_secs(5)) {
+//!         // poll something
+//!     }
+//! });
+//! let worker = client.clone();
+//! shutdown.then("worker", move || {
+//!     worker.shutdown(Duration::from_secs(2));
+//! });
+//! shutdown.run();
+//! ```
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Tells background loops to stop. Cheap to clone; all clones share one flag.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        let (flag, cv) = &*self.inner;
+        *flag.lock().unwrap() = true;
+        cv.notify_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.inner.0.lock().unwrap()
+    }
+
+    /// Sleeps for `dur`, or less if cancelled meanwhile. Returns whether it was cancelled, so a
+    /// loop can use it in place of `thread::sleep`.
+    pub fn wait_timeout(&self, dur: Duration) -> bool {
+        let (flag, cv) = &*self.inner;
+        let deadline = Instant::now() + dur;
+        let mut cancelled = flag.lock().unwrap();
+        while !*cancelled {
+            let Some(left) = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) else {
+                break;
+            };
+            cancelled = cv.wait_timeout(cancelled, left).unwrap().0;
+        }
+        *cancelled
+    }
+}
+
+type Step = Box<dyn FnOnce() + Send>;
+
+/// A cancellation token plus the steps that tear the app down, in order.
+#[derive(Default)]
+pub struct Shutdown {
+    token: CancelToken,
+    steps: Vec<(&'static str, Step)>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The token cancelled when the shutdown starts.
+    pub fn token(&self) -> CancelToken {
+        self.token.clone()
+    }
+
+    /// Adds a step, run after every step added before it.
+    pub fn then(&mut self, name: &'static str, step: impl FnOnce() + Send + 'static) -> &mut Self {
+        self.steps.push((name, Box::new(step)));
+        self
+    }
+
+    /// Cancels the token and runs the steps in order. A step that panics does not stop the ones
+    /// after it; the names of those that did are returned.
+    pub fn run(self) -> Vec<&'static str> {
+        self.token.cancel();
+        let mut failed = Vec::new();
+        for (name, step) in self.steps {
+            if panic::catch_unwind(AssertUnwindSafe(step)).is_err() {
+                failed.push(name);
+            }
+        }
+        failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_run_in_order_after_cancelling() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut shutdown = Shutdown::new();
+        let token = shutdown.token();
+        assert!(!token.wait_timeout(Duration::from_millis(1)));
+        let waiter = std::thread::spawn(move || token.wait_timeout(Duration::from_secs(30)));
+
+        for name in ["inputs", "servers", "worker", "files"] {
+            let log = log.clone();
+            shutdown.then(name, move || {
+                if name == "servers" {
+                    panic!("server did not stop");
+                }
+                log.lock().unwrap().push(name);
+            });
+        }
+        let started = Instant::now();
+        assert_eq!(shutdown.run(), vec!["servers"]);
+        assert!(waiter.join().unwrap());
+        assert!(started.elapsed() < Duration::from_secs(30));
+        assert_eq!(*log.lock().unwrap(), ["inputs", "worker", "files"]);
+    }
+}
@@ -0,0 +1,146 @@
+//! `{name}` placeholders in presence text, filled in from variables right before publishing.
+//!
+//! Unknown names are left as written, so a typo shows up in the presence instead of vanishing.
+//! `{{` and `}}` produce literal braces. Names may contain `:` and `/` so helpers such as
+//! [`Bars::vars`](crate::progress::Bars::vars) can take arguments.
+
+use crate::PresenceCfg;
+
+/// `text` with every `{name}` that `vars` knows replaced by its value.
+pub fn render(text: &str, vars: &dyn Fn(&str) -> Option<String>) -> String {
+    expand(text, vars, false)
+}
+
+/// Like [`render`], but `{{` and `}}` stay as written, so the result can be rendered again. For
+/// filling in some names ahead of the others, such as a preset's own constants.
+pub fn fill(text: &str, vars: &dyn Fn(&str) -> Option<String>) -> String {
+    expand(text, vars, true)
+}
+
+fn expand(text: &str, vars: &dyn Fn(&str) -> Option<String>, keep_escapes: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..if keep_escapes { 2 } else { 1 }]);
+            rest = &tail[2..];
+            continue;
+        }
+        if tail.starts_with('{') {
+            if let Some(end) = tail.find('}') {
+                let name = &tail[1..end];
+                if is_name(name) {
+                    if let Some(value) = vars(name) {
+                        out.push_str(&value);
+                        rest = &tail[end + 1..];
+                        continue;
+                    }
+                }
+            }
+        }
+        out.push_str(&tail[..1]);
+        rest = &tail[1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Whether `text` has any placeholder, known or not.
+pub fn has_placeholders(text: &str) -> bool {
+    let found = std::cell::Cell::new(false);
+    render(text, &|_| {
+        found.set(true);
+        None
+    });
+    found.get()
+}
+
+fn is_name(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || "_.-:/".contains(c))
+}
+
+impl PresenceCfg {
+    /// A copy with placeholders rendered in every text, image and button field. The client id is
+    /// left alone.
+    pub fn render(&self, vars: &dyn Fn(&str) -> Option<String>) -> PresenceCfg {
+        self.map_text(&|s| render(s, vars))
+    }
+
+    /// Like [`render`](Self::render) with [`fill`], and the cycled images included, so what is
+    /// left can still be rendered when publishing.
+    pub fn fill(&self, vars: &dyn Fn(&str) -> Option<String>) -> PresenceCfg {
+        let mut out = self.map_text(&|s| fill(s, vars));
+        for image in &mut out.image_cycle.images {
+            *image = fill(image, vars);
+        }
+        out
+    }
+
+    fn map_text(&self, f: &dyn Fn(&str) -> String) -> PresenceCfg {
+        let opt = |v: &Option<String>| v.as_deref().map(f);
+        let mut out = self.clone();
+        out.details = f(&self.details);
+        out.state = f(&self.state);
+        out.large_image = opt(&self.large_image);
+        out.large_text = opt(&self.large_text);
+        out.small_image = opt(&self.small_image);
+        out.small_text = opt(&self.small_text);
+        for b in &mut out.buttons {
+            b.label = f(&b.label);
+            b.url = f(&b.url);
+        }
+        out
+    }
+
+    /// Whether any field [`render`](Self::render) looks at has a placeholder.
+    pub fn is_template(&self) -> bool {
+        [&self.details, &self.state]
+            .into_iter()
+            .chain([&self.large_image, &self.large_text, &self.small_image, &self.small_text].into_iter().flatten())
+            .chain(self.buttons.iter().flat_map(|b| [&b.label, &b.url]))
+            .any(|s| has_placeholders(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(name: &str) -> Option<String> {
+        match name {
+            "game" => Some("Celeste".to_string()),
+            "repo" => Some("rpc-core".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn replaces_known_names_and_keeps_the_rest() {
+        assert_eq!(render("Playing {game} ({unknown})", &vars), "Playing Celeste ({unknown})");
+        assert_eq!(render("{{game}} {game}}", &vars), "{game} Celeste}");
+        assert_eq!(render("{ game } {", &vars), "{ game } {");
+    }
+
+    #[test]
+    fn fill_keeps_escapes_for_the_final_render() {
+        let filled = fill("{{game}} {game} {time}", &vars);
+        assert_eq!(filled, "{{game}} Celeste {time}");
+        assert_eq!(render(&filled, &|_| None), "{game} Celeste {time}");
+    }
+
+    #[test]
+    fn renders_every_text_field() {
+        let cfg = crate::PresenceBuilder::new("{game}")
+            .details("{game}")
+            .button("{repo}", "https://github.com/x/{repo}")
+            .build();
+        assert!(cfg.is_template());
+        let out = cfg.render(&vars);
+        assert_eq!(out.client_id, "{game}");
+        assert_eq!(out.details, "Celeste");
+        assert_eq!(out.buttons[0].url, "https://github.com/x/rpc-core");
+        assert!(!out.is_template());
+    }
+}
@@ -0,0 +1,221 @@
+//! Field checks for a [`PresenceCfg`], shared by every frontend so they all reject the same input.
+
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{cycle::ImageCycle, PresenceCfg, TimerPolicy};
+
+/// Longest text Discord accepts in details, state and image hover texts.
+pub const MAX_TEXT: usize = 128;
+/// Longest details and state text with the [`marquee`](crate::marquee) on, which scrolls it
+/// through the visible width.
+pub const MAX_MARQUEE_TEXT: usize = 512;
+/// Longest image asset key or URL.
+pub const MAX_IMAGE: usize = 256;
+pub const MAX_BUTTON_LABEL: usize = 32;
+pub const MAX_BUTTON_URL: usize = 512;
+/// Discord shows at most this many buttons.
+pub const MAX_BUTTONS: usize = 2;
+
+/// Length of `s` as users see it: grapheme clusters, so an emoji with skin tone or a flag counts
+/// as one character. All limits here use it.
+pub fn text_len(s: &str) -> usize {
+    s.graphemes(true).count()
+}
+
+/// The first `max` characters of `s`, never splitting a grapheme cluster.
+pub fn truncate(s: &str, max: usize) -> &str {
+    match s.grapheme_indices(true).nth(max) {
+        Some((i, _)) => &s[..i],
+        None => s,
+    }
+}
+
+/// Checks a button URL as Discord would: an http(s) link to a plausible host, with no whitespace
+/// or leftover `{placeholders}`, within [`MAX_BUTTON_URL`].
+pub fn check_button_url(url: &str) -> Result<(), String> {
+    let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) else {
+        return Err("Must be an http(s) link.".to_string());
+    };
+    if text_len(url) > MAX_BUTTON_URL {
+        return Err(format!("Must be at most {} characters.", MAX_BUTTON_URL));
+    }
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_ok = !host.is_empty() && host.chars().all(|c| c.is_alphanumeric() || "-.:".contains(c));
+    if !host_ok || url.contains(|c: char| c.is_whitespace() || c.is_control() || c == '{' || c == '}') {
+        return Err("Not a valid link.".to_string());
+    }
+    Ok(())
+}
+
+/// One problem with one field. `field` uses the `PresenceCfg` JSON names, with buttons addressed
+/// as `buttons[0].label` / `buttons[1].url`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+}
+
+/// Every problem with `cfg`; empty when it can be published as is.
+pub fn validate(cfg: &PresenceCfg) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+
+    let id = cfg.client_id.trim();
+    if id.is_empty() {
+        errors.push(FieldError::new("client_id", "Client ID is required."));
+    } else if !id.chars().all(|c| c.is_ascii_digit()) || id.len() < 15 || id.len() > 21 {
+        errors.push(FieldError::new("client_id", "Client ID must be a numeric Discord application id."));
+    }
+
+    let details = cfg.details.trim();
+    let state = cfg.state.trim();
+    if text_len(details) < 2 && text_len(state) < 2 {
+        errors.push(FieldError::new("details", "Fill Details or State with at least 2 characters."));
+    }
+
+    let line_max = if cfg.marquee.enabled { MAX_MARQUEE_TEXT } else { MAX_TEXT };
+    let texts = [
+        ("details", Some(&cfg.details), line_max),
+        ("state", Some(&cfg.state), line_max),
+        ("large_image", cfg.large_image.as_ref(), MAX_IMAGE),
+        ("large_text", cfg.large_text.as_ref(), MAX_TEXT),
+        ("small_image", cfg.small_image.as_ref(), MAX_IMAGE),
+        ("small_text", cfg.small_text.as_ref(), MAX_TEXT),
+    ];
+    for (field, value, max) in texts {
+        let Some(v) = value else { continue };
+        if text_len(v) > max {
+            errors.push(FieldError::new(field, format!("Must be at most {} characters.", max)));
+        }
+    }
+    if cfg.image_cycle.images.len() > ImageCycle::MAX_IMAGES {
+        errors.push(FieldError::new("image_cycle", format!("At most {} images can be cycled.", ImageCycle::MAX_IMAGES)));
+    }
+    for (i, image) in cfg.image_cycle.images.iter().enumerate() {
+        if text_len(image) > MAX_IMAGE {
+            errors.push(FieldError::new(format!("image_cycle.images[{}]", i), format!("Must be at most {} characters.", MAX_IMAGE)));
+        }
+    }
+    for (field, value) in [("large_text", &cfg.large_text), ("small_text", &cfg.small_text)] {
+        if value.as_deref().is_some_and(|t| text_len(t.trim()) == 1) {
+            errors.push(FieldError::new(field, "Hover text needs at least 2 characters."));
+        }
+    }
+
+    if cfg.buttons.len() > MAX_BUTTONS {
+        errors.push(FieldError::new("buttons", format!("Discord shows at most {} buttons.", MAX_BUTTONS)));
+    }
+    for (i, b) in cfg.buttons.iter().enumerate() {
+        let label = b.label.trim();
+        let url = b.url.trim();
+        if label.is_empty() {
+            errors.push(FieldError::new(format!("buttons[{}].label", i), "Button label is required."));
+        } else if text_len(label) > MAX_BUTTON_LABEL {
+            errors.push(FieldError::new(
+                format!("buttons[{}].label", i),
+                format!("Must be at most {} characters.", MAX_BUTTON_LABEL),
+            ));
+        }
+        // A templated URL is only known once rendered; the worker re-checks it then and drops the
+        // button if it does not hold up.
+        let check = if crate::template::has_placeholders(url) && text_len(url) <= MAX_BUTTON_URL {
+            Ok(())
+        } else {
+            check_button_url(url)
+        };
+        if let Err(message) = check {
+            errors.push(FieldError::new(format!("buttons[{}].url", i), message));
+        }
+    }
+
+    if cfg.with_timestamp {
+        if let TimerPolicy::End { end } = cfg.timer {
+            if end <= crate::now_unix_ts() {
+                errors.push(FieldError::new("timer", "The countdown end is in the past."));
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PresenceBuilder;
+
+    fn fields(cfg: &PresenceCfg) -> Vec<String> {
+        validate(cfg).into_iter().map(|e| e.field).collect()
+    }
+
+    #[test]
+    fn accepts_a_complete_presence() {
+        let cfg = PresenceBuilder::new("123456789012345678")
+            .details("Editing")
+            .large_image("logo", Some("Logo"))
+            .button("Site", "https://example.com")
+            .build();
+        assert!(validate(&cfg).is_empty());
+    }
+
+    #[test]
+    fn reports_each_bad_field() {
+        let cfg = PresenceBuilder::new("abc")
+            .details("x")
+            .button("", "ftp://example.com")
+            .timer(TimerPolicy::End { end: 0 })
+            .build();
+        assert_eq!(fields(&cfg), ["client_id", "details", "buttons[0].label", "buttons[0].url", "timer"]);
+    }
+
+    #[test]
+    fn limits_lengths_and_button_count() {
+        let cfg = PresenceBuilder::new("123456789012345678")
+            .state("x".repeat(MAX_TEXT + 1))
+            .button("a", "https://a.example")
+            .button("b", "https://b.example")
+            .button("c", "https://c.example")
+            .build();
+        assert_eq!(fields(&cfg), ["state", "buttons"]);
+    }
+
+    #[test]
+    fn marquee_allows_longer_lines() {
+        let cfg = PresenceBuilder::new("123456789012345678")
+            .state("x".repeat(MAX_TEXT + 1))
+            .large_image("logo", Some(&"x".repeat(MAX_TEXT + 1)))
+            .marquee(Default::default())
+            .build();
+        assert_eq!(fields(&cfg), ["large_text"]);
+    }
+
+    #[test]
+    fn checks_button_urls_and_defers_templates() {
+        assert!(check_button_url("https://github.com/x/y?tab=1").is_ok());
+        assert!(check_button_url("http://localhost:8080/").is_ok());
+        for bad in ["https://", "https:///path", "https://open.spotify.com/track/{id}", "https://a b.com", "ftp://x.com"] {
+            assert!(check_button_url(bad).is_err(), "{}", bad);
+        }
+        let cfg = PresenceBuilder::new("123456789012345678").details("Listening").button("Song", "{track_url}").build();
+        assert!(validate(&cfg).is_empty());
+    }
+
+    #[test]
+    fn counts_emoji_as_single_characters() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let flag = "\u{1F1E7}\u{1F1F7}";
+        assert_eq!(text_len(family), 1);
+        assert_eq!(text_len(&format!("{}{}", flag, "e\u{301}")), 2);
+
+        let cfg = PresenceBuilder::new("123456789012345678").details(family.repeat(MAX_TEXT)).build();
+        assert!(validate(&cfg).is_empty());
+        assert_eq!(truncate(&format!("ab{}", family), 3), format!("ab{}", family));
+        assert_eq!(truncate(&format!("ab{}", family), 2), "ab");
+    }
+}
@@ -0,0 +1,399 @@
+use rpc_core::mock::MockDiscord;
+use rpc_core::managed::{ManagedClient, RpcStatus};
+use rpc_core::{ButtonCfg, DiscordRpcClient, PresenceCfg, RateLimited, TimerPolicy, PING_TIMEOUT};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+fn unique_path() -> String {
+    static N: AtomicUsize = AtomicUsize::new(0);
+    let name = format!("crp-mock-{}-{}", std::process::id(), N.fetch_add(1, Ordering::SeqCst));
+    #[cfg(unix)]
+    {
+        std::env::temp_dir().join(name).to_string_lossy().into_owned()
+    }
+    #[cfg(windows)]
+    {
+        format!(r"\\.\pipe\{}", name)
+    }
+}
+
+fn cfg() -> PresenceCfg {
+    PresenceCfg {
+        client_id: "123".to_string(),
+        details: "Testing".to_string(),
+        state: "with a mock".to_string(),
+        large_image: None,
+        large_text: None,
+        small_image: None,
+        small_text: None,
+        buttons: vec![ButtonCfg { label: "Repo".to_string(), url: "http://example.com".to_string() }],
+        with_timestamp: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn handshake_returns_ready_user() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (_client, ready) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+    assert_eq!(ready.user.username, "mock-user");
+    assert_eq!(ready.user.global_name.as_deref(), Some("Mock User"));
+    assert_eq!(ready.cdn_host.as_deref(), Some("cdn.discordapp.com"));
+    assert_eq!(ready.environment.as_deref(), Some("mock"));
+}
+
+#[test]
+fn set_activity_is_acked_and_recorded() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (mut client, _) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+
+    client.set_activity(&cfg(), 42).unwrap();
+
+    let sent = server.last_activity().unwrap();
+    assert_eq!(sent["details"], "Testing");
+    assert_eq!(sent["timestamps"]["start"], 42);
+    assert_eq!(sent["buttons"][0]["url"], "https://example.com");
+
+    client.clear_activity().unwrap();
+    assert!(server.last_activity().unwrap().is_null());
+}
+
+#[test]
+fn fields_missing_from_the_echo_are_reported() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (mut client, _) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+
+    client.set_activity(&cfg(), 42).unwrap();
+    assert!(client.echo_mismatches().is_empty());
+
+    server.strip_buttons(true);
+    client.set_activity(&cfg(), 42).unwrap();
+    assert_eq!(client.echo_mismatches(), ["Discord dropped the buttons."]);
+}
+
+#[test]
+fn buttons_with_invalid_expanded_urls_are_dropped() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (mut client, _) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+
+    let buttons = vec![
+        ButtonCfg { label: "Track".to_string(), url: "https://open.spotify.com/track/{track}".to_string() },
+        ButtonCfg { label: "Repo".to_string(), url: "https://github.com/{repo}".to_string() },
+    ];
+    let rendered = PresenceCfg { buttons, ..cfg() }.render(&|name| (name == "repo").then(|| "x/y".to_string()));
+    client.set_activity(&rendered, 0).unwrap();
+
+    let sent = server.last_activity().unwrap();
+    assert_eq!(sent["buttons"].as_array().unwrap().len(), 1);
+    assert_eq!(sent["buttons"][0]["url"], "https://github.com/x/y");
+}
+
+#[test]
+fn timer_policy_picks_the_timestamps() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (mut client, _) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+
+    client.set_activity(&PresenceCfg { timer: TimerPolicy::End { end: 100 }, ..cfg() }, 42).unwrap();
+    let sent = server.last_activity().unwrap();
+    assert_eq!(sent["timestamps"]["end"], 100);
+    assert!(sent["timestamps"].get("start").is_none());
+
+    client.set_activity(&PresenceCfg { timer: TimerPolicy::Fixed { start: 7 }, ..cfg() }, 42).unwrap();
+    assert_eq!(server.last_activity().unwrap()["timestamps"]["start"], 7);
+}
+
+#[test]
+fn dropping_the_client_clears_its_activity() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (mut client, _) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+    client.set_activity(&cfg(), 0).unwrap();
+
+    drop(client);
+    assert!(server.last_activity().unwrap().is_null());
+}
+
+#[test]
+fn dropping_a_client_does_not_wait_for_a_hung_discord() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (mut client, _) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+    client.set_activity(&cfg(), 0).unwrap();
+
+    server.hang(true);
+    let started = Instant::now();
+    drop(client);
+    assert!(started.elapsed() < Duration::from_secs(3));
+    assert!(server.last_activity().unwrap().is_null(), "the clear is still sent");
+}
+
+#[test]
+fn injected_error_surfaces_once() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (mut client, _) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+
+    server.fail_next(4000, "bad payload");
+    let err = client.set_activity(&cfg(), 0).unwrap_err();
+    assert!(format!("{:#}", err).contains("bad payload"));
+
+    client.set_activity(&cfg(), 0).unwrap();
+    assert_eq!(server.activities().len(), 1);
+}
+
+#[test]
+fn rejected_handshake_is_an_error() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    server.reject_handshakes(true);
+    assert!(DiscordRpcClient::connect_and_handshake_at(server.path(), "123").is_err());
+}
+
+#[test]
+fn dropped_connection_is_an_error() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (mut client, _) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+
+    server.drop_next();
+    assert!(client.set_activity(&cfg(), 0).is_err());
+}
+
+#[test]
+fn ping_measures_round_trip() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (mut client, _) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+
+    assert!(client.ping().unwrap() < Duration::from_secs(1));
+    // Pings do not touch the activity.
+    assert!(server.activities().is_empty());
+}
+
+#[test]
+fn a_ping_to_a_hung_discord_fails_in_time() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (mut client, _) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+
+    server.hang(true);
+    let started = Instant::now();
+    assert!(client.ping().is_err());
+    assert!(started.elapsed() < PING_TIMEOUT + Duration::from_secs(2));
+}
+
+#[test]
+fn rate_limit_error_is_typed() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let (mut client, _) = DiscordRpcClient::connect_and_handshake_at(server.path(), "123").unwrap();
+
+    server.fail_next(1000, "You are being rate limited.");
+    let err = client.set_activity(&cfg(), 0).unwrap_err();
+    assert!(err.downcast_ref::<RateLimited>().is_some());
+
+    // The connection survives.
+    client.set_activity(&cfg(), 0).unwrap();
+}
+
+fn wait_for_status(client: &ManagedClient, want: RpcStatus) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while client.status() != want && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert_eq!(client.status(), want);
+}
+
+#[test]
+fn worker_becomes_active_and_clears_on_disable() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let client = ManagedClient::new();
+    client.set_ipc_path(Some(server.path().to_string()));
+    client.enable(cfg());
+
+    wait_for_status(&client, RpcStatus::Active);
+    assert_eq!(server.activities().len(), 2);
+
+    client.disable();
+    wait_for_status(&client, RpcStatus::Inactive);
+    assert!(server.last_activity().unwrap().is_null());
+}
+
+#[test]
+fn worker_throttles_instead_of_reconnecting() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    server.fail_next(1000, "You are being rate limited.");
+
+    let client = ManagedClient::new();
+    client.set_ipc_path(Some(server.path().to_string()));
+    client.enable(cfg());
+
+    wait_for_status(&client, RpcStatus::Throttled);
+    assert!(client.report().pipe.is_some());
+    client.disable();
+}
+
+#[test]
+fn updates_held_back_by_a_throttle_are_reported_pending() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let client = ManagedClient::new();
+    client.set_ipc_path(Some(server.path().to_string()));
+    client.enable(cfg());
+    wait_for_status(&client, RpcStatus::Active);
+    let snap = client.snapshot();
+    assert_eq!(snap.pending_updates, 0);
+    assert!(snap.last_sent_at.is_some() && snap.next_send_at.is_none());
+
+    server.fail_next(1000, "You are being rate limited.");
+    client.update(cfg());
+    wait_for_status(&client, RpcStatus::Throttled);
+    client.update(cfg());
+    client.update(cfg());
+    let snap = client.snapshot();
+    assert_eq!(snap.pending_updates, 3);
+    assert!(snap.next_send_at.is_some_and(|at| at > rpc_core::now_unix_ts()));
+
+    client.disable();
+    wait_for_status(&client, RpcStatus::Inactive);
+    assert_eq!(client.pending_updates(), 0);
+}
+
+#[test]
+fn presence_snapshots_are_immutable_versions() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let client = ManagedClient::new();
+    client.set_ipc_path(Some(server.path().to_string()));
+    client.enable(cfg());
+    wait_for_status(&client, RpcStatus::Active);
+
+    let before = client.presence_snapshot().unwrap();
+    assert!(std::sync::Arc::ptr_eq(&before, &client.presence_snapshot().unwrap()));
+    client.update(PresenceCfg { details: "Changed".to_string(), ..cfg() });
+    let after = client.presence_snapshot().unwrap();
+    assert_eq!(before.details, "Testing");
+    assert_eq!(after.details, "Changed");
+    assert_eq!(client.presence().as_ref(), Some(&*after));
+
+    client.disable();
+    wait_for_status(&client, RpcStatus::Inactive);
+}
+
+#[test]
+fn status_watches_each_see_changes() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let client = ManagedClient::new();
+    client.set_ipc_path(Some(server.path().to_string()));
+    let mut watch = client.watch_status();
+    let watchers: Vec<_> = (0..3)
+        .map(|_| {
+            let mut watch = client.watch_status();
+            std::thread::spawn(move || while watch.changed() != RpcStatus::Active {})
+        })
+        .collect();
+    assert!(!watch.has_changed());
+    assert_eq!(watch.changed_timeout(Duration::from_millis(50)), None);
+
+    client.enable(cfg());
+    wait_for_status(&client, RpcStatus::Active);
+    assert!(watch.has_changed());
+    for w in watchers {
+        w.join().unwrap();
+    }
+    client.disable();
+    wait_for_status(&client, RpcStatus::Inactive);
+    while let Some(status) = watch.changed_timeout(Duration::ZERO) {
+        assert_eq!(status, RpcStatus::Inactive);
+    }
+    assert!(!watch.has_changed());
+}
+
+#[test]
+fn worker_shutdown_clears_the_activity_first() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let client = ManagedClient::new();
+    assert!(client.shutdown(Duration::ZERO));
+    client.set_ipc_path(Some(server.path().to_string()));
+    client.enable(cfg());
+    wait_for_status(&client, RpcStatus::Active);
+
+    assert!(client.shutdown(Duration::from_secs(5)));
+    assert!(server.last_activity().unwrap().is_null());
+    assert_eq!(client.status(), RpcStatus::Inactive);
+    assert!(!client.is_running());
+}
+
+#[test]
+fn replayed_sessions_keep_order_and_timing() {
+    use rpc_core::control::ControlRequest;
+    use rpc_core::session::{self, Entry};
+    use rpc_core::shutdown::CancelToken;
+
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let client = ManagedClient::new();
+    client.set_ipc_path(Some(server.path().to_string()));
+    let later = PresenceCfg { details: "Replayed".to_string(), ..cfg() };
+    let entries = vec![
+        Entry { at_ms: 0, request: ControlRequest::Enable { presence: cfg() } },
+        Entry { at_ms: 1600, request: ControlRequest::Update { presence: later } },
+        Entry { at_ms: 3200, request: ControlRequest::Disable },
+    ];
+
+    let stopped = CancelToken::new();
+    stopped.cancel();
+    assert_eq!(session::replay(&client, &entries, 1.0, &stopped), 0);
+    assert!(!client.is_running());
+
+    let start = Instant::now();
+    assert_eq!(session::replay(&client, &entries[..2], 4.0, &CancelToken::new()), 2);
+    assert!(start.elapsed() >= Duration::from_millis(400));
+    wait_for_status(&client, RpcStatus::Active);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while server.last_activity().is_none_or(|a| a["details"] != "Replayed") {
+        assert!(Instant::now() < deadline, "update was not replayed");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    session::apply(&client, entries[2].request.clone()).unwrap();
+    wait_for_status(&client, RpcStatus::Inactive);
+}
+
+#[test]
+fn worker_reports_rejected_activities_as_degraded() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    server.fail_next(4000, "bad payload");
+
+    let client = ManagedClient::new();
+    client.set_ipc_path(Some(server.path().to_string()));
+    client.enable(cfg());
+
+    wait_for_status(&client, RpcStatus::Degraded);
+    assert!(client.last_error().unwrap().contains("bad payload"));
+    client.update(cfg());
+    wait_for_status(&client, RpcStatus::Active);
+    assert_eq!(client.last_error(), None);
+    let history = client.error_history();
+    assert_eq!(history.len(), 1);
+    assert!(history[0].message.contains("bad payload"));
+    client.disable();
+}
+
+#[test]
+fn worker_pauses_and_resumes() {
+    let server = MockDiscord::start(unique_path()).unwrap();
+    let client = ManagedClient::new();
+    client.set_ipc_path(Some(server.path().to_string()));
+    client.enable(cfg());
+    wait_for_status(&client, RpcStatus::Active);
+
+    client.pause();
+    wait_for_status(&client, RpcStatus::Paused);
+    assert!(server.last_activity().unwrap().is_null());
+    assert!(client.report().pipe.is_none());
+
+    client.resume();
+    wait_for_status(&client, RpcStatus::Active);
+    assert!(!server.last_activity().unwrap().is_null());
+    client.disable();
+}
+
+#[test]
+fn worker_waits_for_a_missing_socket() {
+    let client = ManagedClient::new();
+    client.set_ipc_path(Some(unique_path()));
+    client.enable(cfg());
+    wait_for_status(&client, RpcStatus::WaitingForDiscord);
+    client.disable();
+    wait_for_status(&client, RpcStatus::Inactive);
+}
@@ -0,0 +1,206 @@
+//! Backup and restore of the settings in the config folder (`config.json`, `presets.d/` and the
+//! daemon's hooks) as one zip. Only those are included: logs and crash reports live in the data
+//! folder, and other files in the config folder, such as the daemon's API token or a damaged
+//! config set aside as `config.json.broken`, stay on this machine. The caller passes
+//! `config.json` with its tokens and passwords blanked.
+
+use anyhow::{anyhow, Context};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+
+pub const CONFIG_FILE: &str = "config.json";
+const MAX_ENTRIES: usize = 2048;
+const MAX_ENTRY_LEN: u64 = 8 * 1024 * 1024;
+/// Top-level entries of the config folder a backup holds; nothing else is exported or restored.
+const INCLUDED: [&str; 3] = [CONFIG_FILE, "presets.d", "daemon-hooks.json"];
+
+/// Contents of a backup, read and checked but not applied yet.
+pub struct Backup {
+    /// Raw `config.json`; the caller upgrades and parses it (see `schema`).
+    pub config: Vec<u8>,
+    /// Every other file, relative to the config folder.
+    pub files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+/// Zips `config` as `config.json` and the other included files under `config_dir` into `out`.
+/// Returns the number of files written.
+pub fn export(config_dir: &Path, config: &[u8], out: &Path) -> anyhow::Result<usize> {
+    let mut files = Vec::new();
+    collect(config_dir, config_dir, &mut files)?;
+    files.retain(|p| included(p));
+    if !files.iter().any(|p| p == Path::new(CONFIG_FILE)) {
+        return Err(anyhow!("Nothing to back up yet; save the configuration first."));
+    }
+
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut zip = zip::ZipWriter::new(fs::File::create(out).with_context(|| format!("Cannot create {}", out.display()))?);
+    let opts = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for rel in &files {
+        // Zip paths always use `/`, whatever the platform.
+        let name = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        zip.start_file(name, opts)?;
+        if rel == Path::new(CONFIG_FILE) {
+            zip.write_all(config)?;
+        } else {
+            zip.write_all(&fs::read(config_dir.join(rel))?)?;
+        }
+    }
+    zip.finish()?;
+    Ok(files.len())
+}
+
+fn included(rel: &Path) -> bool {
+    rel.components().next().is_some_and(|top| INCLUDED.iter().any(|name| top.as_os_str() == *name))
+}
+
+fn collect(root: &Path, dir: &Path, out: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            collect(root, &path, out)?;
+        } else if kind.is_file() {
+            out.push(path.strip_prefix(root)?.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// Reads and checks a backup: it must contain `config.json`, and no entry may point outside the
+/// config folder or be unreasonably large.
+pub fn read(path: &Path) -> anyhow::Result<Backup> {
+    let file = fs::File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let mut zip = zip::ZipArchive::new(file).context("Not a backup zip")?;
+    if zip.len() > MAX_ENTRIES {
+        return Err(anyhow!("The backup has too many files."));
+    }
+
+    let mut config = None;
+    let mut files = Vec::new();
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let rel = entry.enclosed_name().ok_or_else(|| anyhow!("The backup contains an unsafe path: {}", entry.name()))?;
+        if entry.size() > MAX_ENTRY_LEN {
+            return Err(anyhow!("{} is too large.", rel.display()));
+        }
+        let mut data = Vec::new();
+        entry.take(MAX_ENTRY_LEN + 1).read_to_end(&mut data)?;
+        if rel == Path::new(CONFIG_FILE) {
+            config = Some(data);
+        } else {
+            files.push((rel, data));
+        }
+    }
+    let config = config.ok_or_else(|| anyhow!("The zip has no {}; is it a backup from this app?", CONFIG_FILE))?;
+    Ok(Backup { config, files })
+}
+
+/// Replaces the drop-in folders and extra files under `config_dir` with the backup's. `config.json`
+/// is left to the caller, which writes it after parsing. Other files, which older backups may
+/// hold (such as the daemon's token), are skipped, so this machine keeps its own.
+pub fn restore_files(backup: &Backup, config_dir: &Path) -> anyhow::Result<()> {
+    let mut cleared = Vec::new();
+    for (rel, data) in backup.files.iter().filter(|(rel, _)| included(rel)) {
+        // A restored folder replaces the current one rather than merging with it.
+        if let Some(top) = rel.components().next().map(|c| config_dir.join(c)) {
+            if rel.components().count() > 1 && top.is_dir() && !cleared.contains(&top) {
+                fs::remove_dir_all(&top)?;
+                cleared.push(top);
+            }
+        }
+        let dest = config_dir.join(rel);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&dest, data).with_context(|| format!("Cannot write {}", dest.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StoredConfig;
+
+    /// An empty folder under the temp dir, unique to this test process.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("crp-backup-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, rel: &str, text: &str) {
+        let path = dir.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn round_trips_the_settings_without_secrets_or_other_files() {
+        let dir = scratch("round-trip");
+        let mut stored = StoredConfig { oauth_token: "secret-oauth".to_string(), ..StoredConfig::default() };
+        stored.sync.gist_token = "secret-gist".to_string();
+        stored.sync.webdav_password = "secret-password".to_string();
+        write(&dir, CONFIG_FILE, &serde_json::to_string(&stored).unwrap());
+        write(&dir, "config.json.broken", "{\"oauth_token\": \"secret-broken\"}");
+        write(&dir, "daemon-token", "secret-daemon");
+        write(&dir, "notes.txt", "not a setting");
+        write(&dir, "presets.d/work.json", "{}");
+        write(&dir, "daemon-hooks.json", "[]");
+
+        let out = dir.join("out").join("backup.zip");
+        let config = serde_json::to_vec(&stored.without_secrets()).unwrap();
+        assert_eq!(export(&dir, &config, &out).unwrap(), 3);
+
+        let backup = read(&out).unwrap();
+        let text = String::from_utf8(backup.config.clone()).unwrap();
+        assert!(!text.contains("secret"), "{}", text);
+        let mut names: Vec<PathBuf> = backup.files.iter().map(|(rel, _)| rel.clone()).collect();
+        names.sort();
+        assert_eq!(names, [PathBuf::from("daemon-hooks.json"), Path::new("presets.d").join("work.json")]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn restoring_keeps_local_only_files() {
+        let dir = scratch("restore");
+        write(&dir, "daemon-token", "local");
+        write(&dir, "presets.d/old.json", "{}");
+        let backup = Backup {
+            config: b"{}".to_vec(),
+            files: vec![
+                (PathBuf::from("daemon-token"), b"from an older backup".to_vec()),
+                (Path::new("presets.d").join("new.json"), b"{}".to_vec()),
+            ],
+        };
+        restore_files(&backup, &dir).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("daemon-token")).unwrap(), "local");
+        assert!(dir.join("presets.d/new.json").exists());
+        assert!(!dir.join("presets.d/old.json").exists(), "a restored folder replaces the current one");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_config_folder() {
+        let dir = scratch("zip-slip");
+        let path = dir.join("evil.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        for name in [CONFIG_FILE, "../escaped.json"] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"{}").unwrap();
+        }
+        zip.finish().unwrap();
+
+        let err = read(&path).err().unwrap();
+        assert!(err.to_string().contains("unsafe path"), "{}", err);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,59 @@
+//! Display helpers for right-to-left text and combining marks.
+//!
+//! egui lays text out strictly left to right, one glyph per `char`, without shaping. Runs of
+//! Hebrew/Arabic are reordered here for display, and decomposed accents are composed so they sit on
+//! their letters. Arabic letters still show in their isolated forms. Only use the result for
+//! painting; the stored text stays in logical order, which is what Discord expects.
+
+use unicode_bidi::BidiInfo;
+use unicode_normalization::UnicodeNormalization;
+
+/// Whether the first paragraph of `text` reads right to left.
+pub fn is_rtl(text: &str) -> bool {
+    let info = BidiInfo::new(text, None);
+    info.paragraphs.first().is_some_and(|p| p.level.is_rtl())
+}
+
+/// `text` in visual order, with combining sequences composed where Unicode has a single character.
+pub fn display(text: &str) -> String {
+    let text: String = text.nfc().collect();
+    let info = BidiInfo::new(&text, None);
+    if !info.has_rtl() {
+        return text;
+    }
+    info.paragraphs.iter().map(|p| info.reorder_line(p, p.range.clone())).collect()
+}
+
+/// Fonts installed with the OS that cover Hebrew and Arabic, which egui's bundled fonts do not.
+/// Every one found is added as a fallback.
+const FALLBACK_FONTS: [&str; 8] = [
+    "/usr/share/fonts/truetype/noto/NotoSansHebrew-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSansArabic-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSansHebrew-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSansArabic-Regular.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+];
+
+/// Appends the system fonts above to every font family, after egui's own.
+pub fn install_fallback_fonts(ctx: &eframe::egui::Context) {
+    use eframe::egui::{FontData, FontDefinitions};
+
+    let mut fonts = FontDefinitions::default();
+    let mut added = 0;
+    for path in FALLBACK_FONTS {
+        let Ok(bytes) = std::fs::read(path) else { continue };
+        let name = format!("fallback-{}", added);
+        fonts.font_data.insert(name.clone(), FontData::from_owned(bytes));
+        for family in fonts.families.values_mut() {
+            family.push(name.clone());
+        }
+        added += 1;
+    }
+    if added > 0 {
+        ctx.set_fonts(fonts);
+    }
+}
+
@@ -0,0 +1,173 @@
+//! Find and replace across every saved preset, e.g. after renaming an uploaded asset or moving
+//! the presets to another Discord application. Changes are worked out on copies first, so the
+//! dialog can show each one before anything is written. Drop-in presets are files the app only
+//! reads, so they are left alone.
+
+use regex::{NoExpand, RegexBuilder};
+
+use crate::presets::Preset;
+use crate::FormConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Target {
+    /// Part of any text: details, state, hover texts, button labels and URLs, and constants' values.
+    #[default]
+    Text,
+    /// The whole Client ID.
+    ClientId,
+    /// A whole image asset key or URL, including the cycled images.
+    AssetKey,
+}
+
+impl Target {
+    pub const ALL: [Target; 3] = [Target::Text, Target::ClientId, Target::AssetKey];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Target::Text => "Text",
+            Target::ClientId => "Client ID",
+            Target::AssetKey => "Asset key",
+        }
+    }
+
+    pub fn hint(self) -> &'static str {
+        match self {
+            Target::Text => "Replaces the text wherever it appears in details, state, hover texts, buttons and constants.",
+            Target::ClientId => "Replaces Client IDs equal to it.",
+            Target::AssetKey => "Replaces large, small and cycled images equal to it.",
+        }
+    }
+}
+
+/// One preset as it would be after the replace, with what changed in it.
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub preset: usize,
+    pub form: FormConfig,
+    /// Field label, value before and after.
+    pub changes: Vec<(&'static str, String, String)>,
+}
+
+/// The presets that would change when `find` is replaced by `replace` in `target`. Whole values
+/// (Client ID, asset keys) are compared trimmed. `match_case` off compares ignoring case.
+pub fn plan(presets: &[Preset], target: Target, find: &str, replace: &str, match_case: bool) -> Vec<Edit> {
+    let find = if target == Target::Text { find } else { find.trim() };
+    if find.is_empty() {
+        return Vec::new();
+    }
+    let Ok(pattern) = RegexBuilder::new(&regex::escape(find)).case_insensitive(!match_case).build() else {
+        return Vec::new();
+    };
+    let same = |v: &str| if match_case { v.trim() == find } else { v.trim().to_lowercase() == find.to_lowercase() };
+    let mut edits = Vec::new();
+    for (i, preset) in presets.iter().enumerate() {
+        let mut form = preset.form.clone();
+        let mut changes = Vec::new();
+        let mut fields: Vec<(&'static str, &mut String)> = match target {
+            Target::Text => vec![
+                ("Details", &mut form.details),
+                ("State", &mut form.state),
+                ("Large text", &mut form.large_text),
+                ("Small text", &mut form.small_text),
+                ("Button 1 label", &mut form.b1label),
+                ("Button 1 url", &mut form.b1url),
+                ("Button 2 label", &mut form.b2label),
+                ("Button 2 url", &mut form.b2url),
+            ]
+            .into_iter()
+            .chain(form.constants.iter_mut().map(|c| ("Constant", &mut c.value)))
+            .collect(),
+            Target::ClientId => vec![("Client ID", &mut form.client_id)],
+            Target::AssetKey => {
+                let mut fields = vec![("Large image", &mut form.large_image), ("Small image", &mut form.small_image)];
+                fields.extend(form.image_cycle.images.iter_mut().map(|v| ("Cycled image", v)));
+                fields
+            }
+        };
+        for (label, value) in fields.iter_mut() {
+            let after = match target {
+                Target::Text => pattern.replace_all(value, NoExpand(replace)).into_owned(),
+                _ if same(value) => replace.trim().to_string(),
+                _ => continue,
+            };
+            if after != **value {
+                changes.push((*label, std::mem::replace(*value, after.clone()), after));
+            }
+        }
+        if !changes.is_empty() {
+            edits.push(Edit { preset: i, form, changes });
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Constant;
+
+    fn preset(edit: impl FnOnce(&mut FormConfig)) -> Preset {
+        let mut form = FormConfig::default();
+        edit(&mut form);
+        Preset { name: "p".to_string(), form, ..Preset::default() }
+    }
+
+    #[test]
+    fn text_replace_ignores_case_unless_asked() {
+        let presets = [preset(|f| {
+            f.details = "Playing Minecraft".to_string();
+            f.state = "minecraft server".to_string();
+            f.constants = vec![Constant { name: "game".to_string(), value: "MINECRAFT".to_string() }];
+        })];
+        let edits = plan(&presets, Target::Text, "Minecraft", "Terraria", false);
+        let form = &edits[0].form;
+        assert_eq!((form.details.as_str(), form.state.as_str()), ("Playing Terraria", "Terraria server"));
+        assert_eq!(form.constants[0].value, "Terraria");
+        assert_eq!(edits[0].changes.len(), 3);
+
+        let edits = plan(&presets, Target::Text, "Minecraft", "Terraria", true);
+        assert_eq!(edits[0].changes, vec![("Details", "Playing Minecraft".to_string(), "Playing Terraria".to_string())]);
+    }
+
+    #[test]
+    fn replacements_are_taken_literally() {
+        let presets = [preset(|f| f.details = "Price: 5".to_string())];
+        let edits = plan(&presets, Target::Text, "5", "$1 $0 ${x}", true);
+        assert_eq!(edits[0].form.details, "Price: $1 $0 ${x}");
+        assert!(plan(&presets, Target::Text, "(5)", "x", true).is_empty(), "the search text is not a regex either");
+    }
+
+    #[test]
+    fn whole_values_match_trimmed() {
+        let presets = [
+            preset(|f| f.client_id = " 123456789012345678 ".to_string()),
+            preset(|f| f.client_id = "1234567890123456789".to_string()),
+        ];
+        let edits = plan(&presets, Target::ClientId, "123456789012345678 ", " 876543210987654321", true);
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].preset, 0);
+        assert_eq!(edits[0].form.client_id, "876543210987654321");
+    }
+
+    #[test]
+    fn asset_keys_include_the_cycled_images() {
+        let presets = [preset(|f| {
+            f.large_image = "Logo".to_string();
+            f.small_image = "logo_small".to_string();
+            f.image_cycle.images = vec!["logo".to_string(), "other".to_string()];
+        })];
+        let edits = plan(&presets, Target::AssetKey, "logo", "brand", false);
+        let form = &edits[0].form;
+        assert_eq!(form.large_image, "brand");
+        assert_eq!(form.small_image, "logo_small", "only whole keys are replaced");
+        assert_eq!(form.image_cycle.images, ["brand", "other"]);
+        assert_eq!(edits[0].changes.iter().map(|c| c.0).collect::<Vec<_>>(), ["Large image", "Cycled image"]);
+    }
+
+    #[test]
+    fn blank_searches_change_nothing() {
+        let presets = [preset(|f| f.client_id = "123456789012345678".to_string())];
+        assert!(plan(&presets, Target::ClientId, "  ", "x", true).is_empty());
+        assert!(plan(&presets, Target::Text, "", "x", true).is_empty());
+    }
+}
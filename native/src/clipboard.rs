@@ -0,0 +1,95 @@
+//! Clipboard watch: while switched on, text copied anywhere that matches a pattern sets a template
+//! variable, so copying `JIRA-123` can make the presence read "Working on JIRA-123". Nothing reads
+//! the clipboard while it is off, and copied text is never stored or logged.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+const POLL_EVERY: Duration = Duration::from_secs(1);
+/// Copies longer than this are never matched; an issue id or a link is far shorter.
+const MAX_LEN: usize = 4096;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipSettings {
+    pub enabled: bool,
+    /// Regex searched for in copied text. With a capture group, the first group is the value.
+    pub pattern: String,
+    /// Template variable the match is stored in, used as `{name}` in the presence.
+    pub var: String,
+}
+
+impl Default for ClipSettings {
+    fn default() -> Self {
+        ClipSettings { enabled: false, pattern: r"\b[A-Z][A-Z0-9]+-\d+\b".to_string(), var: "clip".to_string() }
+    }
+}
+
+impl ClipSettings {
+    pub fn regex(&self) -> Result<Regex, String> {
+        if self.pattern.trim().is_empty() {
+            return Err("The pattern is empty.".to_string());
+        }
+        Regex::new(&self.pattern).map_err(|e| format!("Invalid pattern: {}", e))
+    }
+}
+
+/// The value `re` picks out of `text`, if it matches.
+pub fn extract(re: &Regex, text: &str) -> Option<String> {
+    if text.len() > MAX_LEN {
+        return None;
+    }
+    let caps = re.captures(text)?;
+    let m = caps.get(1).or_else(|| caps.get(0))?;
+    Some(m.as_str().trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Polls the clipboard until dropped.
+pub struct Watcher {
+    running: Arc<AtomicBool>,
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Calls `on_copy` with each text copied from now on (what is on the clipboard already is skipped).
+/// Stops when the returned watcher is dropped, when `on_copy` returns `false`, or right away when
+/// the clipboard cannot be opened.
+pub fn watch(mut on_copy: impl FnMut(String) -> bool + Send + 'static) -> Watcher {
+    let running = Arc::new(AtomicBool::new(true));
+    let flag = running.clone();
+    thread::spawn(move || {
+        let Ok(mut clipboard) = arboard::Clipboard::new() else {
+            crate::logging::warn("clipboard watch: the clipboard is not available");
+            return;
+        };
+        let mut last = clipboard.get_text().ok();
+        while flag.load(Ordering::SeqCst) {
+            thread::sleep(POLL_EVERY);
+            if !flag.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(text) = clipboard.get_text() else { continue };
+            if last.as_deref() == Some(text.as_str()) {
+                continue;
+            }
+            last = Some(text.clone());
+            if !on_copy(text) {
+                break;
+            }
+        }
+    });
+    Watcher { running }
+}
@@ -0,0 +1,138 @@
+//! How times are shown: the date order and 12- or 24-hour clock of the user's locale, with the
+//! hour cycle overridable in Advanced. Like the error language, the choice is process-wide, so
+//! every place that formats a time picks it up.
+//!
+//! Time fields take `YYYY-MM-DD HH:MM` or `YYYY-MM-DD h:MM PM` whatever the locale, so a saved
+//! value reads the same everywhere; only what is displayed follows the locale.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use chrono::{DateTime, Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HourCycle {
+    H12,
+    H24,
+}
+
+impl HourCycle {
+    pub const ALL: [HourCycle; 2] = [HourCycle::H12, HourCycle::H24];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HourCycle::H12 => "12-hour (5:30 PM)",
+            HourCycle::H24 => "24-hour (17:30)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateOrder {
+    /// 10/18/2026
+    Mdy,
+    /// 18/10/2026
+    Dmy,
+    /// 2026-10-18
+    Ymd,
+}
+
+/// Regions that write the month first and use a 12-hour clock.
+const MONTH_FIRST: &[&str] = &["US", "PH", "FM", "MH", "PW"];
+/// Regions (besides [`MONTH_FIRST`]) where a 12-hour clock is the norm.
+const TWELVE_HOUR: &[&str] = &["CA", "AU", "NZ", "IN", "PK", "BD", "EG", "SA", "MY", "KR", "TW"];
+/// Languages that write the year first.
+const YEAR_FIRST: &[&str] = &["ja", "zh", "ko", "hu", "lt", "mn"];
+
+/// 0 follows the locale, otherwise 1 + the [`HourCycle`].
+static CYCLE: AtomicU8 = AtomicU8::new(0);
+
+/// `None` follows the locale.
+pub fn set_hour_cycle(cycle: Option<HourCycle>) {
+    CYCLE.store(cycle.map_or(0, |c| c as u8 + 1), Ordering::SeqCst);
+}
+
+fn hour_cycle() -> HourCycle {
+    match CYCLE.load(Ordering::SeqCst) {
+        1 => HourCycle::H12,
+        2 => HourCycle::H24,
+        _ => system_hour_cycle(),
+    }
+}
+
+/// `pt_BR.UTF-8` → (`pt`, `BR`). Windows sets none of these; there the locale reads as unknown.
+fn locale() -> (String, String) {
+    let tag = ["LC_ALL", "LC_TIME", "LANG"].iter().find_map(|k| std::env::var(k).ok().filter(|v| !v.is_empty() && v != "C" && v != "POSIX"));
+    let tag = tag.unwrap_or_default();
+    let mut parts = tag.split(['.', '@']).next().unwrap_or_default().split(['_', '-']);
+    let lang = parts.next().unwrap_or_default().to_ascii_lowercase();
+    let region = parts.next().unwrap_or_default().to_ascii_uppercase();
+    (lang, region)
+}
+
+/// The locale's hour cycle; 24-hour when the locale is unknown.
+pub fn system_hour_cycle() -> HourCycle {
+    let (_, region) = locale();
+    if MONTH_FIRST.contains(&region.as_str()) || TWELVE_HOUR.contains(&region.as_str()) {
+        HourCycle::H12
+    } else {
+        HourCycle::H24
+    }
+}
+
+fn date_order() -> DateOrder {
+    let (lang, region) = locale();
+    if MONTH_FIRST.contains(&region.as_str()) {
+        DateOrder::Mdy
+    } else if lang.is_empty() || YEAR_FIRST.contains(&lang.as_str()) {
+        DateOrder::Ymd
+    } else {
+        DateOrder::Dmy
+    }
+}
+
+fn local(ts: i64) -> Option<DateTime<Local>> {
+    DateTime::from_timestamp(ts, 0).map(|t| t.with_timezone(&Local))
+}
+
+/// Time of day of `ts`, e.g. `17:30` or `5:30 PM`.
+pub fn fmt_time(ts: i64) -> String {
+    let Some(t) = local(ts) else { return String::new() };
+    match hour_cycle() {
+        HourCycle::H12 => t.format("%-I:%M %p").to_string(),
+        HourCycle::H24 => t.format("%H:%M").to_string(),
+    }
+}
+
+/// Date and time of `ts` in the locale's order, e.g. `18/10/2026 17:30`.
+pub fn fmt_date_time(ts: i64) -> String {
+    let Some(t) = local(ts) else { return String::new() };
+    let date = match date_order() {
+        DateOrder::Mdy => t.format("%m/%d/%Y"),
+        DateOrder::Dmy => t.format("%d/%m/%Y"),
+        DateOrder::Ymd => t.format("%Y-%m-%d"),
+    };
+    format!("{} {}", date, fmt_time(ts))
+}
+
+/// `ts` as typed into time fields: `YYYY-MM-DD` and the time in the current hour cycle.
+pub fn fmt_input(ts: i64) -> String {
+    local(ts).map(|t| format!("{} {}", t.format("%Y-%m-%d"), fmt_time(ts))).unwrap_or_default()
+}
+
+/// Hint for time fields in the current hour cycle.
+pub fn input_hint() -> &'static str {
+    match hour_cycle() {
+        HourCycle::H12 => "YYYY-MM-DD h:MM PM",
+        HourCycle::H24 => "YYYY-MM-DD HH:MM",
+    }
+}
+
+/// A time of day typed as `17:30`, `5:30 PM` or `5:30pm`.
+pub fn parse_time(v: &str) -> Option<NaiveTime> {
+    let v = v.trim().to_ascii_uppercase();
+    let v = v.replace("AM", " AM").replace("PM", " PM");
+    let v = v.split_whitespace().collect::<Vec<_>>().join(" ");
+    ["%H:%M", "%I:%M %p"].iter().find_map(|f| NaiveTime::parse_from_str(&v, f).ok())
+}
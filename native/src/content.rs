@@ -0,0 +1,49 @@
+//! Optional content check before enabling: flags blocked words in the visible presence text, so an
+//! old preset does not go live with something its owner forgot about.
+
+use rpc_core::PresenceCfg;
+
+/// Common profanity in the languages the app ships errors in. Users add their own words in Settings.
+const BUILTIN: [&str; 12] = [
+    "fuck", "fucking", "shit", "bitch", "cunt", "asshole", "bastard", "porra", "caralho", "merda", "puta", "foda",
+];
+
+/// Blocked words found in `cfg`, as `(field, word)`. Matches whole words, ignoring case.
+pub fn flagged(cfg: &PresenceCfg, extra: &str) -> Vec<(&'static str, String)> {
+    let blocked: Vec<String> = BUILTIN
+        .iter()
+        .map(|w| w.to_string())
+        .chain(extra.lines().map(|w| w.trim().to_lowercase()).filter(|w| !w.is_empty()))
+        .collect();
+
+    let mut fields = vec![
+        ("Details", cfg.details.as_str()),
+        ("State", cfg.state.as_str()),
+        ("Large text", cfg.large_text.as_deref().unwrap_or_default()),
+        ("Small text", cfg.small_text.as_deref().unwrap_or_default()),
+    ];
+    for (label, b) in ["Button 1 label", "Button 2 label"].into_iter().zip(&cfg.buttons) {
+        fields.push((label, b.label.as_str()));
+    }
+
+    let mut hits = Vec::new();
+    for (field, text) in fields {
+        let lower = text.to_lowercase();
+        for word in &blocked {
+            if contains_word(&lower, word) && !hits.iter().any(|(f, w)| *f == field && w == word) {
+                hits.push((field, word.clone()));
+            }
+        }
+    }
+    hits
+}
+
+/// `word` appears in `text` without letters or digits right before or after it. Multi-word entries
+/// work too, since only their ends are checked.
+fn contains_word(text: &str, word: &str) -> bool {
+    text.match_indices(word).any(|(i, _)| {
+        let before = text[..i].chars().next_back();
+        let after = text[i + word.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
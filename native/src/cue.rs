@@ -0,0 +1,101 @@
+//! Sound and desktop notification when a preset goes live, for people switching presets while a
+//! full-screen game hides the window.
+//!
+//! Like the Wi-Fi lookup, this asks the OS's own tools: `canberra-gtk-play`/`paplay` and
+//! `notify-send` on Linux, `afplay` and `osascript` on macOS, PowerShell on Windows. A missing tool
+//! only means no cue.
+
+use std::process::Command;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+/// Per-preset choice of cues.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cue {
+    pub sound: bool,
+    pub toast: bool,
+}
+
+impl Cue {
+    pub fn is_on(&self) -> bool {
+        self.sound || self.toast
+    }
+}
+
+/// Plays the cue in the background; never blocks the UI.
+pub fn play(cue: Cue, body: String) {
+    if !cue.is_on() {
+        return;
+    }
+    thread::spawn(move || {
+        if cue.toast {
+            toast("Custom Rich Presence", &body);
+        }
+        if cue.sound {
+            sound();
+        }
+    });
+}
+
+fn sound() {
+    #[cfg(target_os = "linux")]
+    {
+        let _ = spawn("canberra-gtk-play", &["-i", "complete"], &[])
+            || spawn("paplay", &["/usr/share/sounds/freedesktop/stereo/complete.oga"], &[]);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        spawn("afplay", &["/System/Library/Sounds/Glass.aiff"], &[]);
+    }
+    #[cfg(windows)]
+    {
+        spawn("powershell", &["-NoProfile", "-Command", "[System.Media.SystemSounds]::Asterisk.Play(); Start-Sleep -Milliseconds 800"], &[]);
+    }
+}
+
+fn toast(title: &str, body: &str) {
+    #[cfg(target_os = "linux")]
+    {
+        spawn("notify-send", &["-a", title, title, body], &[]);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        // Passed as arguments, so quotes in a preset name need no escaping.
+        spawn(
+            "osascript",
+            &["-e", "on run argv", "-e", "display notification (item 2 of argv) with title (item 1 of argv)", "-e", "end run", title, body],
+            &[],
+        );
+    }
+    #[cfg(windows)]
+    {
+        // A tray balloon shows as a toast on Windows 10 and later; the text comes in through the
+        // environment so it needs no quoting.
+        const SCRIPT: &str = "Add-Type -AssemblyName System.Windows.Forms; \
+            $n = New-Object System.Windows.Forms.NotifyIcon; \
+            $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+            $n.ShowBalloonTip(5000, $env:CRP_CUE_TITLE, $env:CRP_CUE_BODY, 'Info'); \
+            Start-Sleep -Seconds 6; $n.Dispose()";
+        spawn("powershell", &["-NoProfile", "-Command", SCRIPT], &[("CRP_CUE_TITLE", title), ("CRP_CUE_BODY", body)]);
+    }
+    #[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+    {
+        let _ = (title, body);
+    }
+}
+
+/// Runs a command to completion without a console window; returns whether it succeeded.
+#[cfg_attr(not(any(target_os = "linux", windows, target_os = "macos")), allow(dead_code))]
+fn spawn(cmd: &str, args: &[&str], env: &[(&str, &str)]) -> bool {
+    let mut command = Command::new(cmd);
+    command.args(args).envs(env.iter().copied());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command.output().is_ok_and(|o| o.status.success())
+}
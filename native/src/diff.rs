@@ -0,0 +1,81 @@
+//! Field-by-field differences between two forms, for reviewing a draft before it goes live.
+//!
+//! Works on the serialized form, so fields added to `FormConfig` show up without changes here;
+//! nested settings (refresh, marquee, image cycle, cue) are listed per setting.
+
+use serde_json::Value;
+
+use crate::FormConfig;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub field: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// Labels for the form's serialized keys, as the form shows them.
+const LABELS: &[(&str, &str)] = &[
+    ("client_id", "Client ID"),
+    ("details", "Details"),
+    ("state", "State"),
+    ("large_image", "Large image"),
+    ("large_text", "Large text"),
+    ("small_image", "Small image"),
+    ("small_text", "Small text"),
+    ("b1label", "Button 1 label"),
+    ("b1url", "Button 1 URL"),
+    ("b2label", "Button 2 label"),
+    ("b2url", "Button 2 URL"),
+    ("with_timestamp", "Timer"),
+    ("timer_mode", "Timer mode"),
+    ("timer_at", "Timer time"),
+    ("targets", "Discord instances"),
+    ("refresh", "Refresh"),
+    ("marquee", "Marquee"),
+    ("image_cycle", "Image cycle"),
+    ("cue", "When live"),
+];
+
+fn label(key: &str) -> String {
+    LABELS.iter().find(|(k, _)| *k == key).map_or_else(|| key.replace('_', " "), |(_, l)| l.to_string())
+}
+
+/// What changes going from `from` to `to`, in form order.
+pub fn changes(from: &FormConfig, to: &FormConfig) -> Vec<Change> {
+    let (Ok(Value::Object(a)), Ok(Value::Object(b))) = (serde_json::to_value(from), serde_json::to_value(to)) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for (key, new) in &b {
+        let old = a.get(key).unwrap_or(&Value::Null);
+        if old == new {
+            continue;
+        }
+        match (old, new) {
+            (Value::Object(old), Value::Object(new)) => {
+                for (sub, v) in new {
+                    let was = old.get(sub).unwrap_or(&Value::Null);
+                    if was != v {
+                        out.push(Change { field: format!("{}: {}", label(key), sub.replace('_', " ")), from: show(was), to: show(v) });
+                    }
+                }
+            }
+            _ => out.push(Change { field: label(key), from: show(old), to: show(new) }),
+        }
+    }
+    out
+}
+
+fn show(v: &Value) -> String {
+    match v {
+        Value::Null => "-".to_string(),
+        Value::String(s) if s.is_empty() => "(empty)".to_string(),
+        Value::String(s) => s.clone(),
+        Value::Bool(true) => "on".to_string(),
+        Value::Bool(false) => "off".to_string(),
+        Value::Array(items) if items.is_empty() => "(none)".to_string(),
+        Value::Array(items) => items.iter().map(show).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,70 @@
+//! Docking the compact window to a screen corner. egui reports the size of the monitor the window
+//! is on but not where that monitor sits on the desktop, so its origin is inferred from the window
+//! position, assuming monitors of that size side by side. Monitors are told apart by size and
+//! scale, so two identical monitors share their remembered corner. Wayland does not let apps place
+//! their windows; there docking does nothing.
+
+use eframe::egui::{Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Gap kept between the window and the screen edges.
+const MARGIN: f32 = 12.0;
+/// Extra room at the bottom edge, where taskbars and docks usually are.
+const BOTTOM_BAR: f32 = 48.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub const ALL: [Corner; 4] = [Corner::TopLeft, Corner::TopRight, Corner::BottomLeft, Corner::BottomRight];
+
+    pub fn arrow(self) -> &'static str {
+        match self {
+            Corner::TopLeft => "\u{2196}",
+            Corner::TopRight => "\u{2197}",
+            Corner::BottomLeft => "\u{2199}",
+            Corner::BottomRight => "\u{2198}",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Corner::TopLeft => "top left",
+            Corner::TopRight => "top right",
+            Corner::BottomLeft => "bottom left",
+            Corner::BottomRight => "bottom right",
+        }
+    }
+}
+
+/// Key the dock corner is remembered under, e.g. `2560x1440@1.25`.
+pub fn monitor_key(monitor: Vec2, pixels_per_point: f32) -> String {
+    format!("{}x{}@{}", monitor.x.round(), monitor.y.round(), (pixels_per_point * 100.0).round() / 100.0)
+}
+
+/// Where to move the window (its `outer` rect, in points) so it sits in `corner` of the `monitor`
+/// it is on.
+pub fn position(corner: Corner, outer: Rect, monitor: Vec2) -> Pos2 {
+    if monitor.x <= 0.0 || monitor.y <= 0.0 {
+        return outer.min;
+    }
+    let center = outer.center();
+    let origin = Pos2::new((center.x / monitor.x).floor() * monitor.x, (center.y / monitor.y).floor() * monitor.y);
+    let size = outer.size();
+    let left = origin.x + MARGIN;
+    let right = origin.x + monitor.x - size.x - MARGIN;
+    let top = origin.y + MARGIN;
+    let bottom = origin.y + monitor.y - size.y - MARGIN - BOTTOM_BAR;
+    match corner {
+        Corner::TopLeft => Pos2::new(left, top),
+        Corner::TopRight => Pos2::new(right, top),
+        Corner::BottomLeft => Pos2::new(left, bottom),
+        Corner::BottomRight => Pos2::new(right, bottom),
+    }
+}
@@ -0,0 +1,97 @@
+//! Drop-in presets: `*.json` files in `presets.d/` next to the config, for configuration management
+//! and sync tools. They are read-only in the app and reloaded whenever the folder changes.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::presets::{self, Preset};
+
+const SCAN_EVERY: Duration = Duration::from_secs(2);
+const MAX_FILE_LEN: u64 = 64 * 1024;
+
+pub struct DropIn {
+    dir: Option<PathBuf>,
+    /// File names, modification times and sizes seen by the last scan.
+    seen: Vec<(PathBuf, Option<SystemTime>, u64)>,
+    last_scan: Instant,
+    pub presets: Vec<Preset>,
+    /// One line per file that could not be loaded.
+    pub errors: Vec<String>,
+}
+
+impl DropIn {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        let mut d = Self { dir, seen: Vec::new(), last_scan: Instant::now(), presets: Vec::new(), errors: Vec::new() };
+        d.rescan();
+        d
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Rescans the folder when due and reloads it if anything changed. Returns `true` on reload.
+    pub fn poll(&mut self) -> bool {
+        if self.last_scan.elapsed() < SCAN_EVERY {
+            return false;
+        }
+        self.rescan()
+    }
+
+    fn rescan(&mut self) -> bool {
+        self.last_scan = Instant::now();
+        let files = self.dir.as_deref().map(list_json).unwrap_or_default();
+        if files == self.seen {
+            return false;
+        }
+
+        self.presets.clear();
+        self.errors.clear();
+        for (path, _, _) in &files {
+            match load_file(path) {
+                Ok(p) if self.presets.iter().any(|q| q.name == p.name) => {
+                    self.errors.push(format!("{}: another drop-in already uses the name \"{}\".", file_name(path), p.name));
+                }
+                Ok(p) => self.presets.push(p),
+                Err(e) => self.errors.push(format!("{}: {}", file_name(path), e)),
+            }
+        }
+        self.seen = files;
+        true
+    }
+}
+
+/// `*.json` files directly in `dir`, sorted by name so the preset order is stable.
+fn list_json(dir: &Path) -> Vec<(PathBuf, Option<SystemTime>, u64)> {
+    let Ok(entries) = fs::read_dir(dir) else { return Vec::new(); };
+    let mut files: Vec<_> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x.eq_ignore_ascii_case("json")))
+        .filter_map(|e| {
+            let meta = e.metadata().ok().filter(|m| m.is_file())?;
+            Some((e.path(), meta.modified().ok(), meta.len()))
+        })
+        .collect();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    files
+}
+
+/// Reads one preset file (`{"name": ..., "form": {...}}`). Without a name, the file name is used.
+fn load_file(path: &Path) -> Result<Preset, String> {
+    let len = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    if len > MAX_FILE_LEN {
+        return Err(format!("file is larger than {} KiB.", MAX_FILE_LEN / 1024));
+    }
+    let raw = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut preset: Preset = serde_json::from_str(&raw).map_err(|e| format!("not a valid preset: {}", e))?;
+    if preset.name.trim().is_empty() {
+        preset.name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    }
+    presets::validate(&preset)?;
+    Ok(preset)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+}
@@ -0,0 +1,38 @@
+//! Emoji picker for the presence text fields.
+
+use eframe::egui;
+
+/// Emoji that egui's bundled fonts can draw, grouped roughly by use in presences.
+const EMOJI: &[&str] = &[
+    "😀", "😂", "😊", "😎", "🤔", "😴", "😭", "😡", "🥳", "🤯", "👀", "👍", "👎", "👏", "🙏", "💪",
+    "❤", "💔", "⭐", "✨", "🔥", "💯", "✅", "❌", "⚠", "💤", "🎉", "🎵", "🎶", "🎧", "🎤", "🎸",
+    "🎮", "🕹", "👾", "🎲", "♟", "🏆", "⚽", "🏀", "💻", "⌨", "🖥", "📱", "🛠", "⚙", "🐛", "🚀",
+    "📚", "✏", "📝", "📖", "🎨", "📷", "🎬", "📺", "☕", "🍕", "🍔", "🍜", "🌙", "☀", "🌧", "❄",
+];
+
+/// A small button that opens the picker and appends the chosen emoji to `value`. Returns `true`
+/// when `value` changed.
+pub fn picker(ui: &mut egui::Ui, id: &str, value: &mut String) -> bool {
+    let mut picked = None;
+    let resp = ui.menu_button("😊", |ui| {
+        egui::Grid::new(("emoji_grid", id)).spacing([2.0, 2.0]).show(ui, |ui| {
+            for (i, e) in EMOJI.iter().enumerate() {
+                if ui.button(*e).clicked() {
+                    picked = Some(*e);
+                    ui.close_menu();
+                }
+                if i % 8 == 7 {
+                    ui.end_row();
+                }
+            }
+        });
+    });
+    resp.response.on_hover_text("Insert emoji");
+    match picked {
+        Some(e) => {
+            value.push_str(e);
+            true
+        }
+        None => false,
+    }
+}
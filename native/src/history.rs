@@ -0,0 +1,144 @@
+//! Activity history: every presence change and connection status change, one JSON object per line
+//! in `history.jsonl` next to the log. Unlike the log it is structured, so it can be exported to
+//! CSV/JSON for a date range.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The file is rotated to `history.jsonl.1` past this size, keeping roughly twice this much history.
+const MAX_BYTES: u64 = 2 * 1024 * 1024;
+
+static PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// The presence was enabled, updated or disabled.
+    Presence,
+    /// The worker's connection status changed.
+    Connection,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Presence => "presence",
+            Kind::Connection => "connection",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    /// Unix time.
+    pub ts: i64,
+    pub kind: Kind,
+    /// `enabled`/`updated`/`disabled` for presence events, the new status for connection events.
+    pub event: String,
+    #[serde(default)]
+    pub details: String,
+    #[serde(default)]
+    pub state: String,
+    /// Error that came with a connection status, if any.
+    #[serde(default)]
+    pub error: String,
+}
+
+impl Event {
+    pub fn presence(event: &str, cfg: &rpc_core::PresenceCfg) -> Self {
+        Event {
+            ts: rpc_core::now_unix_ts(),
+            kind: Kind::Presence,
+            event: event.to_string(),
+            details: cfg.details.clone(),
+            state: cfg.state.clone(),
+            error: String::new(),
+        }
+    }
+
+    pub fn presence_off() -> Self {
+        Event {
+            ts: rpc_core::now_unix_ts(),
+            kind: Kind::Presence,
+            event: "disabled".to_string(),
+            details: String::new(),
+            state: String::new(),
+            error: String::new(),
+        }
+    }
+
+    pub fn connection(status: rpc_core::RpcStatus, error: Option<&str>) -> Self {
+        Event {
+            ts: rpc_core::now_unix_ts(),
+            kind: Kind::Connection,
+            event: status.as_str().to_string(),
+            details: String::new(),
+            state: String::new(),
+            error: error.unwrap_or_default().to_string(),
+        }
+    }
+}
+
+/// Records to `<dir>/history.jsonl` from now on. Events before this are dropped.
+pub fn init(dir: &Path) {
+    let _ = fs::create_dir_all(dir);
+    *PATH.lock().unwrap_or_else(|e| e.into_inner()) = Some(dir.join("history.jsonl"));
+}
+
+/// Appends `event`, with Discord ids masked while privacy mode is on.
+pub fn record(mut event: Event) {
+    let guard = PATH.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(path) = guard.as_ref() else { return; };
+    for s in [&mut event.details, &mut event.state, &mut event.error] {
+        *s = crate::privacy::redact(s);
+    }
+    if fs::metadata(path).is_ok_and(|m| m.len() >= MAX_BYTES) {
+        let _ = fs::rename(path, path.with_extension("jsonl.1"));
+    }
+    let Ok(line) = serde_json::to_string(&event) else { return; };
+    if let Ok(mut f) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(f, "{}", line);
+    }
+}
+
+/// Events with `from <= ts < to`, oldest first. Lines that do not parse are skipped.
+pub fn read_range(from: i64, to: i64) -> Vec<Event> {
+    let Some(path) = PATH.lock().unwrap_or_else(|e| e.into_inner()).clone() else { return Vec::new(); };
+    [path.with_extension("jsonl.1"), path]
+        .iter()
+        .filter_map(|p| fs::read_to_string(p).ok())
+        .flat_map(|text| text.lines().filter_map(|l| serde_json::from_str::<Event>(l).ok()).collect::<Vec<_>>())
+        .filter(|e| (from..to).contains(&e.ts))
+        .collect()
+}
+
+pub fn to_json(events: &[Event]) -> String {
+    serde_json::to_string_pretty(events).unwrap_or_default()
+}
+
+/// RFC 4180 CSV with a header row; `time` is local time next to the raw Unix `ts`.
+pub fn to_csv(events: &[Event]) -> String {
+    let mut out = String::from("ts,time,kind,event,details,state,error\r\n");
+    for e in events {
+        let time = crate::fmt_local_time(e.ts);
+        let fields = [&e.ts.to_string(), &time, e.kind.as_str(), &e.event, &e.details, &e.state, &e.error];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&row.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
@@ -0,0 +1,241 @@
+//! Outbound HTTP: app metadata, invite and image checks, preset sync.
+//!
+//! Calls go through `rpc_core::http` when the `http` feature is built in and offline mode is off.
+//! Otherwise each one fails with a sentence saying why; the presence itself only talks to Discord
+//! over the local socket and keeps working either way.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+#[cfg(feature = "http")]
+pub use rpc_core::http::{AppMeta, Invite, Proxy, UserStyle};
+#[cfg(not(feature = "http"))]
+pub use disabled::{AppMeta, Invite, Proxy, UserStyle};
+
+/// Whether this build can make HTTP calls at all.
+pub const BUILT_IN: bool = cfg!(feature = "http");
+
+const NOT_BUILT_IN: &str = "This build has no internet features (built without `http`).";
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Turns every call here into an immediate error until switched off again.
+pub fn set_offline(on: bool) {
+    OFFLINE.store(on, Ordering::Relaxed);
+}
+
+/// Why HTTP calls are not made, if they are not.
+pub fn unavailable() -> Option<&'static str> {
+    if !BUILT_IN {
+        Some(NOT_BUILT_IN)
+    } else if OFFLINE.load(Ordering::Relaxed) {
+        Some("Offline mode is on (Settings → Network).")
+    } else {
+        None
+    }
+}
+
+fn ensure_online() -> anyhow::Result<()> {
+    match unavailable() {
+        Some(why) => Err(anyhow::anyhow!(why)),
+        None => Ok(()),
+    }
+}
+
+/// Falls back to the last cached result while offline.
+pub fn fetch_app_meta(client_id: &str) -> anyhow::Result<AppMeta> {
+    if let Err(e) = ensure_online() {
+        return imp::cached_app_meta(client_id).ok_or(e);
+    }
+    imp::fetch_app_meta(client_id)
+}
+
+/// An app icon or avatar; falls back to the cached copy while offline.
+pub fn fetch_image(url: &str) -> anyhow::Result<Vec<u8>> {
+    if let Err(e) = ensure_online() {
+        return imp::cached_image(url).ok_or(e);
+    }
+    imp::fetch_image(url)
+}
+
+pub fn fetch_asset_names(client_id: &str) -> anyhow::Result<Vec<String>> {
+    ensure_online()?;
+    imp::fetch_asset_names(client_id)
+}
+
+pub fn fetch_invite(input: &str) -> anyhow::Result<Invite> {
+    ensure_online()?;
+    imp::fetch_invite(input)
+}
+
+/// Banner and accent color of the account `token` belongs to.
+pub fn fetch_user_style(token: &str) -> anyhow::Result<UserStyle> {
+    ensure_online()?;
+    imp::fetch_user_style(token)
+}
+
+/// Succeeds when `url` answers at all, whatever the status.
+pub fn ping(url: &str, timeout: Duration) -> anyhow::Result<()> {
+    ensure_online()?;
+    imp::ping(url, timeout)
+}
+
+/// Applies the proxy to later calls; a refused setting leaves the previous one in effect.
+pub fn set_proxy(proxy: &Proxy) -> anyhow::Result<()> {
+    imp::set_proxy(proxy)
+}
+
+/// `proxy` with the `user:password@` part of a manual URL removed.
+pub fn without_credentials(proxy: &Proxy) -> Proxy {
+    match proxy {
+        Proxy::Manual(url) => Proxy::Manual(match credentials_end(url) {
+            Some((scheme_end, at)) => format!("{}{}", &url[..scheme_end], &url[at + 1..]),
+            None => url.clone(),
+        }),
+        other => other.clone(),
+    }
+}
+
+/// Whether `proxy` is a manual URL with a `user:password@` part.
+pub fn has_credentials(proxy: &Proxy) -> bool {
+    matches!(proxy, Proxy::Manual(url) if credentials_end(url).is_some())
+}
+
+/// End of `scheme://` and position of the `@` closing the credentials in `url`, if it has any.
+fn credentials_end(url: &str) -> Option<(usize, usize)> {
+    let scheme_end = url.find("://")? + 3;
+    let authority = &url[scheme_end..];
+    let at = authority[..authority.find(['/', '?', '#']).unwrap_or(authority.len())].rfind('@')?;
+    Some((scheme_end, scheme_end + at))
+}
+
+pub fn set_cache_dir(dir: Option<std::path::PathBuf>) {
+    imp::set_cache_dir(dir)
+}
+
+pub use imp::{cache_usage, clear_cache, set_cache_limit, DEFAULT_CACHE_LIMIT};
+
+/// The shared client, for requests `rpc_core` has no helper for.
+#[cfg(feature = "http")]
+pub fn client() -> anyhow::Result<reqwest::blocking::Client> {
+    ensure_online()?;
+    Ok(rpc_core::http::client())
+}
+
+#[cfg(feature = "http")]
+mod imp {
+    use std::time::Duration;
+
+    pub use rpc_core::http::{
+        cache_usage, cached_app_meta, cached_image, clear_cache, fetch_app_meta, fetch_asset_names, fetch_image, fetch_invite,
+        fetch_user_style, set_cache_dir, set_cache_limit, set_proxy, DEFAULT_CACHE_LIMIT,
+    };
+
+    pub fn ping(url: &str, timeout: Duration) -> anyhow::Result<()> {
+        rpc_core::http::client().get(url).timeout(timeout).send()?;
+        Ok(())
+    }
+}
+
+/// Unreachable behind `ensure_online`, but keeps the signatures without `reqwest`.
+#[cfg(not(feature = "http"))]
+mod imp {
+    use super::{AppMeta, Invite, Proxy, UserStyle, NOT_BUILT_IN};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn disabled<T>() -> anyhow::Result<T> {
+        Err(anyhow::anyhow!(NOT_BUILT_IN))
+    }
+
+    pub fn fetch_app_meta(_: &str) -> anyhow::Result<AppMeta> {
+        disabled()
+    }
+
+    pub fn cached_app_meta(_: &str) -> Option<AppMeta> {
+        None
+    }
+
+    pub fn fetch_image(_: &str) -> anyhow::Result<Vec<u8>> {
+        disabled()
+    }
+
+    pub fn cached_image(_: &str) -> Option<Vec<u8>> {
+        None
+    }
+
+    pub fn fetch_asset_names(_: &str) -> anyhow::Result<Vec<String>> {
+        disabled()
+    }
+
+    pub fn fetch_invite(_: &str) -> anyhow::Result<Invite> {
+        disabled()
+    }
+
+    pub fn ping(_: &str, _: Duration) -> anyhow::Result<()> {
+        disabled()
+    }
+
+    pub fn fetch_user_style(_: &str) -> anyhow::Result<UserStyle> {
+        disabled()
+    }
+
+    pub fn set_proxy(_: &Proxy) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    pub fn set_cache_dir(_: Option<PathBuf>) {}
+
+    pub const DEFAULT_CACHE_LIMIT: u64 = 0;
+
+    pub fn set_cache_limit(_: u64) {}
+
+    pub fn cache_usage() -> u64 {
+        0
+    }
+
+    pub fn clear_cache() -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// The `rpc_core::http` types the app stores and passes around, for builds without it.
+#[cfg(not(feature = "http"))]
+mod disabled {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone)]
+    pub struct AppMeta {
+        pub name: String,
+        pub icon_url: Option<String>,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct Invite {
+        pub code: String,
+        pub guild_name: Option<String>,
+        pub expires_at: Option<String>,
+    }
+
+    impl Invite {
+        pub fn url(&self) -> String {
+            format!("https://discord.gg/{}", self.code)
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct UserStyle {
+        pub banner_url: Option<String>,
+        pub accent_color: Option<u32>,
+    }
+
+    /// Kept so a config written by a full build loads and saves unchanged.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Proxy {
+        #[default]
+        System,
+        Direct,
+        Manual(String),
+    }
+}
@@ -0,0 +1,130 @@
+use rpc_core::FrameDir;
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+const TAIL_LINES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Raw protocol frames; only written while the protocol trace is on.
+    Trace,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Level::Trace => "TRACE",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+struct Logger {
+    path: Option<PathBuf>,
+    file: Option<File>,
+    written: u64,
+    tail: VecDeque<String>,
+}
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger {
+    path: None,
+    file: None,
+    written: 0,
+    tail: VecDeque::new(),
+});
+
+/// Opens `<dir>/app.log` for appending. Lines logged before this are only kept in memory.
+pub fn init(dir: PathBuf) {
+    let _ = fs::create_dir_all(&dir);
+    let mut lg = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+    lg.path = Some(dir.join("app.log"));
+    open_file(&mut lg);
+}
+
+fn open_file(lg: &mut Logger) {
+    let Some(path) = lg.path.clone() else { return; };
+    lg.written = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    if lg.written >= MAX_LOG_BYTES {
+        let _ = fs::rename(&path, path.with_extension("log.1"));
+        lg.written = 0;
+    }
+    lg.file = OpenOptions::new().create(true).append(true).open(&path).ok();
+}
+
+pub fn log(level: Level, msg: impl AsRef<str>) {
+    let line = format!(
+        "[{}] {} {}",
+        rpc_core::now_unix_ts(),
+        level.as_str(),
+        crate::privacy::redact(msg.as_ref())
+    );
+
+    // The panic hook logs too, so never propagate a poisoned lock.
+    let mut lg = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+    if lg.tail.len() >= TAIL_LINES {
+        lg.tail.pop_front();
+    }
+    lg.tail.push_back(line.clone());
+
+    if let Some(f) = lg.file.as_mut() {
+        if writeln!(f, "{}", line).is_ok() {
+            lg.written += line.len() as u64 + 1;
+        }
+    }
+    if lg.written >= MAX_LOG_BYTES {
+        lg.file = None;
+        open_file(&mut lg);
+    }
+}
+
+pub fn info(msg: impl AsRef<str>) {
+    log(Level::Info, msg);
+}
+
+pub fn warn(msg: impl AsRef<str>) {
+    log(Level::Warn, msg);
+}
+
+pub fn error(msg: impl AsRef<str>) {
+    log(Level::Error, msg);
+}
+
+/// Makes sure everything logged so far is on disk, before the process exits.
+pub fn flush() {
+    let mut lg = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(f) = lg.file.as_mut() {
+        let _ = f.flush();
+        let _ = f.sync_data();
+    }
+}
+
+/// Last `n` lines logged during this session (oldest first).
+pub fn tail(n: usize) -> Vec<String> {
+    let lg = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+    let skip = lg.tail.len().saturating_sub(n);
+    lg.tail.iter().skip(skip).cloned().collect()
+}
+
+/// Turns the IPC protocol trace on or off.
+pub fn set_protocol_trace(on: bool) {
+    rpc_core::set_frame_tracer(if on { Some(trace_frame) } else { None });
+}
+
+fn trace_frame(dir: FrameDir, opcode: i32, payload: &serde_json::Value) {
+    let arrow = match dir {
+        FrameDir::Sent => ">>",
+        FrameDir::Received => "<<",
+    };
+    log(Level::Trace, format!("{} op={} {}", arrow, opcode, crate::privacy::redact_frame(payload)));
+}
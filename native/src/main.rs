@@ -1,289 +1,67 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use anyhow::Context;
-use directories::ProjectDirs;
+mod backup;
+mod bidi;
+mod bulk;
+mod clipboard;
+mod clock;
+mod content;
+mod crash;
+mod cue;
+mod diff;
+mod dock;
+mod dropin;
+mod emoji;
+mod history;
+mod http;
+mod logging;
+mod migrate;
+mod network;
+mod overlay;
+mod presets;
+mod preview;
+mod privacy;
+mod recent;
+mod rivals;
+mod rules;
+mod schema;
+mod selfcheck;
+mod sync;
+mod takeover;
+mod triggers;
+mod troubleshoot;
+
+use chrono::Datelike;
+use directories::{ProjectDirs, UserDirs};
 use eframe::egui;
-use rpc_core::{ButtonCfg, DiscordRpcClient, PresenceCfg, UserProfile};
+use http::{AppMeta, Invite};
+use presets::{Preset, PresetSort};
+use rpc_core::{
+    i18n::{self, Lang},
+    managed::{ManagedClient, RpcStatus},
+    cycle::ImageCycle,
+    marquee::Marquee,
+    progress::{BarStyle, Bars},
+    ratelimit::{Action, RateLimiter, RateLimits, RatePreset},
+    shutdown::Shutdown,
+    validate::{text_len, MAX_BUTTON_LABEL, MAX_MARQUEE_TEXT, MAX_TEXT},
+    ButtonCfg, DiscordRpcClient, PresenceCfg, Refresh, TimerPolicy, UserProfile,
+};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
     fs,
-    path::PathBuf,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        mpsc,
-        Arc, Condvar, Mutex,
-    },
+    path::{Path, PathBuf},
+    sync::mpsc,
     thread,
     time::{Duration, Instant},
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum RpcStatus {
-    Inactive,
-    Connecting,
-    Active,
-    Error,
-}
-
-impl RpcStatus {
-    fn as_str(&self) -> &'static str {
-        match self {
-            RpcStatus::Inactive => "inactive",
-            RpcStatus::Connecting => "connecting",
-            RpcStatus::Active => "active",
-            RpcStatus::Error => "error",
-        }
-    }
-}
-
-struct RpcWorker {
-    running: AtomicBool,
-    thread_alive: AtomicBool,
-    status: Mutex<RpcStatus>,
-    last_error: Mutex<Option<String>>,
-    cfg: Mutex<Option<PresenceCfg>>,
-    start_ts: Mutex<Option<i64>>,
-}
-
-impl Default for RpcWorker {
-    fn default() -> Self {
-        Self {
-            running: AtomicBool::new(false),
-            thread_alive: AtomicBool::new(false),
-            status: Mutex::new(RpcStatus::Inactive),
-            last_error: Mutex::new(None),
-            cfg: Mutex::new(None),
-            start_ts: Mutex::new(None),
-        }
-    }
-}
-
-impl RpcWorker {
-    fn status(&self) -> RpcStatus {
-        *self.status.lock().unwrap()
-    }
-
-    fn last_error(&self) -> Option<String> {
-        self.last_error.lock().unwrap().clone()
-    }
-
-    fn enable(self: &Arc<Self>, cfg: PresenceCfg, signal: &Arc<RpcSignal>) -> Result<(), String> {
-        {
-            let mut lock = self.cfg.lock().unwrap();
-            *lock = Some(cfg);
-        }
-
-        {
-            let mut st = self.start_ts.lock().unwrap();
-            if st.is_none() {
-                *st = Some(rpc_core::now_unix_ts());
-            }
-        }
-
-        self.running.store(true, Ordering::SeqCst);
-
-        if self.thread_alive.load(Ordering::SeqCst) {
-            signal.poke();
-            return Ok(());
-        }
-
-        self.thread_alive.store(true, Ordering::SeqCst);
-        let w = Arc::clone(self);
-        let sig = Arc::clone(signal);
-
-        thread::spawn(move || {
-            let fast_schedule = [
-                Duration::from_secs(0),
-                Duration::from_secs(1),
-                Duration::from_secs(2),
-                Duration::from_secs(4),
-                Duration::from_secs(8),
-            ];
-            let keepalive_tick = Duration::from_secs(10);
-
-            *w.status.lock().unwrap() = RpcStatus::Connecting;
-            *w.last_error.lock().unwrap() = None;
-
-            let mut client: Option<DiscordRpcClient> = None;
-
-            while w.running.load(Ordering::SeqCst) {
-                let cfg_opt = { w.cfg.lock().unwrap().clone() };
-                let cfg = match cfg_opt {
-                    Some(c) => c,
-                    None => {
-                        *w.status.lock().unwrap() = RpcStatus::Inactive;
-                        break;
-                    }
-                };
-
-                let start_ts = *w.start_ts.lock().unwrap().get_or_insert_with(rpc_core::now_unix_ts);
-
-                if client.is_none() {
-                    *w.status.lock().unwrap() = RpcStatus::Connecting;
-                    match DiscordRpcClient::connect_and_handshake(&cfg.client_id) {
-                        Ok((c, _hs)) => {
-                            client = Some(c);
-                            *w.last_error.lock().unwrap() = None;
-                        }
-                        Err(e) => {
-                            *w.status.lock().unwrap() = RpcStatus::Error;
-                            *w.last_error.lock().unwrap() = Some(e.to_string());
-                            sig.wait_or_timeout(Duration::from_secs(2));
-                            continue;
-                        }
-                    }
-                }
-
-                {
-                    let mut ok_streak = 0u8;
-                    for d in fast_schedule {
-                        if !w.running.load(Ordering::SeqCst) {
-                            break;
-                        }
-                        if d.as_secs() > 0 {
-                            thread::sleep(d);
-                        }
-
-                        let cfg2 = { w.cfg.lock().unwrap().clone() }.unwrap_or_else(|| cfg.clone());
-
-                        let res = match client.as_mut() {
-                            Some(c) => c.set_activity(&cfg2, start_ts),
-                            None => Err(anyhow::anyhow!("client is None")),
-                        };
-
-                        match res {
-                            Ok(_) => {
-                                ok_streak = ok_streak.saturating_add(1);
-                                *w.last_error.lock().unwrap() = None;
-                                if ok_streak >= 2 {
-                                    *w.status.lock().unwrap() = RpcStatus::Active;
-                                    break;
-                                } else {
-                                    *w.status.lock().unwrap() = RpcStatus::Connecting;
-                                }
-                            }
-                            Err(e) => {
-                                *w.status.lock().unwrap() = RpcStatus::Error;
-                                *w.last_error.lock().unwrap() = Some(e.to_string());
-                                client = None;
-                                break;
-                            }
-                        }
-                    }
-                }
-
-                if !w.running.load(Ordering::SeqCst) {
-                    break;
-                }
-
-                sig.wait_or_timeout(keepalive_tick);
-                if !w.running.load(Ordering::SeqCst) {
-                    break;
-                }
-
-                let cfg3 = { w.cfg.lock().unwrap().clone() }.unwrap_or_else(|| cfg.clone());
-                let res = match client.as_mut() {
-                    Some(c) => c.set_activity(&cfg3, start_ts),
-                    None => Err(anyhow::anyhow!("client is None")),
-                };
-
-                match res {
-                    Ok(_) => {
-                        *w.status.lock().unwrap() = RpcStatus::Active;
-                        *w.last_error.lock().unwrap() = None;
-                    }
-                    Err(e) => {
-                        *w.status.lock().unwrap() = RpcStatus::Error;
-                        *w.last_error.lock().unwrap() = Some(e.to_string());
-                        client = None;
-                        sig.wait_or_timeout(Duration::from_secs(2));
-                    }
-                }
-            }
-
-            if let Some(mut c) = client {
-                let _ = c.clear_activity();
-            }
-
-            *w.start_ts.lock().unwrap() = None;
-            *w.status.lock().unwrap() = RpcStatus::Inactive;
-            *w.last_error.lock().unwrap() = None;
-            w.thread_alive.store(false, Ordering::SeqCst);
-        });
-
-        Ok(())
-    }
-
-    fn update(&self, cfg: PresenceCfg, signal: &Arc<RpcSignal>) -> Result<(), String> {
-        {
-            let mut lock = self.cfg.lock().unwrap();
-            *lock = Some(cfg);
-        }
-
-        if self.running.load(Ordering::SeqCst) {
-            signal.poke();
-        }
-
-        Ok(())
-    }
-
-    fn disable(&self, signal: &Arc<RpcSignal>) -> Result<(), String> {
-        self.running.store(false, Ordering::SeqCst);
-        signal.poke();
-        Ok(())
-    }
-}
-
-struct RpcSignal {
-    cv: Condvar,
-    flag: Mutex<bool>,
-}
-
-impl Default for RpcSignal {
-    fn default() -> Self {
-        Self {
-            cv: Condvar::new(),
-            flag: Mutex::new(false),
-        }
-    }
-}
-
-impl RpcSignal {
-    fn poke(&self) {
-        let mut f = self.flag.lock().unwrap();
-        *f = true;
-        self.cv.notify_all();
-    }
-
-    fn wait_or_timeout(&self, dur: Duration) {
-        let mut f = self.flag.lock().unwrap();
-        if *f {
-            *f = false;
-            return;
-        }
-        let (mut f2, _) = self.cv.wait_timeout(f, dur).unwrap();
-        *f2 = false;
-    }
-}
-
-#[derive(Default)]
-struct RateState {
-    last: Option<Instant>,
-}
-
-fn rate_check(state: &Mutex<RateState>, min_delay: Duration) -> Result<(), String> {
-    let mut st = state.lock().unwrap();
-    if let Some(last) = st.last {
-        if last.elapsed() < min_delay {
-            return Err("Rate limit: please wait a moment before repeating the action.".to_string());
-        }
-    }
-    st.last = Some(Instant::now());
-    Ok(())
-}
-
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
 struct StoredConfig {
+    /// Layout version this config was written in; see `schema`.
+    version: u32,
     client_id: String,
     details: String,
     state: String,
@@ -296,13 +74,98 @@ struct StoredConfig {
     b2label: String,
     b2url: String,
     with_timestamp: bool,
+    timer_mode: TimerMode,
+    timer_at: String,
+    targets: Vec<String>,
+    refresh: Refresh,
+    marquee: Marquee,
+    image_cycle: ImageCycle,
+    cue: cue::Cue,
+    constants: Vec<Constant>,
     last_user_name: String,
     last_user_avatar: String,
+    /// Avatar decoration of the last synced user (empty = none).
+    last_user_decoration: String,
+    /// Profile banner and accent color (`0xRRGGBB`), only known with an OAuth token.
+    last_user_banner: String,
+    last_user_accent: Option<u32>,
+    /// Optional OAuth2 access token (`identify` scope) used only to read the banner and accent color.
+    oauth_token: String,
     last_app_name: String,
     last_app_icon: String,
+    /// Advanced: IPC socket/pipe used instead of discovery (e.g. a bind-mounted socket or the
+    /// `mock-discord` server); `$NAME` expands to an environment variable.
+    ipc_path: String,
+    /// Advanced: PID reported with the activity instead of this app's (empty = this app).
+    pid_override: String,
+    /// Advanced: log every IPC frame (masked) at TRACE level.
+    protocol_trace: bool,
+    presets: Vec<Preset>,
+    /// egui zoom factor on top of the monitor's own DPI scale (0 = unset).
+    ui_scale: f32,
+    privacy_mode: bool,
+    /// Language of Discord connection errors (None = follow the system locale).
+    language: Option<Lang>,
+    /// 12- or 24-hour clock for displayed times (None = follow the system locale).
+    hour_cycle: Option<clock::HourCycle>,
+    /// Warn before enabling a presence that contains blocked words.
+    content_warning: bool,
+    /// Stop pointing out that your own buttons are invisible to you.
+    hide_button_note: bool,
+    /// The reminder about Discord's activity privacy setting was shown after the first enable.
+    privacy_reminder_shown: bool,
+    /// Extra blocked words, one per line, on top of the built-in list.
+    blocked_words: String,
+    /// Look of `{bar:value/max}` placeholders.
+    bars: Bars,
+    /// Copied text matching a pattern sets a template variable (off by default).
+    clipboard: clipboard::ClipSettings,
+    /// Presets applied automatically when a condition holds.
+    rules: Vec<rules::Rule>,
+    /// Pending "Enable later…"; kept across restarts.
+    deferred: Option<rules::Deferred>,
+    /// Hidden: locked UI showing only the status and the enable/disable toggle (also `--kiosk`).
+    kiosk: bool,
+    /// Small window with only the status, the toggle and a preset picker.
+    compact: bool,
+    /// Keep the window above other apps.
+    always_on_top: bool,
+    /// Corner of the click-through status overlay; `None` hides it.
+    overlay: Option<dock::Corner>,
+    /// Values sent or saved before, suggested while typing in the form.
+    field_history: recent::FieldHistory,
+    /// Corner the compact window is docked to, per monitor (`dock::monitor_key`).
+    compact_docks: BTreeMap<String, dock::Corner>,
+    /// Preset library sync; credentials are stored here in plain text like the rest of the config.
+    sync: sync::SyncSettings,
+    /// Proxy for app metadata, asset checks and preset sync.
+    proxy: http::Proxy,
+    /// No HTTP calls at all; the presence only needs the local Discord socket.
+    offline: bool,
+    /// Size cap of the icon/avatar/metadata cache in MB (0 = the default).
+    cache_limit_mb: u32,
+    /// Minimum delays between sync, enable/disable and update clicks.
+    rate_preset: RatePreset,
+    /// Delays used when `rate_preset` is custom.
+    rate_limits: RateLimits,
+    preset_sort: PresetSort,
+}
+
+impl StoredConfig {
+    /// The config with its credentials blanked, for copies that leave the config file: backups
+    /// and crash reports. A new secret setting belongs here.
+    fn without_secrets(mut self) -> Self {
+        self.sync.gist_token.clear();
+        self.sync.webdav_user.clear();
+        self.sync.webdav_password.clear();
+        self.oauth_token.clear();
+        self.proxy = http::without_credentials(&self.proxy);
+        self
+    }
 }
 
-#[derive(Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(default)]
 struct FormConfig {
     client_id: String,
     details: String,
@@ -316,9 +179,75 @@ struct FormConfig {
     b2label: String,
     b2url: String,
     with_timestamp: bool,
+    timer_mode: TimerMode,
+    /// Local time ("YYYY-MM-DD HH:MM") for the fixed and end modes.
+    timer_at: String,
+    /// IPC paths of the Discord instances to publish to; empty = the first one found.
+    targets: Vec<String>,
+    /// Advanced: keepalive interval and connect burst for this preset.
+    refresh: Refresh,
+    marquee: Marquee,
+    image_cycle: ImageCycle,
+    /// Sound and/or notification once Discord shows this preset.
+    cue: cue::Cue,
+    /// This preset's own `{name}` values, filled in before any other placeholder.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    constants: Vec<Constant>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(default)]
+struct Constant {
+    name: String,
+    value: String,
+}
+
+impl Constant {
+    /// Letters, digits and `_`, so a constant never reads as one of the built-in helpers.
+    fn valid_name(name: &str) -> bool {
+        !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    }
+}
+
+/// Per-preset timer behaviour; see `rpc_core::TimerPolicy`.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum TimerMode {
+    #[default]
+    Continue,
+    Reset,
+    Fixed,
+    End,
+}
+
+impl TimerMode {
+    const ALL: [TimerMode; 4] = [TimerMode::Continue, TimerMode::Reset, TimerMode::Fixed, TimerMode::End];
+
+    fn label(self) -> &'static str {
+        match self {
+            TimerMode::Continue => "Continue current",
+            TimerMode::Reset => "Reset when applied",
+            TimerMode::Fixed => "Elapsed since…",
+            TimerMode::End => "Countdown to…",
+        }
+    }
+
+    fn needs_time(self) -> bool {
+        matches!(self, TimerMode::Fixed | TimerMode::End)
+    }
 }
 
 impl FormConfig {
+    /// Falls back to `Continue` while the fixed/end time does not parse.
+    fn timer_policy(&self) -> TimerPolicy {
+        match (self.timer_mode, parse_local_time(&self.timer_at)) {
+            (TimerMode::Reset, _) => TimerPolicy::Reset,
+            (TimerMode::Fixed, Ok(start)) => TimerPolicy::Fixed { start },
+            (TimerMode::End, Ok(end)) => TimerPolicy::End { end },
+            _ => TimerPolicy::Continue,
+        }
+    }
+
     fn to_presence_cfg(&self) -> PresenceCfg {
         let mut buttons = Vec::new();
         if !self.b1label.trim().is_empty() || !self.b1url.trim().is_empty() {
@@ -337,17 +266,28 @@ impl FormConfig {
         let details = self.details.trim().to_string();
         let state = self.state.trim().to_string();
 
-        PresenceCfg {
+        let cfg = PresenceCfg {
             client_id: self.client_id.trim().to_string(),
-            details: if details.len() >= 2 { details } else { String::new() },
-            state: if state.len() >= 2 { state } else { String::new() },
+            details: if text_len(&details) >= 2 { details } else { String::new() },
+            state: if text_len(&state) >= 2 { state } else { String::new() },
             large_image: opt_str(&self.large_image),
             large_text: opt_str(&self.large_text),
             small_image: opt_str(&self.small_image),
             small_text: opt_str(&self.small_text),
             buttons,
             with_timestamp: self.with_timestamp,
+            timer: self.timer_policy(),
+            refresh: self.refresh,
+            marquee: self.marquee,
+            image_cycle: ImageCycle {
+                images: self.image_cycle.images.iter().filter_map(|i| opt_str(i)).collect(),
+                ..self.image_cycle.clone()
+            },
+        };
+        if self.constants.is_empty() {
+            return cfg;
         }
+        cfg.fill(&|name| self.constants.iter().find(|c| c.name.trim() == name && Constant::valid_name(name)).map(|c| c.value.trim().to_string()))
     }
 
     fn from_stored(s: &StoredConfig) -> Self {
@@ -364,47 +304,407 @@ impl FormConfig {
             b2label: s.b2label.clone(),
             b2url: s.b2url.clone(),
             with_timestamp: s.with_timestamp,
+            timer_mode: s.timer_mode,
+            timer_at: s.timer_at.clone(),
+            targets: s.targets.clone(),
+            refresh: s.refresh,
+            marquee: s.marquee,
+            image_cycle: s.image_cycle.clone(),
+            cue: s.cue,
+            constants: s.constants.clone(),
         }
     }
 }
 
+/// Language of the user's locale, from the usual POSIX variables (Windows sets none; falls back to English).
+fn system_lang() -> Lang {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .find_map(|k| std::env::var(k).ok().filter(|v| !v.is_empty()))
+        .map_or(Lang::En, |tag| Lang::from_tag(&tag))
+}
+
 fn opt_str(v: &str) -> Option<String> {
     let s = v.trim();
     if s.is_empty() { None } else { Some(s.to_string()) }
 }
 
+/// Parses "YYYY-MM-DD HH:MM" (or "YYYY-MM-DD h:MM PM") in the local timezone into a Unix timestamp.
+fn parse_local_time(v: &str) -> Result<i64, String> {
+    let err = || format!("Use the format {}.", clock::input_hint());
+    let (date, time) = v.trim().split_once(' ').ok_or_else(err)?;
+    let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| err())?;
+    let time = clock::parse_time(time).ok_or_else(err)?;
+    Ok(rules::local_instant(&chrono::Local, date.and_time(time)).timestamp())
+}
+
+fn fmt_local_time(ts: i64) -> String {
+    clock::fmt_date_time(ts)
+}
+
+/// `discord-ipc-N` part of a socket/pipe path, for compact display.
+fn pipe_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Empty means "no override".
+fn parse_pid(v: &str) -> Result<Option<u32>, String> {
+    match v.trim() {
+        "" => Ok(None),
+        s => s.parse::<u32>().ok().filter(|p| *p > 0).map(Some).ok_or_else(|| "PID must be a positive number.".to_string()),
+    }
+}
+
+/// Destructive actions that need an explicit confirmation first.
 #[derive(Debug, Clone)]
-struct AppMeta {
-    name: String,
-    icon_url: Option<String>,
+enum Confirm {
+    DeletePreset(usize),
+    Reset(ResetScope),
+    /// A sync was refused because of changes on both sides; the message says which.
+    SyncConflict(sync::Direction, String),
+    ImportBackup(PathBuf),
+    /// Blocked words found before enabling, already formatted for display.
+    ContentWarning(String),
+    /// Disabling while rules or an "Enable later" would bring the presence back; the message says which.
+    Disable(String),
+}
+
+/// What Settings → Reset puts back to defaults. A backup of the config folder is saved first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResetScope {
+    /// The form, presets and every setting.
+    All,
+    Presets,
+    /// Preset sync, the profile token, the proxy and clipboard watching.
+    Integrations,
+    /// The icon/avatar/app name cache and the last user and app remembered in the config.
+    Cache,
+}
+
+impl ResetScope {
+    const ALL: [ResetScope; 4] = [ResetScope::All, ResetScope::Presets, ResetScope::Integrations, ResetScope::Cache];
+
+    fn label(self) -> &'static str {
+        match self {
+            ResetScope::All => "Everything…",
+            ResetScope::Presets => "Presets…",
+            ResetScope::Integrations => "Integrations…",
+            ResetScope::Cache => "Cached data…",
+        }
+    }
+
+    fn question(self) -> &'static str {
+        match self {
+            ResetScope::All => "Reset the form, presets and every setting to defaults?",
+            ResetScope::Presets => "Delete every preset? The form, rules and other settings stay. Files in presets.d are not touched.",
+            ResetScope::Integrations => {
+                "Forget the preset sync account, the profile token, the proxy and clipboard watching? Presets and the form stay."
+            }
+            ResetScope::Cache => "Delete cached app icons, avatars and app names, and forget the last user and app shown?",
+        }
+    }
+
+    /// `stored` with this scope put back to defaults.
+    fn apply(self, stored: StoredConfig) -> StoredConfig {
+        let d = StoredConfig::default();
+        match self {
+            ResetScope::All => d,
+            ResetScope::Presets => StoredConfig { presets: d.presets, ..stored },
+            ResetScope::Integrations => StoredConfig {
+                sync: d.sync,
+                oauth_token: d.oauth_token,
+                last_user_banner: d.last_user_banner,
+                last_user_accent: d.last_user_accent,
+                proxy: d.proxy,
+                clipboard: d.clipboard,
+                ..stored
+            },
+            ResetScope::Cache => StoredConfig {
+                last_user_name: d.last_user_name,
+                last_user_avatar: d.last_user_avatar,
+                last_user_decoration: d.last_user_decoration,
+                last_user_banner: d.last_user_banner,
+                last_user_accent: d.last_user_accent,
+                last_app_name: d.last_app_name,
+                last_app_icon: d.last_app_icon,
+                ..stored
+            },
+        }
+    }
+}
+
+// At most one exists at a time, so the variant size difference does not matter.
+#[allow(clippy::large_enum_variant)]
+enum UndoAction {
+    RestorePreset(usize, Preset),
+    /// A preset overwritten by an import with the same name.
+    RestoreReplaced(usize, Preset),
+    /// Form edits dropped by "Revert to live".
+    ReapplyEdits(FormConfig),
+    RestoreConfig(StoredConfig),
+}
+
+struct UndoToast {
+    message: String,
+    action: UndoAction,
+    until: Instant,
 }
 
-#[derive(Debug, Deserialize)]
-struct RpcAppResp {
+/// Presets shown in the recent bar.
+const RECENT_PRESETS: usize = 5;
+
+const UNDO_WINDOW: Duration = Duration::from_secs(8);
+/// How often to look for Discord while none of its sockets exist.
+const DISCORD_PROBE_EVERY: Duration = Duration::from_secs(2);
+
+struct ShareDialog {
     name: String,
-    icon: Option<String>,
+    code: String,
+    qr: Option<(usize, Vec<bool>)>,
+}
+
+/// Find and replace across the saved presets, with the changes listed before applying.
+#[derive(Default)]
+struct BulkDialog {
+    target: bulk::Target,
+    find: String,
+    replace: String,
+    match_case: bool,
+}
+
+#[derive(Default)]
+struct ImportDialog {
+    text: String,
+    error: String,
+    preview: Option<Preset>,
+}
+
+/// Latest self-check results. Shown on its own after a check with failures, or when asked for.
+#[derive(Default)]
+struct SelfCheck {
+    checks: Vec<selfcheck::Check>,
+    running: bool,
+    open: bool,
+}
+
+/// "Enable later…" as typed: times are `HH:MM`, `h:MM PM` or `+N` minutes, `until` may be empty.
+#[derive(Default)]
+struct DeferDialog {
+    /// Preset to load first; empty keeps the form.
+    preset: String,
+    at: String,
+    until: String,
+    error: String,
+}
+
+/// Fills a button from a server invite once Discord confirms it is valid.
+#[derive(Default)]
+struct InviteDialog {
+    text: String,
+    /// Button to fill: 0 or 1.
+    slot: usize,
+    checking: bool,
+    error: String,
+}
+
+/// Fake rule inputs from the Simulate panel, kept as typed.
+struct Simulation {
+    inputs: rules::Inputs,
+    /// Day of this week the faked time is on, 0 = Monday.
+    weekday: u32,
+    time: String,
+    /// Comma-separated process names.
+    processes: String,
+}
+
+impl Simulation {
+    fn from_inputs(inputs: &rules::Inputs) -> Self {
+        let now = chrono::Local::now();
+        Self {
+            // The real process list is long; start from none so the panel shows everything faked.
+            inputs: rules::Inputs { processes: Vec::new(), ..inputs.clone() },
+            weekday: now.weekday().num_days_from_monday(),
+            time: now.format("%H:%M").to_string(),
+            processes: String::new(),
+        }
+    }
+
+    /// Points the faked time at `weekday` and `time` this week.
+    fn set_time(&mut self) {
+        let Some(time) = rules::parse_hhmm(&self.time) else { return };
+        let today = chrono::Local::now().date_naive();
+        let day = today + chrono::Duration::days(i64::from(self.weekday) - i64::from(today.weekday().num_days_from_monday()));
+        self.inputs.now = rules::local_instant(&chrono::Local, day.and_time(time)).timestamp();
+    }
 }
 
 enum AppEvent {
     UserProfile(Result<UserProfile, String>),
+    UserStyle(Result<http::UserStyle, String>),
     AppMeta(Result<AppMeta, String>),
+    Invite(Result<Invite, String>),
+    /// Text was copied while the clipboard watch is on.
+    Clipboard(String),
+    SelfCheck(Vec<selfcheck::Check>),
+    Sync(sync::Direction, Result<sync::Outcome, String>),
+    /// Something rules can react to changed.
+    RuleInputs(rules::Inputs),
+    /// An icon, avatar or decoration finished downloading (`None` when it could not be loaded).
+    Picture(String, Option<Vec<(egui::ColorImage, Duration)>>),
+    /// `presence-daemon` answered the request to release the presence.
+    TakeOver(Result<(), String>),
+}
+
+/// App icon, avatar or avatar decoration shown next to the last synced app and user. Animated
+/// ones have a frame per entry, each with how long it stays up.
+enum Picture {
+    Loading,
+    /// Decoded off the UI thread; becomes textures the next time it is drawn.
+    Decoded(Vec<(egui::ColorImage, Duration)>),
+    Shown(Vec<(egui::TextureHandle, Duration)>),
+    Missing,
 }
 
 struct AppState {
-    worker: Arc<RpcWorker>,
-    signal: Arc<RpcSignal>,
-    rate: Mutex<RateState>,
+    /// Publishes to the first target (or the discovered/hidden IPC path when there are none).
+    worker: ManagedClient,
+    /// One worker per additional target instance, keyed by IPC path.
+    extra_workers: Vec<(String, ManagedClient)>,
+    /// Discord instances found by the last scan.
+    ipc_pipes: Vec<String>,
+    last_probe: Instant,
+    /// Enable was pressed while Discord was not running; connect as soon as it shows up.
+    enable_when_found: bool,
+    rate: RateLimiter,
+    rate_preset: RatePreset,
+    rate_limits: RateLimits,
     events_tx: mpsc::Sender<AppEvent>,
     events_rx: mpsc::Receiver<AppEvent>,
     cfg_path: Option<PathBuf>,
+    /// Why the config file could not be loaded at startup, if it could not.
+    config_error: Option<String>,
+    self_check: SelfCheck,
+    crash_dir: Option<PathBuf>,
+    crash_report: Option<crash::CrashReport>,
     form: FormConfig,
     last_user_name: String,
     last_user_avatar: String,
+    last_user_decoration: String,
+    last_user_banner: String,
+    last_user_accent: Option<u32>,
+    oauth_token: String,
     last_app_name: String,
     last_app_icon: String,
+    ipc_path: String,
+    pid_override: String,
+    protocol_trace: bool,
+    presets: Vec<Preset>,
+    selected_preset: Option<usize>,
+    /// Read-only presets from `presets.d/`.
+    dropin: dropin::DropIn,
+    /// Selected drop-in preset; exclusive with `selected_preset`.
+    selected_dropin: Option<usize>,
+    new_preset_name: String,
+    /// Filter typed above the preset list; see [`Preset::matches`].
+    preset_query: String,
+    preset_sort: PresetSort,
+    /// Tags of the selected preset as typed, with the preset they belong to; saved on focus loss.
+    tags_edit: (String, String),
+    /// The form as last sent with Enable or Update, i.e. what Discord shows while running.
+    applied: Option<FormConfig>,
+    /// "Apply changes" dialog open: the draft's differences from the live presence.
+    apply_review: bool,
+    /// Preset picked with Load while the form differs from it; loads once confirmed.
+    load_review: Option<Preset>,
+    share: Option<ShareDialog>,
+    import: Option<ImportDialog>,
+    bulk: Option<BulkDialog>,
+    invite: Option<InviteDialog>,
+    /// Settings found in an old config folder on first run, with its `config.json` parsed if it
+    /// had a readable one; offered once.
+    migration: Option<(migrate::Legacy, Option<StoredConfig>)>,
+    ui_scale: f32,
+    scale_drag: Option<f32>,
+    privacy_mode: bool,
+    language: Option<Lang>,
+    hour_cycle: Option<clock::HourCycle>,
+    sync: sync::SyncSettings,
+    sync_busy: bool,
+    /// Waiting for `presence-daemon` to release the presence.
+    takeover_busy: bool,
+    /// Path typed in Settings for "Import backup"; dropping a zip on the window also works.
+    backup_path: String,
+    /// Date range (`YYYY-MM-DD`, inclusive) and format for Settings → History export.
+    history_from: String,
+    history_to: String,
+    history_json: bool,
+    hide_button_note: bool,
+    button_guide: bool,
+    privacy_reminder_shown: bool,
+    privacy_reminder: bool,
+    /// Open visibility checklist, with the manual steps the user ticked off.
+    troubleshoot: Option<HashSet<&'static str>>,
+    content_warning: bool,
+    blocked_words: String,
+    bars: Bars,
+    clipboard: clipboard::ClipSettings,
+    /// Running while the clipboard watch is on.
+    clip_watcher: Option<clipboard::Watcher>,
+    /// Stops the background samplers and the worker when the window closes; see `on_exit`.
+    shutdown: Shutdown,
+    /// Value the clipboard watch last set.
+    clip_value: Option<String>,
+    proxy: http::Proxy,
+    offline: bool,
+    /// Keyed by URL; filled from the disk cache or the network on first use.
+    pictures: HashMap<String, Picture>,
+    cache_limit_mb: u32,
+    /// Disk space the cache takes, measured when Settings shows it.
+    cache_usage: Option<u64>,
+    /// URL field of the manual proxy, kept while another mode is selected.
+    proxy_url: String,
+    /// Why the proxy setting was refused; the previous one stays in effect.
+    proxy_error: Option<String>,
+    rules: Vec<rules::Rule>,
+    deferred: Option<rules::Deferred>,
+    defer_dialog: Option<DeferDialog>,
+    rule_inputs: rules::Inputs,
+    /// Rule whose preset was applied last, so it is not re-applied over manual edits.
+    applied_rule: Option<usize>,
+    /// Open Simulate panel; it never applies anything.
+    simulation: Option<Simulation>,
+    /// Kiosk mode from the config file; `--kiosk` adds `kiosk_cli` without persisting it.
+    kiosk: bool,
+    kiosk_cli: bool,
+    compact: bool,
+    compact_docks: BTreeMap<String, dock::Corner>,
+    /// Monitor the compact window was last sized and docked for; `None` does it again.
+    compact_monitor: Option<String>,
+    always_on_top: bool,
+    /// Window level last sent to the window; `None` sends it again.
+    shown_on_top: Option<bool>,
+    overlay: Option<dock::Corner>,
+    field_history: recent::FieldHistory,
+    /// Window size before switching to compact, restored when leaving it.
+    full_size: Option<egui::Vec2>,
+    confirm: Option<Confirm>,
+    undo: Option<UndoToast>,
     last_message: String,
     last_error: String,
+    last_status: RpcStatus,
+    /// Preset loaded since the last enable/update; the next one sent plays its cue.
+    cue_preset: Option<String>,
+    /// Preset last loaded into the form, saved or drop-in, shown by the status overlay. Unlike
+    /// `cue_preset` it is kept after sending.
+    live_preset: Option<String>,
+    /// Cue to play once Discord acknowledges what was just sent.
+    pending_cue: Option<(cue::Cue, String)>,
+    /// What Discord dropped or changed in the last update, as last logged.
+    last_echo: Vec<String>,
     dirty_since: Option<Instant>,
+    preview_start: i64,
+    card_rect: egui::Rect,
+    export_rect: Option<egui::Rect>,
 }
 
 impl AppState {
@@ -412,37 +712,209 @@ impl AppState {
         let (tx, rx) = mpsc::channel();
         let cfg_path = config_path();
         let mut stored = StoredConfig::default();
+        let mut config_error = None;
         if let Some(path) = &cfg_path {
-            if let Ok(raw) = fs::read_to_string(path) {
-                if let Ok(parsed) = serde_json::from_str::<StoredConfig>(&raw) {
-                    stored = parsed;
-                }
+            match fs::read_to_string(path) {
+                Ok(raw) => match schema::parse(raw.as_bytes()) {
+                    Ok(parsed) => stored = parsed,
+                    Err(e) => config_error = Some(e),
+                },
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => config_error = Some(e.to_string()),
+                Err(_) => {}
             }
         }
+        if let Some(e) = &config_error {
+            logging::warn(format!("config not loaded: {}", e));
+        }
+        let migration = cfg_path.as_deref().and_then(Path::parent).and_then(migrate::find).map(|legacy| {
+            logging::info(format!("found settings from an earlier install in {}", legacy.dir.display()));
+            let old = legacy.config.as_deref().and_then(|raw| match schema::parse(raw) {
+                Ok(old) => Some(old),
+                Err(e) => {
+                    logging::warn(format!("old config not readable: {}", e));
+                    None
+                }
+            });
+            (legacy, old)
+        });
 
-        let form = FormConfig::from_stored(&stored);
+        let crash_dir = crash_dir();
+        let crash_report = crash_dir.as_deref().and_then(crash::pending_report);
 
-        Self {
-            worker: Arc::new(RpcWorker::default()),
-            signal: Arc::new(RpcSignal::default()),
-            rate: Mutex::new(RateState::default()),
+        let mut app = Self {
+            worker: ManagedClient::new(),
+            extra_workers: Vec::new(),
+            ipc_pipes: rpc_core::discover_ipc_paths(),
+            last_probe: Instant::now(),
+            enable_when_found: false,
+            rate: RateLimiter::default(),
+            rate_preset: RatePreset::Default,
+            rate_limits: RateLimits::DEFAULT,
             events_tx: tx,
             events_rx: rx,
             cfg_path,
-            form,
-            last_user_name: stored.last_user_name,
-            last_user_avatar: stored.last_user_avatar,
-            last_app_name: stored.last_app_name,
-            last_app_icon: stored.last_app_icon,
+            config_error,
+            self_check: SelfCheck::default(),
+            crash_dir,
+            crash_report,
+            form: FormConfig::default(),
+            last_user_name: String::new(),
+            last_user_avatar: String::new(),
+            last_user_decoration: String::new(),
+            last_user_banner: String::new(),
+            last_user_accent: None,
+            oauth_token: String::new(),
+            last_app_name: String::new(),
+            last_app_icon: String::new(),
+            ipc_path: String::new(),
+            pid_override: String::new(),
+            protocol_trace: false,
+            presets: Vec::new(),
+            selected_preset: None,
+            dropin: dropin::DropIn::new(dropin_dir()),
+            selected_dropin: None,
+            new_preset_name: String::new(),
+            preset_query: String::new(),
+            preset_sort: PresetSort::Saved,
+            tags_edit: (String::new(), String::new()),
+            applied: None,
+            apply_review: false,
+            load_review: None,
+            share: None,
+            import: None,
+            bulk: None,
+            invite: None,
+            migration,
+            ui_scale: 1.0,
+            scale_drag: None,
+            privacy_mode: false,
+            language: None,
+            hour_cycle: None,
+            sync: sync::SyncSettings::default(),
+            sync_busy: false,
+            takeover_busy: false,
+            backup_path: String::new(),
+            history_from: (chrono::Local::now() - chrono::Duration::days(30)).format("%Y-%m-%d").to_string(),
+            history_to: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            history_json: false,
+            content_warning: false,
+            hide_button_note: false,
+            privacy_reminder_shown: false,
+            privacy_reminder: false,
+            button_guide: false,
+            troubleshoot: None,
+            blocked_words: String::new(),
+            bars: Bars::default(),
+            clipboard: clipboard::ClipSettings::default(),
+            clip_watcher: None,
+            shutdown: Shutdown::new(),
+            clip_value: None,
+            proxy: http::Proxy::System,
+            offline: false,
+            pictures: HashMap::new(),
+            cache_limit_mb: 0,
+            cache_usage: None,
+            proxy_url: String::new(),
+            proxy_error: None,
+            rules: Vec::new(),
+            deferred: None,
+            defer_dialog: None,
+            rule_inputs: rules::Inputs::default(),
+            applied_rule: None,
+            simulation: None,
+            kiosk: false,
+            kiosk_cli: std::env::args().skip(1).any(|a| a == "--kiosk"),
+            compact: false,
+            compact_docks: BTreeMap::new(),
+            compact_monitor: None,
+            always_on_top: false,
+            shown_on_top: None,
+            overlay: None,
+            field_history: recent::FieldHistory::default(),
+            full_size: None,
+            confirm: None,
+            undo: None,
             last_message: String::new(),
             last_error: String::new(),
+            last_status: RpcStatus::Inactive,
+            cue_preset: None,
+            live_preset: None,
+            pending_cue: None,
+            last_echo: Vec::new(),
             dirty_since: None,
+            preview_start: rpc_core::now_unix_ts(),
+            card_rect: egui::Rect::NOTHING,
+            export_rect: None,
+        };
+        app.apply_stored(stored);
+        app.run_self_check();
+        let tx = app.events_tx.clone();
+        triggers::watch(app.shutdown.token(), move |inputs| tx.send(AppEvent::RuleInputs(inputs)).is_ok());
+        app
+    }
+
+    /// Replaces every persisted setting. The caller is responsible for re-applying the zoom factor.
+    fn apply_stored(&mut self, stored: StoredConfig) {
+        self.form = FormConfig::from_stored(&stored);
+        self.last_user_name = stored.last_user_name;
+        self.last_user_avatar = stored.last_user_avatar;
+        self.last_user_decoration = stored.last_user_decoration;
+        self.last_user_banner = stored.last_user_banner;
+        self.last_user_accent = stored.last_user_accent;
+        self.oauth_token = stored.oauth_token;
+        self.last_app_name = stored.last_app_name;
+        self.last_app_icon = stored.last_app_icon;
+        self.ipc_path = stored.ipc_path;
+        self.pid_override = stored.pid_override;
+        self.worker.set_pid_override(parse_pid(&self.pid_override).ok().flatten());
+        self.protocol_trace = stored.protocol_trace;
+        logging::set_protocol_trace(self.protocol_trace);
+        self.presets = stored.presets;
+        self.selected_preset = None;
+        self.ui_scale = clamp_ui_scale(stored.ui_scale);
+        self.privacy_mode = stored.privacy_mode;
+        privacy::set_enabled(self.privacy_mode);
+        self.language = stored.language;
+        self.hour_cycle = stored.hour_cycle;
+        clock::set_hour_cycle(self.hour_cycle);
+        self.sync = stored.sync;
+        self.kiosk = stored.kiosk;
+        self.compact = stored.compact;
+        self.always_on_top = stored.always_on_top;
+        self.overlay = stored.overlay;
+        self.field_history = stored.field_history;
+        self.compact_docks = stored.compact_docks;
+        self.compact_monitor = None;
+        self.content_warning = stored.content_warning;
+        self.hide_button_note = stored.hide_button_note;
+        self.privacy_reminder_shown = stored.privacy_reminder_shown;
+        self.blocked_words = stored.blocked_words;
+        self.bars = stored.bars;
+        self.worker.set_bars(self.bars);
+        self.clipboard = stored.clipboard;
+        self.set_clip_watch();
+        if let http::Proxy::Manual(url) = &stored.proxy {
+            self.proxy_url = url.clone();
         }
+        self.proxy = stored.proxy;
+        self.set_proxy();
+        self.offline = stored.offline;
+        http::set_offline(self.offline);
+        self.cache_limit_mb = stored.cache_limit_mb;
+        http::set_cache_limit(self.cache_limit());
+        self.rate_preset = stored.rate_preset;
+        self.preset_sort = stored.preset_sort;
+        self.rate_limits = stored.rate_limits;
+        self.rate.set_limits(self.rate_preset.limits(self.rate_limits));
+        self.rules = stored.rules;
+        self.deferred = stored.deferred;
+        self.applied_rule = None;
+        i18n::set_lang(self.language.unwrap_or_else(system_lang));
     }
 
-    fn save_config(&mut self) {
-        let Some(path) = &self.cfg_path else { return; };
-        let stored = StoredConfig {
+    fn to_stored(&self) -> StoredConfig {
+        StoredConfig {
+            version: schema::CURRENT,
             client_id: self.form.client_id.clone(),
             details: self.form.details.clone(),
             state: self.form.state.clone(),
@@ -455,11 +927,57 @@ impl AppState {
             b2label: self.form.b2label.clone(),
             b2url: self.form.b2url.clone(),
             with_timestamp: self.form.with_timestamp,
+            timer_mode: self.form.timer_mode,
+            timer_at: self.form.timer_at.clone(),
+            targets: self.form.targets.clone(),
+            refresh: self.form.refresh,
+            marquee: self.form.marquee,
+            image_cycle: self.form.image_cycle.clone(),
+            cue: self.form.cue,
+            constants: self.form.constants.clone(),
             last_user_name: self.last_user_name.clone(),
             last_user_avatar: self.last_user_avatar.clone(),
+            last_user_decoration: self.last_user_decoration.clone(),
+            last_user_banner: self.last_user_banner.clone(),
+            last_user_accent: self.last_user_accent,
+            oauth_token: self.oauth_token.clone(),
             last_app_name: self.last_app_name.clone(),
             last_app_icon: self.last_app_icon.clone(),
-        };
+            ipc_path: self.ipc_path.clone(),
+            pid_override: self.pid_override.clone(),
+            protocol_trace: self.protocol_trace,
+            presets: self.presets.clone(),
+            ui_scale: self.ui_scale,
+            privacy_mode: self.privacy_mode,
+            language: self.language,
+            hour_cycle: self.hour_cycle,
+            kiosk: self.kiosk,
+            compact: self.compact,
+            always_on_top: self.always_on_top,
+            overlay: self.overlay,
+            field_history: self.field_history.clone(),
+            compact_docks: self.compact_docks.clone(),
+            content_warning: self.content_warning,
+            hide_button_note: self.hide_button_note,
+            privacy_reminder_shown: self.privacy_reminder_shown,
+            blocked_words: self.blocked_words.clone(),
+            bars: self.bars,
+            clipboard: self.clipboard.clone(),
+            rules: self.rules.clone(),
+            deferred: self.deferred.clone(),
+            sync: self.sync.clone(),
+            proxy: self.proxy.clone(),
+            offline: self.offline,
+            cache_limit_mb: self.cache_limit_mb,
+            rate_preset: self.rate_preset,
+            preset_sort: self.preset_sort,
+            rate_limits: self.rate_limits,
+        }
+    }
+
+    fn save_config(&mut self) {
+        let Some(path) = &self.cfg_path else { return; };
+        let stored = self.to_stored();
 
         if let Some(dir) = path.parent() {
             let _ = fs::create_dir_all(dir);
@@ -487,15 +1005,29 @@ impl AppState {
             self.last_error = "Client ID is required.".to_string();
             return;
         }
-        if let Err(e) = rate_check(&self.rate, Duration::from_millis(650)) {
+        if let Err(e) = self.rate.check(Action::Sync) {
             self.last_error = e;
             return;
         }
 
+        // The running worker already holds a handshake for this app; no need for a second connection.
+        if let Some(ready) = self.worker.report().ready {
+            if self.worker.client_id().as_deref() == Some(client_id.as_str()) {
+                self.apply_profile(ready.user);
+                self.last_message = "User synced.".to_string();
+                self.last_error.clear();
+                return;
+            }
+        }
+
+        let ipc_path = self.ipc_override();
         let tx = self.events_tx.clone();
         thread::spawn(move || {
-            let res = rpc_core::get_user_profile_via_handshake(&client_id)
-                .map_err(|e| e.to_string());
+            let res = match ipc_path {
+                Some(p) => DiscordRpcClient::connect_and_handshake_at(&p, &client_id).map(|(_c, ready)| ready.user),
+                None => rpc_core::get_user_profile_via_handshake(&client_id),
+            }
+            .map_err(|e| e.to_string());
             let _ = tx.send(AppEvent::UserProfile(res));
         });
     }
@@ -506,100 +1038,3232 @@ impl AppState {
             self.last_error = "Client ID is required.".to_string();
             return;
         }
-        if let Err(e) = rate_check(&self.rate, Duration::from_millis(650)) {
+        if let Err(e) = self.rate.check(Action::Sync) {
             self.last_error = e;
             return;
         }
 
         let tx = self.events_tx.clone();
         thread::spawn(move || {
-            let res = fetch_app_meta(&client_id).map_err(|e| e.to_string());
+            let res = http::fetch_app_meta(&client_id).map_err(|e| e.to_string());
             let _ = tx.send(AppEvent::AppMeta(res));
         });
     }
 
-    fn enable_rpc(&mut self) {
-        let cfg = self.form.to_presence_cfg();
-        if cfg.client_id.is_empty() {
-            self.last_error = "Client ID is required.".to_string();
-            return;
-        }
-        if let Err(e) = rate_check(&self.rate, Duration::from_millis(900)) {
-            self.last_error = e;
-            return;
+    /// Current frame of the icon, avatar or decoration at `url`, starting its download the first
+    /// time. Animated pictures schedule a repaint for their next frame.
+    fn picture(&mut self, ctx: &egui::Context, url: &str) -> Option<egui::TextureId> {
+        if url.is_empty() {
+            return None;
         }
-        if let Err(e) = self.worker.enable(cfg, &self.signal) {
-            self.last_error = e;
-            return;
+        match self.pictures.get(url) {
+            None => {
+                self.pictures.insert(url.to_string(), Picture::Loading);
+                let url = url.to_string();
+                let tx = self.events_tx.clone();
+                thread::spawn(move || {
+                    let frames = http::fetch_image(&url).ok().and_then(|bytes| preview::decode_frames(&bytes));
+                    let _ = tx.send(AppEvent::Picture(url, frames));
+                });
+                None
+            }
+            Some(Picture::Decoded(_)) => {
+                let Some(Picture::Decoded(frames)) = self.pictures.remove(url) else { return None };
+                let frames = frames
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (image, delay))| (ctx.load_texture(format!("{}#{}", url, i), image, egui::TextureOptions::LINEAR), delay))
+                    .collect();
+                self.pictures.insert(url.to_string(), Picture::Shown(frames));
+                self.picture(ctx, url)
+            }
+            Some(Picture::Shown(frames)) => {
+                let (texture, next) = preview::frame_at(frames, ctx.input(|i| i.time));
+                if let Some(next) = next {
+                    ctx.request_repaint_after(next);
+                }
+                Some(texture.id())
+            }
+            Some(Picture::Loading | Picture::Missing) => None,
         }
-        self.last_message = "RPC enabled.".to_string();
-        self.save_config();
     }
 
-    fn update_rpc(&mut self) {
-        let cfg = self.form.to_presence_cfg();
-        if cfg.client_id.is_empty() {
-            self.last_error = "Client ID is required.".to_string();
-            return;
-        }
-        if let Err(e) = rate_check(&self.rate, Duration::from_millis(350)) {
-            self.last_error = e;
-            return;
+    /// The last synced user, on their banner or accent color when an OAuth token provided them.
+    fn user_card_ui(&mut self, ui: &mut egui::Ui) {
+        // Avatar and banner are as identifying as the ids privacy mode masks.
+        let styled = !self.privacy_mode;
+        let banner = if styled { self.picture(ui.ctx(), &self.last_user_banner.clone()) } else { None };
+        let fill = match self.last_user_accent {
+            Some(rgb) if styled => {
+                let [_, r, g, b] = rgb.to_be_bytes();
+                egui::Color32::from_rgb(r, g, b).gamma_multiply(0.6)
+            }
+            _ => egui::Color32::TRANSPARENT,
+        };
+        egui::Frame::none().fill(fill).rounding(egui::Rounding::same(6.0)).inner_margin(egui::Margin::same(4.0)).show(ui, |ui| {
+            if let Some(id) = banner {
+                ui.add(egui::Image::new((id, egui::vec2(240.0, 60.0))).rounding(egui::Rounding::same(4.0)));
+            }
+            ui.horizontal(|ui| {
+                if styled {
+                    self.avatar_ui(ui);
+                }
+                ui.label(format!("Last user: {}", if self.last_user_name.is_empty() { "-" } else { &self.last_user_name }));
+            });
+            if self.worker.report().ready.is_some_and(|r| r.is_arrpc()) {
+                ui.weak("Connected through arRPC (Vesktop, WebCord or another web client), which does not share the account.");
+            }
+        });
+    }
+
+    /// Avatar with its decoration on top, the way Discord draws it.
+    fn avatar_ui(&mut self, ui: &mut egui::Ui) {
+        let avatar = self.last_user_avatar.clone();
+        let decoration = self.last_user_decoration.clone();
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(24.0, 24.0), egui::Sense::hover());
+        if let Some(id) = self.picture(ui.ctx(), &avatar) {
+            egui::Image::new((id, egui::vec2(20.0, 20.0)))
+                .rounding(egui::Rounding::same(10.0))
+                .paint_at(ui, egui::Rect::from_center_size(rect.center(), egui::vec2(20.0, 20.0)));
         }
-        if let Err(e) = self.worker.update(cfg, &self.signal) {
-            self.last_error = e;
-            return;
+        if let Some(id) = self.picture(ui.ctx(), &decoration) {
+            egui::Image::new((id, rect.size())).paint_at(ui, rect);
         }
-        self.last_message = "RPC updated.".to_string();
-        self.save_config();
     }
 
-    fn disable_rpc(&mut self) {
-        if let Err(e) = rate_check(&self.rate, Duration::from_millis(900)) {
-            self.last_error = e;
-            return;
-        }
-        if let Err(e) = self.worker.disable(&self.signal) {
-            self.last_error = e;
+    fn profile_token_ui(&mut self, ui: &mut egui::Ui) {
+        if !http::BUILT_IN {
             return;
         }
+        ui.horizontal(|ui| {
+            let label = ui.label("Banner token");
+            let changed = ui
+                .add(egui::TextEdit::singleline(&mut self.oauth_token).password(true).hint_text("optional OAuth2 token"))
+                .labelled_by(label.id)
+                .on_hover_text("An OAuth2 access token with the identify scope. Only used to read your profile banner and accent color; stored in config.json in plain text.")
+                .changed();
+            if changed {
+                self.mark_dirty();
+            }
+            if ui.add_enabled(!self.oauth_token.trim().is_empty(), egui::Button::new("Fetch banner")).clicked() {
+                self.sync_user_style();
+            }
+        });
+    }
+
+    fn cache_limit(&self) -> u64 {
+        match self.cache_limit_mb {
+            0 => http::DEFAULT_CACHE_LIMIT,
+            mb => mb as u64 * 1024 * 1024,
+        }
+    }
+
+    fn cache_ui(&mut self, ui: &mut egui::Ui) {
+        if !http::BUILT_IN {
+            return;
+        }
+        let usage = *self.cache_usage.get_or_insert_with(http::cache_usage);
+        ui.horizontal(|ui| {
+            let label = ui.label(format!("Image cache: {:.1} MB of", usage as f64 / (1024.0 * 1024.0)));
+            let mut mb = (self.cache_limit() / (1024 * 1024)) as u32;
+            let resp = ui
+                .add(egui::DragValue::new(&mut mb).clamp_range(1..=2048).suffix(" MB"))
+                .labelled_by(label.id)
+                .on_hover_text("App icons, avatars and app names are kept here; the least recently used go first when it is full.");
+            if resp.changed() {
+                self.cache_limit_mb = mb;
+                http::set_cache_limit(self.cache_limit());
+                self.cache_usage = None;
+                self.mark_dirty();
+            }
+            if ui.button("Clear cache").clicked() {
+                match http::clear_cache() {
+                    Ok(()) => self.last_message = "Cache cleared.".to_string(),
+                    Err(e) => self.last_error = format!("Could not clear the cache: {:#}", e),
+                }
+                self.cache_usage = None;
+            }
+        });
+    }
+
+    /// Routes every HTTP call through the configured proxy.
+    fn set_proxy(&mut self) {
+        self.proxy_error = http::set_proxy(&self.proxy).err().map(|e| e.to_string());
+        if let Some(e) = &self.proxy_error {
+            logging::warn(format!("proxy not applied: {}", e));
+        }
+    }
+
+    fn network_ui(&mut self, ui: &mut egui::Ui) {
+        use http::Proxy;
+        ui.label("Network");
+        if !http::BUILT_IN {
+            ui.weak("This build has no internet features; only the local Discord connection is used.");
+            return;
+        }
+        if ui
+            .checkbox(&mut self.offline, "Offline mode")
+            .on_hover_text("Make no internet requests at all: no app name or icon lookup, invite or image checks, or preset sync. The presence keeps working.")
+            .changed()
+        {
+            http::set_offline(self.offline);
+            self.mark_dirty();
+        }
+        if self.offline {
+            return;
+        }
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            let label = ui.label("Proxy");
+            let text = match self.proxy {
+                Proxy::System => "System (environment)",
+                Proxy::Direct => "None",
+                Proxy::Manual(_) => "Manual",
+            };
+            egui::ComboBox::from_id_source("proxy_mode")
+                .selected_text(text)
+                .show_ui(ui, |ui| {
+                    changed |= ui.selectable_value(&mut self.proxy, Proxy::System, "System (environment)").changed();
+                    changed |= ui.selectable_value(&mut self.proxy, Proxy::Direct, "None").changed();
+                    let manual = matches!(self.proxy, Proxy::Manual(_));
+                    if ui.selectable_label(manual, "Manual").clicked() && !manual {
+                        self.proxy = Proxy::Manual(self.proxy_url.clone());
+                        changed = true;
+                    }
+                })
+                .response
+                .labelled_by(label.id)
+                .on_hover_text("Used for app metadata, image checks and preset sync. System reads HTTPS_PROXY, HTTP_PROXY, ALL_PROXY and NO_PROXY.");
+            if matches!(self.proxy, Proxy::Manual(_)) {
+                let resp = ui
+                    .add(egui::TextEdit::singleline(&mut self.proxy_url).hint_text("http://proxy:3128 or socks5://host:1080"))
+                    .labelled_by(label.id);
+                if resp.changed() {
+                    self.proxy = Proxy::Manual(self.proxy_url.trim().to_string());
+                    changed = true;
+                }
+            }
+        });
+        if changed {
+            self.set_proxy();
+            self.mark_dirty();
+        }
+        if let Some(e) = &self.proxy_error {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+    }
+
+    /// Starts or stops the clipboard watch to match the settings.
+    fn set_clip_watch(&mut self) {
+        if !self.clipboard.enabled {
+            self.clip_watcher = None;
+            self.set_clip_var(None);
+            return;
+        }
+        if self.clip_watcher.is_none() {
+            let tx = self.events_tx.clone();
+            self.clip_watcher = Some(clipboard::watch(move |text| tx.send(AppEvent::Clipboard(text)).is_ok()));
+        }
+    }
+
+    fn set_clip_var(&mut self, value: Option<String>) {
+        let name = self.clipboard.var.trim();
+        if !name.is_empty() {
+            self.worker.set_var(name, value.clone());
+            for (_, w) in &self.extra_workers {
+                w.set_var(name, value.clone());
+            }
+        }
+        self.clip_value = value;
+    }
+
+    fn clipboard_ui(&mut self, ui: &mut egui::Ui) {
+        let toggle = ui
+            .checkbox(&mut self.clipboard.enabled, "Watch the clipboard")
+            .on_hover_text("While on, copied text matching the pattern sets a template variable. Nothing is read while off, and copied text is never saved or logged.");
+        if toggle.changed() {
+            self.set_clip_watch();
+            self.mark_dirty();
+        }
+        if !self.clipboard.enabled {
+            return;
+        }
+        let mut changed = false;
+        let old_var = self.clipboard.var.clone();
+        egui::Grid::new("clipboard_grid").num_columns(2).show(ui, |ui| {
+            changed |= text_row(ui, "Pattern", &mut self.clipboard.pattern);
+            changed |= text_row(ui, "Variable", &mut self.clipboard.var);
+        });
+        if self.clipboard.var != old_var {
+            // Move the value over so the old name does not linger in the presence.
+            for w in std::iter::once(&self.worker).chain(self.extra_workers.iter().map(|(_, w)| w)) {
+                w.set_var(old_var.trim(), None);
+            }
+            self.set_clip_var(self.clip_value.clone());
+        }
+        match self.clipboard.regex() {
+            Err(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            Ok(_) => {
+                ui.horizontal(|ui| {
+                    let var = self.clipboard.var.trim();
+                    match &self.clip_value {
+                        Some(v) => ui.label(format!("{{{}}} = {}", var, privacy::redact(v))),
+                        None => ui.weak(format!("Copy matching text to set {{{}}}.", var)),
+                    };
+                    if self.clip_value.is_some() && ui.small_button("Clear").clicked() {
+                        self.set_clip_var(None);
+                    }
+                });
+            }
+        }
+        if changed {
+            self.mark_dirty();
+        }
+    }
+
+    /// Starts the self-check on a background thread; results arrive as [`AppEvent::SelfCheck`].
+    fn run_self_check(&mut self) {
+        if self.self_check.running {
+            return;
+        }
+        self.self_check.running = true;
+        self.ipc_pipes = rpc_core::discover_ipc_paths();
+        let input = selfcheck::Input {
+            config_error: self.config_error.clone(),
+            ipc_pipes: self.ipc_pipes.clone(),
+            ipc_path: self.primary_path(),
+            cfg: self.form.to_presence_cfg(),
+        };
+        let tx = self.events_tx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(AppEvent::SelfCheck(selfcheck::run(&input)));
+        });
+    }
+
+    fn apply_fix(&mut self, ctx: &egui::Context, fix: selfcheck::Fix) {
+        match fix {
+            selfcheck::Fix::ResetConfig => {
+                if let Some(path) = &self.cfg_path {
+                    let aside = path.with_extension("json.broken");
+                    match fs::rename(path, &aside) {
+                        Ok(()) => self.last_message = format!("The old settings file was kept as {}.", aside.display()),
+                        Err(e) => logging::warn(format!("could not set the config aside: {}", e)),
+                    }
+                }
+                self.config_error = None;
+                self.save_config();
+            }
+            selfcheck::Fix::Rescan => {}
+            selfcheck::Fix::ClearImage(slot) => {
+                match slot {
+                    selfcheck::ImageSlot::Large => self.form.large_image.clear(),
+                    selfcheck::ImageSlot::Small => self.form.small_image.clear(),
+                }
+                self.mark_dirty();
+            }
+            selfcheck::Fix::OpenUrl(url) => {
+                ctx.open_url(egui::OpenUrl::new_tab(url));
+                return;
+            }
+            selfcheck::Fix::CopyCommand(command) => {
+                ctx.copy_text(command);
+                self.last_message = "Command copied. Run it in a terminal, then restart the app.".to_string();
+                return;
+            }
+        }
+        self.run_self_check();
+    }
+
+    fn self_check_dialog(&mut self, ctx: &egui::Context) {
+        if !self.self_check.open {
+            return;
+        }
+        let mut open = true;
+        let mut fix = None;
+        let mut again = false;
+        egui::Window::new("Self-check")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if self.self_check.checks.is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Checking…");
+                    });
+                }
+                egui::Grid::new("self_check_grid").num_columns(2).spacing([8.0, 6.0]).show(ui, |ui| {
+                    for check in &self.self_check.checks {
+                        match &check.outcome {
+                            Ok(note) => {
+                                ui.colored_label(egui::Color32::from_rgb(60, 170, 90), format!("\u{2714} {}", check.name));
+                                ui.weak(privacy::redact(note));
+                            }
+                            Err(problem) => {
+                                ui.colored_label(egui::Color32::from_rgb(200, 60, 60), format!("\u{2716} {}", check.name));
+                                ui.vertical(|ui| {
+                                    ui.add(egui::Label::new(privacy::redact(problem)).wrap(true));
+                                    ui.horizontal(|ui| {
+                                        for f in &check.fixes {
+                                            if ui.small_button(f.label()).clicked() {
+                                                fix = Some(f.clone());
+                                            }
+                                        }
+                                    });
+                                });
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    again = ui.add_enabled(!self.self_check.running, egui::Button::new("Run again")).clicked();
+                    if self.self_check.running && !self.self_check.checks.is_empty() {
+                        ui.spinner();
+                    }
+                });
+            });
+        if let Some(f) = fix {
+            self.apply_fix(ctx, f);
+        } else if again {
+            self.run_self_check();
+        }
+        if !open || esc_pressed(ctx) {
+            self.self_check.open = false;
+        }
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        for w in std::iter::once(&self.worker).chain(self.extra_workers.iter().map(|(_, w)| w)) {
+            if paused {
+                w.pause();
+            } else {
+                w.resume();
+            }
+        }
+        logging::info(if paused { "RPC paused" } else { "RPC resumed" });
+        self.last_message = if paused { "Presence paused." } else { "Presence resumed." }.to_string();
+    }
+
+    /// Runs a due step of the pending "Enable later…".
+    fn run_deferred(&mut self) {
+        let Some(d) = self.deferred.as_mut() else { return };
+        let Some(step) = d.due(rpc_core::now_unix_ts()) else { return };
+        let preset = d.preset.clone();
+        if d.finished() {
+            self.deferred = None;
+        }
+        self.last_error.clear();
+        match step {
+            rules::DeferredStep::Enable => {
+                if !preset.is_empty() {
+                    let Some(p) = self.find_preset(&preset).cloned() else {
+                        self.deferred = None;
+                        self.last_error = format!("Scheduled preset \"{}\" no longer exists.", preset);
+                        self.save_config();
+                        return;
+                    };
+                    self.apply_preset(&p);
+                }
+                logging::info("scheduled enable");
+                self.enable_rpc();
+            }
+            rules::DeferredStep::Disable => {
+                logging::info("scheduled disable");
+                self.disable_rpc();
+            }
+        }
+        self.save_config();
+    }
+
+    fn defer_dialog(&mut self, ctx: &egui::Context) {
+        let names: Vec<String> = self.presets.iter().chain(&self.dropin.presets).map(|p| p.name.clone()).collect();
+        let Some(dialog) = self.defer_dialog.as_mut() else { return };
+        let mut open = true;
+        let mut schedule = false;
+        egui::Window::new("Enable later")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("defer_grid").num_columns(2).show(ui, |ui| {
+                    let label = ui.label("Preset");
+                    egui::ComboBox::from_id_source("defer_preset")
+                        .selected_text(if dialog.preset.is_empty() { "Current form" } else { dialog.preset.as_str() })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut dialog.preset, String::new(), "Current form");
+                            for name in &names {
+                                ui.selectable_value(&mut dialog.preset, name.clone(), name);
+                            }
+                        })
+                        .response
+                        .labelled_by(label.id);
+                    ui.end_row();
+                    let label = ui.label("Enable at");
+                    ui.add(egui::TextEdit::singleline(&mut dialog.at).hint_text("19:00 or +30").desired_width(120.0))
+                        .labelled_by(label.id);
+                    ui.end_row();
+                    let label = ui.label("Disable at");
+                    ui.add(egui::TextEdit::singleline(&mut dialog.until).hint_text("optional, e.g. 22:00 or +90").desired_width(120.0))
+                        .labelled_by(label.id)
+                        .on_hover_text("+N counts from the enable time");
+                    ui.end_row();
+                });
+                if !dialog.error.is_empty() {
+                    ui.colored_label(egui::Color32::from_rgb(200, 60, 60), &dialog.error);
+                }
+                schedule = ui.button("Schedule").clicked();
+            });
+        if schedule {
+            let parsed = rules::parse_when(&dialog.at, rpc_core::now_unix_ts()).and_then(|on| {
+                let off = match dialog.until.trim() {
+                    "" => None,
+                    until => Some(rules::parse_when(until, on)?),
+                };
+                Ok((on, off))
+            });
+            match parsed {
+                Ok((enable_at, disable_at)) => {
+                    let preset = dialog.preset.clone();
+                    self.defer_dialog = None;
+                    self.deferred = Some(rules::Deferred { preset, enable_at, disable_at, started: false });
+                    self.last_message = format!("Scheduled to enable at {}.", fmt_local_time(enable_at));
+                    self.last_error.clear();
+                    self.save_config();
+                }
+                Err(e) => dialog.error = e,
+            }
+        } else if !open || esc_pressed(ctx) {
+            self.defer_dialog = None;
+        }
+    }
+
+    fn check_invite(&mut self) {
+        let Some(dialog) = self.invite.as_mut() else { return };
+        if let Err(e) = self.rate.check(Action::Sync) {
+            dialog.error = e;
+            return;
+        }
+        dialog.checking = true;
+        dialog.error.clear();
+        let text = dialog.text.clone();
+        let tx = self.events_tx.clone();
+        thread::spawn(move || {
+            let res = http::fetch_invite(&text).map_err(|e| e.to_string());
+            let _ = tx.send(AppEvent::Invite(res));
+        });
+    }
+
+    fn fill_invite_button(&mut self, slot: usize, invite: &Invite) {
+        let f = &mut self.form;
+        let (label, url) = if slot == 0 { (&mut f.b1label, &mut f.b1url) } else { (&mut f.b2label, &mut f.b2url) };
+        if label.trim().is_empty() {
+            *label = "Join my server".to_string();
+        }
+        *url = invite.url();
+        self.mark_dirty();
+        let guild = invite.guild_name.as_deref().unwrap_or("the server");
+        self.last_message = format!("Button {} now links to {}.", slot + 1, guild);
+        if let Some(at) = &invite.expires_at {
+            let at = chrono::DateTime::parse_from_rfc3339(at).map(|t| fmt_local_time(t.timestamp())).unwrap_or_else(|_| at.clone());
+            self.last_message.push_str(&format!(" This invite expires {}; use a permanent one to keep the button working.", at));
+        }
+        self.last_error.clear();
+    }
+
+    fn enable_rpc(&mut self) {
+        let cfg = self.form.to_presence_cfg();
+        if let Some(e) = rpc_core::validate::validate(&cfg).into_iter().next() {
+            self.last_error = e.message;
+            return;
+        }
+        if self.content_warning {
+            let hits = content::flagged(&cfg, &self.blocked_words);
+            if !hits.is_empty() {
+                let list: Vec<String> = hits.iter().map(|(field, word)| format!("{}: \"{}\"", field, word)).collect();
+                self.confirm = Some(Confirm::ContentWarning(list.join(", ")));
+                return;
+            }
+        }
+        self.start_rpc();
+    }
+
+    /// Enables after the checks in [`enable_rpc`](Self::enable_rpc) passed or were confirmed.
+    fn start_rpc(&mut self) {
+        let cfg = self.form.to_presence_cfg();
+        if let Err(e) = self.rate.check(Action::Toggle) {
+            self.last_error = e;
+            return;
+        }
+        if self.discord_missing() {
+            // Starting the worker now would only cycle through connection errors.
+            self.enable_when_found = true;
+            self.last_message = "Discord not detected. The presence will connect once it starts.".to_string();
+            return;
+        }
+        self.enable_when_found = false;
+        self.worker.set_ipc_path(self.primary_path());
+        self.sync_extra_workers(&cfg);
+        history::record(history::Event::presence("enabled", &cfg));
+        self.worker.enable(cfg);
+        self.applied = Some(self.form.clone());
+        self.remember_form();
+        self.arm_cue(true);
+        logging::info("RPC enabled");
+        self.last_message = "RPC enabled.".to_string();
+        self.save_config();
+    }
+
+    fn update_rpc(&mut self) {
+        let cfg = self.form.to_presence_cfg();
+        if cfg.client_id.is_empty() {
+            self.last_error = "Client ID is required.".to_string();
+            return;
+        }
+        if let Err(e) = self.rate.check(Action::Update) {
+            self.last_error = e;
+            return;
+        }
+        if self.worker.is_running() {
+            self.sync_extra_workers(&cfg);
+        }
+        self.worker.set_ipc_path(self.primary_path());
+        history::record(history::Event::presence("updated", &cfg));
+        self.worker.update(cfg);
+        self.applied = Some(self.form.clone());
+        self.remember_form();
+        self.arm_cue(false);
+        logging::info("RPC updated");
+        self.last_message = "RPC updated.".to_string();
+        self.save_config();
+    }
+
+    /// Adds the form's text to the suggestions offered while typing.
+    fn remember_form(&mut self) {
+        use recent::Field;
+        let f = &self.form;
+        let h = &mut self.field_history;
+        h.record(Field::Details, &f.details);
+        h.record(Field::State, &f.state);
+        h.record(Field::LargeImage, &f.large_image);
+        h.record(Field::LargeText, &f.large_text);
+        h.record(Field::SmallImage, &f.small_image);
+        h.record(Field::SmallText, &f.small_text);
+        h.record(Field::ButtonLabel, &f.b1label);
+        h.record(Field::ButtonLabel, &f.b2label);
+        h.record(Field::ButtonUrl, &f.b1url);
+        h.record(Field::ButtonUrl, &f.b2url);
+    }
+
+    /// Queues the form's cue for when Discord acknowledges the presence just sent: on enable, and on
+    /// updates after a preset was loaded (by hand, a rule or a schedule).
+    fn arm_cue(&mut self, enabled: bool) {
+        let preset = self.cue_preset.take();
+        if !self.form.cue.is_on() || !(enabled || preset.is_some()) {
+            return;
+        }
+        let body = match preset {
+            Some(name) => format!("Preset \"{}\" is live on Discord.", name),
+            None => "Rich Presence is live on Discord.".to_string(),
+        };
+        self.pending_cue = Some((self.form.cue, body));
+    }
+
+    fn disable_rpc(&mut self) {
+        if self.enable_when_found {
+            self.enable_when_found = false;
+            self.last_message = "No longer waiting for Discord.".to_string();
+            return;
+        }
+        if let Err(e) = self.rate.check(Action::Toggle) {
+            self.last_error = e;
+            return;
+        }
+        self.pending_cue = None;
+        self.worker.disable();
+        for (_, w) in self.extra_workers.drain(..) {
+            w.disable();
+        }
+        history::record(history::Event::presence_off());
+        logging::info("RPC disabled");
         self.last_message = "RPC disabled.".to_string();
         self.save_config();
     }
 
-    fn handle_events(&mut self) {
-        while let Ok(evt) = self.events_rx.try_recv() {
-            match evt {
-                AppEvent::UserProfile(res) => match res {
-                    Ok(profile) => {
-                        let display = if let Some(g) = profile.global_name.as_ref() {
-                            if !g.trim().is_empty() { g.clone() } else { profile.username.clone() }
-                        } else {
-                            profile.username.clone()
-                        };
-                        self.last_user_name = display;
-                        self.last_user_avatar = profile.avatar_url.unwrap_or_default();
-                        self.last_message = "User synced.".to_string();
-                        self.last_error.clear();
-                        self.save_config();
+    /// What keeps running after a manual disable: enabled rules switch the preset again, and a
+    /// pending "Enable later" turns the presence back on. `None` when nothing would.
+    fn disable_warning(&self) -> Option<String> {
+        if self.enable_when_found {
+            return None;
+        }
+        let mut parts = Vec::new();
+        let rules: Vec<String> = self.rules.iter().filter(|r| r.enabled).map(|r| format!("\"{}\"", r.preset)).collect();
+        if !rules.is_empty() {
+            parts.push(format!(
+                "{} schedule rule{} ({}) stay{} on and will switch the preset again.",
+                rules.len(),
+                if rules.len() == 1 { "" } else { "s" },
+                rules.join(", "),
+                if rules.len() == 1 { "s" } else { "" }
+            ));
+        }
+        if let Some(d) = self.deferred.as_ref().filter(|d| !d.started) {
+            let what = if d.preset.is_empty() { "the current form".to_string() } else { format!("\"{}\"", d.preset) };
+            parts.push(format!("\"Enable later\" will still enable {} at {}.", what, fmt_local_time(d.enable_at)));
+        }
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    /// No Discord socket was found and none was configured explicitly (targets or the IPC socket setting).
+    fn discord_missing(&self) -> bool {
+        self.ipc_pipes.is_empty() && self.primary_path().is_none()
+    }
+
+    /// While Discord is missing, rescans its sockets and runs a pending enable once one appears.
+    /// The sockets found earlier are rescanned too while the worker is off or waiting, since
+    /// Discord may have quit or come back on another socket since.
+    fn probe_discord(&mut self) {
+        let unsure = matches!(self.worker.status(), RpcStatus::Inactive | RpcStatus::WaitingForDiscord);
+        if (!self.ipc_pipes.is_empty() && !unsure) || self.last_probe.elapsed() < DISCORD_PROBE_EVERY {
+            return;
+        }
+        self.last_probe = Instant::now();
+        let found = rpc_core::discover_ipc_paths();
+        if found == self.ipc_pipes {
+            return;
+        }
+        let was_missing = std::mem::replace(&mut self.ipc_pipes, found).is_empty();
+        if self.ipc_pipes.is_empty() {
+            logging::info("Discord sockets are gone");
+            return;
+        }
+        if !was_missing {
+            logging::info(format!("Discord sockets moved to {}", self.ipc_pipes.join(", ")));
+            return;
+        }
+        logging::info(format!("Discord detected at {}", self.ipc_pipes.join(", ")));
+        if self.enable_when_found {
+            self.start_rpc();
+        } else {
+            self.last_message = "Discord detected.".to_string();
+        }
+    }
+
+    fn primary_path(&self) -> Option<String> {
+        self.form.targets.first().cloned().or_else(|| self.ipc_override())
+    }
+
+    /// The IPC socket setting, expanded; `None` when empty or invalid.
+    fn ipc_override(&self) -> Option<String> {
+        opt_str(&self.ipc_path).and_then(|s| rpc_core::resolve_ipc_path(&s).ok())
+    }
+
+    /// Runs one extra worker per target after the first, stopping those no longer targeted.
+    fn sync_extra_workers(&mut self, cfg: &PresenceCfg) {
+        let wanted: Vec<String> = self.form.targets.iter().skip(1).cloned().collect();
+        self.extra_workers.retain(|(path, w)| {
+            let keep = wanted.contains(path);
+            if !keep {
+                w.disable();
+            }
+            keep
+        });
+        let pid = parse_pid(&self.pid_override).ok().flatten();
+        for path in wanted {
+            if let Some((_, w)) = self.extra_workers.iter().find(|(p, _)| *p == path) {
+                w.enable(cfg.clone());
+                continue;
+            }
+            let w = ManagedClient::new();
+            w.set_ipc_path(Some(path.clone()));
+            w.set_pid_override(pid);
+            w.set_bars(self.bars);
+            if let Some(v) = &self.clip_value {
+                w.set_var(&self.clipboard.var, Some(v.clone()));
+            }
+            w.enable(cfg.clone());
+            self.extra_workers.push((path, w));
+        }
+    }
+
+    fn apply_profile(&mut self, profile: UserProfile) {
+        // arRPC answers with a placeholder account; keep the last real one.
+        if profile.id == rpc_core::ARRPC_USER_ID {
+            return;
+        }
+        let display = match profile.global_name {
+            Some(g) if !g.trim().is_empty() => g,
+            _ => profile.username,
+        };
+        let avatar = profile.avatar_url.unwrap_or_default();
+        let decoration = profile.avatar_decoration.map(|d| d.url).unwrap_or_default();
+        if display != self.last_user_name || avatar != self.last_user_avatar || decoration != self.last_user_decoration {
+            self.last_user_name = display;
+            self.last_user_avatar = avatar;
+            self.last_user_decoration = decoration;
+            self.save_config();
+            self.sync_user_style();
+        }
+    }
+
+    /// Reads the banner and accent color when an OAuth token is set; the handshake has neither.
+    fn sync_user_style(&mut self) {
+        let token = self.oauth_token.trim().to_string();
+        if token.is_empty() {
+            return;
+        }
+        let tx = self.events_tx.clone();
+        thread::spawn(move || {
+            let res = http::fetch_user_style(&token).map_err(|e| format!("{:#}", e));
+            let _ = tx.send(AppEvent::UserStyle(res));
+        });
+    }
+
+    fn handle_events(&mut self) {
+        while let Ok(evt) = self.events_rx.try_recv() {
+            match evt {
+                AppEvent::UserStyle(res) => match res {
+                    Ok(style) => {
+                        self.last_user_banner = style.banner_url.unwrap_or_default();
+                        self.last_user_accent = style.accent_color;
+                        self.save_config();
+                    }
+                    Err(e) => {
+                        logging::warn(format!("user banner sync failed: {}", e));
+                        self.last_error = e;
+                    }
+                },
+                AppEvent::UserProfile(res) => match res {
+                    Ok(profile) => {
+                        self.apply_profile(profile);
+                        self.last_message = "User synced.".to_string();
+                        self.last_error.clear();
+                    }
+                    Err(e) => {
+                        logging::warn(format!("user sync failed: {}", e));
+                        self.last_error = e;
+                    }
+                },
+                AppEvent::AppMeta(res) => match res {
+                    Ok(meta) => {
+                        self.last_app_name = meta.name;
+                        self.last_app_icon = meta.icon_url.unwrap_or_default();
+                        self.last_message = "App synced.".to_string();
+                        self.last_error.clear();
+                        self.save_config();
+                    }
+                    Err(e) => {
+                        logging::warn(format!("app sync failed: {}", e));
+                        self.last_error = e;
+                    }
+                },
+                AppEvent::Picture(url, frames) => {
+                    self.pictures.insert(url, frames.map_or(Picture::Missing, Picture::Decoded));
+                    self.cache_usage = None;
+                }
+                AppEvent::Invite(res) => {
+                    let Some(dialog) = self.invite.as_mut() else { continue };
+                    match res {
+                        Ok(invite) => {
+                            let slot = dialog.slot;
+                            self.invite = None;
+                            self.fill_invite_button(slot, &invite);
+                        }
+                        Err(e) => {
+                            dialog.checking = false;
+                            dialog.error = e;
+                        }
+                    }
+                }
+                AppEvent::SelfCheck(checks) => {
+                    let failed: Vec<&str> = checks.iter().filter(|c| !c.passed()).map(|c| c.name).collect();
+                    if failed.is_empty() {
+                        logging::info("self-check passed");
+                    } else {
+                        logging::warn(format!("self-check failed: {}", failed.join(", ")));
+                        self.self_check.open = true;
+                    }
+                    self.self_check.checks = checks;
+                    self.self_check.running = false;
+                }
+                AppEvent::Clipboard(text) => {
+                    let Ok(re) = self.clipboard.regex() else { continue };
+                    if let Some(value) = clipboard::extract(&re, &text) {
+                        self.set_clip_var(Some(value));
+                    }
+                }
+                AppEvent::RuleInputs(inputs) => {
+                    if inputs.ssid != self.rule_inputs.ssid {
+                        logging::info(format!("Wi-Fi network: {}", inputs.ssid.as_deref().map_or("-".to_string(), privacy::redact)));
+                    }
+                    self.rule_inputs = inputs;
+                    self.evaluate_rules();
+                }
+                AppEvent::TakeOver(res) => {
+                    self.takeover_busy = false;
+                    match res {
+                        Ok(()) => {
+                            logging::info("presence-daemon released the presence");
+                            self.last_error.clear();
+                            if self.worker.is_running() {
+                                self.update_rpc();
+                            } else {
+                                self.enable_rpc();
+                            }
+                        }
+                        Err(e) => self.last_error = e,
+                    }
+                }
+                AppEvent::Sync(dir, res) => {
+                    self.sync_busy = false;
+                    self.finish_sync(dir, res);
+                }
+            }
+        }
+    }
+}
+
+impl AppState {
+    fn set_ui_scale(&mut self, ctx: &egui::Context, scale: f32) {
+        self.ui_scale = clamp_ui_scale(scale);
+        ctx.set_zoom_factor(self.ui_scale);
+        self.mark_dirty();
+    }
+
+    /// Keeps the stored scale in sync with egui's built-in Ctrl +/- zoom.
+    fn sync_zoom(&mut self, ctx: &egui::Context) {
+        let z = ctx.zoom_factor();
+        if self.scale_drag.is_none() && (z - self.ui_scale).abs() > 0.001 {
+            self.ui_scale = clamp_ui_scale(z);
+            if (self.ui_scale - z).abs() > 0.001 {
+                ctx.set_zoom_factor(self.ui_scale);
+            }
+            self.mark_dirty();
+        }
+    }
+
+    fn sync_window_level(&mut self, ctx: &egui::Context) {
+        if self.shown_on_top != Some(self.always_on_top) {
+            let level = if self.always_on_top { egui::WindowLevel::AlwaysOnTop } else { egui::WindowLevel::Normal };
+            ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(level));
+            self.shown_on_top = Some(self.always_on_top);
+        }
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Settings").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let label = ui.label("UI scale");
+                // Apply on release: rescaling mid-drag moves the slider away from the pointer.
+                let mut v = self.scale_drag.unwrap_or(self.ui_scale);
+                let resp = ui
+                    .add(egui::Slider::new(&mut v, UI_SCALE_MIN..=UI_SCALE_MAX).step_by(0.05).fixed_decimals(2))
+                    .labelled_by(label.id)
+                    .on_hover_text("Ctrl + / Ctrl - / Ctrl 0 also work");
+                if resp.dragged() {
+                    self.scale_drag = Some(v);
+                } else if resp.changed() || resp.drag_stopped() {
+                    self.scale_drag = None;
+                    self.set_ui_scale(ui.ctx(), v);
+                }
+                if ui.button("Reset").clicked() {
+                    self.set_ui_scale(ui.ctx(), 1.0);
+                }
+            });
+            if let Some(native) = ui.ctx().native_pixels_per_point() {
+                ui.label(format!("Display scale {:.2}x, effective {:.2}x", native, native * self.ui_scale));
+            }
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Error language");
+                let system = format!("System ({})", system_lang().label());
+                let current = self.language.map_or(system.clone(), |l| l.label().to_string());
+                let mut changed = false;
+                egui::ComboBox::from_id_source("error_language")
+                    .selected_text(current)
+                    .show_ui(ui, |ui| {
+                        changed |= ui.selectable_value(&mut self.language, None, system).changed();
+                        for lang in Lang::ALL {
+                            changed |= ui.selectable_value(&mut self.language, Some(lang), lang.label()).changed();
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id)
+                    .on_hover_text("Language of the errors reported while talking to Discord.");
+                if changed {
+                    i18n::set_lang(self.language.unwrap_or_else(system_lang));
+                    self.mark_dirty();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Clock");
+                let system = format!("System ({})", clock::system_hour_cycle().label());
+                let current = self.hour_cycle.map_or(system.clone(), |c| c.label().to_string());
+                let mut changed = false;
+                egui::ComboBox::from_id_source("hour_cycle")
+                    .selected_text(current)
+                    .show_ui(ui, |ui| {
+                        changed |= ui.selectable_value(&mut self.hour_cycle, None, system).changed();
+                        for cycle in clock::HourCycle::ALL {
+                            changed |= ui.selectable_value(&mut self.hour_cycle, Some(cycle), cycle.label()).changed();
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id)
+                    .on_hover_text("How times are shown in the preview, countdowns and schedules. Dates follow the system locale.");
+                if changed {
+                    clock::set_hour_cycle(self.hour_cycle);
+                    self.mark_dirty();
+                }
+            });
+
+            let on_top = ui
+                .checkbox(&mut self.always_on_top, "Keep this window on top of other apps")
+                .on_hover_text("Handy for tweaking the presence while another app has focus. Compact mode has an On top button for it too.");
+            if on_top.changed() {
+                self.mark_dirty();
+            }
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Status overlay");
+                let corner_text = |c: Option<dock::Corner>| c.map_or("Off".to_string(), |c| format!("{} {}", c.arrow(), c.label()));
+                let mut changed = false;
+                egui::ComboBox::from_id_source("status_overlay")
+                    .selected_text(corner_text(self.overlay))
+                    .show_ui(ui, |ui| {
+                        changed |= ui.selectable_value(&mut self.overlay, None, corner_text(None)).changed();
+                        for corner in dock::Corner::ALL {
+                            changed |= ui.selectable_value(&mut self.overlay, Some(corner), corner_text(Some(corner))).changed();
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id)
+                    .on_hover_text("A small always-on-top window with the status and preset name, in a corner of this window's screen. Clicks go through it.");
+                if changed {
+                    self.mark_dirty();
+                }
+            });
+
+            let warn = ui
+                .checkbox(&mut self.content_warning, "Warn about blocked words before enabling")
+                .on_hover_text("Checks details, state, hover texts and button labels against common profanity and your own list.");
+            if warn.changed() {
+                self.mark_dirty();
+            }
+            if self.content_warning {
+                let label = ui.label("Extra blocked words (one per line)");
+                let words = ui
+                    .add(egui::TextEdit::multiline(&mut self.blocked_words).desired_rows(3))
+                    .labelled_by(label.id);
+                if words.changed() {
+                    self.mark_dirty();
+                }
+            }
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Progress bars");
+                let mut changed = false;
+                let preview = |bars: Bars| bars.render(3.0, 5.0);
+                egui::ComboBox::from_id_source("bar_style")
+                    .selected_text(preview(Bars { percent: false, width: 5, ..self.bars }))
+                    .show_ui(ui, |ui| {
+                        for style in BarStyle::ALL {
+                            let text = preview(Bars { style, width: 5, percent: false });
+                            changed |= ui.selectable_value(&mut self.bars.style, style, text).changed();
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id)
+                    .on_hover_text("Look of {bar:value/max} placeholders, e.g. {bar:elapsed/1500} for a 25 minute pomodoro.");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut self.bars.width).clamp_range(1..=Bars::MAX_WIDTH).suffix(" cells"))
+                    .changed();
+                changed |= ui.checkbox(&mut self.bars.percent, "percentage").changed();
+                if changed {
+                    self.worker.set_bars(self.bars);
+                    for (_, w) in &self.extra_workers {
+                        w.set_bars(self.bars);
+                    }
+                    self.mark_dirty();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label(format!("Suggestions: {} values remembered", self.field_history.len()))
+                    .on_hover_text("Text sent to Discord or saved in a preset is suggested again while typing in the same field.");
+                if ui.add_enabled(!self.field_history.is_empty(), egui::Button::new("Forget")).clicked() {
+                    self.field_history.clear();
+                    self.mark_dirty();
+                }
+            });
+
+            ui.add_space(6.0);
+            self.clipboard_ui(ui);
+
+            ui.add_space(6.0);
+            self.network_ui(ui);
+            self.cache_ui(ui);
+
+            ui.add_space(6.0);
+            self.profile_token_ui(ui);
+
+            ui.add_space(6.0);
+            self.sync_ui(ui);
+
+            ui.add_space(6.0);
+            self.backup_ui(ui);
+
+            ui.add_space(6.0);
+            self.history_ui(ui);
+
+            ui.add_space(6.0);
+            ui.horizontal(|ui| {
+                ui.label("Reset").on_hover_text("Each asks first and saves a backup zip before changing anything.");
+                for scope in ResetScope::ALL {
+                    if ui.button(scope.label()).clicked() {
+                        self.confirm = Some(Confirm::Reset(scope));
+                    }
+                }
+            });
+        });
+    }
+
+    fn advanced_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Advanced").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let label = ui.label("Report as PID");
+                let resp = ui
+                    .add(egui::TextEdit::singleline(&mut self.pid_override).hint_text("this app").desired_width(100.0))
+                    .labelled_by(label.id)
+                    .on_hover_text("Process Discord links the activity to, e.g. a game you are watching. Applies on the next update.");
+                if resp.changed() {
+                    if let Ok(pid) = parse_pid(&self.pid_override) {
+                        self.worker.set_pid_override(pid);
+                        for (_, w) in &self.extra_workers {
+                            w.set_pid_override(pid);
+                        }
+                    }
+                    self.mark_dirty();
+                }
+                if let Err(e) = parse_pid(&self.pid_override) {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+            });
+            ui.horizontal(|ui| {
+                let label = ui.label("IPC socket");
+                let hint = if cfg!(windows) { r"\\.\pipe\discord-ipc-0" } else { "$XDG_RUNTIME_DIR/discord-ipc-0" };
+                let resp = ui
+                    .add(egui::TextEdit::singleline(&mut self.ipc_path).hint_text(hint).desired_width(220.0))
+                    .labelled_by(label.id)
+                    .on_hover_text(
+                        "Connect here instead of looking for Discord, for containers with a bind-mounted socket or custom sandboxes. \
+                         $NAME and ${NAME} are replaced with environment variables. Applies on the next connect.",
+                    );
+                if resp.changed() {
+                    self.worker.set_ipc_path(self.primary_path());
+                    self.mark_dirty();
+                }
+                match opt_str(&self.ipc_path).map(|s| rpc_core::resolve_ipc_path(&s)) {
+                    Some(Err(e)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                    Some(Ok(path)) if !std::path::Path::new(&path).exists() => {
+                        ui.weak("Nothing is listening there yet.");
+                    }
+                    _ => {}
+                }
+            });
+            if let Ok(path) = std::env::var(rpc_core::IPC_PATH_ENV) {
+                ui.weak(format!("{} is set: discovery only tries {}.", rpc_core::IPC_PATH_ENV, path.trim()));
+            }
+            let trace = ui
+                .checkbox(&mut self.protocol_trace, "Protocol trace")
+                .on_hover_text("Writes every frame exchanged with Discord to the log, with ids and tokens masked.");
+            if trace.changed() {
+                logging::set_protocol_trace(self.protocol_trace);
+                self.mark_dirty();
+            }
+            self.rate_limits_ui(ui);
+            if ui
+                .add_enabled(!self.self_check.running, egui::Button::new("Run self-check"))
+                .on_hover_text("Checks the settings file, Discord, the Client ID, images and the internet connection")
+                .clicked()
+            {
+                self.self_check.open = true;
+                self.run_self_check();
+            }
+            if ui
+                .button("Troubleshoot visibility…")
+                .on_hover_text("Why others may not see the presence, including Discord's own privacy settings")
+                .clicked()
+            {
+                self.troubleshoot.get_or_insert_with(HashSet::new);
+            }
+            ui.add_space(6.0);
+
+            self.error_history_ui(ui);
+            ui.add_space(6.0);
+            ui.weak(format!("Platform: {} (v{})", rpc_core::platform(), env!("CARGO_PKG_VERSION")));
+            match rpc_core::packaging::detect() {
+                rpc_core::packaging::Packaging::Plain => {}
+                rpc_core::packaging::Packaging::Flatpak { app_id } => {
+                    ui.weak(format!("Running as Flatpak {}; settings are kept in its sandbox.", app_id));
+                }
+                rpc_core::packaging::Packaging::AppImage { path } => {
+                    ui.weak(format!("Running from AppImage {}.", path.display()));
+                }
+            }
+
+            ui.label("Handshake (READY payload)");
+            let Some(ready) = self.worker.report().ready else {
+                ui.weak("No handshake yet. Enable the presence to connect.");
+                return;
+            };
+            let mut text = privacy::redact(&privacy::redact_ready(&ready.raw));
+            if ui.button("Copy").clicked() {
+                ui.ctx().copy_text(text.clone());
+            }
+            egui::ScrollArea::vertical().max_height(220.0).show(ui, |ui| {
+                ui.add(egui::TextEdit::multiline(&mut text).code_editor().desired_width(f32::INFINITY));
+            });
+        });
+    }
+
+    fn rate_limits_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            let label = ui.label("Rate limits");
+            egui::ComboBox::from_id_source("rate_preset")
+                .selected_text(self.rate_preset.label())
+                .show_ui(ui, |ui| {
+                    for preset in RatePreset::ALL {
+                        changed |= ui.selectable_value(&mut self.rate_preset, preset, preset.label()).changed();
+                    }
+                })
+                .response
+                .labelled_by(label.id)
+                .on_hover_text("Minimum time between two actions. Relaxed suits scripted use; Discord may still drop updates sent faster than about one per four seconds.");
+        });
+        if self.rate_preset == RatePreset::Custom {
+            ui.horizontal(|ui| {
+                for (name, ms) in [
+                    ("Sync", &mut self.rate_limits.sync_ms),
+                    ("Enable/disable", &mut self.rate_limits.toggle_ms),
+                    ("Update", &mut self.rate_limits.update_ms),
+                ] {
+                    let label = ui.label(name);
+                    changed |= ui
+                        .add(egui::DragValue::new(ms).clamp_range(0..=10_000).suffix(" ms"))
+                        .labelled_by(label.id)
+                        .changed();
+                }
+            });
+        }
+        if changed {
+            self.rate.set_limits(self.rate_preset.limits(self.rate_limits));
+            self.mark_dirty();
+        }
+    }
+
+    /// Distinct worker errors, oldest first, so the first failure stays visible after reconnects.
+    fn error_history_ui(&mut self, ui: &mut egui::Ui) {
+        let history = self.worker.error_history();
+        ui.horizontal(|ui| {
+            ui.label("Error history");
+            if history.is_empty() {
+                return;
+            }
+            if ui.small_button("Copy").clicked() {
+                let text = history
+                    .iter()
+                    .map(|e| format!("{} .. {} (x{}) {}", fmt_local_time(e.first_seen), fmt_local_time(e.last_seen), e.count, privacy::redact(&e.message)))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                ui.ctx().copy_text(text);
+            }
+            if ui.small_button("Clear").clicked() {
+                self.worker.clear_error_history();
+            }
+        });
+        if history.is_empty() {
+            ui.weak("No errors so far.");
+            return;
+        }
+        egui::ScrollArea::vertical().id_source("error_history").max_height(160.0).show(ui, |ui| {
+            egui::Grid::new("error_history_grid").num_columns(3).striped(true).show(ui, |ui| {
+                for e in &history {
+                    let seen = if e.count > 1 {
+                        format!("{} .. {}", fmt_local_time(e.first_seen), fmt_local_time(e.last_seen))
+                    } else {
+                        fmt_local_time(e.first_seen)
+                    };
+                    ui.weak(seen);
+                    ui.label(format!("x{}", e.count));
+                    ui.add(egui::Label::new(privacy::redact(&e.message)).wrap(true));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    fn form_ui(&mut self, ui: &mut egui::Ui) {
+        use recent::Field;
+        let mut open_invite = false;
+        egui::Grid::new("cfg_grid").num_columns(2).spacing([12.0, 6.0]).show(ui, |ui| {
+            let f = &mut self.form;
+            let h = &self.field_history;
+            let mut changed = false;
+            changed |= secret_row(ui, "Client ID", &mut f.client_id, self.privacy_mode);
+            let line_max = if f.marquee.enabled { MAX_MARQUEE_TEXT } else { MAX_TEXT };
+            changed |= emoji_row(ui, "Details", &mut f.details, line_max, (h, Field::Details));
+            changed |= emoji_row(ui, "State", &mut f.state, line_max, (h, Field::State));
+            changed |= recent_row(ui, "Large image", &mut f.large_image, (h, Field::LargeImage));
+
+            let label = ui.label("Cycle large image");
+            ui.vertical(|ui| {
+                let mut text = f.image_cycle.images.join("\n");
+                let edit = ui
+                    .add(egui::TextEdit::multiline(&mut text).desired_rows(2).hint_text("one asset key or URL per line"))
+                    .labelled_by(label.id)
+                    .on_hover_text("With two or more, these replace Large image in turn.");
+                if edit.changed() {
+                    f.image_cycle.images = text.split('\n').map(str::to_string).collect();
+                    changed = true;
+                }
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut f.image_cycle.interval_secs)
+                                .clamp_range(ImageCycle::MIN_INTERVAL_SECS..=3600)
+                                .prefix("every ")
+                                .suffix(" s"),
+                        )
+                        .changed();
+                    changed |= ui.checkbox(&mut f.image_cycle.random, "random order").changed();
+                });
+            });
+            ui.end_row();
+            changed |= emoji_row(ui, "Large text", &mut f.large_text, MAX_TEXT, (h, Field::LargeText));
+            changed |= recent_row(ui, "Small image", &mut f.small_image, (h, Field::SmallImage));
+            changed |= emoji_row(ui, "Small text", &mut f.small_text, MAX_TEXT, (h, Field::SmallText));
+            changed |= emoji_row(ui, "Button 1 label", &mut f.b1label, MAX_BUTTON_LABEL, (h, Field::ButtonLabel));
+            changed |= recent_row(ui, "Button 1 url", &mut f.b1url, (h, Field::ButtonUrl));
+            changed |= emoji_row(ui, "Button 2 label", &mut f.b2label, MAX_BUTTON_LABEL, (h, Field::ButtonLabel));
+            changed |= recent_row(ui, "Button 2 url", &mut f.b2url, (h, Field::ButtonUrl));
+            ui.label("");
+            if ui.small_button("Join button from an invite…").on_hover_text("Check a Discord server invite and fill a button with it").clicked() {
+                open_invite = true;
+            }
+            ui.end_row();
+
+            ui.label("Constants").on_hover_text("Values of this preset used as {name} in any text, image or button field, so one change updates them all.");
+            ui.vertical(|ui| {
+                let mut remove = None;
+                for (i, c) in f.constants.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        changed |= ui.add(egui::TextEdit::singleline(&mut c.name).hint_text("name").desired_width(100.0)).changed();
+                        changed |= ui.add(egui::TextEdit::singleline(&mut c.value).hint_text("value").desired_width(220.0)).changed();
+                        if ui.small_button("✕").on_hover_text("Remove").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    f.constants.remove(i);
+                    changed = true;
+                }
+                let names: Vec<&str> = f.constants.iter().map(|c| c.name.trim()).collect();
+                if let Some(bad) = names.iter().find(|n| !Constant::valid_name(n)) {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("\"{}\" is not used: names take letters, digits and _.", bad));
+                } else if let Some(dup) = names.iter().enumerate().find_map(|(i, n)| names[..i].contains(n).then_some(n)) {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("{{{}}} is defined twice; the first one is used.", dup));
+                }
+                if ui.small_button("Add constant").clicked() {
+                    f.constants.push(Constant::default());
+                    changed = true;
+                }
+            });
+            ui.end_row();
+
+            let label = ui.label("Timestamp");
+            changed |= ui.checkbox(&mut f.with_timestamp, "enabled").labelled_by(label.id).changed();
+            ui.end_row();
+
+            if f.with_timestamp {
+                ui.label("Timer");
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("timer_mode").selected_text(f.timer_mode.label()).show_ui(ui, |ui| {
+                        for m in TimerMode::ALL {
+                            changed |= ui.selectable_value(&mut f.timer_mode, m, m.label()).changed();
+                        }
+                    });
+                    if f.timer_mode.needs_time() {
+                        changed |= ui
+                            .add(egui::TextEdit::singleline(&mut f.timer_at).hint_text(clock::input_hint()).desired_width(140.0))
+                            .changed();
+                        if ui.small_button("Now").clicked() {
+                            f.timer_at = clock::fmt_input(rpc_core::now_unix_ts());
+                            changed = true;
+                        }
+                        match parse_local_time(&f.timer_at) {
+                            Ok(at) => {
+                                ui.weak(fmt_local_time(at));
+                            }
+                            Err(e) => {
+                                ui.colored_label(ui.visuals().error_fg_color, e);
+                            }
+                        }
+                    }
+                });
+                ui.end_row();
+            }
+
+            ui.label("Discord instances");
+            ui.vertical(|ui| {
+                let mut pipes = self.ipc_pipes.clone();
+                pipes.extend(f.targets.iter().filter(|t| !self.ipc_pipes.contains(t)).cloned());
+                if pipes.is_empty() {
+                    ui.weak("No running Discord found.");
+                }
+                for pipe in pipes {
+                    let mut on = f.targets.contains(&pipe);
+                    let running = self.ipc_pipes.contains(&pipe);
+                    let mut label = match rpc_core::ipc_client_label(&pipe) {
+                        Some(client) => format!("{}: {}", client, pipe),
+                        None => pipe.clone(),
+                    };
+                    if !running {
+                        label.push_str(" (not running)");
+                    }
+                    if ui.checkbox(&mut on, label).changed() {
+                        if on {
+                            f.targets.push(pipe);
+                        } else {
+                            f.targets.retain(|t| *t != pipe);
+                        }
+                        changed = true;
+                    }
+                }
+                ui.horizontal(|ui| {
+                    if ui.small_button("Rescan").clicked() {
+                        self.ipc_pipes = rpc_core::discover_ipc_paths();
+                    }
+                    ui.weak("None checked = first one found.");
+                });
+            });
+            ui.end_row();
+
+            ui.label("Refresh");
+            ui.horizontal(|ui| {
+                let range = Refresh::MIN_KEEPALIVE_SECS..=Refresh::MAX_KEEPALIVE_SECS;
+                changed |= ui
+                    .add(egui::DragValue::new(&mut f.refresh.keepalive_secs).clamp_range(range).prefix("every ").suffix(" s"))
+                    .on_hover_text("How often the presence is re-sent while active. Lower keeps changing text fresh; higher means less IPC traffic.")
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut f.refresh.burst, "burst on connect")
+                    .on_hover_text("Re-send a few times right after connecting, in case Discord drops the first update")
+                    .changed();
+                changed |= ui
+                    .add(egui::DragValue::new(&mut f.refresh.template_secs).clamp_range(1..=3600).prefix("templates every ").suffix(" s"))
+                    .on_hover_text("How often {time}, {time_s}, {date} and {weekday} are re-rendered. The presence is only re-sent when the text changes, at most every 4 s.")
+                    .changed();
+            });
+            ui.end_row();
+
+            let label = ui.label("When live");
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .checkbox(&mut f.cue.sound, "play a sound")
+                    .labelled_by(label.id)
+                    .on_hover_text("Once Discord shows this preset after Enable, or after a rule, schedule or Update switched to it. Handy while a full-screen game hides this window.")
+                    .changed();
+                changed |= ui.checkbox(&mut f.cue.toast, "show a notification").labelled_by(label.id).changed();
+            });
+            ui.end_row();
+
+            let label = ui.label("Marquee");
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .checkbox(&mut f.marquee.enabled, "scroll long text")
+                    .labelled_by(label.id)
+                    .on_hover_text(format!("Details and State longer than the width scroll through it, up to {} characters", MAX_MARQUEE_TEXT))
+                    .changed();
+                ui.add_enabled_ui(f.marquee.enabled, |ui| {
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut f.marquee.width).clamp_range(Marquee::MIN_WIDTH..=MAX_TEXT as u32).suffix(" wide"))
+                        .changed();
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut f.marquee.step).clamp_range(1..=64).prefix("step ").suffix(" chars"))
+                        .changed();
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut f.marquee.interval_secs).clamp_range(Marquee::MIN_INTERVAL_SECS..=60).prefix("every ").suffix(" s"))
+                        .changed();
+                });
+            });
+            ui.end_row();
+
+            if changed {
+                self.mark_dirty();
+            }
+        });
+        if open_invite {
+            self.invite = Some(InviteDialog::default());
+        }
+    }
+
+    fn confirm_dialog(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.confirm.clone() else { return; };
+        let (title, text, verb) = match &action {
+            Confirm::DeletePreset(i) => {
+                let name = self.presets.get(*i).map(|p| p.name.as_str()).unwrap_or("?");
+                ("Delete preset", format!("Delete the preset \"{}\"?", name), "Delete")
+            }
+            Confirm::Reset(scope) => (
+                "Reset",
+                format!("{} A backup of the current settings is saved first.", scope.question()),
+                "Reset",
+            ),
+            Confirm::SyncConflict(sync::Direction::Push, why) => (
+                "Sync conflict",
+                format!("{} Push anyway and overwrite the remote library?", why),
+                "Push anyway",
+            ),
+            Confirm::SyncConflict(sync::Direction::Pull, why) => (
+                "Sync conflict",
+                format!("{} Pull anyway and replace the presets on this machine?", why),
+                "Pull anyway",
+            ),
+            Confirm::ContentWarning(hits) => (
+                "Check the presence text",
+                format!("The presence contains blocked words ({}). Enable it anyway?", hits),
+                "Enable anyway",
+            ),
+            Confirm::Disable(why) => ("Disable presence", format!("{} Disable anyway?", why), "Disable"),
+            Confirm::ImportBackup(path) => (
+                "Import backup",
+                format!(
+                    "Replace the current settings and presets with the backup {}? Files in presets.d are replaced too.",
+                    path.display()
+                ),
+                "Import",
+            ),
+        };
+        let undoable = !matches!(
+            action,
+            Confirm::SyncConflict(sync::Direction::Push, _)
+                | Confirm::ImportBackup(_)
+                | Confirm::ContentWarning(_)
+                | Confirm::Disable(_)
+                | Confirm::Reset(ResetScope::Cache)
+        );
+
+        let mut decided: Option<bool> = None;
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(text);
+                if undoable {
+                    ui.label("You can undo this for a few seconds afterwards.");
+                }
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    let danger = egui::Button::new(egui::RichText::new(verb).color(egui::Color32::WHITE))
+                        .fill(egui::Color32::from_rgb(180, 50, 50));
+                    if ui.add(danger).clicked() {
+                        decided = Some(true);
+                    }
+                    let cancel = ui.button("Cancel");
+                    // Default focus on Cancel so a stray Enter does not destroy anything.
+                    if !ctx.memory(|m| m.focused().is_some()) {
+                        cancel.request_focus();
+                    }
+                    if cancel.clicked() || esc_pressed(ctx) {
+                        decided = Some(false);
+                    }
+                });
+            });
+
+        match decided {
+            Some(true) => {
+                self.confirm = None;
+                self.run_confirmed(ctx, action);
+            }
+            Some(false) => self.confirm = None,
+            None => {}
+        }
+    }
+
+    fn run_confirmed(&mut self, ctx: &egui::Context, action: Confirm) {
+        match action {
+            Confirm::DeletePreset(i) => {
+                if i >= self.presets.len() {
+                    return;
+                }
+                // Presets based on it keep what they inherited, as their own values.
+                let name = self.presets[i].name.clone();
+                let children: Vec<usize> = (0..self.presets.len()).filter(|&c| self.presets[c].base == name).collect();
+                let before = (!children.is_empty()).then(|| self.to_stored());
+                for c in children {
+                    self.set_base(c, String::new());
+                }
+                let removed = self.presets.remove(i);
+                self.selected_preset = None;
+                let message = format!("Preset \"{}\" deleted.", removed.name);
+                match before {
+                    Some(before) => self.show_undo(message, UndoAction::RestoreConfig(before)),
+                    None => self.show_undo(message, UndoAction::RestorePreset(i, removed)),
+                }
+            }
+            Confirm::Reset(scope) => {
+                let backup = match self.write_backup("before-reset") {
+                    Ok(path) => path,
+                    Err(e) => {
+                        self.last_error = format!("Nothing was reset: the backup failed. {}", e);
+                        return;
+                    }
+                };
+                logging::info(format!("saved {} before resetting {:?}", backup.display(), scope));
+                if scope == ResetScope::Cache {
+                    if let Err(e) = http::clear_cache() {
+                        self.last_error = format!("Could not clear the cache: {:#}", e);
+                    }
+                    self.cache_usage = None;
+                }
+                let before = self.to_stored();
+                self.apply_stored(scope.apply(before.clone()));
+                ctx.set_zoom_factor(self.ui_scale);
+                let message = format!("Reset done; the previous settings are in {}.", backup.display());
+                if scope == ResetScope::Cache {
+                    self.last_message = message;
+                } else {
+                    self.show_undo(message, UndoAction::RestoreConfig(before));
+                }
+            }
+            Confirm::SyncConflict(dir, _) => self.start_sync(dir, true),
+            Confirm::ContentWarning(_) => {
+                // `start_rpc` saves on success.
+                self.start_rpc();
+                return;
+            }
+            Confirm::Disable(_) => {
+                // `disable_rpc` saves.
+                self.disable_rpc();
+                return;
+            }
+            Confirm::ImportBackup(path) => {
+                self.import_backup(&path);
+                ctx.set_zoom_factor(self.ui_scale);
+                // `import_backup` already saved the imported config.
+                return;
+            }
+        }
+        self.save_config();
+    }
+
+    fn show_undo(&mut self, message: String, action: UndoAction) {
+        logging::info(&message);
+        self.last_message.clear();
+        self.undo = Some(UndoToast { message, action, until: Instant::now() + UNDO_WINDOW });
+    }
+
+    fn undo_toast(&mut self, ctx: &egui::Context) {
+        let Some(toast) = &self.undo else { return; };
+        if Instant::now() >= toast.until {
+            self.undo = None;
+            return;
+        }
+
+        let mut undo = false;
+        egui::Area::new(egui::Id::new("undo_toast"))
+            .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -16.0])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(&toast.message);
+                        if ui.button("Undo").clicked() {
+                            undo = true;
+                        }
+                    });
+                });
+            });
+
+        if !undo {
+            return;
+        }
+        let Some(toast) = self.undo.take() else { return; };
+        match toast.action {
+            UndoAction::RestorePreset(i, preset) => {
+                let i = i.min(self.presets.len());
+                self.presets.insert(i, preset);
+                self.selected_preset = Some(i);
+                self.selected_dropin = None;
+            }
+            UndoAction::RestoreReplaced(i, preset) => {
+                if let Some(slot) = self.presets.get_mut(i) {
+                    *slot = preset;
+                }
+            }
+            UndoAction::RestoreConfig(stored) => {
+                self.apply_stored(stored);
+                ctx.set_zoom_factor(self.ui_scale);
+            }
+            UndoAction::ReapplyEdits(form) => {
+                self.form = form;
+                self.mark_dirty();
+            }
+        }
+        self.last_message = "Undone.".to_string();
+        self.save_config();
+    }
+
+    fn sync_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Preset sync");
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            let label = ui.label("Provider");
+            egui::ComboBox::from_id_source("sync_provider")
+                .selected_text(self.sync.provider.label())
+                .show_ui(ui, |ui| {
+                    for p in sync::Provider::ALL {
+                        changed |= ui.selectable_value(&mut self.sync.provider, p, p.label()).changed();
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+        });
+        egui::Grid::new("sync_grid").num_columns(2).show(ui, |ui| match self.sync.provider {
+            sync::Provider::Off => {}
+            sync::Provider::Gist => {
+                // Left empty, the first push creates a private gist and fills this in.
+                changed |= text_row(ui, "Gist id", &mut self.sync.gist_id);
+                changed |= secret_row(ui, "Token", &mut self.sync.gist_token, true);
+            }
+            sync::Provider::WebDav => {
+                changed |= text_row(ui, "File URL", &mut self.sync.webdav_url);
+                changed |= text_row(ui, "User", &mut self.sync.webdav_user);
+                changed |= secret_row(ui, "Password", &mut self.sync.webdav_password, true);
+            }
+        });
+        if changed {
+            self.mark_dirty();
+        }
+        if self.sync.provider == sync::Provider::Off {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(!self.sync_busy, |ui| {
+                if ui.button("Push").on_hover_text("Upload this machine's presets").clicked() {
+                    self.start_sync(sync::Direction::Push, false);
+                }
+                if ui.button("Pull").on_hover_text("Replace this machine's presets with the remote ones").clicked() {
+                    self.start_sync(sync::Direction::Pull, false);
+                }
+            });
+            if self.sync_busy {
+                ui.spinner();
+            }
+        });
+    }
+
+    fn backup_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Backup");
+        ui.horizontal(|ui| {
+            if ui.button("Export backup").on_hover_text("Config and presets.d as one zip; logs are not included").clicked() {
+                self.export_backup();
+            }
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Backup file");
+            ui.add(egui::TextEdit::singleline(&mut self.backup_path).hint_text("path to a .zip, or drop it on the window"))
+                .labelled_by(label.id);
+            if ui.add_enabled(!self.backup_path.trim().is_empty(), egui::Button::new("Import backup…")).clicked() {
+                self.confirm = Some(Confirm::ImportBackup(PathBuf::from(self.backup_path.trim())));
+            }
+        });
+    }
+
+    fn history_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("History export").on_hover_text("Presence changes and connection events, for your own analysis");
+        ui.horizontal(|ui| {
+            ui.label("From");
+            ui.add(egui::TextEdit::singleline(&mut self.history_from).hint_text("YYYY-MM-DD").desired_width(90.0));
+            ui.label("to");
+            ui.add(egui::TextEdit::singleline(&mut self.history_to).hint_text("YYYY-MM-DD").desired_width(90.0));
+            ui.selectable_value(&mut self.history_json, false, "CSV");
+            ui.selectable_value(&mut self.history_json, true, "JSON");
+            if ui.button("Export").clicked() {
+                self.export_history();
+            }
+        });
+    }
+
+    fn export_history(&mut self) {
+        let day = |v: &str, what: &str| {
+            let date = chrono::NaiveDate::parse_from_str(v.trim(), "%Y-%m-%d")
+                .map_err(|_| format!("{} date: use the format YYYY-MM-DD.", what))?;
+            parse_local_time(&format!("{} 00:00", date))
+        };
+        let range = day(&self.history_from, "From").and_then(|from| Ok((from, day(&self.history_to, "To")? + 24 * 3600)));
+        let (from, to) = match range {
+            Ok(r) => r,
+            Err(e) => {
+                self.last_error = e;
+                return;
+            }
+        };
+        let events = history::read_range(from, to);
+        if events.is_empty() {
+            self.last_error = "No history in that date range.".to_string();
+            return;
+        }
+        let (ext, text) = if self.history_json { ("json", history::to_json(&events)) } else { ("csv", history::to_csv(&events)) };
+        let Some(dir) = backup_dir() else {
+            self.last_error = "No folder available to save the export.".to_string();
+            return;
+        };
+        let out = dir.join(format!("history-{}-{}.{}", self.history_from.trim(), self.history_to.trim(), ext));
+        match fs::create_dir_all(&dir).and_then(|_| fs::write(&out, text)) {
+            Ok(()) => {
+                logging::info(format!("exported {} history events to {}", events.len(), out.display()));
+                self.last_message = format!("Exported {} events to {}", events.len(), out.display());
+                self.last_error.clear();
+            }
+            Err(e) => self.last_error = format!("History export failed: {}", e),
+        }
+    }
+
+    fn export_backup(&mut self) {
+        match self.write_backup("backup") {
+            Ok(out) => {
+                self.last_message = format!("Backup saved to {}", out.display());
+                self.last_error.clear();
+            }
+            Err(e) => self.last_error = format!("Backup failed: {}", e),
+        }
+    }
+
+    /// Saves the config, then zips the settings into the backup folder as `<name>-<time>.zip`.
+    /// Sync credentials, the banner token and the proxy's login are left out of the zipped config.
+    fn write_backup(&mut self, name: &str) -> Result<PathBuf, String> {
+        self.save_config();
+        let dir = self.cfg_path.as_deref().and_then(|p| p.parent()).ok_or("No config folder to back up.")?;
+        let out = backup_dir()
+            .map(|d| d.join(format!("{}-{}.zip", name, rpc_core::now_unix_ts())))
+            .ok_or("No folder available to save the backup.")?;
+        let config = serde_json::to_vec_pretty(&self.to_stored().without_secrets()).map_err(|e| e.to_string())?;
+        let n = backup::export(dir, &config, &out).map_err(|e| format!("{:#}", e))?;
+        logging::info(format!("exported backup ({} files) to {}", n, out.display()));
+        Ok(out)
+    }
+
+    /// Restores a backup zip. Its `config.json` is upgraded from the version it was written in (see
+    /// `schema`), and settings it did not have yet take their defaults. Secrets already set on this
+    /// machine are kept, since backups leave them out.
+    fn import_backup(&mut self, path: &Path) {
+        let res = backup::read(path).and_then(|b| {
+            let mut stored = schema::parse(&b.config).map_err(|e| anyhow::anyhow!("The backup's config cannot be used: {}", e))?;
+            for (own, imported) in [
+                (&self.sync.gist_token, &mut stored.sync.gist_token),
+                (&self.sync.webdav_user, &mut stored.sync.webdav_user),
+                (&self.sync.webdav_password, &mut stored.sync.webdav_password),
+                (&self.oauth_token, &mut stored.oauth_token),
+            ] {
+                if !own.is_empty() {
+                    imported.clone_from(own);
+                }
+            }
+            if http::has_credentials(&self.proxy) && !http::has_credentials(&stored.proxy) {
+                stored.proxy = self.proxy.clone();
+            }
+            if let Some(dir) = self.cfg_path.as_deref().and_then(|p| p.parent()) {
+                backup::restore_files(&b, dir)?;
+            }
+            Ok(stored)
+        });
+        match res {
+            Ok(stored) => {
+                self.apply_stored(stored);
+                self.save_config();
+                self.backup_path.clear();
+                logging::info(format!("imported backup {}", path.display()));
+                self.last_message = "Backup imported.".to_string();
+                self.last_error.clear();
+            }
+            Err(e) => self.last_error = format!("Import failed: {:#}", e),
+        }
+    }
+
+    fn start_sync(&mut self, dir: sync::Direction, force: bool) {
+        if self.sync_busy {
+            return;
+        }
+        self.sync_busy = true;
+        self.last_error.clear();
+        let settings = self.sync.clone();
+        let presets = self.presets.clone();
+        let tx = self.events_tx.clone();
+        thread::spawn(move || {
+            let res = sync::run(&settings, dir, &presets, force).map_err(|e| format!("{:#}", e));
+            let _ = tx.send(AppEvent::Sync(dir, res));
+        });
+    }
+
+    fn finish_sync(&mut self, dir: sync::Direction, res: Result<sync::Outcome, String>) {
+        match res {
+            Ok(sync::Outcome::UpToDate { base }) => {
+                self.sync.base = base;
+                self.last_message = "Presets are already in sync.".to_string();
+            }
+            Ok(sync::Outcome::Pushed { base, gist_id }) => {
+                self.sync.base = base;
+                if let Some(id) = gist_id {
+                    self.sync.gist_id = id;
+                }
+                logging::info("presets pushed");
+                self.last_message = "Presets pushed.".to_string();
+            }
+            Ok(sync::Outcome::Pulled { base, presets }) => {
+                let before = self.to_stored();
+                let last_used: HashMap<String, i64> =
+                    self.presets.iter().filter_map(|p| Some((p.name.clone(), p.last_used?))).collect();
+                self.presets = presets;
+                for p in &mut self.presets {
+                    p.last_used = last_used.get(&p.name).copied();
+                }
+                self.selected_preset = None;
+                self.sync.base = base;
+                self.show_undo(format!("Pulled {} preset(s).", self.presets.len()), UndoAction::RestoreConfig(before));
+            }
+            Ok(sync::Outcome::Conflict(why)) => {
+                self.confirm = Some(Confirm::SyncConflict(dir, why));
+                return;
+            }
+            Err(e) => {
+                logging::warn(format!("preset sync failed: {}", e));
+                self.last_error = e;
+                return;
+            }
+        }
+        self.save_config();
+    }
+
+    fn save_preset(&mut self) {
+        let name = self.new_preset_name.trim().to_string();
+        if name.is_empty() {
+            self.last_error = "Type a name for the preset.".to_string();
+            return;
+        }
+        let preset = Preset { name: name.clone(), form: self.form.clone(), ..Default::default() };
+        match self.presets.iter().position(|p| p.name == name) {
+            Some(i) => {
+                // Overwriting keeps where the preset is filed, and what it is based on.
+                self.presets[i].form = self.own_values(&preset.form, &self.presets[i].base);
+                self.selected_preset = Some(i);
+                self.selected_dropin = None;
+            }
+            None => {
+                self.presets.push(preset);
+                self.selected_preset = Some(self.presets.len() - 1);
+                self.selected_dropin = None;
+            }
+        }
+        self.new_preset_name.clear();
+        self.remember_form();
+        self.last_message = format!("Preset \"{}\" saved.", name);
+        self.save_config();
+    }
+
+    /// Saves the form as a new preset named after the current time, without asking for a name.
+    fn snapshot_preset(&mut self) {
+        let base = format!("Snapshot {}", chrono::Local::now().format("%Y-%m-%d %H:%M"));
+        let mut name = base.clone();
+        let mut n = 2;
+        while self.presets.iter().any(|p| p.name == name) {
+            name = format!("{} ({})", base, n);
+            n += 1;
+        }
+        self.presets.push(Preset { name: name.clone(), form: self.form.clone(), ..Default::default() });
+        self.selected_preset = Some(self.presets.len() - 1);
+        self.selected_dropin = None;
+        self.last_message = format!("Form saved as preset \"{}\".", name);
+        self.save_config();
+    }
+
+    /// The form as last sent, while the presence is on and the form has changed since.
+    fn revertable(&self) -> Option<&FormConfig> {
+        self.applied.as_ref().filter(|a| self.worker.is_running() && **a != self.form)
+    }
+
+    /// Puts the live presence back into the form, with an undo for the edits it drops.
+    fn revert_to_applied(&mut self) {
+        let Some(applied) = self.revertable().cloned() else { return };
+        let edits = std::mem::replace(&mut self.form, applied);
+        self.mark_dirty();
+        self.show_undo("Form reverted to the live presence.".to_string(), UndoAction::ReapplyEdits(edits));
+    }
+
+    /// Applies the preset of the matching rule when that rule changes, and pushes it to Discord if
+    /// the presence is on.
+    fn evaluate_rules(&mut self) {
+        let picked = rules::pick(&self.rules, &self.rule_inputs);
+        if picked == self.applied_rule {
+            return;
+        }
+        self.applied_rule = picked;
+        let Some(rule) = picked.and_then(|i| self.rules.get(i)) else { return; };
+        let Some(preset) = self.find_preset(&rule.preset).cloned() else {
+            self.last_error = format!("Rule preset \"{}\" does not exist.", rule.preset);
+            return;
+        };
+        logging::info(format!("rule applies preset \"{}\"", preset.name));
+        self.apply_preset(&preset);
+        if self.worker.is_running() {
+            self.update_rpc();
+        }
+    }
+
+    /// Saved or drop-in preset by name.
+    fn find_preset(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().chain(&self.dropin.presets).find(|p| p.name == name)
+    }
+
+    fn rules_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Rules").show(ui, |ui| {
+            let inputs = &self.rule_inputs;
+            let ssid = inputs.ssid.clone();
+            ui.label(format!(
+                "Wi-Fi: {} · idle: {} · media: {}",
+                ssid.as_deref().map_or("not connected".to_string(), privacy::redact),
+                inputs.idle_secs.map_or("unknown".to_string(), |s| format!("{} min", s / 60)),
+                match inputs.media_playing {
+                    Some(true) => "playing",
+                    Some(false) => "not playing",
+                    None => "unknown",
+                }
+            ));
+            match self.applied_rule.and_then(|i| self.rules.get(i).map(|r| (i, r))) {
+                Some((i, rule)) => ui.label(format!("Active: rule {} → \"{}\"", i + 1, rule.preset)),
+                None => ui.weak("No rule applies right now."),
+            };
+            let names: Vec<String> = self.presets.iter().chain(&self.dropin.presets).map(|p| p.name.clone()).collect();
+            let mut changed = false;
+            let mut remove = None;
+            for (i, rule) in self.rules.iter_mut().enumerate() {
+                ui.horizontal_wrapped(|ui| {
+                    changed |= ui.checkbox(&mut rule.enabled, "").on_hover_text("Rule enabled").changed();
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut rule.priority).prefix("priority "))
+                        .on_hover_text("When several rules match, the highest priority wins")
+                        .changed();
+                    ui.label("When");
+                    egui::ComboBox::from_id_source(("rule_kind", i))
+                        .selected_text(rule.condition.kind())
+                        .show_ui(ui, |ui| {
+                            for kind in rules::Condition::kinds() {
+                                let same = std::mem::discriminant(&kind) == std::mem::discriminant(&rule.condition);
+                                if ui.selectable_label(same, kind.kind()).clicked() && !same {
+                                    rule.condition = kind;
+                                    changed = true;
+                                }
+                            }
+                        });
+                    changed |= condition_ui(ui, &mut rule.condition, ssid.as_deref());
+                    ui.label("apply");
+                    egui::ComboBox::from_id_source(("rule_preset", i))
+                        .selected_text(if rule.preset.is_empty() { "-" } else { rule.preset.as_str() })
+                        .show_ui(ui, |ui| {
+                            for name in &names {
+                                changed |= ui.selectable_value(&mut rule.preset, name.clone(), name).changed();
+                            }
+                        });
+                    if ui.small_button("✖").on_hover_text("Delete rule").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                self.rules.remove(i);
+                changed = true;
+            }
+            if ui.button("Add rule").clicked() {
+                self.rules.push(rules::Rule { enabled: true, ..Default::default() });
+                changed = true;
+            }
+            if changed {
+                self.applied_rule = None;
+                self.evaluate_rules();
+                self.mark_dirty();
+            }
+            self.simulate_ui(ui);
+        });
+    }
+
+    /// Lets the user fake the inputs and see which rule would win, without applying it.
+    fn simulate_ui(&mut self, ui: &mut egui::Ui) {
+        let mut open = self.simulation.is_some();
+        if ui.checkbox(&mut open, "Simulate").on_hover_text("Try fake inputs against the rules. Nothing is applied.").changed() {
+            self.simulation = open.then(|| Simulation::from_inputs(&self.rule_inputs));
+        }
+        let Some(sim) = &mut self.simulation else { return; };
+        egui::Grid::new("simulate_grid").num_columns(2).show(ui, |ui| {
+            ui.label("Wi-Fi");
+            let mut ssid = sim.inputs.ssid.clone().unwrap_or_default();
+            if ui.add(egui::TextEdit::singleline(&mut ssid).hint_text("not connected")).changed() {
+                sim.inputs.ssid = opt_str(&ssid);
+            }
+            ui.end_row();
+
+            ui.label("Day and time");
+            ui.horizontal(|ui| {
+                let mut changed = false;
+                egui::ComboBox::from_id_source("simulate_day")
+                    .selected_text(rules::WEEKDAYS[sim.weekday as usize % 7])
+                    .show_ui(ui, |ui| {
+                        for (i, day) in rules::WEEKDAYS.iter().enumerate() {
+                            changed |= ui.selectable_value(&mut sim.weekday, i as u32, *day).changed();
+                        }
+                    });
+                let valid = rules::parse_hhmm(&sim.time);
+                let mut edit = egui::TextEdit::singleline(&mut sim.time).desired_width(45.0);
+                if valid.is_none() {
+                    edit = edit.text_color(ui.visuals().error_fg_color);
+                }
+                changed |= ui.add(edit).changed();
+                if changed {
+                    sim.set_time();
+                }
+            });
+            ui.end_row();
+
+            ui.label("Idle");
+            ui.horizontal(|ui| {
+                let mut minutes = sim.inputs.idle_secs.map_or(0, |s| s / 60);
+                if ui.add(egui::DragValue::new(&mut minutes).clamp_range(0..=1440).suffix(" min")).changed() {
+                    sim.inputs.idle_secs = Some(minutes * 60);
+                }
+            });
+            ui.end_row();
+
+            ui.label("Processes");
+            if ui.add(egui::TextEdit::singleline(&mut sim.processes).hint_text("e.g. code, steam")).changed() {
+                sim.inputs.processes =
+                    sim.processes.split(',').map(str::trim).filter(|p| !p.is_empty()).map(str::to_string).collect();
+            }
+            ui.end_row();
+
+            ui.label("Media");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut sim.inputs.media_playing, None, "unknown");
+                ui.selectable_value(&mut sim.inputs.media_playing, Some(true), "playing");
+                ui.selectable_value(&mut sim.inputs.media_playing, Some(false), "not playing");
+            });
+            ui.end_row();
+        });
+
+        let picked = rules::pick(&self.rules, &sim.inputs);
+        for (i, rule) in self.rules.iter().enumerate() {
+            let text = format!("Rule {}: {} → \"{}\"", i + 1, rule.condition.kind(), rule.preset);
+            if picked == Some(i) {
+                ui.colored_label(ui.visuals().selection.stroke.color, format!("{}  ✔ wins", text));
+            } else if !rule.enabled {
+                ui.weak(format!("{}  (disabled)", text));
+            } else if rule.preset.is_empty() {
+                ui.weak(format!("{}  (no preset)", text));
+            } else if rule.condition.matches(&sim.inputs) {
+                ui.label(format!("{}  matches, lower priority", text));
+            } else {
+                ui.weak(format!("{}  no match", text));
+            }
+        }
+        if picked.is_none() {
+            ui.label("No rule would apply; the form stays as it is.");
+        }
+    }
+
+    fn apply_preset(&mut self, preset: &Preset) {
+        self.form = self.resolved_form(preset);
+        self.cue_preset = Some(preset.name.clone());
+        self.live_preset = Some(preset.name.clone());
+        if let Some(p) = self.presets.iter_mut().find(|p| p.name == preset.name) {
+            p.last_used = Some(chrono::Utc::now().timestamp());
+        }
+        self.last_message = format!("Preset \"{}\" loaded into the form.", preset.name);
+        self.mark_dirty();
+    }
+
+    /// Asks `presence-daemon` to let go of the presence; enables here once it has.
+    fn take_over(&mut self) {
+        self.takeover_busy = true;
+        let tx = self.events_tx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(AppEvent::TakeOver(takeover::release_daemon()));
+        });
+    }
+
+    /// One-click switch from the recent bar: loads the preset and, if the presence is on, sends it.
+    fn switch_to_preset(&mut self, i: usize) {
+        let Some(preset) = self.presets.get(i).cloned() else { return };
+        self.selected_preset = Some(i);
+        self.selected_dropin = None;
+        self.apply_preset(&preset);
+        if self.worker.is_running() {
+            self.last_error.clear();
+            self.update_rpc();
+        }
+    }
+
+    fn add_imported(&mut self, mut preset: Preset) {
+        let base = preset.name.clone();
+        let mut n = 2;
+        while self.presets.iter().any(|p| p.name == preset.name) {
+            preset.name = format!("{} ({})", base, n);
+            n += 1;
+        }
+        self.last_message = format!("Preset \"{}\" imported.", preset.name);
+        self.presets.push(preset);
+        self.selected_preset = Some(self.presets.len() - 1);
+        self.selected_dropin = None;
+        self.save_config();
+    }
+
+    /// Overwrites the preset with the same name, with an undo.
+    fn replace_preset(&mut self, preset: Preset) {
+        let Some(i) = self.presets.iter().position(|p| p.name == preset.name) else {
+            self.add_imported(preset);
+            return;
+        };
+        let old = std::mem::replace(&mut self.presets[i], preset);
+        self.selected_preset = Some(i);
+        self.selected_dropin = None;
+        self.show_undo(format!("Preset \"{}\" replaced.", old.name), UndoAction::RestoreReplaced(i, old));
+        self.save_config();
+    }
+
+    /// Picks up changes in `presets.d/`, keeping the selected drop-in selected if it still exists.
+    fn reload_dropins(&mut self) {
+        let selected = self.selected_dropin.and_then(|i| self.dropin.presets.get(i)).map(|p| p.name.clone());
+        if !self.dropin.poll() {
+            return;
+        }
+        logging::info(format!("presets.d reloaded: {} preset(s)", self.dropin.presets.len()));
+        for e in &self.dropin.errors {
+            logging::warn(format!("presets.d: {}", e));
+        }
+        self.selected_dropin = selected.and_then(|name| self.dropin.presets.iter().position(|p| p.name == name));
+    }
+
+    /// The form `preset` stands for, with what it inherits from its base filled in.
+    fn resolved_form(&self, preset: &Preset) -> FormConfig {
+        presets::resolve(preset, |name| self.find_preset(name))
+    }
+
+    /// `preset` with its inherited values filled in and no base, e.g. to share it.
+    fn standalone(&self, preset: &Preset) -> Preset {
+        Preset { form: self.resolved_form(preset), base: String::new(), ..preset.clone() }
+    }
+
+    /// What to store for `form` in a preset based on `base`.
+    fn own_values(&self, form: &FormConfig, base: &str) -> FormConfig {
+        match self.find_preset(base) {
+            Some(base) => presets::strip_inherited(form, &self.resolved_form(base)),
+            None => form.clone(),
+        }
+    }
+
+    /// Rebases the saved preset `i` on `base` (empty for none) without changing what it stands for.
+    fn set_base(&mut self, i: usize, base: String) {
+        let form = self.resolved_form(&self.presets[i]);
+        self.presets[i].form = self.own_values(&form, &base);
+        self.presets[i].base = base;
+    }
+
+    fn selected_preset(&self) -> Option<&Preset> {
+        match self.selected_dropin {
+            Some(i) => self.dropin.presets.get(i),
+            None => self.selected_preset.and_then(|i| self.presets.get(i)),
+        }
+    }
+
+    fn presets_ui(&mut self, ui: &mut egui::Ui) {
+        let recent = presets::recent(&self.presets, RECENT_PRESETS);
+        if !recent.is_empty() {
+            let mut pick = None;
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Recent");
+                for i in recent {
+                    let p = &self.presets[i];
+                    let on = self.form == self.resolved_form(p);
+                    let hint = if self.worker.is_running() { "Load and send to Discord" } else { "Load into the form" };
+                    if ui.selectable_label(on, &p.name).on_hover_text(hint).clicked() {
+                        pick = Some(i);
+                    }
+                }
+            });
+            if let Some(i) = pick {
+                self.switch_to_preset(i);
+            }
+        }
+
+        if self.presets.len() + self.dropin.presets.len() > 1 {
+            ui.horizontal(|ui| {
+                let label = ui.label("Find");
+                ui.add(egui::TextEdit::singleline(&mut self.preset_query).hint_text("name, folder or #tag").desired_width(180.0))
+                    .labelled_by(label.id);
+                let before = self.preset_sort;
+                egui::ComboBox::from_id_source("preset_sort")
+                    .selected_text(self.preset_sort.label())
+                    .show_ui(ui, |ui| {
+                        for sort in PresetSort::ALL {
+                            ui.selectable_value(&mut self.preset_sort, sort, sort.label());
+                        }
+                    });
+                if self.preset_sort != before {
+                    self.mark_dirty();
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            let label = ui.label("Preset");
+            let selected = self.selected_preset().map(|p| p.name.clone()).unwrap_or_else(|| "-".to_string());
+            let listed = presets::listing(&self.presets, &self.preset_query, self.preset_sort);
+            let listed_dropins = presets::listing(&self.dropin.presets, &self.preset_query, self.preset_sort);
+            egui::ComboBox::from_id_source("preset_pick")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    let mut folder = "";
+                    for &i in &listed {
+                        let p = &self.presets[i];
+                        if p.folder != folder {
+                            folder = &p.folder;
+                            ui.separator();
+                            ui.weak(folder);
+                        }
+                        let on = self.selected_dropin.is_none() && self.selected_preset == Some(i);
+                        let mut item = ui.selectable_label(on, &p.name);
+                        if !p.tags.is_empty() {
+                            item = item.on_hover_text(format!("Tags: {}", p.tags.join(", ")));
+                        }
+                        if item.clicked() {
+                            self.selected_preset = Some(i);
+                            self.selected_dropin = None;
+                        }
+                    }
+                    if !listed_dropins.is_empty() {
+                        ui.separator();
+                        let heading = ui.weak("presets.d");
+                        if let Some(dir) = self.dropin.dir() {
+                            heading.on_hover_text(dir.display().to_string());
+                        }
+                    }
+                    for &i in &listed_dropins {
+                        if ui.selectable_label(self.selected_dropin == Some(i), &self.dropin.presets[i].name).clicked() {
+                            self.selected_dropin = Some(i);
+                            self.selected_preset = None;
+                        }
+                    }
+                    if listed.is_empty() && listed_dropins.is_empty() {
+                        ui.weak("No preset matches.");
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+
+            let current = self.selected_preset().map(|p| self.standalone(p));
+            let is_dropin = self.selected_dropin.is_some();
+            ui.add_enabled_ui(current.is_some(), |ui| {
+                if ui.button("Load").on_hover_text("Shows what changes first if the form has other values").clicked() {
+                    if let Some(p) = &current {
+                        if p.form == self.form {
+                            self.last_message = format!("The form already matches \"{}\".", p.name);
+                        } else if self.form == FormConfig::default() {
+                            self.apply_preset(p);
+                        } else {
+                            self.load_review = Some(p.clone());
+                        }
+                    }
+                }
+                let delete = ui
+                    .add_enabled(!is_dropin, egui::Button::new("Delete"))
+                    .on_disabled_hover_text("Drop-in preset: remove its file from presets.d instead.");
+                if delete.clicked() {
+                    if let Some(i) = self.selected_preset {
+                        self.confirm = Some(Confirm::DeletePreset(i));
+                    }
+                }
+                if ui.button("Share").clicked() {
+                    if let Some(p) = &current {
+                        match presets::encode_share_code(p) {
+                            Ok(code) => {
+                                let qr = presets::qr_modules(&code);
+                                self.share = Some(ShareDialog { name: p.name.clone(), code, qr });
+                            }
+                            Err(e) => self.last_error = format!("Failed to encode preset: {}", e),
+                        }
+                    }
+                }
+            });
+        });
+
+        if let Some(i) = self.selected_preset.filter(|&i| i < self.presets.len() && self.selected_dropin.is_none()) {
+            if self.tags_edit.0 != self.presets[i].name {
+                self.tags_edit = (self.presets[i].name.clone(), self.presets[i].tags.join(", "));
+            }
+            let mut changed = false;
+            ui.horizontal(|ui| {
+                let label = ui.label("Folder");
+                changed |= ui
+                    .add(egui::TextEdit::singleline(&mut self.presets[i].folder).hint_text("none").desired_width(120.0))
+                    .labelled_by(label.id)
+                    .changed();
+                let label = ui.label("Tags");
+                let tags = ui
+                    .add(egui::TextEdit::singleline(&mut self.tags_edit.1).hint_text("comma separated").desired_width(180.0))
+                    .labelled_by(label.id);
+                if tags.lost_focus() {
+                    let parsed = presets::parse_tags(&self.tags_edit.1);
+                    self.tags_edit.1 = parsed.join(", ");
+                    if parsed != self.presets[i].tags {
+                        self.presets[i].tags = parsed;
+                        changed = true;
+                    }
+                }
+                let label = ui.label("Based on");
+                let own = self.presets[i].name.clone();
+                let current = self.presets[i].base.clone();
+                let mut picked = None;
+                egui::ComboBox::from_id_source("preset_base")
+                    .selected_text(if current.is_empty() { "nothing" } else { current.as_str() })
+                    .show_ui(ui, |ui| {
+                        if ui.selectable_label(current.is_empty(), "nothing").clicked() {
+                            picked = Some(String::new());
+                        }
+                        for p in self.presets.iter().chain(&self.dropin.presets) {
+                            if presets::builds_on(p, &own, |name| self.find_preset(name)) {
+                                continue;
+                            }
+                            if ui.selectable_label(p.name == current, &p.name).clicked() {
+                                picked = Some(p.name.clone());
+                            }
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id)
+                    .on_hover_text("Blank text fields, missing constants and an empty image cycle come from this preset, so shared buttons or branding are kept in one place.");
+                if !current.is_empty() && self.find_preset(&current).is_none() {
+                    ui.colored_label(ui.visuals().warn_fg_color, "\u{26a0} missing")
+                        .on_hover_text(format!("No preset is called \"{}\"; blank fields stay blank until it is back or another base is picked.", current));
+                }
+                if let Some(base) = picked.filter(|b| *b != current) {
+                    self.set_base(i, base);
+                    changed = true;
+                }
+            });
+            if changed {
+                self.mark_dirty();
+            }
+        }
+
+        ui.horizontal(|ui| {
+            let label = ui.label("Name");
+            ui.add(egui::TextEdit::singleline(&mut self.new_preset_name).hint_text("New preset name"))
+                .labelled_by(label.id);
+            if ui.button("Save as preset").clicked() {
+                self.last_error.clear();
+                self.save_preset();
+            }
+            if ui.button("Snapshot").on_hover_text("Save the form as a new preset named after the current time").clicked() {
+                self.snapshot_preset();
+            }
+            let revert = ui
+                .add_enabled(self.revertable().is_some(), egui::Button::new("Revert to live"))
+                .on_hover_text("Replace the form with the presence Discord is showing, dropping edits since the last Enable or Apply")
+                .on_disabled_hover_text("The form matches what Discord is showing, or the presence is off.");
+            if revert.clicked() {
+                self.revert_to_applied();
+            }
+            if ui.button("Import code").clicked() {
+                self.import = Some(ImportDialog::default());
+            }
+            let bulk = ui
+                .add_enabled(!self.presets.is_empty(), egui::Button::new("Find and replace…"))
+                .on_hover_text("Change text, a Client ID or an asset key in every saved preset at once");
+            if bulk.clicked() {
+                self.bulk = Some(BulkDialog::default());
+            }
+        });
+
+        if !self.dropin.errors.is_empty() {
+            let resp = ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!("{} file(s) in presets.d could not be loaded.", self.dropin.errors.len()),
+            );
+            resp.on_hover_text(self.dropin.errors.join("\n"));
+        }
+    }
+
+    fn invite_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = self.invite.as_mut() else { return };
+        let mut open = true;
+        let mut check = false;
+        egui::Window::new("Invite button")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let label = ui.label("Invite code or link:");
+                let resp = ui
+                    .add(egui::TextEdit::singleline(&mut dialog.text).hint_text("discord.gg/…").desired_width(260.0))
+                    .labelled_by(label.id);
+                check |= resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut dialog.slot, 0, "Button 1");
+                    ui.radio_value(&mut dialog.slot, 1, "Button 2");
+                });
+                if !dialog.error.is_empty() {
+                    ui.colored_label(egui::Color32::from_rgb(200, 60, 60), &dialog.error);
+                }
+                ui.horizontal(|ui| {
+                    let ready = !dialog.checking && !dialog.text.trim().is_empty();
+                    check |= ui.add_enabled(ready, egui::Button::new("Check and fill")).clicked();
+                    if dialog.checking {
+                        ui.spinner();
+                    }
+                });
+            });
+        if check && !dialog.checking {
+            self.check_invite();
+        } else if !open || esc_pressed(ctx) {
+            self.invite = None;
+        }
+    }
+
+    fn bulk_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = self.bulk.as_mut() else { return };
+        let edits = bulk::plan(&self.presets, dialog.target, &dialog.find, &dialog.replace, dialog.match_case);
+        let mut open = true;
+        let mut apply = false;
+        egui::Window::new("Find and replace in presets")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for target in bulk::Target::ALL {
+                        ui.radio_value(&mut dialog.target, target, target.label()).on_hover_text(target.hint());
+                    }
+                });
+                egui::Grid::new("bulk_grid").num_columns(2).show(ui, |ui| {
+                    let label = ui.label("Find");
+                    ui.add(egui::TextEdit::singleline(&mut dialog.find).desired_width(300.0)).labelled_by(label.id);
+                    ui.end_row();
+                    let label = ui.label("Replace with");
+                    ui.add(egui::TextEdit::singleline(&mut dialog.replace).desired_width(300.0)).labelled_by(label.id);
+                    ui.end_row();
+                });
+                ui.checkbox(&mut dialog.match_case, "Match case");
+                ui.separator();
+                if dialog.find.trim().is_empty() {
+                    ui.weak("Type what to find; the changes are listed here before anything is replaced.");
+                    return;
+                }
+                if edits.is_empty() {
+                    ui.weak("No saved preset contains it.");
+                    return;
+                }
+                // Client IDs stay out of screenshots in privacy mode.
+                let hide = dialog.target == bulk::Target::ClientId && privacy::enabled();
+                egui::ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+                    egui::Grid::new("bulk_preview").num_columns(3).striped(true).show(ui, |ui| {
+                        for edit in &edits {
+                            for (field, before, after) in &edit.changes {
+                                ui.label(&self.presets[edit.preset].name);
+                                ui.weak(*field);
+                                if hide {
+                                    ui.label("(hidden in privacy mode)");
+                                } else {
+                                    ui.add(egui::Label::new(format!("{} → {}", before, after)).wrap(true));
+                                }
+                                ui.end_row();
+                            }
+                        }
+                    });
+                });
+                let count: usize = edits.iter().map(|e| e.changes.len()).sum();
+                apply = ui.button(format!("Replace {} value(s) in {} preset(s)", count, edits.len())).clicked();
+            });
+        if apply {
+            let before = self.to_stored();
+            let presets = edits.len();
+            for edit in edits {
+                self.presets[edit.preset].form = edit.form;
+            }
+            self.bulk = None;
+            self.show_undo(format!("Replaced in {} preset(s).", presets), UndoAction::RestoreConfig(before));
+            self.save_config();
+        } else if !open || esc_pressed(ctx) {
+            self.bulk = None;
+        }
+    }
+
+    fn preset_dialogs(&mut self, ctx: &egui::Context) {
+        if let Some(share) = &self.share {
+            let mut open = true;
+            egui::Window::new(format!("Share \"{}\"", share.name))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let label = ui.label("Anyone can import this code with \"Import code\".");
+                    if privacy::enabled() {
+                        ui.label("The code includes the Client ID, so it is hidden in privacy mode.");
+                    } else {
+                        let mut code = share.code.as_str();
+                        ui.add(egui::TextEdit::multiline(&mut code).desired_rows(3).desired_width(320.0))
+                            .labelled_by(label.id);
+                    }
+                    if ui.button("Copy code").clicked() {
+                        ctx.copy_text(share.code.clone());
+                    }
+                    if let Some(qr) = share.qr.as_ref().filter(|_| !privacy::enabled()) {
+                        ui.add_space(6.0);
+                        preview::qr(ui, qr, 220.0, &format!("QR code for preset {}", share.name));
+                    }
+                });
+            if !open || esc_pressed(ctx) {
+                self.share = None;
+            }
+        }
+
+        let Some(import) = self.import.as_mut() else { return; };
+        let mut open = true;
+        let mut action: Option<bool> = None;
+        let mut replace = false;
+        egui::Window::new("Import preset")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let label = ui.label("Paste a preset code:");
+                let resp = ui
+                    .add(egui::TextEdit::multiline(&mut import.text).desired_rows(3).desired_width(320.0))
+                    .labelled_by(label.id);
+                if resp.changed() {
+                    match presets::decode_share_code(&import.text) {
+                        Ok(p) => {
+                            import.preview = Some(p);
+                            import.error.clear();
+                        }
+                        Err(e) => {
+                            import.preview = None;
+                            import.error = if import.text.trim().is_empty() { String::new() } else { e };
+                        }
+                    }
+                }
+                if !import.error.is_empty() {
+                    ui.colored_label(egui::Color32::from_rgb(200, 60, 60), &import.error);
+                }
+                if let Some(p) = &import.preview {
+                    let existing = self.presets.iter().find(|e| e.name == p.name);
+                    ui.add_space(6.0);
+                    let client = if p.form.client_id.is_empty() { "-".to_string() } else { privacy::redact(&p.form.client_id) };
+                    ui.label(format!("Preset \"{}\" (client {})", p.name, client));
+                    let cfg = p.form.to_presence_cfg();
+                    let now = rpc_core::now_unix_ts();
+                    preview::card(ui, &cfg, "", preview::timer(&cfg, now, now));
+                    if let Some(existing) = existing {
+                        ui.add_space(6.0);
+                        let changes = diff::changes(&existing.form, &p.form);
+                        if changes.is_empty() {
+                            ui.label(format!("A preset named \"{}\" already exists with the same values.", p.name));
+                        } else {
+                            ui.label(format!("A preset named \"{}\" already exists. Replacing it changes:", p.name));
+                            changes_grid(ui, "import_conflict_grid", &changes, "Existing", "Imported");
+                        }
+                    }
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        if existing.is_some() && ui.button("Replace").on_hover_text("Overwrite the existing preset; can be undone").clicked() {
+                            replace = true;
+                            action = Some(false);
+                        }
+                        let add = if existing.is_some() { "Add as copy" } else { "Add to presets" };
+                        if ui.button(add).clicked() {
+                            action = Some(false);
+                        }
+                        if existing.is_none() && ui.button("Add and load").clicked() {
+                            action = Some(true);
+                        }
+                    });
+                }
+            });
+
+        if let Some(load) = action {
+            if let Some(p) = self.import.take().and_then(|i| i.preview) {
+                if replace {
+                    self.replace_preset(p);
+                    return;
+                }
+                self.add_imported(p.clone());
+                if load {
+                    self.apply_preset(&p);
+                }
+            }
+        } else if !open || esc_pressed(ctx) {
+            self.import = None;
+        }
+    }
+
+    /// Locked UI for shared PCs: status and the enable/disable toggle only.
+    fn kiosk(&self) -> bool {
+        self.kiosk || self.kiosk_cli
+    }
+
+    /// Status shown to the user; a pending enable reads as waiting rather than inactive.
+    fn status_text(&self, status: RpcStatus) -> &'static str {
+        if self.enable_when_found { RpcStatus::WaitingForDiscord.label() } else { status.label() }
+    }
+
+    /// Whether the toggle reads "Disable": enabled, or waiting to enable once Discord starts.
+    fn presence_on(&self, status: RpcStatus) -> bool {
+        self.enable_when_found || !matches!(status, RpcStatus::Inactive | RpcStatus::Error)
+    }
+
+    fn toggle_presence(&mut self, on: bool) {
+        self.last_error.clear();
+        if on {
+            match self.disable_warning() {
+                Some(why) => self.confirm = Some(Confirm::Disable(why)),
+                None => self.disable_rpc(),
+            }
+        } else {
+            self.enable_rpc();
+        }
+    }
+
+    fn status_overlay(&self, ctx: &egui::Context, status: RpcStatus) {
+        let Some(corner) = self.overlay else { return };
+        let preset = self.live_preset.as_deref().unwrap_or("No preset");
+        let text = format!("{} · {}", self.status_text(status), preset);
+        let (main, monitor) = ctx.input(|i| (i.viewport().outer_rect, i.viewport().monitor_size));
+        overlay::show(ctx, corner, main, monitor, status_color(status), &text);
+    }
+
+    fn set_compact(&mut self, ctx: &egui::Context, on: bool) {
+        if on == self.compact {
+            return;
+        }
+        if on {
+            self.full_size = ctx.input(|i| i.viewport().inner_rect.map(|r| r.size()));
+        } else {
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(self.full_size.take().unwrap_or(FULL_SIZE)));
+        }
+        self.compact = on;
+        self.compact_monitor = None;
+        self.mark_dirty();
+    }
+
+    /// Sizes the compact window and docks it to the corner remembered for its monitor, whenever it
+    /// lands on another monitor (and once on entering compact mode).
+    fn dock_compact(&mut self, ctx: &egui::Context) {
+        let (monitor, ppp) = ctx.input(|i| (i.viewport().monitor_size, i.viewport().native_pixels_per_point));
+        let key = monitor.map(|m| dock::monitor_key(m, ppp.unwrap_or(1.0)));
+        if key.is_some() && key == self.compact_monitor {
+            return;
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(COMPACT_SIZE));
+        let corner = key.as_ref().and_then(|k| self.compact_docks.get(k)).copied();
+        if let Some(corner) = corner {
+            self.dock_to(ctx, corner);
+        }
+        self.compact_monitor = key;
+    }
+
+    fn dock_to(&mut self, ctx: &egui::Context, corner: dock::Corner) {
+        let (outer, inner, monitor, ppp) = ctx.input(|i| {
+            let v = i.viewport();
+            (v.outer_rect, v.inner_rect, v.monitor_size, v.native_pixels_per_point)
+        });
+        let (Some(outer), Some(inner), Some(monitor)) = (outer, inner, monitor) else { return };
+        // Place the window as it will be once the compact size applies, title bar included.
+        let window = egui::Rect::from_min_size(outer.min, COMPACT_SIZE + (outer.size() - inner.size()));
+        ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(dock::position(corner, window, monitor)));
+        self.compact_docks.insert(dock::monitor_key(monitor, ppp.unwrap_or(1.0)), corner);
+        self.mark_dirty();
+    }
+
+    /// The compact layout: status, the enable toggle and a preset picker.
+    fn compact_ui(&mut self, ctx: &egui::Context, status: RpcStatus, toggle: bool) {
+        self.dock_compact(ctx);
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let (dot, _) = ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                ui.painter().circle_filled(dot.center(), 5.0, status_color(status));
+                ui.label(self.status_text(status));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("Full window").clicked() {
+                        self.set_compact(ctx, false);
+                    }
+                    if ui.toggle_value(&mut self.always_on_top, "On top").on_hover_text("Keep this window on top of other apps").changed() {
+                        self.mark_dirty();
+                    }
+                    for corner in dock::Corner::ALL.into_iter().rev() {
+                        let hint = format!("Dock to the {} corner of this screen; remembered for this screen", corner.label());
+                        if ui.small_button(corner.arrow()).on_hover_text(hint).clicked() {
+                            self.dock_to(ctx, corner);
+                        }
+                    }
+                });
+            });
+            ui.horizontal(|ui| {
+                let on = self.presence_on(status);
+                if ui.button(if on { "Disable" } else { "Enable" }).on_hover_text("Ctrl+E").clicked() || toggle {
+                    self.toggle_presence(on);
+                }
+                if self.kiosk() || self.presets.is_empty() {
+                    return;
+                }
+                let current = self.selected_preset.and_then(|i| self.presets.get(i)).map_or("Preset…", |p| p.name.as_str());
+                let mut picked = None;
+                egui::ComboBox::from_id_source("compact_preset").selected_text(current.to_string()).width(170.0).show_ui(ui, |ui| {
+                    for i in presets::listing(&self.presets, "", self.preset_sort) {
+                        if ui.selectable_label(self.selected_preset == Some(i), &self.presets[i].name).clicked() {
+                            picked = Some(i);
+                        }
+                    }
+                });
+                if let Some(i) = picked {
+                    self.switch_to_preset(i);
+                }
+            });
+            if !self.last_error.is_empty() {
+                ui.colored_label(egui::Color32::from_rgb(200, 60, 60), privacy::redact(&self.last_error)).on_hover_text(privacy::redact(&self.last_error));
+            }
+        });
+    }
+
+    fn status_bar(&self, ctx: &egui::Context, status: RpcStatus) {
+        let report = self.worker.report();
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("Status: {}", self.status_text(status)));
+                ui.separator();
+                ui.label(format!("Pipe: {}", report.pipe.as_deref().unwrap_or("-")));
+                ui.separator();
+                match report.rtt {
+                    Some(rtt) => ui.label(format!("Ping: {} ms", rtt.as_millis())),
+                    None => ui.label("Ping: -"),
+                }
+                .on_hover_text("Round trip to the Discord client, measured every keepalive.");
+                ui.separator();
+                match report.last_ok {
+                    Some(t) => ui.label(format!("Last update: {}s ago", t.elapsed().as_secs())),
+                    None => ui.label("Last update: -"),
+                };
+                if status != RpcStatus::Inactive {
+                    if let Some(next) = report.next_send {
+                        ui.separator();
+                        let secs = next.saturating_duration_since(Instant::now()).as_secs_f32().ceil();
+                        ui.label(format!("Next update in {}s", secs as u64));
+                    }
+                    match self.worker.pending_updates() {
+                        0 => {}
+                        n => {
+                            ui.separator();
+                            ui.label(format!("{} update{} pending", n, if n == 1 { "" } else { "s" }))
+                                .on_hover_text("Not sent to Discord yet. Only the latest goes out, with the next update.");
+                        }
+                    }
+                }
+                for (path, w) in &self.extra_workers {
+                    ui.separator();
+                    let resp = ui.label(format!("{}: {}", pipe_name(path), w.status().label()));
+                    if let Some(e) = w.last_error() {
+                        resp.on_hover_text(e);
+                    }
+                }
+            });
+        });
+    }
+
+    fn preview_panel(&mut self, ctx: &egui::Context) {
+        let cfg = self.form.to_presence_cfg();
+        let start = self.worker.start_ts().unwrap_or(self.preview_start);
+        let timer = preview::timer(&cfg, start, rpc_core::now_unix_ts());
+
+        egui::SidePanel::right("preview_panel").resizable(false).show(ctx, |ui| {
+            ui.add_space(6.0);
+            ui.heading("Preview");
+            ui.add_space(6.0);
+            self.card_rect = preview::card(ui, &cfg, &self.last_app_name, timer).rect;
+            ui.add_space(6.0);
+            if ui.button("Export image").clicked() && self.export_rect.is_none() {
+                self.export_rect = Some(self.card_rect);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot);
+            }
+        });
+    }
+
+    fn handle_screenshot(&mut self, ctx: &egui::Context) {
+        let Some(rect) = self.export_rect else { return; };
+        let shot = ctx.input(|i| {
+            i.raw.events.iter().find_map(|e| match e {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        let Some(shot) = shot else { return; };
+        self.export_rect = None;
+
+        let card = shot.region(&rect, Some(ctx.pixels_per_point()));
+        let Some(path) = export_dir().map(|d| d.join(format!("presence-{}.png", rpc_core::now_unix_ts()))) else {
+            self.last_error = "No folder available to save the image.".to_string();
+            return;
+        };
+        match preview::save_png(&card, &path) {
+            Ok(()) => {
+                logging::info(format!("exported preview to {}", path.display()));
+                self.last_message = format!("Image saved to {}", path.display());
+                self.last_error.clear();
+            }
+            Err(e) => self.last_error = format!("Failed to save image: {}", e),
+        }
+    }
+
+    /// The published presence has buttons and Discord's echo kept them.
+    fn buttons_live(&self) -> bool {
+        let has_buttons = self.worker.presence_snapshot().is_some_and(|p| {
+            p.buttons.iter().any(|b| !b.label.trim().is_empty() && !b.url.trim().is_empty())
+        });
+        has_buttons && !self.last_echo.iter().any(|l| l == rpc_core::echo::BUTTONS_DROPPED)
+    }
+
+    fn button_guide(&mut self, ctx: &egui::Context) {
+        if !self.button_guide {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Checking your buttons")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(380.0)
+            .show(ctx, |ui| {
+                ui.label("Discord hides the buttons of your own activity from you, in the member list and on your profile. Everyone else sees them; this is not a bug.");
+                ui.add_space(6.0);
+                ui.label("To see them yourself:");
+                ui.label("1. Sign in with a second account that shares a server with you, e.g. in a private browser window.");
+                ui.label("2. Open your profile from that account. The buttons show under the activity.");
+                ui.label("3. Or ask a friend to look and click one.");
+                ui.add_space(6.0);
+                ui.label("If they are missing there too, check that each URL is a full https:// link and each label is at most 32 characters, then look at the warnings under the status.");
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Open Discord in the browser").clicked() {
+                        ctx.open_url(egui::OpenUrl::new_tab("https://discord.com/app"));
+                    }
+                    if ui.button("Close").clicked() {
+                        self.button_guide = false;
+                    }
+                });
+            });
+        if !open || esc_pressed(ctx) {
+            self.button_guide = false;
+        }
+    }
+
+    fn privacy_reminder_dialog(&mut self, ctx: &egui::Context) {
+        if !self.privacy_reminder {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Your presence is live")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(380.0)
+            .show(ctx, |ui| {
+                ui.label("Discord accepted the presence. Whether others see it depends on a Discord setting this app cannot read:");
+                ui.add_space(4.0);
+                ui.strong("User Settings → Activity Privacy → \"Share your detected activities with others\"");
+                ui.add_space(4.0);
+                ui.label("When it is off, the status here says Active but nobody sees the presence. Being Invisible hides it too.");
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Open Activity Privacy").on_hover_text("Opens the Discord desktop app on that page").clicked() {
+                        ctx.open_url(egui::OpenUrl::same_tab(troubleshoot::ACTIVITY_PRIVACY_URL));
+                    }
+                    if ui.button("More checks…").clicked() {
+                        self.troubleshoot.get_or_insert_with(HashSet::new);
+                        self.privacy_reminder = false;
+                    }
+                    if ui.button("Got it").clicked() {
+                        self.privacy_reminder = false;
+                    }
+                });
+            });
+        if !open || esc_pressed(ctx) {
+            self.privacy_reminder = false;
+        }
+    }
+
+    /// First run with settings left in an old config folder: bring them over or start fresh.
+    fn migration_dialog(&mut self, ctx: &egui::Context) {
+        let Some((legacy, old)) = &self.migration else { return; };
+        let mut found = Vec::new();
+        match old {
+            Some(old) => found.push(format!("settings with {} preset(s)", old.presets.len())),
+            None if legacy.config.is_some() => found.push("a config.json that cannot be read".to_string()),
+            None => {}
+        }
+        if !legacy.dropins.is_empty() {
+            found.push(format!("{} drop-in preset file(s)", legacy.dropins.len()));
+        }
+
+        let mut decided: Option<bool> = None;
+        egui::Window::new("Settings from an earlier install")
+            .collapsible(false)
+            .resizable(false)
+            .default_width(420.0)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!("Found {} in {}.", found.join(" and "), legacy.dir.display()));
+                ui.label("Bring them over? Presets are added to any you already have; the old folder is left as it is.");
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Bring them over").clicked() {
+                        decided = Some(true);
+                    }
+                    if ui.button("Start fresh").clicked() || esc_pressed(ctx) {
+                        decided = Some(false);
+                    }
+                });
+            });
+
+        let Some(import) = decided else { return; };
+        let Some((legacy, old)) = self.migration.take() else { return; };
+        if import {
+            self.migrate_from(ctx, legacy, old);
+        } else {
+            logging::info(format!("left the settings in {} behind", legacy.dir.display()));
+        }
+        // Writing config.json marks the first run as done, so the offer is not repeated.
+        self.save_config();
+    }
+
+    fn migrate_from(&mut self, ctx: &egui::Context, legacy: migrate::Legacy, old: Option<StoredConfig>) {
+        let mut brought = Vec::new();
+        if let Some(mut old) = old {
+            let mut presets = std::mem::take(&mut self.presets);
+            let added = presets::merge(&mut presets, std::mem::take(&mut old.presets));
+            old.presets = presets;
+            self.apply_stored(old);
+            ctx.set_zoom_factor(self.ui_scale);
+            brought.push(format!("settings and {} preset(s)", added));
+        }
+        if let Some(dir) = self.cfg_path.as_deref().and_then(Path::parent) {
+            match migrate::copy_dropins(&legacy, dir) {
+                Ok(0) => {}
+                Ok(n) => brought.push(format!("{} drop-in preset file(s)", n)),
+                Err(e) => self.last_error = format!("Could not copy the drop-in presets: {}", e),
+            }
+        }
+        let message = if brought.is_empty() {
+            format!("Nothing new to bring over from {}.", legacy.dir.display())
+        } else {
+            format!("Brought over {} from {}.", brought.join(" and "), legacy.dir.display())
+        };
+        logging::info(&message);
+        self.last_message = message;
+    }
+
+    fn apply_review_dialog(&mut self, ctx: &egui::Context) {
+        if !self.apply_review {
+            return;
+        }
+        let Some(live) = self.revertable() else {
+            self.apply_review = false;
+            return;
+        };
+        let changes = diff::changes(live, &self.form);
+        let mut open = true;
+        let mut apply = false;
+        egui::Window::new("Apply changes")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(440.0)
+            .show(ctx, |ui| {
+                ui.label("Discord still shows the live presence. Applying sends the draft:");
+                ui.add_space(6.0);
+                changes_grid(ui, "apply_review_grid", &changes, "Live", "Draft");
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").on_hover_text("Ctrl+U applies without this review").clicked();
+                    if ui.button("Cancel").clicked() {
+                        self.apply_review = false;
                     }
-                    Err(e) => {
-                        self.last_error = e;
+                });
+            });
+        if apply {
+            self.apply_review = false;
+            self.last_error.clear();
+            self.update_rpc();
+        }
+        if !open || esc_pressed(ctx) {
+            self.apply_review = false;
+        }
+    }
+
+    fn load_review_dialog(&mut self, ctx: &egui::Context) {
+        let Some(preset) = &self.load_review else { return };
+        let changes = diff::changes(&self.form, &preset.form);
+        let mut open = true;
+        let mut load = false;
+        let mut cancel = false;
+        egui::Window::new(format!("Load \"{}\"", preset.name))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(440.0)
+            .show(ctx, |ui| {
+                ui.label("Loading the preset replaces these form values:");
+                ui.add_space(6.0);
+                changes_grid(ui, "load_review_grid", &changes, "Form", "Preset");
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    load = ui.button("Load").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if load {
+            if let Some(p) = self.load_review.take() {
+                self.apply_preset(&p);
+            }
+        } else if cancel || !open || esc_pressed(ctx) {
+            self.load_review = None;
+        }
+    }
+
+    fn troubleshoot_dialog(&mut self, ctx: &egui::Context) {
+        let Some(confirmed) = self.troubleshoot.as_mut() else { return };
+        let report = self.worker.report();
+        let last_error = self.worker.last_error();
+        let steps = troubleshoot::steps(&troubleshoot::Input {
+            status: self.worker.status(),
+            last_error: last_error.as_deref(),
+            pipes: &self.ipc_pipes,
+            targets: &self.form.targets,
+            user: report.ready.as_ref().map(|r| if r.is_arrpc() { "an arRPC client" } else { r.user.username.as_str() }),
+            echo: &self.last_echo,
+            rivals: &rivals::running(&self.rule_inputs.processes),
+        });
+        let done = steps
+            .iter()
+            .filter(|s| matches!(s.finding, troubleshoot::Finding::Ok(_)) || confirmed.contains(s.title))
+            .count();
+        let mut open = true;
+        let mut rescan = false;
+        egui::Window::new("Nobody sees the presence?")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                ui.label(format!("{} of {} checked. Work down the list; the first open item is the likely cause.", done, steps.len()));
+                ui.add_space(6.0);
+                egui::Grid::new("troubleshoot_grid").num_columns(2).spacing([8.0, 8.0]).show(ui, |ui| {
+                    for step in &steps {
+                        match &step.finding {
+                            troubleshoot::Finding::Ok(_) => {
+                                ui.colored_label(egui::Color32::from_rgb(60, 170, 90), "\u{2714}");
+                            }
+                            troubleshoot::Finding::Problem(_) => {
+                                ui.colored_label(egui::Color32::from_rgb(200, 60, 60), "\u{2716}");
+                            }
+                            troubleshoot::Finding::Manual => {
+                                let mut ticked = confirmed.contains(step.title);
+                                if ui.checkbox(&mut ticked, "").on_hover_text("Tick once you checked this in Discord").changed() {
+                                    if ticked {
+                                        confirmed.insert(step.title);
+                                    } else {
+                                        confirmed.remove(step.title);
+                                    }
+                                }
+                            }
+                        }
+                        ui.vertical(|ui| {
+                            ui.strong(step.title);
+                            ui.add(egui::Label::new(step.explain).wrap(true));
+                            match &step.finding {
+                                troubleshoot::Finding::Ok(note) => {
+                                    ui.weak(privacy::redact(note));
+                                }
+                                troubleshoot::Finding::Problem(problem) => {
+                                    ui.colored_label(ui.visuals().error_fg_color, privacy::redact(problem));
+                                }
+                                troubleshoot::Finding::Manual => {}
+                            }
+                            if let Some((label, url)) = step.link {
+                                if ui.small_button(label).on_hover_text("Opens the Discord desktop app on that page").clicked() {
+                                    ctx.open_url(egui::OpenUrl::same_tab(url));
+                                }
+                            }
+                        });
+                        ui.end_row();
                     }
-                },
-                AppEvent::AppMeta(res) => match res {
-                    Ok(meta) => {
-                        self.last_app_name = meta.name;
-                        self.last_app_icon = meta.icon_url.unwrap_or_default();
-                        self.last_message = "App synced.".to_string();
-                        self.last_error.clear();
-                        self.save_config();
+                });
+                ui.add_space(6.0);
+                rescan = ui.button("Look for Discord again").clicked();
+            });
+        if rescan {
+            self.ipc_pipes = rpc_core::discover_ipc_paths();
+        }
+        if !open || esc_pressed(ctx) {
+            self.troubleshoot = None;
+        }
+    }
+
+    fn crash_dialog(&mut self, ctx: &egui::Context) {
+        let Some(report) = self.crash_report.clone() else { return; };
+        let mut close = false;
+
+        egui::Window::new("The app crashed last time")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(&report.summary);
+                ui.add_space(4.0);
+                ui.label(format!("A crash report was saved to {}", report.path.display()));
+                ui.label("You can open a GitHub issue with the report pre-filled. Review it before submitting.");
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Report on GitHub").clicked() {
+                        ctx.open_url(egui::OpenUrl::new_tab(crash::issue_url(&report)));
+                        close = true;
                     }
-                    Err(e) => {
-                        self.last_error = e;
+                    if ui.button("Copy report").clicked() {
+                        ctx.copy_text(report.body.clone());
                     }
-                },
+                    if ui.button("Dismiss").clicked() || esc_pressed(ctx) {
+                        close = true;
+                    }
+                });
+            });
+
+        if close {
+            if let Some(dir) = &self.crash_dir {
+                crash::dismiss(dir);
             }
+            self.crash_report = None;
         }
     }
 }
@@ -607,91 +4271,204 @@ impl AppState {
 impl eframe::App for AppState {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.handle_events();
+        // Each worker handshake already carries the connected user, so keep the user card current from it.
+        if let Some(ready) = self.worker.report().ready {
+            self.apply_profile(ready.user);
+        }
+        self.sync_zoom(ctx);
+        self.sync_window_level(ctx);
         self.maybe_autosave();
+        self.probe_discord();
+        self.run_deferred();
+        self.reload_dropins();
+        let dropped = ctx.input(|i| i.raw.dropped_files.iter().find_map(|f| f.path.clone()));
+        let dropped = dropped.filter(|_| !self.kiosk());
+        if let Some(path) = dropped.filter(|p| p.extension().is_some_and(|x| x.eq_ignore_ascii_case("zip"))) {
+            self.confirm = Some(Confirm::ImportBackup(path));
+        }
 
         let status = self.worker.status();
         let err = self.worker.last_error();
+        if status != self.last_status {
+            match &err {
+                Some(e) => logging::warn(format!("RPC status: {} ({})", status.as_str(), e)),
+                None => logging::info(format!("RPC status: {}", status.as_str())),
+            }
+            history::record(history::Event::connection(status, err.as_deref()));
+            self.last_status = status;
+            if status == RpcStatus::Active && !self.privacy_reminder_shown {
+                // Once ever: "it says active but nobody sees it" is nearly always this setting.
+                self.privacy_reminder_shown = true;
+                self.privacy_reminder = true;
+                self.save_config();
+            }
+        }
+        if self.pending_cue.is_some()
+            && matches!(status, RpcStatus::Stabilizing | RpcStatus::Active)
+            && self.worker.pending_updates() == 0
+        {
+            if let Some((cue, body)) = self.pending_cue.take() {
+                cue::play(cue, body);
+            }
+        }
+        let echo = self.worker.report().echo_mismatches;
+        if echo != self.last_echo {
+            if !echo.is_empty() {
+                logging::warn(format!("Discord changed the presence: {}", echo.join(" ")));
+            }
+            self.last_echo = echo;
+        }
+        self.status_overlay(ctx, status);
+
+        self.crash_dialog(ctx);
+        self.handle_screenshot(ctx);
+        self.preset_dialogs(ctx);
+        self.bulk_dialog(ctx);
+        self.invite_dialog(ctx);
+        self.defer_dialog(ctx);
+        self.self_check_dialog(ctx);
+        self.button_guide(ctx);
+        self.troubleshoot_dialog(ctx);
+        self.apply_review_dialog(ctx);
+        self.load_review_dialog(ctx);
+        self.privacy_reminder_dialog(ctx);
+        self.migration_dialog(ctx);
+        self.confirm_dialog(ctx);
+        self.undo_toast(ctx);
+
+        let shortcuts = Shortcuts::read(ctx);
+        if self.compact {
+            self.compact_ui(ctx, status, shortcuts.toggle);
+            ctx.request_repaint_after(Duration::from_millis(200));
+            return;
+        }
+        self.status_bar(ctx, status);
+        self.preview_panel(ctx);
 
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Custom Rich Presence (Native)");
             ui.add_space(6.0);
 
             ui.horizontal(|ui| {
-                ui.label(format!("RPC status: {}", status.as_str()));
+                ui.label(format!("RPC status: {}", self.status_text(status)));
                 if let Some(e) = err {
-                    ui.label(format!("error: {}", e));
+                    ui.label(format!("error: {}", privacy::redact(&e)));
+                }
+                if self.kiosk() {
+                    return;
                 }
+                if status.is_connected() && ui.small_button("Nobody sees it?").clicked() {
+                    self.troubleshoot.get_or_insert_with(HashSet::new);
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("Compact").on_hover_text("Only the status, the toggle and a preset picker, in a small window").clicked() {
+                        self.set_compact(ui.ctx(), true);
+                    }
+                    if ui
+                        .checkbox(&mut self.privacy_mode, "Privacy mode")
+                        .on_hover_text("Hide the Client ID and Discord ids, e.g. while streaming")
+                        .changed()
+                    {
+                        privacy::set_enabled(self.privacy_mode);
+                        self.mark_dirty();
+                    }
+                });
             });
 
-            if !self.last_error.is_empty() {
-                ui.colored_label(egui::Color32::from_rgb(200, 60, 60), &self.last_error);
-            } else if !self.last_message.is_empty() {
-                ui.colored_label(egui::Color32::from_rgb(60, 170, 90), &self.last_message);
+            let rivals = rivals::running(&self.rule_inputs.processes);
+            let daemon = rivals.contains(&rivals::DAEMON);
+            if !rivals.is_empty() && (status.is_connected() || daemon) {
+                ui.horizontal_wrapped(|ui| {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(210, 150, 40),
+                        format!(
+                            "Also running: {}. Two apps setting a presence replace each other's; close one or disable its presence.",
+                            rivals.join(", ")
+                        ),
+                    )
+                    .on_hover_text("Discord shows one activity per account. Found in the process list.");
+                    if daemon && !self.kiosk() {
+                        let take = ui
+                            .add_enabled(!self.takeover_busy, egui::Button::new("Take over"))
+                            .on_hover_text("Ask presence-daemon to disable its presence, then enable this one");
+                        if take.clicked() {
+                            self.take_over();
+                        }
+                    }
+                });
             }
 
-            ui.separator();
-            egui::Grid::new("cfg_grid").num_columns(2).spacing([12.0, 6.0]).show(ui, |ui| {
-                ui.label("Client ID");
-                if ui.text_edit_singleline(&mut self.form.client_id).changed() { self.mark_dirty(); }
-                ui.end_row();
-
-                ui.label("Details");
-                if ui.text_edit_singleline(&mut self.form.details).changed() { self.mark_dirty(); }
-                ui.end_row();
-
-                ui.label("State");
-                if ui.text_edit_singleline(&mut self.form.state).changed() { self.mark_dirty(); }
-                ui.end_row();
-
-                ui.label("Large image");
-                if ui.text_edit_singleline(&mut self.form.large_image).changed() { self.mark_dirty(); }
-                ui.end_row();
-
-                ui.label("Large text");
-                if ui.text_edit_singleline(&mut self.form.large_text).changed() { self.mark_dirty(); }
-                ui.end_row();
-
-                ui.label("Small image");
-                if ui.text_edit_singleline(&mut self.form.small_image).changed() { self.mark_dirty(); }
-                ui.end_row();
-
-                ui.label("Small text");
-                if ui.text_edit_singleline(&mut self.form.small_text).changed() { self.mark_dirty(); }
-                ui.end_row();
-
-                ui.label("Button 1 label");
-                if ui.text_edit_singleline(&mut self.form.b1label).changed() { self.mark_dirty(); }
-                ui.end_row();
+            if !self.last_echo.is_empty() && status.is_connected() {
+                ui.colored_label(egui::Color32::from_rgb(210, 150, 40), "Discord is not showing the presence exactly as sent:")
+                    .on_hover_text("Compared with the activity Discord echoes back after each update.");
+                for line in &self.last_echo {
+                    ui.colored_label(egui::Color32::from_rgb(210, 150, 40), format!("  \u{2022} {}", line));
+                }
+            }
 
-                ui.label("Button 1 url");
-                if ui.text_edit_singleline(&mut self.form.b1url).changed() { self.mark_dirty(); }
-                ui.end_row();
+            if status == RpcStatus::Active && !self.hide_button_note && self.buttons_live() {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("\u{2139} Buttons are live, but Discord never shows you your own buttons.");
+                    if ui.small_button("How to check…").clicked() {
+                        self.button_guide = true;
+                    }
+                    if ui.small_button("Don't show again").clicked() {
+                        self.hide_button_note = true;
+                        self.mark_dirty();
+                    }
+                });
+            }
 
-                ui.label("Button 2 label");
-                if ui.text_edit_singleline(&mut self.form.b2label).changed() { self.mark_dirty(); }
-                ui.end_row();
+            if self.discord_missing() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Discord not detected \u{2014} waiting. Start the Discord desktop app to connect.");
+                });
+            }
 
-                ui.label("Button 2 url");
-                if ui.text_edit_singleline(&mut self.form.b2url).changed() { self.mark_dirty(); }
-                ui.end_row();
+            if !self.last_error.is_empty() {
+                ui.colored_label(egui::Color32::from_rgb(200, 60, 60), privacy::redact(&self.last_error));
+            } else if !self.last_message.is_empty() {
+                ui.colored_label(egui::Color32::from_rgb(60, 170, 90), privacy::redact(&self.last_message));
+            }
 
-                ui.label("Timestamp");
-                if ui.checkbox(&mut self.form.with_timestamp, "enabled").changed() { self.mark_dirty(); }
-                ui.end_row();
-            });
+            if !self.kiosk() {
+                ui.separator();
+                self.form_ui(ui);
+            }
 
             ui.add_space(8.0);
             ui.horizontal(|ui| {
-                let active = matches!(status, RpcStatus::Active | RpcStatus::Connecting);
-                if ui.button(if active { "Disable" } else { "Enable" }).clicked() {
-                    self.last_error.clear();
-                    if active {
-                        self.disable_rpc();
-                    } else {
-                        self.enable_rpc();
+                let active = self.presence_on(status);
+                let toggle = ui.button(if active { "Disable" } else { "Enable" }).on_hover_text("Ctrl+E");
+                if toggle.clicked() || shortcuts.toggle {
+                    self.toggle_presence(active);
+                }
+                if self.kiosk() {
+                    return;
+                }
+                if active && !self.enable_when_found {
+                    let paused = status == RpcStatus::Paused;
+                    if ui.button(if paused { "Resume" } else { "Pause" }).on_hover_text("Hide the presence without disabling it").clicked() {
+                        self.set_paused(!paused);
                     }
                 }
-                if ui.button("Update").clicked() {
+                if ui.button("Enable later…").on_hover_text("Enable a preset at a set time, and optionally disable it again").clicked() {
+                    self.defer_dialog = Some(DeferDialog::default());
+                }
+                let draft = self.revertable().is_some();
+                let apply = ui
+                    .add_enabled(draft, egui::Button::new("Apply…"))
+                    .on_hover_text("Review the draft and send it to Discord (Ctrl+U sends it directly)")
+                    .on_disabled_hover_text(if self.worker.is_running() {
+                        "The form matches what Discord shows"
+                    } else {
+                        "Enable first; the form is sent as it is"
+                    });
+                if apply.clicked() {
+                    self.apply_review = true;
+                }
+                if shortcuts.update {
                     self.last_error.clear();
                     self.update_rpc();
                 }
@@ -703,52 +4480,339 @@ impl eframe::App for AppState {
                     self.last_error.clear();
                     self.sync_app();
                 }
-                if ui.button("Save").clicked() {
+                if ui.button("Save").on_hover_text("Ctrl+S").clicked() || shortcuts.save {
                     self.save_config();
                     self.last_message = "Configuration saved.".to_string();
                     self.last_error.clear();
                 }
             });
 
+            if let Some(live) = self.revertable().filter(|_| !self.kiosk()) {
+                let n = diff::changes(live, &self.form).len();
+                ui.horizontal(|ui| {
+                    ui.weak(format!("Draft has {} change{} not on Discord yet.", n, if n == 1 { "" } else { "s" }));
+                    if ui.small_button("Review…").clicked() {
+                        self.apply_review = true;
+                    }
+                });
+            }
+
+            if let Some(d) = &self.deferred {
+                let mut cancel = false;
+                ui.horizontal(|ui| {
+                    let what = if d.preset.is_empty() { "the current form".to_string() } else { format!("\"{}\"", d.preset) };
+                    let text = match (d.started, d.disable_at) {
+                        (false, Some(off)) => format!("Enables {} at {}, disables at {}.", what, fmt_local_time(d.enable_at), fmt_local_time(off)),
+                        (false, None) => format!("Enables {} at {}.", what, fmt_local_time(d.enable_at)),
+                        (true, off) => format!("Disables at {}.", fmt_local_time(off.unwrap_or_default())),
+                    };
+                    ui.weak(text);
+                    cancel = !self.kiosk() && ui.small_button("Cancel").clicked();
+                });
+                if cancel {
+                    self.deferred = None;
+                    self.last_message = "Scheduled enable cancelled.".to_string();
+                    self.save_config();
+                }
+            }
+
+            if self.kiosk() {
+                ui.add_space(8.0);
+                ui.weak("Locked: presets and settings are managed by someone else on this PC.");
+                return;
+            }
+
+            ui.separator();
+            self.presets_ui(ui);
+
             ui.separator();
-            ui.label(format!("Last user: {}", if self.last_user_name.is_empty() { "-" } else { &self.last_user_name }));
-            ui.label(format!("User avatar URL: {}", if self.last_user_avatar.is_empty() { "-" } else { &self.last_user_avatar }));
-            ui.label(format!("Last app: {}", if self.last_app_name.is_empty() { "-" } else { &self.last_app_name }));
-            ui.label(format!("App icon URL: {}", if self.last_app_icon.is_empty() { "-" } else { &self.last_app_icon }));
+            self.rules_ui(ui);
+            self.settings_ui(ui);
+            self.advanced_ui(ui);
+
+            ui.separator();
+            self.user_card_ui(ui);
+            ui.label(format!("User avatar URL: {}", if self.last_user_avatar.is_empty() { "-".to_string() } else { privacy::redact(&self.last_user_avatar) }));
+            ui.horizontal(|ui| {
+                let icon = self.last_app_icon.clone();
+                if let Some(id) = self.picture(ui.ctx(), &icon) {
+                    ui.image((id, egui::vec2(20.0, 20.0)));
+                }
+                ui.label(format!("Last app: {}", if self.last_app_name.is_empty() { "-" } else { &self.last_app_name }));
+            });
+            ui.label(format!("App icon URL: {}", if self.last_app_icon.is_empty() { "-".to_string() } else { privacy::redact(&self.last_app_icon) }));
         });
 
         ctx.request_repaint_after(Duration::from_millis(200));
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        logging::info("shutting down");
+        // The samplers stop first so nothing sends the presence again, then the worker clears it;
+        // settings and the log are written last, once nothing can change them.
+        self.clip_watcher = None;
+        let mut shutdown = std::mem::take(&mut self.shutdown);
+        let worker = self.worker.clone();
+        shutdown.then("worker", move || {
+            if !worker.shutdown(WORKER_STOP) {
+                logging::warn("Discord did not confirm clearing the presence before exit");
+            }
+        });
+        for step in shutdown.run() {
+            logging::error(format!("shutdown step '{}' failed", step));
+        }
+        if self.dirty_since.take().is_some() {
+            self.save_config();
+        }
+        logging::flush();
+    }
 }
 
-fn config_path() -> Option<PathBuf> {
-    let proj = ProjectDirs::from("com", "Watashi", "CustomRichPresence")?;
-    Some(proj.config_dir().join("config.json"))
-}
-
-fn fetch_app_meta(client_id: &str) -> anyhow::Result<AppMeta> {
-    let url = format!("https://discord.com/api/v10/oauth2/applications/{}/rpc", client_id);
-    let resp = reqwest::blocking::Client::new()
-        .get(url)
-        .send()
-        .context("Failed to call Discord API")?
-        .error_for_status()
-        .context("HTTP error while fetching app metadata")?
-        .json::<RpcAppResp>()
-        .context("Failed to decode response")?;
-
-    let icon_url = resp.icon.as_ref().map(|h| {
-        format!("https://cdn.discordapp.com/app-icons/{}/{}.png?size=256", client_id, h)
+/// Inner size of the compact window, and of the full one when its size before is unknown.
+const COMPACT_SIZE: egui::Vec2 = egui::vec2(390.0, 96.0);
+const FULL_SIZE: egui::Vec2 = egui::vec2(1000.0, 760.0);
+
+fn status_color(status: RpcStatus) -> egui::Color32 {
+    match status {
+        RpcStatus::Active => egui::Color32::from_rgb(60, 170, 90),
+        RpcStatus::Inactive => egui::Color32::GRAY,
+        RpcStatus::Error | RpcStatus::Degraded => egui::Color32::from_rgb(200, 60, 60),
+        _ => egui::Color32::from_rgb(210, 150, 40),
+    }
+}
+
+/// How long closing the window waits for the worker to clear the presence.
+const WORKER_STOP: Duration = Duration::from_secs(2);
+
+const UI_SCALE_MIN: f32 = 0.75;
+const UI_SCALE_MAX: f32 = 2.5;
+
+fn clamp_ui_scale(v: f32) -> f32 {
+    if v > 0.0 { v.clamp(UI_SCALE_MIN, UI_SCALE_MAX) } else { 1.0 }
+}
+
+/// A labelled single-line field in a two-column grid; the label is exposed to screen readers.
+fn text_row(ui: &mut egui::Ui, label: &str, value: &mut String) -> bool {
+    let label = ui.label(label);
+    let changed = ui.text_edit_singleline(value).labelled_by(label.id).changed();
+    ui.end_row();
+    changed
+}
+
+/// Like [`text_row`], suggesting values used before in `field`.
+fn recent_row(ui: &mut egui::Ui, label: &str, value: &mut String, (history, field): (&recent::FieldHistory, recent::Field)) -> bool {
+    let label = ui.label(label);
+    let edit = ui.text_edit_singleline(value).labelled_by(label.id);
+    let changed = edit.changed() | history.popup(ui, field, &edit, value);
+    ui.end_row();
+    changed
+}
+
+/// Like [`recent_row`], with an emoji picker and a character counter (emoji count as one).
+fn emoji_row(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut String,
+    max: usize,
+    (history, field): (&recent::FieldHistory, recent::Field),
+) -> bool {
+    let label_resp = ui.label(label);
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        // The field keeps logical order (egui cannot edit reordered text), but RTL text starts at the right.
+        let align = if bidi::is_rtl(value) { egui::Align::RIGHT } else { egui::Align::LEFT };
+        let edit = ui.add(egui::TextEdit::singleline(value).horizontal_align(align)).labelled_by(label_resp.id);
+        changed |= edit.changed() | history.popup(ui, field, &edit, value);
+        changed |= emoji::picker(ui, label, value);
+        let len = text_len(value);
+        if len > max {
+            ui.colored_label(ui.visuals().error_fg_color, format!("{}/{}", len, max));
+        } else if len > 0 {
+            ui.weak(format!("{}/{}", len, max));
+        }
+    });
+    ui.end_row();
+    changed
+}
+
+/// Like [`text_row`], but the value is masked while privacy mode is on.
+fn secret_row(ui: &mut egui::Ui, label: &str, value: &mut String, hidden: bool) -> bool {
+    let label = ui.label(label);
+    let changed = ui
+        .add(egui::TextEdit::singleline(value).password(hidden))
+        .labelled_by(label.id)
+        .changed();
+    ui.end_row();
+    changed
+}
+
+/// Editor for the parameters of one rule condition. Returns `true` when it changed.
+fn condition_ui(ui: &mut egui::Ui, condition: &mut rules::Condition, current_ssid: Option<&str>) -> bool {
+    let mut changed = false;
+    match condition {
+        rules::Condition::Ssid { ssid } => {
+            changed |= ui.add(egui::TextEdit::singleline(ssid).desired_width(120.0)).changed();
+            if let Some(current) = current_ssid {
+                if ui.small_button("current").on_hover_text("Use the network you are on now").clicked() {
+                    *ssid = current.to_string();
+                    changed = true;
+                }
+            }
+        }
+        rules::Condition::Schedule { days, from, to } => {
+            for (day, label) in days.iter_mut().zip(rules::WEEKDAYS) {
+                if ui.selectable_label(*day, label).clicked() {
+                    *day = !*day;
+                    changed = true;
+                }
+            }
+            for (value, hint) in [(from, "from"), (to, "to")] {
+                let valid = rules::parse_hhmm(value).is_some();
+                let mut edit = egui::TextEdit::singleline(value).hint_text(hint).desired_width(45.0);
+                if !valid {
+                    edit = edit.text_color(ui.visuals().error_fg_color);
+                }
+                changed |= ui.add(edit).on_hover_text("HH:MM, local time").changed();
+            }
+        }
+        rules::Condition::Idle { minutes } => {
+            changed |= ui.add(egui::DragValue::new(minutes).clamp_range(1..=1440).suffix(" min")).changed();
+        }
+        rules::Condition::Process { name } => {
+            changed |= ui
+                .add(egui::TextEdit::singleline(name).hint_text("e.g. code").desired_width(120.0))
+                .on_hover_text("Process name, without the path")
+                .changed();
+        }
+        rules::Condition::Media { playing } => {
+            changed |= ui.selectable_value(playing, true, "playing").changed();
+            changed |= ui.selectable_value(playing, false, "not playing").changed();
+        }
+    }
+    changed
+}
+
+/// Field | before | after table for a [`diff::changes`] result.
+fn changes_grid(ui: &mut egui::Ui, id: &str, changes: &[diff::Change], from: &str, to: &str) {
+    egui::ScrollArea::vertical().id_source(id).max_height(320.0).show(ui, |ui| {
+        egui::Grid::new(id).num_columns(3).striped(true).spacing([12.0, 4.0]).show(ui, |ui| {
+            ui.strong("Field");
+            ui.strong(from);
+            ui.strong(to);
+            ui.end_row();
+            for change in changes {
+                ui.label(&change.field);
+                ui.weak(privacy::redact(&change.from));
+                ui.label(privacy::redact(&change.to));
+                ui.end_row();
+            }
+        });
     });
-    Ok(AppMeta { name: resp.name, icon_url })
+}
+
+fn esc_pressed(ctx: &egui::Context) -> bool {
+    ctx.input(|i| i.key_pressed(egui::Key::Escape))
+}
+
+/// Global keyboard shortcuts, read once per frame so each action fires once.
+#[derive(Default)]
+struct Shortcuts {
+    toggle: bool,
+    update: bool,
+    save: bool,
+}
+
+impl Shortcuts {
+    fn read(ctx: &egui::Context) -> Self {
+        let sc = |key| egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, key);
+        ctx.input_mut(|i| Self {
+            toggle: i.consume_shortcut(&sc(egui::Key::E)),
+            update: i.consume_shortcut(&sc(egui::Key::U)),
+            save: i.consume_shortcut(&sc(egui::Key::S)),
+        })
+    }
+}
+
+/// Makes keyboard focus clearly visible: focused widgets use the "active" look,
+/// which gets a thick accent outline here.
+fn apply_style(ctx: &egui::Context) {
+    ctx.style_mut(|style| {
+        let accent = style.visuals.selection.stroke.color;
+        style.visuals.widgets.active.bg_stroke = egui::Stroke::new(2.0, accent);
+        style.visuals.selection.stroke.width = 2.0;
+    });
+}
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("com", "Watashi", "CustomRichPresence")
+}
+
+fn config_path() -> Option<PathBuf> {
+    Some(project_dirs()?.config_dir().join(backup::CONFIG_FILE))
+}
+
+/// Drop-in preset files, see `dropin.rs`.
+fn dropin_dir() -> Option<PathBuf> {
+    Some(project_dirs()?.config_dir().join("presets.d"))
+}
+
+fn log_dir() -> Option<PathBuf> {
+    Some(project_dirs()?.data_dir().join("logs"))
+}
+
+fn crash_dir() -> Option<PathBuf> {
+    Some(project_dirs()?.data_dir().join("crashes"))
+}
+
+/// Where presence images are saved.
+fn export_dir() -> Option<PathBuf> {
+    UserDirs::new()
+        .and_then(|u| u.picture_dir().map(|p| p.join("CustomRichPresence")))
+        .or_else(|| Some(project_dirs()?.data_dir().join("exports")))
+}
+
+/// Where backups and history exports are saved.
+fn backup_dir() -> Option<PathBuf> {
+    UserDirs::new()
+        .and_then(|u| u.document_dir().map(|p| p.join("CustomRichPresence")))
+        .or_else(|| Some(project_dirs()?.data_dir().join("backups")))
 }
 
 fn main() -> eframe::Result<()> {
+    if let Some(dir) = log_dir() {
+        history::init(&dir);
+        logging::init(dir);
+    }
+    if let Some(dir) = crash_dir() {
+        crash::install(dir, config_path());
+    }
+    logging::info(format!("starting v{} on {}", env!("CARGO_PKG_VERSION"), rpc_core::platform()));
+    http::set_cache_dir(project_dirs().map(|d| d.cache_dir().to_path_buf()));
+
     let app = AppState::new();
+    let ui_scale = app.ui_scale;
     let options = eframe::NativeOptions::default();
     eframe::run_native(
         "Custom Rich Presence (Native)",
         options,
-        Box::new(|_cc| Box::new(app)),
+        Box::new(move |cc| {
+            apply_style(&cc.egui_ctx);
+            bidi::install_fallback_fonts(&cc.egui_ctx);
+            // Zoom multiplies the per-monitor DPI scale egui already tracks, so moving the
+            // window between monitors keeps the chosen size.
+            cc.egui_ctx.set_zoom_factor(ui_scale);
+            // Redraw as soon as the worker's status changes instead of on the next timed repaint.
+            let ctx = cc.egui_ctx.clone();
+            let mut watch = app.worker.watch_status();
+            let stop = app.shutdown.token();
+            thread::spawn(move || {
+                while !stop.is_cancelled() {
+                    if watch.changed_timeout(Duration::from_secs(1)).is_some() {
+                        ctx.request_repaint();
+                    }
+                }
+            });
+            Box::new(app)
+        }),
     )
 }
@@ -0,0 +1,100 @@
+//! Settings left in a config folder the app no longer uses: one named after an earlier app
+//! identifier, the folder the Tauri variant uses for its app identifier, or, inside a Flatpak, the
+//! host's own config folder from before the app was installed as one. On a first run (no
+//! `config.json` yet) the app offers to bring them over instead of starting empty.
+//!
+//! The old folder is only read. The Tauri variant keeps its form in the WebView's storage, so from
+//! its folder only files in this app's layout (`config.json`, `presets.d/`) are picked up.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use directories::{BaseDirs, ProjectDirs};
+
+use crate::backup::CONFIG_FILE;
+
+/// Identifier of the Tauri variant (`src-tauri/tauri.conf.json`).
+const TAURI_IDENTIFIER: &str = "com.watashi.custom-rich-presence";
+const DROPIN_DIR: &str = "presets.d";
+const MAX_FILE_LEN: u64 = 8 * 1024 * 1024;
+
+/// An old config folder with something worth bringing over.
+#[derive(Debug, Clone)]
+pub struct Legacy {
+    pub dir: PathBuf,
+    /// Raw `config.json`, if there is one; the caller parses it like a backup's.
+    pub config: Option<Vec<u8>>,
+    /// `*.json` files in its `presets.d/`.
+    pub dropins: Vec<PathBuf>,
+}
+
+/// Folders earlier versions or variants may have used, most likely first. `current` is left out.
+fn candidates(current: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    let mut add = |dir: PathBuf| {
+        if dir != current && !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    };
+    if let Some(p) = ProjectDirs::from("", "", "custom_rich_presence") {
+        add(p.config_dir().to_path_buf());
+    }
+    if let Some(p) = ProjectDirs::from("com", "watashi", "custom-rich-presence") {
+        add(p.config_dir().to_path_buf());
+    }
+    if let Some(base) = BaseDirs::new() {
+        add(base.config_dir().join(TAURI_IDENTIFIER));
+        // Inside a Flatpak the config folder is in the sandbox; the host's is where a build run
+        // before the Flatpak kept it (readable only with a --filesystem permission).
+        if matches!(rpc_core::packaging::detect(), rpc_core::packaging::Packaging::Flatpak { .. }) {
+            if let Some(name) = current.file_name() {
+                add(base.home_dir().join(".config").join(name));
+            }
+        }
+    }
+    dirs
+}
+
+/// The first old folder with a `config.json` or drop-in presets, when `current` has no config yet.
+pub fn find(current: &Path) -> Option<Legacy> {
+    if current.join(CONFIG_FILE).exists() {
+        return None;
+    }
+    candidates(current).into_iter().find_map(|dir| {
+        let config = fs::metadata(dir.join(CONFIG_FILE))
+            .ok()
+            .filter(|m| m.is_file() && m.len() <= MAX_FILE_LEN)
+            .and_then(|_| fs::read(dir.join(CONFIG_FILE)).ok());
+        let dropins = list_dropins(&dir.join(DROPIN_DIR));
+        (config.is_some() || !dropins.is_empty()).then_some(Legacy { dir, config, dropins })
+    })
+}
+
+fn list_dropins(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e.eq_ignore_ascii_case("json")))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Copies the old drop-in presets into `current`'s `presets.d/`, keeping files already there.
+/// Returns how many were copied.
+pub fn copy_dropins(legacy: &Legacy, current: &Path) -> std::io::Result<usize> {
+    let out = current.join(DROPIN_DIR);
+    let mut copied = 0;
+    for file in &legacy.dropins {
+        let Some(name) = file.file_name() else { continue };
+        let target = out.join(name);
+        if target.exists() {
+            continue;
+        }
+        fs::create_dir_all(&out)?;
+        fs::copy(file, &target)?;
+        copied += 1;
+    }
+    Ok(copied)
+}
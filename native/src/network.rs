@@ -0,0 +1,63 @@
+//! Current Wi-Fi network, for rules that switch presets between e.g. office and home.
+//!
+//! There is no portable API for this, so each OS's own tool is asked: `nmcli` (or `iwgetid`) on
+//! Linux, `netsh` on Windows and `networksetup` on macOS. `None` means not on Wi-Fi, or unknown.
+
+use std::process::Command;
+
+/// SSID of the Wi-Fi network this machine is connected to.
+pub fn current_ssid() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        run("nmcli", &["-t", "-f", "active,ssid", "dev", "wifi"])
+            .and_then(|out| out.lines().find_map(|l| l.strip_prefix("yes:").map(unescape_nmcli)))
+            .or_else(|| run("iwgetid", &["-r"]).map(|s| s.trim().to_string()))
+            .filter(|s| !s.is_empty())
+    }
+    #[cfg(windows)]
+    {
+        let out = run("netsh", &["wlan", "show", "interfaces"])?;
+        out.lines()
+            .filter_map(|l| l.split_once(':'))
+            .find(|(k, _)| k.trim() == "SSID")
+            .map(|(_, v)| v.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let out = run("networksetup", &["-getairportnetwork", "en0"])?;
+        out.trim().strip_prefix("Current Wi-Fi Network: ").map(|s| s.to_string())
+    }
+    #[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+    {
+        None
+    }
+}
+
+/// Stdout of a command that succeeded, without flashing a console window on Windows.
+pub(crate) fn run(cmd: &str, args: &[&str]) -> Option<String> {
+    let mut command = Command::new(cmd);
+    command.args(args);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    let out = command.output().ok().filter(|o| o.status.success())?;
+    Some(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// `nmcli -t` escapes `:` and `\` in values with a backslash.
+#[cfg(target_os = "linux")]
+fn unescape_nmcli(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
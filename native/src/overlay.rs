@@ -0,0 +1,43 @@
+//! Status overlay: a small frameless window with the status dot and the preset name, kept above
+//! other apps and transparent to the mouse, so a streamer can tell whether the presence is live
+//! without leaving the game. It sits in a corner of the monitor the main window is on; since
+//! clicks go through it, it is moved by picking another corner. Wayland does not let apps place
+//! windows, and some compositors draw the transparent parts black.
+
+use eframe::egui::{self, Color32, Rect, Vec2};
+
+use crate::dock::{self, Corner};
+
+const SIZE: Vec2 = egui::vec2(240.0, 30.0);
+
+/// Shows the overlay for this frame, in `corner` of the monitor holding the center of `main` (the
+/// main window's outer rect) of size `monitor`. Without them it opens wherever the system puts it.
+pub fn show(ctx: &egui::Context, corner: Corner, main: Option<Rect>, monitor: Option<Vec2>, color: Color32, text: &str) {
+    let mut builder = egui::ViewportBuilder::default()
+        .with_title("Custom Rich Presence status")
+        .with_inner_size(SIZE)
+        .with_decorations(false)
+        .with_resizable(false)
+        .with_transparent(true)
+        .with_taskbar(false)
+        .with_window_level(egui::WindowLevel::AlwaysOnTop)
+        .with_mouse_passthrough(true);
+    if let (Some(main), Some(monitor)) = (main, monitor) {
+        builder = builder.with_position(dock::position(corner, Rect::from_center_size(main.center(), SIZE), monitor));
+    }
+    ctx.show_viewport_immediate(egui::ViewportId::from_hash_of("status_overlay"), builder, |ctx, class| {
+        // Without real windows it would only be a panel inside the main window; nothing to see there.
+        if class == egui::ViewportClass::Embedded {
+            return;
+        }
+        let frame = egui::Frame::none().fill(Color32::from_black_alpha(190)).rounding(8.0).inner_margin(egui::vec2(10.0, 6.0));
+        egui::CentralPanel::default().frame(frame).show(ctx, |ui| {
+            ui.horizontal_centered(|ui| {
+                let (dot, _) = ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                ui.painter().circle_filled(dot.center(), 5.0, color);
+                ui.add(egui::Label::new(egui::RichText::new(text).color(Color32::WHITE)).truncate(true));
+            });
+        });
+    });
+}
+
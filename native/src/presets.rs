@@ -0,0 +1,458 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+use rpc_core::{
+    cycle::ImageCycle,
+    template::has_placeholders,
+    validate::{text_len, MAX_BUTTON_LABEL, MAX_BUTTON_URL, MAX_IMAGE, MAX_MARQUEE_TEXT, MAX_TEXT},
+};
+
+use crate::FormConfig;
+
+/// Share codes are `CRP1:` + base64url(deflate(json)). Bump the prefix when the payload changes shape.
+pub const CODE_PREFIX: &str = "CRP1:";
+const MAX_CODE_LEN: usize = 4096;
+const MAX_JSON_LEN: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Preset {
+    pub name: String,
+    /// Group in the preset list; empty means top level.
+    pub folder: String,
+    pub tags: Vec<String>,
+    /// Name of the preset this one builds on; see [`resolve`].
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub base: String,
+    /// When the preset was last loaded, in unix seconds. Local to this machine: not shared or synced.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<i64>,
+    pub form: FormConfig,
+}
+
+const MAX_FOLDER: usize = 64;
+const MAX_TAGS: usize = 16;
+const MAX_TAG: usize = 32;
+const MAX_CONSTANTS: usize = 16;
+const MAX_CONSTANT_NAME: usize = 32;
+
+impl Preset {
+    /// Whether every word of `query` is found in the name, folder or tags, ignoring case. A word
+    /// starting with `#` only matches a tag, in full.
+    pub fn matches(&self, query: &str) -> bool {
+        let name = self.name.to_lowercase();
+        let folder = self.folder.to_lowercase();
+        query.split_whitespace().map(str::to_lowercase).all(|word| match word.strip_prefix('#') {
+            Some(tag) => self.tags.iter().any(|t| t.to_lowercase() == tag),
+            None => {
+                name.contains(&word) || folder.contains(&word) || self.tags.iter().any(|t| t.to_lowercase().contains(&word))
+            }
+        })
+    }
+}
+
+/// Reads a comma-separated tag list, dropping blanks and repeats (ignoring case).
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Order of the preset list within each folder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresetSort {
+    /// The order presets were saved in.
+    #[default]
+    Saved,
+    Name,
+    Recent,
+}
+
+impl PresetSort {
+    pub const ALL: [PresetSort; 3] = [PresetSort::Saved, PresetSort::Name, PresetSort::Recent];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PresetSort::Saved => "Saved order",
+            PresetSort::Name => "Name",
+            PresetSort::Recent => "Last used",
+        }
+    }
+}
+
+/// Adds `incoming` to `presets`, skipping any identical to one already there. One whose name is
+/// taken by a different preset is added as "name (imported)", "name (imported 2)" and so on.
+/// Returns how many were added.
+pub fn merge(presets: &mut Vec<Preset>, incoming: Vec<Preset>) -> usize {
+    let mut added = 0;
+    for mut p in incoming {
+        if presets.iter().any(|q| q.name == p.name && q.form == p.form) {
+            continue;
+        }
+        if presets.iter().any(|q| q.name == p.name) {
+            let base = p.name.clone();
+            p.name = (1..)
+                .map(|n| if n == 1 { format!("{} (imported)", base) } else { format!("{} (imported {})", base, n) })
+                .find(|name| !presets.iter().any(|q| &q.name == name))
+                .unwrap_or(base);
+        }
+        presets.push(p);
+        added += 1;
+    }
+    added
+}
+
+/// How many bases deep [`resolve`] follows, which also ends loops.
+const MAX_BASE_DEPTH: usize = 8;
+
+fn text_fields(f: &mut FormConfig) -> [&mut String; 11] {
+    [
+        &mut f.client_id,
+        &mut f.details,
+        &mut f.state,
+        &mut f.large_image,
+        &mut f.large_text,
+        &mut f.small_image,
+        &mut f.small_text,
+        &mut f.b1label,
+        &mut f.b1url,
+        &mut f.b2label,
+        &mut f.b2url,
+    ]
+}
+
+/// The form `preset` stands for: text fields it leaves blank, constants it does not define and an
+/// empty image cycle come from its base preset (looked up by name with `find`), and so on up the
+/// chain. Timer, refresh and the other settings are always the preset's own.
+pub fn resolve<'a>(preset: &Preset, find: impl Fn(&str) -> Option<&'a Preset>) -> FormConfig {
+    let mut form = preset.form.clone();
+    let mut seen = vec![preset.name.as_str()];
+    let mut base_name = preset.base.as_str();
+    while !base_name.is_empty() && !seen.contains(&base_name) && seen.len() <= MAX_BASE_DEPTH {
+        let Some(base) = find(base_name) else { break };
+        let mut inherited = base.form.clone();
+        for (own, from_base) in text_fields(&mut form).into_iter().zip(text_fields(&mut inherited)) {
+            if own.trim().is_empty() {
+                *own = std::mem::take(from_base);
+            }
+        }
+        for c in inherited.constants {
+            if !form.constants.iter().any(|own| own.name.trim() == c.name.trim()) {
+                form.constants.push(c);
+            }
+        }
+        if form.image_cycle.images.iter().all(|i| i.trim().is_empty()) {
+            form.image_cycle = inherited.image_cycle;
+        }
+        seen.push(base_name);
+        base_name = base.base.as_str();
+    }
+    form
+}
+
+/// `form` as stored in a preset based on a preset resolving to `base`: what matches the base is
+/// left out, so it keeps following the base.
+pub fn strip_inherited(form: &FormConfig, base: &FormConfig) -> FormConfig {
+    let mut own = form.clone();
+    let mut base = base.clone();
+    for (v, from_base) in text_fields(&mut own).into_iter().zip(text_fields(&mut base)) {
+        if v.trim() == from_base.trim() {
+            v.clear();
+        }
+    }
+    own.constants.retain(|c| !base.constants.contains(c));
+    if own.image_cycle == base.image_cycle {
+        own.image_cycle.images.clear();
+    }
+    own
+}
+
+/// Whether `preset` is the preset called `name` or builds on it, directly or further up, so
+/// basing `name` on `preset` would loop.
+pub fn builds_on<'a>(preset: &'a Preset, name: &str, find: impl Fn(&str) -> Option<&'a Preset>) -> bool {
+    let mut current = Some(preset);
+    for _ in 0..=MAX_BASE_DEPTH {
+        let Some(p) = current else { return false };
+        if p.name == name {
+            return true;
+        }
+        current = find(&p.base);
+    }
+    true
+}
+
+/// Indices of the `n` most recently loaded presets, newest first.
+pub fn recent(presets: &[Preset], n: usize) -> Vec<usize> {
+    let mut used: Vec<usize> = (0..presets.len()).filter(|&i| presets[i].last_used.is_some()).collect();
+    used.sort_by(|&a, &b| presets[b].last_used.cmp(&presets[a].last_used));
+    used.truncate(n);
+    used
+}
+
+/// Indices of the presets matching `query`, grouped by folder (top level first, then folders by
+/// name) and sorted within each folder.
+pub fn listing(presets: &[Preset], query: &str, sort: PresetSort) -> Vec<usize> {
+    let mut picked: Vec<usize> = (0..presets.len()).filter(|&i| presets[i].matches(query)).collect();
+    picked.sort_by(|&a, &b| {
+        let (a, b) = (&presets[a], &presets[b]);
+        let by_folder = (!a.folder.is_empty(), a.folder.to_lowercase()).cmp(&(!b.folder.is_empty(), b.folder.to_lowercase()));
+        by_folder.then_with(|| match sort {
+            PresetSort::Saved => std::cmp::Ordering::Equal,
+            PresetSort::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            PresetSort::Recent => b.last_used.cmp(&a.last_used),
+        })
+    });
+    picked
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SharePayload {
+    v: u32,
+    preset: Preset,
+}
+
+pub fn encode_share_code(preset: &Preset) -> anyhow::Result<String> {
+    // Discord instance targets are paths on this machine; they mean nothing elsewhere.
+    let mut preset = preset.clone();
+    preset.form.targets.clear();
+    preset.last_used = None;
+    let json = serde_json::to_vec(&SharePayload { v: 1, preset })?;
+    let mut enc = DeflateEncoder::new(Vec::new(), Compression::best());
+    enc.write_all(&json)?;
+    Ok(format!("{}{}", CODE_PREFIX, URL_SAFE_NO_PAD.encode(enc.finish()?)))
+}
+
+/// Decodes and validates a share code. Errors are meant to be shown to the user as-is.
+pub fn decode_share_code(code: &str) -> Result<Preset, String> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() > MAX_CODE_LEN {
+        return Err("Code is too long.".to_string());
+    }
+    let body = code
+        .strip_prefix(CODE_PREFIX)
+        .ok_or_else(|| format!("Not a preset code (expected it to start with {}).", CODE_PREFIX))?;
+    let packed = URL_SAFE_NO_PAD
+        .decode(body)
+        .map_err(|_| "Code is damaged (invalid base64).".to_string())?;
+
+    let mut json = Vec::new();
+    DeflateDecoder::new(packed.as_slice())
+        .take(MAX_JSON_LEN as u64 + 1)
+        .read_to_end(&mut json)
+        .map_err(|_| "Code is damaged (cannot decompress).".to_string())?;
+    if json.len() > MAX_JSON_LEN {
+        return Err("Code is too large.".to_string());
+    }
+
+    let mut payload: SharePayload =
+        serde_json::from_slice(&json).map_err(|e| format!("Code does not contain a valid preset: {}", e))?;
+    // Never let a shared code pick which local sockets the app connects to.
+    payload.preset.form.targets.clear();
+    payload.preset.last_used = None;
+    if payload.v != 1 {
+        return Err(format!("Preset code version {} is not supported by this app.", payload.v));
+    }
+    validate(&payload.preset)?;
+    Ok(payload.preset)
+}
+
+pub fn validate(p: &Preset) -> Result<(), String> {
+    let f = &p.form;
+    let name = p.name.trim();
+    if name.is_empty() || text_len(name) > 64 {
+        return Err("Preset name must have 1 to 64 characters.".to_string());
+    }
+    if text_len(&p.base) > 64 {
+        return Err("Base preset name is longer than 64 characters.".to_string());
+    }
+    if text_len(&p.folder) > MAX_FOLDER {
+        return Err(format!("Folder name is longer than {} characters.", MAX_FOLDER));
+    }
+    if p.tags.len() > MAX_TAGS || p.tags.iter().any(|t| text_len(t) > MAX_TAG) {
+        return Err(format!("A preset can have at most {} tags of up to {} characters.", MAX_TAGS, MAX_TAG));
+    }
+    let id = f.client_id.trim();
+    if !id.is_empty() && (!id.chars().all(|c| c.is_ascii_digit()) || id.len() < 15 || id.len() > 21) {
+        return Err("Client ID must be a numeric Discord application id.".to_string());
+    }
+    let line_max = if f.marquee.enabled { MAX_MARQUEE_TEXT } else { MAX_TEXT };
+    for (label, v, max) in [
+        ("Details", &f.details, line_max),
+        ("State", &f.state, line_max),
+        ("Large image", &f.large_image, MAX_IMAGE),
+        ("Large text", &f.large_text, MAX_TEXT),
+        ("Small image", &f.small_image, MAX_IMAGE),
+        ("Small text", &f.small_text, MAX_TEXT),
+        ("Button 1 label", &f.b1label, MAX_BUTTON_LABEL),
+        ("Button 2 label", &f.b2label, MAX_BUTTON_LABEL),
+        ("Button 1 url", &f.b1url, MAX_BUTTON_URL),
+        ("Button 2 url", &f.b2url, MAX_BUTTON_URL),
+    ] {
+        if text_len(v) > max {
+            return Err(format!("{} is longer than {} characters.", label, max));
+        }
+    }
+    let images: Vec<&String> = f.image_cycle.images.iter().filter(|i| !i.trim().is_empty()).collect();
+    if images.len() > ImageCycle::MAX_IMAGES {
+        return Err(format!("At most {} images can be cycled.", ImageCycle::MAX_IMAGES));
+    }
+    if images.iter().any(|i| text_len(i) > MAX_IMAGE) {
+        return Err(format!("Cycled images must be at most {} characters.", MAX_IMAGE));
+    }
+    if f.constants.len() > MAX_CONSTANTS {
+        return Err(format!("A preset can have at most {} constants.", MAX_CONSTANTS));
+    }
+    if f.constants.iter().any(|c| text_len(&c.name) > MAX_CONSTANT_NAME || text_len(&c.value) > MAX_BUTTON_URL) {
+        return Err(format!("Constant names can have up to {} characters and values up to {}.", MAX_CONSTANT_NAME, MAX_BUTTON_URL));
+    }
+    for (label, url) in [("Button 1 url", &f.b1url), ("Button 2 url", &f.b2url)] {
+        let url = url.trim();
+        if !url.is_empty() && !has_placeholders(url) && !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(format!("{} must be an http(s) link.", label));
+        }
+    }
+    if f.with_timestamp && f.timer_mode.needs_time() {
+        crate::parse_local_time(&f.timer_at).map_err(|e| format!("Timer: {}", e))?;
+    }
+    Ok(())
+}
+
+/// QR modules for `code`, row-major (`true` = dark), with the side length.
+pub fn qr_modules(code: &str) -> Option<(usize, Vec<bool>)> {
+    let qr = qrcode::QrCode::with_error_correction_level(code.as_bytes(), qrcode::EcLevel::L).ok()?;
+    let width = qr.width();
+    let modules = qr.to_colors().into_iter().map(|c| c == qrcode::Color::Dark).collect();
+    Some((width, modules))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Constant;
+
+    fn preset(name: &str, base: &str, edit: impl FnOnce(&mut FormConfig)) -> Preset {
+        let mut form = FormConfig::default();
+        edit(&mut form);
+        Preset { name: name.to_string(), base: base.to_string(), form, ..Preset::default() }
+    }
+
+    fn constant(name: &str, value: &str) -> Constant {
+        Constant { name: name.to_string(), value: value.to_string() }
+    }
+
+    fn lookup<'a>(presets: &'a [Preset]) -> impl Fn(&str) -> Option<&'a Preset> {
+        move |name| presets.iter().find(|p| p.name == name)
+    }
+
+    /// `n` presets where each builds on the one before and only the first has details.
+    fn chain(n: usize) -> Vec<Preset> {
+        (0..n)
+            .map(|i| {
+                let base = if i == 0 { String::new() } else { format!("p{}", i - 1) };
+                preset(&format!("p{}", i), &base, |f| {
+                    if i == 0 {
+                        f.details = "root".to_string();
+                    }
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn blank_fields_come_from_the_base() {
+        let all = [
+            preset("brand", "", |f| {
+                f.details = "Streaming".to_string();
+                f.state = "Live".to_string();
+                f.b1label = "Site".to_string();
+            }),
+            preset("game", "brand", |f| {
+                f.details = "Playing".to_string();
+                f.state = "  ".to_string();
+            }),
+        ];
+        let form = resolve(&all[1], lookup(&all));
+        assert_eq!(form.details, "Playing");
+        assert_eq!(form.state, "Live");
+        assert_eq!(form.b1label, "Site");
+    }
+
+    #[test]
+    fn constants_merge_and_own_ones_win() {
+        let all = [
+            preset("brand", "", |f| f.constants = vec![constant("team", "Red"), constant("site", "example.com")]),
+            preset("game", "brand", |f| f.constants = vec![constant(" team ", "Blue")]),
+        ];
+        let form = resolve(&all[1], lookup(&all));
+        assert_eq!(form.constants, vec![constant(" team ", "Blue"), constant("site", "example.com")]);
+    }
+
+    #[test]
+    fn an_empty_image_cycle_is_taken_whole_from_the_base() {
+        let all = [
+            preset("brand", "", |f| {
+                f.image_cycle.images = vec!["a".to_string(), "b".to_string()];
+                f.image_cycle.interval_secs = 45;
+            }),
+            preset("blank", "brand", |f| f.image_cycle.images = vec![" ".to_string()]),
+            preset("own", "brand", |f| f.image_cycle.images = vec!["c".to_string()]),
+        ];
+        assert_eq!(resolve(&all[1], lookup(&all)).image_cycle, all[0].form.image_cycle);
+        assert_eq!(resolve(&all[2], lookup(&all)).image_cycle, all[2].form.image_cycle);
+    }
+
+    #[test]
+    fn loops_and_long_chains_stop() {
+        let looped = [preset("a", "b", |_| {}), preset("b", "a", |f| f.details = "from b".to_string())];
+        assert_eq!(resolve(&looped[0], lookup(&looped)).details, "from b");
+
+        let all = chain(12);
+        assert_eq!(resolve(&all[MAX_BASE_DEPTH], lookup(&all)).details, "root");
+        assert_eq!(resolve(&all[MAX_BASE_DEPTH + 1], lookup(&all)).details, "");
+    }
+
+    #[test]
+    fn builds_on_follows_the_chain() {
+        let all = chain(3);
+        assert!(builds_on(&all[2], "p0", lookup(&all)));
+        assert!(builds_on(&all[1], "p1", lookup(&all)));
+        assert!(!builds_on(&all[0], "p2", lookup(&all)));
+
+        // Too deep or looping counts as building on it, so such a base cannot be picked.
+        let all = chain(12);
+        assert!(builds_on(&all[11], "p0", lookup(&all)));
+        let looped = [preset("a", "b", |_| {}), preset("b", "a", |_| {})];
+        assert!(builds_on(&looped[0], "c", lookup(&looped)));
+    }
+
+    #[test]
+    fn saving_over_a_based_preset_keeps_what_it_resolves_to() {
+        let mut all = vec![
+            preset("brand", "", |f| {
+                f.details = "Streaming".to_string();
+                f.b1label = "Site".to_string();
+                f.constants = vec![constant("site", "example.com")];
+                f.image_cycle.images = vec!["a".to_string()];
+            }),
+            preset("game", "brand", |f| f.details = "Playing".to_string()),
+        ];
+        let mut edited = resolve(&all[1], lookup(&all));
+        edited.state = "Ranked".to_string();
+        edited.b1label = "Site".to_string();
+
+        let base = resolve(&all[0], lookup(&all));
+        all[1].form = strip_inherited(&edited, &base);
+        assert_eq!(all[1].form.b1label, "", "a value equal to the base keeps following it");
+        assert!(all[1].form.constants.is_empty());
+        assert!(all[1].form.image_cycle.images.is_empty());
+        assert_eq!(resolve(&all[1], lookup(&all)), edited);
+    }
+}
@@ -0,0 +1,236 @@
+use eframe::egui::{self, Color32, RichText, Rounding, Stroke};
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder},
+    AnimationDecoder, ImageFormat,
+};
+use rpc_core::{PresenceCfg, TimerPolicy};
+use std::{io::Cursor, path::Path, time::Duration};
+
+use crate::{bidi, clock};
+
+const CARD_BG: Color32 = Color32::from_rgb(0x23, 0x24, 0x28);
+const ART_BG: Color32 = Color32::from_rgb(0x38, 0x3a, 0x40);
+const BUTTON_BG: Color32 = Color32::from_rgb(0x4e, 0x50, 0x58);
+const MUTED: Color32 = Color32::from_rgb(0xb5, 0xba, 0xc1);
+
+/// What the card's timer line shows.
+#[derive(Debug, Clone, Copy)]
+pub enum Timer {
+    Elapsed(i64),
+    Left(i64),
+}
+
+/// Timer line for `cfg` at `now`, when the session started at `session_start`.
+pub fn timer(cfg: &PresenceCfg, session_start: i64, now: i64) -> Option<Timer> {
+    if !cfg.with_timestamp {
+        return None;
+    }
+    Some(match cfg.timer {
+        TimerPolicy::Fixed { start } => Timer::Elapsed(now - start),
+        TimerPolicy::End { end } => Timer::Left(end - now),
+        TimerPolicy::Continue | TimerPolicy::Reset => Timer::Elapsed(now - session_start),
+    })
+}
+
+/// Paints a Discord-like activity card for `cfg`. `timer` is `None` when the timestamp is off.
+pub fn card(ui: &mut egui::Ui, cfg: &PresenceCfg, app_name: &str, timer: Option<Timer>) -> egui::Response {
+    let resp = egui::Frame::none()
+        .fill(CARD_BG)
+        .rounding(Rounding::same(8.0))
+        .inner_margin(egui::Margin::same(12.0))
+        .show(ui, |ui| {
+            ui.set_width(300.0);
+            ui.label(RichText::new("PLAYING A GAME").small().strong().color(MUTED));
+            ui.add_space(6.0);
+
+            ui.horizontal(|ui| {
+                art(ui, cfg);
+                ui.vertical(|ui| {
+                    let name = if app_name.trim().is_empty() { "App" } else { app_name };
+                    ui.label(RichText::new(name).strong().color(Color32::WHITE));
+                    if !cfg.details.is_empty() {
+                        text_line(ui, &cfg.details);
+                    }
+                    if !cfg.state.is_empty() {
+                        text_line(ui, &cfg.state);
+                    }
+                    let now = rpc_core::now_unix_ts();
+                    match timer {
+                        Some(Timer::Elapsed(secs)) => {
+                            ui.label(RichText::new(fmt_elapsed(secs)).color(MUTED))
+                                .on_hover_text(format!("Started {}", clock::fmt_date_time(now - secs)));
+                        }
+                        Some(Timer::Left(secs)) => {
+                            ui.label(RichText::new(fmt_left(secs)).color(MUTED))
+                                .on_hover_text(format!("Ends {}", clock::fmt_date_time(now + secs)));
+                        }
+                        None => {}
+                    }
+                });
+            });
+
+            let buttons: Vec<_> = cfg
+                .buttons
+                .iter()
+                .filter(|b| !b.label.trim().is_empty() && !b.url.trim().is_empty())
+                .take(2)
+                .collect();
+            if !buttons.is_empty() {
+                ui.add_space(8.0);
+            }
+            for b in buttons {
+                egui::Frame::none()
+                    .fill(BUTTON_BG)
+                    .rounding(Rounding::same(3.0))
+                    .inner_margin(egui::Margin::symmetric(8.0, 5.0))
+                    .show(ui, |ui| {
+                        ui.set_width(ui.available_width());
+                        ui.vertical_centered(|ui| {
+                            ui.label(RichText::new(bidi::display(b.label.trim())).color(Color32::WHITE));
+                        });
+                    });
+                ui.add_space(4.0);
+            }
+        })
+        .response;
+
+    // The card is painted, so give screen readers a plain-text summary of it.
+    let summary = [app_name, &cfg.details, &cfg.state]
+        .iter()
+        .filter(|s| !s.trim().is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(", ");
+    resp.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Other, format!("Presence preview: {}", summary)));
+    resp
+}
+
+/// One line of user text, reordered for display and right-aligned when it reads right to left.
+fn text_line(ui: &mut egui::Ui, text: &str) {
+    let label = RichText::new(bidi::display(text)).color(Color32::WHITE);
+    if bidi::is_rtl(text) {
+        ui.with_layout(egui::Layout::top_down(egui::Align::Max), |ui| ui.label(label));
+    } else {
+        ui.label(label);
+    }
+}
+
+/// Image placeholders: real assets are not downloaded, so the key is drawn instead.
+fn art(ui: &mut egui::Ui, cfg: &PresenceCfg) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(64.0, 64.0), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, Rounding::same(6.0), ART_BG);
+    if let Some(key) = &cfg.large_image {
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            short(key, 8),
+            egui::FontId::proportional(11.0),
+            MUTED,
+        );
+    }
+    if let Some(key) = &cfg.small_image {
+        let c = rect.right_bottom() - egui::vec2(4.0, 4.0);
+        painter.circle(c, 10.0, BUTTON_BG, Stroke::new(3.0, CARD_BG));
+        painter.text(c, egui::Align2::CENTER_CENTER, short(key, 2), egui::FontId::proportional(9.0), Color32::WHITE);
+    }
+}
+
+fn short(s: &str, n: usize) -> String {
+    let s = s.rsplit('/').next().unwrap_or(s);
+    s.chars().take(n).collect()
+}
+
+pub fn fmt_elapsed(secs: i64) -> String {
+    format!("{} elapsed", fmt_clock(secs))
+}
+
+pub fn fmt_left(secs: i64) -> String {
+    format!("{} left", fmt_clock(secs))
+}
+
+fn fmt_clock(secs: i64) -> String {
+    let secs = secs.max(0);
+    if secs >= 3600 {
+        format!("{:02}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Paints a QR code (`(side, modules)` from `presets::qr_modules`) on a white quiet zone.
+pub fn qr(ui: &mut egui::Ui, (side, modules): &(usize, Vec<bool>), size: f32, description: &str) {
+    let (rect, resp) = ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::hover());
+    resp.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Other, description));
+    let painter = ui.painter();
+    painter.rect_filled(rect, Rounding::ZERO, Color32::WHITE);
+
+    let cell = size / (*side as f32 + 8.0);
+    let origin = rect.min + egui::vec2(cell * 4.0, cell * 4.0);
+    for (i, dark) in modules.iter().enumerate() {
+        if *dark {
+            let (x, y) = ((i % side) as f32, (i / side) as f32);
+            let min = origin + egui::vec2(x * cell, y * cell);
+            painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(cell, cell)), Rounding::ZERO, Color32::BLACK);
+        }
+    }
+}
+
+/// Frames of a PNG, APNG or GIF with how long each stays up; a still image is a single frame.
+pub fn decode_frames(bytes: &[u8]) -> Option<Vec<(egui::ColorImage, Duration)>> {
+    let frames = match image::guess_format(bytes).ok()? {
+        ImageFormat::Gif => GifDecoder::new(Cursor::new(bytes)).ok()?.into_frames().collect_frames().ok()?,
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(bytes)).ok()?;
+            if !decoder.is_apng() {
+                let still = image::load_from_memory_with_format(bytes, ImageFormat::Png).ok()?.to_rgba8();
+                return Some(vec![(color_image(&still), Duration::ZERO)]);
+            }
+            decoder.apng().into_frames().collect_frames().ok()?
+        }
+        _ => return None,
+    };
+    let frames: Vec<_> = frames
+        .into_iter()
+        .map(|f| {
+            let (num, den) = f.delay().numer_denom_ms();
+            // Browsers show frames shorter than 20 ms (often 0) for 100 ms; match them.
+            let ms = num.checked_div(den).filter(|ms| *ms >= 20).unwrap_or(100);
+            (color_image(f.buffer()), Duration::from_millis(ms as u64))
+        })
+        .collect();
+    (!frames.is_empty()).then_some(frames)
+}
+
+fn color_image(rgba: &image::RgbaImage) -> egui::ColorImage {
+    egui::ColorImage::from_rgba_unmultiplied([rgba.width() as usize, rgba.height() as usize], rgba.as_raw())
+}
+
+/// The frame to show `time` seconds into a looping animation, and how long until the next one
+/// (`None` for a still image).
+pub fn frame_at<T>(frames: &[(T, Duration)], time: f64) -> (&T, Option<Duration>) {
+    let total: Duration = frames.iter().map(|f| f.1).sum();
+    if frames.len() < 2 || total.is_zero() {
+        return (&frames[0].0, None);
+    }
+    let mut t = Duration::from_secs_f64(time.max(0.0) % total.as_secs_f64());
+    for (frame, delay) in frames {
+        if t < *delay {
+            return (frame, Some(*delay - t));
+        }
+        t -= *delay;
+    }
+    (&frames[0].0, Some(frames[0].1))
+}
+
+pub fn save_png(image: &egui::ColorImage, path: &Path) -> anyhow::Result<()> {
+    let [w, h] = image.size;
+    let bytes: Vec<u8> = image.pixels.iter().flat_map(|c| c.to_srgba_unmultiplied()).collect();
+    let buf = image::RgbaImage::from_raw(w as u32, h as u32, bytes)
+        .ok_or_else(|| anyhow::anyhow!("screenshot has an unexpected size"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    buf.save_with_format(path, image::ImageFormat::Png)?;
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! Streamer privacy mode: masks Discord ids in everything the app shows or writes.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const REDACTED: &str = "<redacted>";
+/// Discord snowflakes are 17-20 digits; anything this long is treated as an id.
+const MIN_ID_DIGITS: usize = 15;
+
+/// String fields masked wherever they appear in a traced frame (numeric error `code`s stay).
+const SECRET_KEYS: [&str; 4] = ["access_token", "refresh_token", "token", "code"];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_enabled(on: bool) {
+    ENABLED.store(on, Ordering::SeqCst);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// Returns `s` with Discord ids masked while privacy mode is on, unchanged otherwise.
+pub fn redact(s: &str) -> String {
+    if !enabled() {
+        return s.to_string();
+    }
+    mask_digit_runs(s)
+}
+
+/// Pretty-prints a handshake READY payload with the user id and avatar hash masked,
+/// regardless of privacy mode. The username stays so the connected account can be identified.
+pub fn redact_ready(ready: &serde_json::Value) -> String {
+    let mut v = ready.clone();
+    mask_user(&mut v);
+    serde_json::to_string_pretty(&v).unwrap_or_default()
+}
+
+/// One-line IPC frame for the protocol trace: the READY user's id/avatar and any token-like
+/// fields are always masked; ids elsewhere follow privacy mode via `redact`.
+pub fn redact_frame(frame: &serde_json::Value) -> String {
+    let mut v = frame.clone();
+    mask_user(&mut v);
+    mask_secrets(&mut v);
+    v.to_string()
+}
+
+fn mask_user(v: &mut serde_json::Value) {
+    if let Some(user) = v.pointer_mut("/data/user").and_then(|u| u.as_object_mut()) {
+        for key in ["id", "avatar", "email"] {
+            if let Some(field) = user.get_mut(key) {
+                if field.as_str().is_some_and(|s| !s.is_empty()) {
+                    *field = serde_json::Value::String(REDACTED.to_string());
+                }
+            }
+        }
+    }
+}
+
+fn mask_secrets(v: &mut serde_json::Value) {
+    match v {
+        serde_json::Value::Object(map) => {
+            for (k, field) in map.iter_mut() {
+                if SECRET_KEYS.contains(&k.as_str()) && field.is_string() {
+                    *field = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    mask_secrets(field);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(mask_secrets),
+        _ => {}
+    }
+}
+
+fn mask_digit_runs(s: &str) -> String {
+    fn flush(run: &mut String, out: &mut String) {
+        out.push_str(if run.len() >= MIN_ID_DIGITS { REDACTED } else { run.as_str() });
+        run.clear();
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut run = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            run.push(c);
+        } else {
+            flush(&mut run, &mut out);
+            out.push(c);
+        }
+    }
+    flush(&mut run, &mut out);
+    out
+}
@@ -0,0 +1,101 @@
+//! Values recently used in the form's text fields, offered as suggestions while typing. A value
+//! counts as used once it is sent to Discord or saved in a preset, so half-typed text never shows
+//! up. Both button labels share one list, as do both button URLs.
+
+use std::collections::BTreeMap;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// Values kept per field, most recent first.
+const MAX_PER_FIELD: usize = 20;
+/// Suggestions shown at once.
+const MAX_SHOWN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    Details,
+    State,
+    LargeImage,
+    LargeText,
+    SmallImage,
+    SmallText,
+    ButtonLabel,
+    ButtonUrl,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FieldHistory(BTreeMap<Field, Vec<String>>);
+
+impl FieldHistory {
+    /// Moves `value` to the front of `field`'s list. Blank values are ignored.
+    pub fn record(&mut self, field: Field, value: &str) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        let list = self.0.entry(field).or_default();
+        list.retain(|v| v != value);
+        list.insert(0, value.to_string());
+        list.truncate(MAX_PER_FIELD);
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Earlier values of `field` containing `typed` (ignoring case), other than `typed` itself.
+    fn suggestions(&self, field: Field, typed: &str) -> Vec<&str> {
+        let typed_lower = typed.trim().to_lowercase();
+        self.0
+            .get(&field)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .filter(|v| *v != typed.trim() && v.to_lowercase().contains(&typed_lower))
+            .take(MAX_SHOWN)
+            .collect()
+    }
+
+    /// Shows the suggestions for `field` under the text edit `edit` while it has focus. Returns
+    /// whether one was picked into `value`.
+    pub fn popup(&self, ui: &egui::Ui, field: Field, edit: &egui::Response, value: &mut String) -> bool {
+        let id = edit.id.with("recent");
+        let suggestions = self.suggestions(field, value);
+        if edit.has_focus() && !suggestions.is_empty() {
+            ui.memory_mut(|m| m.open_popup(id));
+        }
+        let mut picked = None;
+        egui::popup_below_widget(ui, id, edit, |ui| {
+            ui.set_min_width(edit.rect.width());
+            for s in &suggestions {
+                if ui.selectable_label(false, *s).clicked() {
+                    picked = Some(s.to_string());
+                }
+            }
+        });
+        // Pressing on a suggestion takes the focus from the field; keep the list until the click ends.
+        let closing = suggestions.is_empty() || (!edit.has_focus() && !ui.input(|i| i.pointer.any_down()));
+        if closing && ui.memory(|m| m.is_popup_open(id)) {
+            ui.memory_mut(|m| m.close_popup());
+        }
+        match picked {
+            Some(v) => {
+                *value = v;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
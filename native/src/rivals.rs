@@ -0,0 +1,43 @@
+//! Other presence tools that may be running. Discord shows one activity per account, and several
+//! apps setting one take turns replacing each other, so the presence flickers or vanishes without
+//! any error here. Discord's IPC does not tell a client that its activity was replaced, so this
+//! looks for known tools in the process list the rule triggers already sample.
+
+use crate::rules::process_name;
+
+/// Shown for `presence-daemon`, the one tool that can be asked to let go; see `takeover`.
+pub const DAEMON: &str = "presence-daemon";
+
+/// Process names (lowercase, without `.exe`) of tools that set a Discord activity, with the name
+/// shown to the user.
+const KNOWN: &[(&str, &str)] = &[
+    ("premid", "PreMiD"),
+    ("customrp", "CustomRP"),
+    ("easyrp", "EasyRP"),
+    ("discordrpcmaker", "Discord RPC Maker"),
+    ("discord-rpc-maker", "Discord RPC Maker"),
+    ("custom_rich_presence", "Custom Rich Presence (desktop)"),
+    ("custom_rich_presence_native", "Custom Rich Presence (native)"),
+    ("presence-daemon", DAEMON),
+];
+
+/// Known presence tools among `processes`, by display name. This app's own executable is left out.
+pub fn running(processes: &[String]) -> Vec<&'static str> {
+    let own = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| process_name(&n.to_string_lossy())))
+        .unwrap_or_default();
+    let mut found: Vec<&'static str> = Vec::new();
+    for p in processes {
+        let name = process_name(p);
+        if name == own {
+            continue;
+        }
+        if let Some((_, label)) = KNOWN.iter().find(|(k, _)| *k == name) {
+            if !found.contains(label) {
+                found.push(label);
+            }
+        }
+    }
+    found
+}
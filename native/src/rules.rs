@@ -0,0 +1,324 @@
+//! Rules that apply a preset automatically while a condition holds: `when <condition> then apply
+//! <preset>`. Schedule, idle, process, media and Wi-Fi triggers are all conditions here, so they
+//! compose: of the enabled rules that match, the one with the highest priority wins, and on a tie
+//! the one listed first.
+//!
+//! Schedule times are wall-clock times in the system's time zone, so "09:00" stays 09:00 across
+//! DST changes and when the machine moves to another zone. Each check turns them into instants in
+//! the current zone ([`local_instant`]), which is where DST's skipped and repeated hours are settled.
+
+use chrono::{DateTime, Datelike, Duration, Local, LocalResult, NaiveDateTime, NaiveTime, Offset, TimeZone};
+use serde::{Deserialize, Serialize};
+
+pub const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// What a rule waits for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "when", rename_all = "snake_case")]
+pub enum Condition {
+    /// Connected to the Wi-Fi network with this SSID (case-sensitive, like SSIDs themselves).
+    Ssid { ssid: String },
+    /// Local time is within `from..to` (`HH:MM`) on one of `days` (Monday first). A range that
+    /// ends before it starts runs past midnight, and counts for the day it started on.
+    Schedule { days: [bool; 7], from: String, to: String },
+    /// No keyboard or mouse input for at least this many minutes.
+    Idle { minutes: u32 },
+    /// A process with this name is running, ignoring case and a trailing `.exe`.
+    Process { name: String },
+    /// A media player is playing (or, with `playing: false`, none is).
+    Media { playing: bool },
+}
+
+impl Default for Condition {
+    fn default() -> Self {
+        Condition::Ssid { ssid: String::new() }
+    }
+}
+
+impl Condition {
+    /// One of each kind, for the rule editor's kind picker.
+    pub fn kinds() -> [Condition; 5] {
+        [
+            Condition::Ssid { ssid: String::new() },
+            Condition::Schedule { days: [true, true, true, true, true, false, false], from: "09:00".into(), to: "17:00".into() },
+            Condition::Idle { minutes: 10 },
+            Condition::Process { name: String::new() },
+            Condition::Media { playing: true },
+        ]
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Condition::Ssid { .. } => "Wi-Fi is",
+            Condition::Schedule { .. } => "Time is",
+            Condition::Idle { .. } => "Idle for",
+            Condition::Process { .. } => "Running",
+            Condition::Media { .. } => "Media",
+        }
+    }
+
+    pub fn matches(&self, inputs: &Inputs) -> bool {
+        match self {
+            Condition::Ssid { ssid } => !ssid.is_empty() && inputs.ssid.as_deref() == Some(ssid.as_str()),
+            Condition::Schedule { days, from, to } => {
+                let (Some(from), Some(to)) = (parse_hhmm(from), parse_hhmm(to)) else { return false };
+                in_window(&Local, days, from, to, inputs.now)
+            }
+            Condition::Idle { minutes } => inputs.idle_secs.is_some_and(|s| s >= u64::from(*minutes) * 60),
+            Condition::Process { name } => {
+                let name = process_name(name);
+                !name.is_empty() && inputs.processes.iter().any(|p| process_name(p) == name)
+            }
+            Condition::Media { playing } => inputs.media_playing == Some(*playing),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rule {
+    pub enabled: bool,
+    /// Higher wins when several rules match.
+    pub priority: i32,
+    pub condition: Condition,
+    /// Name of the preset to apply.
+    pub preset: String,
+}
+
+/// What the conditions are evaluated against. Fields a platform cannot detect stay `None`, and
+/// conditions on them never match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inputs {
+    pub ssid: Option<String>,
+    /// Unix time, in seconds.
+    pub now: i64,
+    pub idle_secs: Option<u64>,
+    /// Names of running processes.
+    pub processes: Vec<String>,
+    pub media_playing: Option<bool>,
+}
+
+/// Index of the rule that applies to `inputs`, if any.
+pub fn pick(rules: &[Rule], inputs: &Inputs) -> Option<usize> {
+    rules
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.enabled && !r.preset.is_empty() && r.condition.matches(inputs))
+        .min_by_key(|(i, r)| (std::cmp::Reverse(r.priority), *i))
+        .map(|(i, _)| i)
+}
+
+/// Time of day for `HH:MM`.
+pub fn parse_hhmm(s: &str) -> Option<NaiveTime> {
+    let (h, m) = s.trim().split_once(':')?;
+    NaiveTime::from_hms_opt(h.parse().ok()?, m.parse().ok()?, 0)
+}
+
+/// The instant at which the clock in `tz` shows `local`. Twice a year DST makes that ambiguous: a
+/// time the clock shows twice (when it goes back) is its first occurrence, and a time it skips
+/// (when it goes forward) is read with the offset from before the jump, so with 02:00 jumping to
+/// 03:00, 02:30 is 03:30.
+pub fn local_instant<Tz: TimeZone>(tz: &Tz, local: NaiveDateTime) -> DateTime<Tz> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => t,
+        LocalResult::None => {
+            // Skipped times are a few hours at most: look back until the clock was valid.
+            let before = (1..=48)
+                .find_map(|n| tz.offset_from_local_datetime(&(local - Duration::minutes(30 * n))).earliest())
+                .map_or(0, |off| off.fix().local_minus_utc());
+            tz.from_utc_datetime(&(local - Duration::seconds(i64::from(before))))
+        }
+    }
+}
+
+/// Whether `now` is within a `from..to` window in `tz` that started on one of `days`. Windows
+/// are compared as instants, so one in the hour repeated in autumn runs once, not twice, and one
+/// starting in the hour skipped in spring starts when the clock jumps instead of never.
+fn in_window<Tz: TimeZone>(tz: &Tz, days: &[bool; 7], from: NaiveTime, to: NaiveTime, now: i64) -> bool {
+    let Some(today) = tz.timestamp_opt(now, 0).single().map(|t| t.date_naive()) else { return false };
+    [today, today - Duration::days(1)].into_iter().any(|day| {
+        let end_day = if from <= to { day } else { day + Duration::days(1) };
+        days[day.weekday().num_days_from_monday() as usize]
+            && (local_instant(tz, day.and_time(from)).timestamp()..local_instant(tz, end_day.and_time(to)).timestamp())
+                .contains(&now)
+    })
+}
+
+/// Normalized process name: lowercase, without a trailing `.exe`.
+pub fn process_name(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    name.strip_suffix(".exe").map(str::to_string).unwrap_or(name)
+}
+
+/// A one-shot "enable later": loads `preset` (or keeps the form as it is, when empty) and enables
+/// the presence at `enable_at`, then disables it at `disable_at` if set. Unlike a rule it runs once
+/// and is then dropped. Times are Unix seconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deferred {
+    pub preset: String,
+    pub enable_at: i64,
+    pub disable_at: Option<i64>,
+    /// The enable step has run.
+    #[serde(default)]
+    pub started: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeferredStep {
+    Enable,
+    Disable,
+}
+
+impl Deferred {
+    /// The step due at `now`, if any, marked as done.
+    pub fn due(&mut self, now: i64) -> Option<DeferredStep> {
+        if !self.started {
+            if now < self.enable_at {
+                return None;
+            }
+            self.started = true;
+            return Some(DeferredStep::Enable);
+        }
+        if self.disable_at.is_some_and(|t| now >= t) {
+            self.disable_at = None;
+            return Some(DeferredStep::Disable);
+        }
+        None
+    }
+
+    /// Nothing is left to run.
+    pub fn finished(&self) -> bool {
+        self.started && self.disable_at.is_none()
+    }
+}
+
+/// Unix time for `when`: `HH:MM` (or `h:MM PM`) is the next time the clock shows it after `after`, and `+N` is
+/// N minutes after `after`.
+pub fn parse_when(when: &str, after: i64) -> Result<i64, String> {
+    let when = when.trim();
+    if let Some(mins) = when.strip_prefix('+') {
+        let mins: i64 = mins.trim().parse().map_err(|_| "Use +N for N minutes from now.".to_string())?;
+        return Ok(after + mins.max(0) * 60);
+    }
+    let time = crate::clock::parse_time(when).ok_or_else(|| "Use HH:MM, h:MM PM or +N minutes.".to_string())?;
+    next_at(&Local, time, after).ok_or_else(|| "Invalid time.".to_string())
+}
+
+/// Unix time of the first `time` of day in `tz` after `after`.
+fn next_at<Tz: TimeZone>(tz: &Tz, time: NaiveTime, after: i64) -> Option<i64> {
+    let base = tz.timestamp_opt(after, 0).single()?.date_naive();
+    (0..=2).map(|d| local_instant(tz, (base + Duration::days(d)).and_time(time)).timestamp()).find(|&t| t > after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, NaiveDate};
+
+    /// Central European Time for 2026: UTC+2 from March 29 to October 25 (01:00 UTC), else UTC+1.
+    #[derive(Debug, Clone, Copy)]
+    struct Cet;
+
+    impl Cet {
+        fn is_summer(utc: &NaiveDateTime) -> bool {
+            (at(2026, 3, 29, 1, 0)..at(2026, 10, 25, 1, 0)).contains(utc)
+        }
+    }
+
+    impl TimeZone for Cet {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Cet
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(12, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            // Summer first: the same wall-clock time comes earlier under the larger offset.
+            let fits: Vec<FixedOffset> =
+                [hours(2), hours(1)].into_iter().filter(|off| self.offset_from_utc_datetime(&(*local - *off)) == *off).collect();
+            match fits[..] {
+                [] => LocalResult::None,
+                [one] => LocalResult::Single(one),
+                [first, second, ..] => LocalResult::Ambiguous(first, second),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(12, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            if Cet::is_summer(utc) { hours(2) } else { hours(1) }
+        }
+    }
+
+    fn hours(h: i32) -> FixedOffset {
+        FixedOffset::east_opt(h * 3600).unwrap()
+    }
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(h, min, 0).unwrap()
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> i64 {
+        at(y, m, d, h, min).and_utc().timestamp()
+    }
+
+    fn hhmm(s: &str) -> NaiveTime {
+        parse_hhmm(s).unwrap()
+    }
+
+    #[test]
+    fn skipped_and_repeated_times_resolve_once() {
+        // 02:00 jumps to 03:00: 02:30 is read as 03:30.
+        assert_eq!(local_instant(&Cet, at(2026, 3, 29, 2, 30)).timestamp(), utc(2026, 3, 29, 1, 30));
+        // 03:00 goes back to 02:00: 02:30 is its first occurrence, still in summer time.
+        assert_eq!(local_instant(&Cet, at(2026, 10, 25, 2, 30)).timestamp(), utc(2026, 10, 25, 0, 30));
+        assert_eq!(local_instant(&Cet, at(2026, 7, 1, 9, 0)).timestamp(), utc(2026, 7, 1, 7, 0));
+    }
+
+    #[test]
+    fn nine_oclock_stays_nine_oclock_across_dst() {
+        let nine = hhmm("09:00");
+        assert_eq!(next_at(&Cet, nine, utc(2026, 3, 28, 12, 0)), Some(utc(2026, 3, 29, 7, 0)));
+        assert_eq!(next_at(&Cet, nine, utc(2026, 10, 24, 12, 0)), Some(utc(2026, 10, 25, 8, 0)));
+        // Scheduled for a time the clock skips: runs when it jumps, not a day late.
+        assert_eq!(next_at(&Cet, hhmm("02:30"), utc(2026, 3, 28, 12, 0)), Some(utc(2026, 3, 29, 1, 30)));
+
+        let every_day = [true; 7];
+        for (now, expected) in [
+            (utc(2026, 3, 29, 6, 59), false),
+            (utc(2026, 3, 29, 7, 0), true),
+            (utc(2026, 10, 25, 7, 59), false),
+            (utc(2026, 10, 25, 8, 0), true),
+        ] {
+            assert_eq!(in_window(&Cet, &every_day, nine, hhmm("17:00"), now), expected, "{}", now);
+        }
+    }
+
+    #[test]
+    fn windows_in_the_repeated_hour_run_once() {
+        let every_day = [true; 7];
+        let (from, to) = (hhmm("02:15"), hhmm("02:45"));
+        // 02:30 summer time, then 02:30 again an hour later in winter time.
+        assert!(in_window(&Cet, &every_day, from, to, utc(2026, 10, 25, 0, 30)));
+        assert!(!in_window(&Cet, &every_day, from, to, utc(2026, 10, 25, 1, 30)));
+        // In spring the same window moves to 03:15..03:45 rather than vanishing.
+        assert!(in_window(&Cet, &every_day, from, to, utc(2026, 3, 29, 1, 30)));
+    }
+
+    #[test]
+    fn overnight_windows_count_for_the_day_they_start() {
+        let mut fridays = [false; 7];
+        fridays[4] = true;
+        let (from, to) = (hhmm("22:00"), hhmm("02:00"));
+        // Saturday 00:30 local, in a window that started on Friday.
+        assert!(in_window(&Cet, &fridays, from, to, utc(2026, 10, 30, 23, 30)));
+        // Sunday 00:30 local: Saturday's window is not enabled.
+        assert!(!in_window(&Cet, &fridays, from, to, utc(2026, 10, 31, 23, 30)));
+    }
+}
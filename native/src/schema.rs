@@ -0,0 +1,61 @@
+//! Layout version of `config.json`. Each saved config records the version it was written in, and
+//! one from an older version (an old install or backup) goes through the upgrade steps below, in
+//! order, before it is read. A setting that is renamed or reshaped gets a step here that moves its
+//! value over; without one serde would drop it and use the default. Settings that are only added
+//! need no step, since missing ones take their defaults.
+
+use serde_json::{Map, Value};
+
+use crate::StoredConfig;
+
+/// Version written into every saved config.
+pub const CURRENT: u32 = 1;
+
+/// Step `i` turns a version `i` config into version `i + 1`.
+const UPGRADES: [fn(&mut Map<String, Value>); CURRENT as usize] = [from_unversioned];
+
+/// Configs from before versioning already have this layout; every setting since was an addition.
+fn from_unversioned(_: &mut Map<String, Value>) {}
+
+/// Reads a `config.json`, upgrading it from the version it was written in. Errors are meant to be
+/// shown as-is.
+pub fn parse(raw: &[u8]) -> Result<StoredConfig, String> {
+    let mut value: Value = serde_json::from_slice(raw).map_err(|e| e.to_string())?;
+    let config = value.as_object_mut().ok_or("the config is not a JSON object")?;
+    let version = config.get("version").map_or(Some(0), Value::as_u64).ok_or("the config version is not a number")?;
+    let steps = usize::try_from(version).ok().and_then(|v| UPGRADES.get(v..)).ok_or_else(|| {
+        format!("it was written by a newer version of the app (settings version {}, this one reads up to {})", version, CURRENT)
+    })?;
+    for step in steps {
+        step(config);
+    }
+    config.insert("version".to_string(), CURRENT.into());
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned_configs_keep_their_settings() {
+        let stored = parse(br#"{ "details": "Playing", "with_timestamp": true }"#).unwrap();
+        assert_eq!(stored.version, CURRENT);
+        assert_eq!(stored.details, "Playing");
+        assert!(stored.with_timestamp);
+    }
+
+    #[test]
+    fn configs_from_a_newer_version_are_refused() {
+        let err = parse(format!(r#"{{ "version": {} }}"#, CURRENT + 1).as_bytes()).unwrap_err();
+        assert!(err.contains("newer version"), "{}", err);
+        assert!(parse(br#"{ "version": "2" }"#).is_err());
+        assert!(parse(b"[]").is_err());
+    }
+
+    #[test]
+    fn there_is_a_step_for_every_older_version() {
+        assert_eq!(UPGRADES.len(), CURRENT as usize);
+        assert_eq!(parse(format!(r#"{{ "version": {} }}"#, CURRENT).as_bytes()).unwrap().version, CURRENT);
+    }
+}
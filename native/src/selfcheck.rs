@@ -0,0 +1,205 @@
+//! Startup self-check: a short checklist run once at launch (and again on demand) so problems show
+//! up together, each with a fix, instead of one error at a time as the user runs into them.
+
+use std::time::Duration;
+
+use rpc_core::{packaging::Packaging, template::has_placeholders, PresenceCfg};
+
+use crate::http;
+
+const PORTAL_URL: &str = "https://discord.com/developers/applications";
+const README_URL: &str = "https://github.com/Watashi00/DiscordCustomPresence#readme";
+const TIMEOUT: Duration = Duration::from_secs(8);
+
+/// What the app can do about a failed check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fix {
+    /// Set the broken config file aside and start from defaults.
+    ResetConfig,
+    /// Look for Discord's socket again.
+    Rescan,
+    /// Clear an image field whose asset does not exist.
+    ClearImage(ImageSlot),
+    OpenUrl(String),
+    /// Put a shell command on the clipboard.
+    CopyCommand(String),
+}
+
+impl Fix {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Fix::ResetConfig => "Reset settings",
+            Fix::Rescan => "Check again",
+            Fix::ClearImage(ImageSlot::Large) => "Clear large image",
+            Fix::ClearImage(ImageSlot::Small) => "Clear small image",
+            Fix::OpenUrl(url) if url.starts_with(PORTAL_URL) => "Open Developer Portal",
+            Fix::OpenUrl(_) => "Read the docs",
+            Fix::CopyCommand(_) => "Copy command",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageSlot {
+    Large,
+    Small,
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    /// `Ok` with a short note, or the problem.
+    pub outcome: Result<String, String>,
+    pub fixes: Vec<Fix>,
+}
+
+impl Check {
+    fn ok(name: &'static str, note: impl Into<String>) -> Self {
+        Check { name, outcome: Ok(note.into()), fixes: Vec::new() }
+    }
+
+    fn failed(name: &'static str, problem: impl Into<String>, fixes: Vec<Fix>) -> Self {
+        Check { name, outcome: Err(problem.into()), fixes }
+    }
+
+    /// A check that could not run because an earlier one failed.
+    fn skipped(name: &'static str, why: &str) -> Self {
+        Check::ok(name, format!("skipped: {}", why))
+    }
+
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// What the checks look at, taken from the app state before running them on a background thread.
+pub struct Input {
+    /// Why the config file could not be loaded, if it could not.
+    pub config_error: Option<String>,
+    pub ipc_pipes: Vec<String>,
+    /// An explicitly configured socket path, which makes discovery irrelevant.
+    pub ipc_path: Option<String>,
+    pub cfg: PresenceCfg,
+}
+
+/// Runs every check in order. Blocking: the network checks wait up to a few seconds each.
+pub fn run(input: &Input) -> Vec<Check> {
+    let mut checks = vec![config(input), discord(input)];
+    let id_error = rpc_core::validate::validate(&input.cfg).into_iter().find(|e| e.field == "client_id").map(|e| e.message);
+    let online = match http::unavailable() {
+        Some(why) => {
+            checks.push(Check::skipped("Internet", why));
+            false
+        }
+        None => {
+            let internet = internet();
+            let online = internet.passed();
+            checks.push(internet);
+            online
+        }
+    };
+
+    let app = match (&id_error, online) {
+        (Some(e), _) => Check::failed("Client ID", e.clone(), vec![Fix::OpenUrl(PORTAL_URL.to_string())]),
+        (None, false) => Check::skipped("Client ID", "the Discord API is not reachable"),
+        (None, true) => match http::fetch_app_meta(&input.cfg.client_id) {
+            Ok(meta) => Check::ok("Client ID", format!("application \"{}\"", meta.name)),
+            Err(e) => Check::failed(
+                "Client ID",
+                format!("Discord does not know this application ({:#}).", e),
+                vec![Fix::OpenUrl(PORTAL_URL.to_string())],
+            ),
+        },
+    };
+    let app_ok = app.passed() && online && id_error.is_none();
+    checks.push(app);
+    checks.push(if app_ok { assets(&input.cfg) } else { Check::skipped("Images", "the Client ID is not confirmed") });
+    checks
+}
+
+fn config(input: &Input) -> Check {
+    match &input.config_error {
+        None => Check::ok("Settings file", "readable"),
+        Some(e) => Check::failed("Settings file", format!("Could not be loaded, so defaults are in use: {}", e), vec![Fix::ResetConfig]),
+    }
+}
+
+fn discord(input: &Input) -> Check {
+    if let Some(path) = &input.ipc_path {
+        return Check::ok("Discord", format!("using {}", path));
+    }
+    match input.ipc_pipes.first() {
+        Some(pipe) => match rpc_core::ipc_client_label(pipe) {
+            Some(client) => Check::ok("Discord", format!("{} at {}", client, pipe)),
+            None => Check::ok("Discord", format!("found at {}", pipe)),
+        },
+        None => not_found(),
+    }
+}
+
+fn not_found() -> Check {
+    if let Packaging::Flatpak { app_id } = rpc_core::packaging::detect() {
+        let missing = rpc_core::packaging::missing_flatpak_permissions();
+        if !missing.is_empty() {
+            return Check::failed(
+                "Discord",
+                format!(
+                    "No Discord socket found. This Flatpak ({}) is not allowed to see it; it lacks {}. Grant that and restart the app.",
+                    app_id,
+                    missing.join(", ")
+                ),
+                vec![Fix::CopyCommand(rpc_core::packaging::flatpak_override_command(&app_id, &missing)), Fix::Rescan],
+            );
+        }
+    }
+    if !rpc_core::DISCORD_DESKTOP_AVAILABLE {
+        return Check::failed(
+            "Discord",
+            format!(
+                "No Discord socket found. Discord has no desktop app for {}; start a client with arRPC, such as Vesktop, and turn its Rich Presence on.",
+                rpc_core::platform()
+            ),
+            vec![Fix::Rescan, Fix::OpenUrl(README_URL.to_string())],
+        );
+    }
+    Check::failed(
+        "Discord",
+        "No Discord socket found. Start the Discord desktop app; the browser version cannot show a presence.",
+        vec![Fix::Rescan, Fix::OpenUrl(README_URL.to_string())],
+    )
+}
+
+fn internet() -> Check {
+    match http::ping("https://discord.com/api/v10/gateway", TIMEOUT) {
+        Ok(_) => Check::ok("Internet", "Discord API reachable"),
+        Err(e) => Check::failed(
+            "Internet",
+            format!("The Discord API cannot be reached ({}). Syncing and image checks need it; the presence itself does not.", e),
+            vec![Fix::Rescan],
+        ),
+    }
+}
+
+/// Image keys that name no uploaded asset. URLs and templated values are left alone.
+fn assets(cfg: &PresenceCfg) -> Check {
+    let keys: Vec<(ImageSlot, &str)> = [(ImageSlot::Large, &cfg.large_image), (ImageSlot::Small, &cfg.small_image)]
+        .into_iter()
+        .filter_map(|(slot, v)| Some((slot, v.as_deref()?.trim())))
+        .filter(|(_, k)| !k.is_empty() && !k.contains("://") && !k.starts_with("mp:") && !has_placeholders(k))
+        .collect();
+    if keys.is_empty() {
+        return Check::ok("Images", "no asset keys to check");
+    }
+    let names = match http::fetch_asset_names(&cfg.client_id) {
+        Ok(names) => names,
+        Err(e) => return Check::failed("Images", format!("Could not list the app's assets ({:#}).", e), vec![Fix::Rescan]),
+    };
+    let missing: Vec<_> = keys.into_iter().filter(|(_, k)| !names.iter().any(|n| n == k)).collect();
+    if missing.is_empty() {
+        return Check::ok("Images", "all asset keys exist");
+    }
+    let list = missing.iter().map(|(_, k)| format!("\"{}\"", k)).collect::<Vec<_>>().join(", ");
+    let mut fixes: Vec<Fix> = missing.iter().map(|(slot, _)| Fix::ClearImage(*slot)).collect();
+    fixes.push(Fix::OpenUrl(format!("{}/{}/rich-presence/assets", PORTAL_URL, cfg.client_id.trim())));
+    Check::failed("Images", format!("No asset named {} in this app. Upload it in the Developer Portal or use an image URL.", list), fixes)
+}
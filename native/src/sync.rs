@@ -0,0 +1,341 @@
+//! Preset library sync through a private GitHub Gist or a WebDAV file.
+//!
+//! The library is one JSON file. Each successful sync records a hash of it as the common base; a
+//! push is refused when the remote moved away from that base, and a pull when both sides did, so
+//! one machine never silently overwrites another's changes.
+
+use anyhow::{anyhow, Context};
+#[cfg(feature = "http")]
+use reqwest::blocking::{Client, RequestBuilder};
+#[cfg(feature = "http")]
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "http")]
+use serde_json::json;
+
+#[cfg(feature = "http")]
+use crate::http;
+
+use crate::presets::Preset;
+
+#[cfg(feature = "http")]
+const FILE_NAME: &str = "custom-rich-presence-presets.json";
+#[cfg(feature = "http")]
+const GIST_API: &str = "https://api.github.com/gists";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    #[default]
+    Off,
+    Gist,
+    WebDav,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 3] = [Provider::Off, Provider::Gist, Provider::WebDav];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Provider::Off => "Off",
+            Provider::Gist => "GitHub Gist",
+            Provider::WebDav => "WebDAV",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncSettings {
+    pub provider: Provider,
+    /// Empty until the first push creates the gist.
+    pub gist_id: String,
+    /// Personal access token with the `gist` scope.
+    pub gist_token: String,
+    /// Full URL of the library file, e.g. `https://dav.example.com/remote.php/dav/files/me/presets.json`.
+    pub webdav_url: String,
+    pub webdav_user: String,
+    pub webdav_password: String,
+    /// Hash of the library at the last successful sync (empty = never synced).
+    pub base: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Push,
+    Pull,
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+    /// `gist_id` is set when the push created a new gist.
+    Pushed { base: String, gist_id: Option<String> },
+    Pulled { base: String, presets: Vec<Preset> },
+    /// Both sides hold the same library.
+    UpToDate { base: String },
+    /// Going ahead would lose changes made elsewhere; retry with `force` to do it anyway.
+    Conflict(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Library {
+    v: u32,
+    presets: Vec<Preset>,
+}
+
+/// What a sync does, worked out from the library hashes alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    UpToDate,
+    Store,
+    Load,
+    Conflict(&'static str),
+    NothingToPull,
+}
+
+/// Compares this machine's library (`local_hash`, `local_empty`) and the remote's (`None` when
+/// there is none yet) against `base`, the hash at the last sync (empty before the first one).
+fn decide(dir: Direction, local_hash: &str, local_empty: bool, remote_hash: Option<&str>, base: &str, force: bool) -> Step {
+    if remote_hash == Some(local_hash) {
+        return Step::UpToDate;
+    }
+    let remote_moved = remote_hash.is_some_and(|h| h != base);
+    match dir {
+        Direction::Push if remote_moved && !force => Step::Conflict("The remote library changed since the last sync."),
+        Direction::Push => Step::Store,
+        Direction::Pull if remote_hash.is_none() => Step::NothingToPull,
+        Direction::Pull => {
+            // Before the first sync an empty library has nothing to lose.
+            let local_moved = !local_empty && local_hash != base;
+            if remote_moved && local_moved && !force {
+                Step::Conflict("Both this machine and the remote changed since the last sync.")
+            } else {
+                Step::Load
+            }
+        }
+    }
+}
+
+/// Runs one push or pull. Blocking; call it off the UI thread.
+pub fn run(settings: &SyncSettings, dir: Direction, local: &[Preset], force: bool) -> anyhow::Result<Outcome> {
+    let remote = Remote::new(settings)?;
+    let local_text = serialize(local)?;
+    let local_hash = hash(&local_text);
+    let remote_text = remote.fetch()?;
+    let remote_hash = remote_text.as_deref().map(hash);
+
+    match decide(dir, &local_hash, local.is_empty(), remote_hash.as_deref(), &settings.base, force) {
+        Step::UpToDate => Ok(Outcome::UpToDate { base: local_hash }),
+        Step::Conflict(why) => Ok(Outcome::Conflict(why.to_string())),
+        Step::NothingToPull => Err(anyhow!("Nothing to pull yet; push from a machine first.")),
+        Step::Store => {
+            let gist_id = remote.store(&local_text)?;
+            Ok(Outcome::Pushed { base: local_hash, gist_id })
+        }
+        Step::Load => {
+            let (Some(text), Some(remote_hash)) = (remote_text, remote_hash) else {
+                return Err(anyhow!("Nothing to pull yet; push from a machine first."));
+            };
+            let lib: Library = serde_json::from_str(&text).context("The remote file is not a preset library")?;
+            if lib.v != 1 {
+                return Err(anyhow!("Library version {} is not supported by this app.", lib.v));
+            }
+            let mut presets = lib.presets;
+            for p in &mut presets {
+                p.form.targets.clear();
+            }
+            Ok(Outcome::Pulled { base: remote_hash, presets })
+        }
+    }
+}
+
+fn serialize(presets: &[Preset]) -> anyhow::Result<String> {
+    // Discord instance targets are paths on this machine and last use is per machine; neither
+    // belongs in the shared library.
+    let mut presets = presets.to_vec();
+    for p in &mut presets {
+        p.form.targets.clear();
+        p.last_used = None;
+    }
+    Ok(serde_json::to_string_pretty(&Library { v: 1, presets })?)
+}
+
+/// FNV-1a, hex encoded. Only compares library versions, so it needs to be stable, not strong.
+fn hash(text: &str) -> String {
+    let h = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+    format!("{:016x}", h)
+}
+
+#[cfg(feature = "http")]
+enum Remote<'a> {
+    Gist { client: Client, id: &'a str, token: &'a str },
+    WebDav { client: Client, url: &'a str, user: &'a str, password: &'a str },
+}
+
+#[cfg(feature = "http")]
+impl<'a> Remote<'a> {
+    fn new(s: &'a SyncSettings) -> anyhow::Result<Self> {
+        let client = http::client()?;
+        match s.provider {
+            Provider::Off => Err(anyhow!("Choose a sync provider first.")),
+            Provider::Gist if s.gist_token.trim().is_empty() => Err(anyhow!("A GitHub token with the gist scope is required.")),
+            Provider::Gist => Ok(Remote::Gist { client, id: s.gist_id.trim(), token: s.gist_token.trim() }),
+            Provider::WebDav if !s.webdav_url.trim().starts_with("https://") && !s.webdav_url.trim().starts_with("http://") => {
+                Err(anyhow!("The WebDAV URL must be an http(s) link."))
+            }
+            Provider::WebDav => Ok(Remote::WebDav {
+                client,
+                url: s.webdav_url.trim(),
+                user: s.webdav_user.trim(),
+                password: &s.webdav_password,
+            }),
+        }
+    }
+
+    /// The remote library text, or `None` when there is none yet.
+    fn fetch(&self) -> anyhow::Result<Option<String>> {
+        match self {
+            Remote::Gist { id: "", .. } => Ok(None),
+            Remote::Gist { client, id, token } => {
+                let resp = send(auth_gist(client.get(format!("{}/{}", GIST_API, id)), token))?;
+                let body: serde_json::Value = resp.json().context("Unexpected reply from GitHub")?;
+                Ok(body.pointer(&format!("/files/{}/content", FILE_NAME)).and_then(|c| c.as_str()).map(str::to_string))
+            }
+            Remote::WebDav { client, url, user, password } => {
+                let resp = auth_dav(client.get(*url), user, password).send().context("WebDAV server unreachable")?;
+                if resp.status() == StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+                Ok(Some(check(resp)?.text()?))
+            }
+        }
+    }
+
+    /// Uploads the library. Returns the id of a newly created gist.
+    fn store(&self, text: &str) -> anyhow::Result<Option<String>> {
+        let files = json!({ FILE_NAME: { "content": text } });
+        match self {
+            Remote::Gist { client, id: "", token } => {
+                let body = json!({ "description": "Custom Rich Presence presets", "public": false, "files": files });
+                let resp = send(auth_gist(client.post(GIST_API), token).json(&body))?;
+                let created: serde_json::Value = resp.json().context("Unexpected reply from GitHub")?;
+                let id = created.get("id").and_then(|v| v.as_str()).ok_or_else(|| anyhow!("GitHub did not return a gist id"))?;
+                Ok(Some(id.to_string()))
+            }
+            Remote::Gist { client, id, token } => {
+                send(auth_gist(client.patch(format!("{}/{}", GIST_API, id)), token).json(&json!({ "files": files })))?;
+                Ok(None)
+            }
+            Remote::WebDav { client, url, user, password } => {
+                let req = auth_dav(client.put(*url), user, password).header("Content-Type", "application/json");
+                send(req.body(text.to_string()))?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Builds without HTTP have no remote to reach.
+#[cfg(not(feature = "http"))]
+enum Remote {}
+
+#[cfg(not(feature = "http"))]
+impl Remote {
+    fn new(_: &SyncSettings) -> anyhow::Result<Self> {
+        Err(anyhow!(crate::http::unavailable().unwrap_or_default()))
+    }
+
+    fn fetch(&self) -> anyhow::Result<Option<String>> {
+        match *self {}
+    }
+
+    fn store(&self, _: &str) -> anyhow::Result<Option<String>> {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "http")]
+fn auth_gist(req: RequestBuilder, token: &str) -> RequestBuilder {
+    req.bearer_auth(token).header("Accept", "application/vnd.github+json")
+}
+
+#[cfg(feature = "http")]
+fn auth_dav(req: RequestBuilder, user: &str, password: &str) -> RequestBuilder {
+    if user.is_empty() { req } else { req.basic_auth(user, Some(password)) }
+}
+
+#[cfg(feature = "http")]
+fn send(req: RequestBuilder) -> anyhow::Result<reqwest::blocking::Response> {
+    check(req.send().context("Sync server unreachable")?)
+}
+
+#[cfg(feature = "http")]
+fn check(resp: reqwest::blocking::Response) -> anyhow::Result<reqwest::blocking::Response> {
+    match resp.status() {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(anyhow!("The server refused the credentials ({}).", resp.status())),
+        StatusCode::NOT_FOUND => Err(anyhow!("Not found on the server; check the gist id or URL.")),
+        s if !s.is_success() => Err(anyhow!("Sync failed: HTTP {}", s)),
+        _ => Ok(resp),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Direction::{Pull, Push};
+
+    const LOCAL: &str = "local";
+    const REMOTE: &str = "remote";
+    const BASE: &str = "base";
+
+    fn conflict(step: Step) -> bool {
+        matches!(step, Step::Conflict(_))
+    }
+
+    #[test]
+    fn matching_libraries_are_up_to_date() {
+        assert_eq!(decide(Push, LOCAL, false, Some(LOCAL), BASE, false), Step::UpToDate);
+        assert_eq!(decide(Pull, LOCAL, false, Some(LOCAL), "", false), Step::UpToDate);
+    }
+
+    #[test]
+    fn first_sync_with_an_existing_remote() {
+        assert!(conflict(decide(Push, LOCAL, false, Some(REMOTE), "", false)));
+        assert!(conflict(decide(Pull, LOCAL, false, Some(REMOTE), "", false)));
+        // Nothing here yet: taking the remote loses nothing.
+        assert_eq!(decide(Pull, LOCAL, true, Some(REMOTE), "", false), Step::Load);
+        // No remote yet: the first push creates it, and there is nothing to pull.
+        assert_eq!(decide(Push, LOCAL, false, None, "", false), Step::Store);
+        assert_eq!(decide(Pull, LOCAL, false, None, "", false), Step::NothingToPull);
+    }
+
+    #[test]
+    fn remote_moved() {
+        assert!(conflict(decide(Push, BASE, false, Some(REMOTE), BASE, false)));
+        assert_eq!(decide(Pull, BASE, false, Some(REMOTE), BASE, false), Step::Load);
+    }
+
+    #[test]
+    fn only_local_moved() {
+        assert_eq!(decide(Push, LOCAL, false, Some(BASE), BASE, false), Step::Store);
+        assert_eq!(decide(Pull, LOCAL, false, Some(BASE), BASE, false), Step::Load);
+    }
+
+    #[test]
+    fn both_moved() {
+        assert!(conflict(decide(Push, LOCAL, false, Some(REMOTE), BASE, false)));
+        assert!(conflict(decide(Pull, LOCAL, false, Some(REMOTE), BASE, false)));
+    }
+
+    #[test]
+    fn an_empty_library_never_conflicts_on_pull() {
+        assert_eq!(decide(Pull, LOCAL, true, Some(REMOTE), BASE, false), Step::Load);
+    }
+
+    #[test]
+    fn force_overrides_conflicts() {
+        assert_eq!(decide(Push, LOCAL, false, Some(REMOTE), BASE, true), Step::Store);
+        assert_eq!(decide(Pull, LOCAL, false, Some(REMOTE), BASE, true), Step::Load);
+        assert_eq!(decide(Pull, LOCAL, false, None, BASE, true), Step::NothingToPull);
+    }
+}
@@ -0,0 +1,43 @@
+//! "Take over" from a running `presence-daemon`: asks it to disable its presence through its
+//! control API before this app enables, so the two do not keep replacing each other's activity.
+//! The desktop apps have no control socket, so a second copy of either can only be closed by hand.
+//!
+//! The call goes to the loopback listener only and is plain HTTP, so it works without the `http`
+//! feature and in offline mode. The token is read from where the daemon keeps it by default.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use rpc_core::control::DEFAULT_ADDR;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Disables the daemon's presence. Errors are sentences for the user.
+pub fn release_daemon() -> Result<(), String> {
+    let token = crate::project_dirs()
+        .map(|d| d.config_dir().join("daemon-token"))
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .ok_or("The daemon's token file was not found; disable its presence by hand.")?;
+    let addr: SocketAddr = DEFAULT_ADDR.parse().map_err(|_| "Invalid daemon address.".to_string())?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)
+        .map_err(|_| format!("No daemon answers on {}; it may listen elsewhere (--bind).", DEFAULT_ADDR))?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok();
+    stream.set_write_timeout(Some(TIMEOUT)).ok();
+    let request = format!(
+        "POST /api/disable HTTP/1.1\r\nHost: {}\r\nAuthorization: Bearer {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        DEFAULT_ADDR, token
+    );
+    stream.write_all(request.as_bytes()).map_err(|e| format!("Could not reach the daemon: {}", e))?;
+    let mut head = [0u8; 64];
+    let n = stream.read(&mut head).map_err(|e| format!("The daemon did not answer: {}", e))?;
+    let status = String::from_utf8_lossy(&head[..n]).split_whitespace().nth(1).unwrap_or_default().to_string();
+    match status.as_str() {
+        "200" => Ok(()),
+        "401" => Err("The daemon refused the token; it may use another --token-file.".to_string()),
+        "429" => Err("The daemon is rate limiting requests; try again in a moment.".to_string()),
+        other => Err(format!("The daemon answered with HTTP {}.", if other.is_empty() { "?" } else { other })),
+    }
+}
@@ -0,0 +1,134 @@
+//! Samples everything rules can react to on a background thread, so slow OS tools never block the
+//! UI. Like the Wi-Fi lookup, each probe shells out to what the OS provides and reports `None` when
+//! that is missing: idle time via `xprintidle` (X11 only) on Linux and `ioreg` on macOS, media via
+//! `playerctl` (MPRIS) on Linux only.
+
+use std::thread;
+use std::time::Duration;
+
+use rpc_core::shutdown::CancelToken;
+
+use crate::network::{self, run};
+use crate::rules::Inputs;
+
+const POLL_EVERY: Duration = Duration::from_secs(5);
+/// Asking for the SSID can be slow, so it is refreshed on every third poll only.
+const SSID_EVERY: u32 = 3;
+
+/// Calls `on_change` with fresh inputs whenever any of them changes (once at start too). Stops
+/// when `on_change` returns `false` or `stop` is cancelled.
+pub fn watch(stop: CancelToken, mut on_change: impl FnMut(Inputs) -> bool + Send + 'static) {
+    thread::spawn(move || {
+        let mut last: Option<Inputs> = None;
+        let mut ssid = None;
+        for tick in 0u32.. {
+            if tick % SSID_EVERY == 0 {
+                ssid = network::current_ssid();
+            }
+            let mut inputs = Inputs {
+                ssid: ssid.clone(),
+                now: rpc_core::now_unix_ts(),
+                idle_secs: idle_secs(),
+                processes: processes(),
+                media_playing: media_playing(),
+            };
+            inputs.processes.sort();
+            inputs.processes.dedup();
+            // Time and idle time change every poll; only crossing into a new minute can flip a rule.
+            let coarse = |i: &Inputs| Inputs { now: i.now.div_euclid(60), idle_secs: i.idle_secs.map(|s| s / 60), ..i.clone() };
+            if last.as_ref().map(coarse) != Some(coarse(&inputs)) {
+                if !on_change(inputs.clone()) {
+                    return;
+                }
+                last = Some(inputs);
+            }
+            if stop.wait_timeout(POLL_EVERY) {
+                return;
+            }
+        }
+    });
+}
+
+/// Seconds since the last keyboard or mouse input.
+fn idle_secs() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        run("xprintidle", &[])?.trim().parse::<u64>().ok().map(|ms| ms / 1000)
+    }
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::SystemInformation::GetTickCount;
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+        let mut info = LASTINPUTINFO { cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32, dwTime: 0 };
+        // SAFETY: `info` is a valid LASTINPUTINFO with `cbSize` set, as the call requires.
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return None;
+        }
+        // SAFETY: no preconditions.
+        let now = unsafe { GetTickCount() };
+        Some(u64::from(now.wrapping_sub(info.dwTime)) / 1000)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let out = run("ioreg", &["-c", "IOHIDSystem", "-d", "4"])?;
+        let line = out.lines().find(|l| l.contains("\"HIDIdleTime\""))?;
+        line.rsplit('=').next()?.trim().parse::<u64>().ok().map(|ns| ns / 1_000_000_000)
+    }
+    #[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+    {
+        None
+    }
+}
+
+/// Names of the running processes (executable names, not full paths).
+fn processes() -> Vec<String> {
+    #[cfg(target_os = "linux")]
+    {
+        let Ok(dir) = std::fs::read_dir("/proc") else { return Vec::new() };
+        dir.flatten()
+            .filter(|e| e.file_name().to_str().is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit())))
+            .filter_map(|e| {
+                // `comm` is cut to 15 bytes; the executable's name is not, but is only readable
+                // for our own processes.
+                let exe = std::fs::read_link(e.path().join("exe")).ok();
+                match exe.as_ref().and_then(|p| p.file_name()) {
+                    Some(name) => Some(name.to_string_lossy().into_owned()),
+                    None => std::fs::read_to_string(e.path().join("comm")).ok().map(|s| s.trim().to_string()),
+                }
+            })
+            .collect()
+    }
+    #[cfg(windows)]
+    {
+        run("tasklist", &["/fo", "csv", "/nh"])
+            .map(|out| {
+                out.lines()
+                    .filter_map(|l| l.split("\",\"").next())
+                    .map(|n| n.trim_start_matches('"').to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    #[cfg(target_os = "macos")]
+    {
+        run("ps", &["-axco", "comm="]).map(|out| out.lines().map(|l| l.trim().to_string()).collect()).unwrap_or_default()
+    }
+    #[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+    {
+        Vec::new()
+    }
+}
+
+/// Whether any MPRIS player is playing.
+fn media_playing() -> Option<bool> {
+    #[cfg(target_os = "linux")]
+    {
+        let out = run("playerctl", &["--all-players", "status"])?;
+        Some(out.lines().any(|l| l.trim() == "Playing"))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
@@ -0,0 +1,154 @@
+//! "Nobody sees my presence": a checklist of the reasons an activity that is live here does not show
+//! to others. What this app can see (Discord running, the worker status, several installs, Discord's
+//! echo, other presence tools) is checked automatically; Discord's own privacy settings are not exposed over IPC, so those
+//! steps explain where the setting is, link to it where Discord has a deep link, and are ticked off
+//! by the user.
+
+use rpc_core::RpcStatus;
+
+/// Discord's settings deep links; they open the desktop app on that page.
+pub const ACTIVITY_PRIVACY_URL: &str = "discord://-/settings/activity-privacy";
+const REGISTERED_GAMES_URL: &str = "discord://-/settings/registered-games";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    Ok(String),
+    Problem(String),
+    /// Cannot be detected; the user confirms it.
+    Manual,
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub title: &'static str,
+    pub explain: &'static str,
+    pub finding: Finding,
+    /// Button label and URL that open the relevant page.
+    pub link: Option<(&'static str, &'static str)>,
+}
+
+/// What the automatic steps look at, taken from the app state.
+pub struct Input<'a> {
+    pub status: RpcStatus,
+    pub last_error: Option<&'a str>,
+    pub pipes: &'a [String],
+    /// Instances picked in the form; empty means the first one found.
+    pub targets: &'a [String],
+    /// Account from the current handshake.
+    pub user: Option<&'a str>,
+    pub echo: &'a [String],
+    /// Other presence tools found running; see `rivals`.
+    pub rivals: &'a [&'static str],
+}
+
+pub fn steps(input: &Input) -> Vec<Step> {
+    vec![
+        Step {
+            title: "Discord desktop app",
+            explain: "Only the desktop app can publish a presence; Discord in a browser or on a phone cannot.",
+            finding: match input.pipes.first() {
+                Some(pipe) => Finding::Ok(format!("running ({})", pipe)),
+                None if !rpc_core::DISCORD_DESKTOP_AVAILABLE => {
+                    Finding::Problem("Not running. On this CPU, start a client with arRPC, such as Vesktop.".to_string())
+                }
+                None => Finding::Problem("Not running. Start the Discord desktop app.".to_string()),
+            },
+            link: None,
+        },
+        Step {
+            title: "Presence accepted",
+            explain: "The presence has to be enabled here and acknowledged by Discord.",
+            finding: accepted(input),
+            link: None,
+        },
+        Step {
+            title: "Right account",
+            explain: "With several Discord installs (Stable, PTB, Canary) running, the presence goes to one of them only.",
+            finding: account(input),
+            link: None,
+        },
+        Step {
+            title: "Shown as sent",
+            explain: "Discord echoes the activity back; anything it dropped is listed here.",
+            finding: if input.echo.is_empty() {
+                Finding::Ok("Discord kept every field.".to_string())
+            } else {
+                Finding::Problem(input.echo.join(" "))
+            },
+            link: None,
+        },
+        Step {
+            title: "No other presence tool",
+            explain: "Another app setting a presence (or a second copy of this one) replaces it, and the two take turns. Close the others or disable their presence.",
+            finding: if input.rivals.is_empty() {
+                Finding::Ok("none of the known tools is running".to_string())
+            } else {
+                Finding::Problem(format!("Running: {}.", input.rivals.join(", ")))
+            },
+            link: None,
+        },
+        Step {
+            title: "Activity sharing is on",
+            explain: "Discord → User Settings → Activity Privacy: turn on \"Share your detected activities with others\" (older versions: \"Display current activity as a status message\"). When it is off, Discord accepts the presence but shows it to no one.",
+            finding: Finding::Manual,
+            link: Some(("Open Activity Privacy", ACTIVITY_PRIVACY_URL)),
+        },
+        Step {
+            title: "Not invisible",
+            explain: "Invisible hides your activity from everyone. Click your avatar at the bottom left of Discord and pick Online, Idle or Do Not Disturb.",
+            finding: Finding::Manual,
+            link: None,
+        },
+        Step {
+            title: "Shared in this server",
+            explain: "Activity sharing can also be off for a single server: right-click the server icon → Privacy Settings → Activity Status. Friends only see the presence in servers where it is on, or in DMs.",
+            finding: Finding::Manual,
+            link: None,
+        },
+        Step {
+            title: "No game overrides it",
+            explain: "A detected game takes the spot of this presence. Under Activity Settings → Registered Games, remove or hide games you are not playing.",
+            finding: Finding::Manual,
+            link: Some(("Open Registered Games", REGISTERED_GAMES_URL)),
+        },
+        Step {
+            title: "Checked from another account",
+            explain: "Discord hides parts of your own activity, such as buttons, from you. Look at your profile from a second account or ask a friend.",
+            finding: Finding::Manual,
+            link: None,
+        },
+    ]
+}
+
+fn accepted(input: &Input) -> Finding {
+    match input.status {
+        RpcStatus::Active | RpcStatus::Stabilizing => Finding::Ok("Discord shows it.".to_string()),
+        RpcStatus::Inactive => Finding::Problem("The presence is not enabled.".to_string()),
+        RpcStatus::Paused => Finding::Problem("The presence is paused.".to_string()),
+        RpcStatus::Degraded => Finding::Problem(format!(
+            "Discord rejected it: {}",
+            input.last_error.unwrap_or("no reason given")
+        )),
+        RpcStatus::Throttled => Finding::Problem("Discord is rate limiting updates; it will show once the backoff ends.".to_string()),
+        RpcStatus::Connecting | RpcStatus::WaitingForDiscord | RpcStatus::Error => Finding::Problem(match input.last_error {
+            Some(e) => format!("Not connected: {}", e),
+            None => "Not connected to Discord yet.".to_string(),
+        }),
+    }
+}
+
+fn account(input: &Input) -> Finding {
+    let user = input.user.map(|u| format!(", signed in as {}", u)).unwrap_or_default();
+    if input.pipes.len() > 1 && input.targets.is_empty() {
+        return Finding::Problem(format!(
+            "{} Discord instances are running; the presence goes to the first one{}. Pick the one you use under Discord instances.",
+            input.pipes.len(),
+            user
+        ));
+    }
+    match input.user {
+        Some(u) => Finding::Ok(format!("signed in as {}", u)),
+        None if input.pipes.is_empty() => Finding::Problem("No Discord instance found.".to_string()),
+        None => Finding::Ok("one Discord instance; connect to see the account".to_string()),
+    }
+}
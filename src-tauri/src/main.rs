@@ -1,189 +1,228 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use rpc_core::{DiscordRpcClient, PresenceCfg, UserProfile};
-use std::sync::{Arc, Mutex, Condvar};
+use rpc_core::{
+    control::StatusSnapshot,
+    http::{AppMeta, UserStyle},
+    managed::{ErrorRecord, ManagedClient, RpcStatus},
+    ratelimit::{Action, RateLimiter},
+    shutdown::{CancelToken, Shutdown},
+    validate::FieldError,
+    PresenceCfg, UserProfile,
+};
+use serde::Serialize;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::thread;
 use std::time::{Duration, Instant};
+use tauri::Manager;
+use tauri_plugin_notification::NotificationExt;
 
 /// ----------------------------
-/// Backend rate limiter
+/// Connection notifications
 /// ----------------------------
-struct RateState {
-    last: Option<Instant>,
-}
-impl Default for RateState {
-    fn default() -> Self { Self { last: None } }
+
+/// Notify once the worker has been failing for this long; shorter blips usually recover alone.
+const NOTIFY_ERROR_AFTER: Duration = Duration::from_secs(15);
+const NOTIFY_POLL: Duration = Duration::from_secs(1);
+/// How long quitting waits for the worker to clear the presence.
+const WORKER_STOP: Duration = Duration::from_secs(2);
+
+/// User setting, pushed by the frontend with `set_notifications`.
+struct Notifications {
+    enabled: AtomicBool,
 }
 
-fn rate_check(state: &Mutex<RateState>, min_delay: Duration) -> Result<(), String> {
-    let mut st = state.lock().unwrap();
-    if let Some(last) = st.last {
-        if last.elapsed() < min_delay {
-            return Err("Rate limit: please wait a moment before repeating the action.".to_string());
-        }
+fn notify(app: &tauri::AppHandle, title: &str, body: &str) {
+    if !app.state::<Notifications>().enabled.load(Ordering::SeqCst) {
+        return;
     }
-    st.last = Some(Instant::now());
-    Ok(())
+    let _ = app.notification().builder().title(title).body(body).show();
 }
 
-/// ----------------------------
-/// RPC status + worker state
-/// ----------------------------
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum RpcStatus {
-    Inactive,
-    Connecting,
-    Active,
-    Error,
-}
-impl RpcStatus {
-    fn as_str(&self) -> &'static str {
-        match self {
-            RpcStatus::Inactive => "inactive",
-            RpcStatus::Connecting => "connecting",
-            RpcStatus::Active => "active",
-            RpcStatus::Error => "error",
+/// Raises an OS notification when the presence has been failing for a while, and again when it
+/// recovers. Reconnect attempts in between keep counting as failing.
+fn watch_connection(app: tauri::AppHandle, worker: ManagedClient, stop: CancelToken) {
+    let mut error_since: Option<Instant> = None;
+    let mut notified = false;
+    let mut watch = worker.watch_status();
+    let mut status = watch.current();
+    while !stop.is_cancelled() {
+        // Wakes on every change, and every NOTIFY_POLL to time how long a failure has lasted.
+        if let Some(now) = watch.changed_timeout(NOTIFY_POLL) {
+            status = now;
+        }
+        match status {
+            // A rejected activity fails just as visibly as a lost connection.
+            RpcStatus::Error | RpcStatus::Degraded => {
+                let since = *error_since.get_or_insert_with(Instant::now);
+                if !notified && since.elapsed() >= NOTIFY_ERROR_AFTER {
+                    let reason = worker.last_error().unwrap_or_else(|| "Discord is not responding.".to_string());
+                    notify(&app, "Discord presence is failing", &reason);
+                    notified = true;
+                }
+            }
+            RpcStatus::Active => {
+                if notified {
+                    notify(&app, "Discord presence restored", "Your Rich Presence is showing again.");
+                }
+                error_since = None;
+                notified = false;
+            }
+            RpcStatus::Inactive | RpcStatus::Paused => {
+                error_since = None;
+                notified = false;
+            }
+            RpcStatus::Connecting | RpcStatus::WaitingForDiscord | RpcStatus::Stabilizing | RpcStatus::Throttled => {}
         }
     }
 }
 
-struct RpcWorker {
-    running: AtomicBool,
-    thread_alive: AtomicBool,
-
-    status: Mutex<RpcStatus>,
-    last_error: Mutex<Option<String>>,
-
-    /// Latest config snapshot (updated by rpc_enable/rpc_update)
-    cfg: Mutex<Option<PresenceCfg>>,
+/// ----------------------------
+/// Tauri commands
+/// ----------------------------
 
-    /// Fixed start timestamp for elapsed timer (do NOT change while running)
-    start_ts: Mutex<Option<i64>>,
+/// Language of Discord connection errors; the frontend passes the webview's locale tag.
+#[tauri::command]
+fn set_language(tag: String) {
+    rpc_core::i18n::set_lang(rpc_core::i18n::Lang::from_tag(&tag));
 }
 
-impl Default for RpcWorker {
-    fn default() -> Self {
-        Self {
-            running: AtomicBool::new(false),
-            thread_alive: AtomicBool::new(false),
-            status: Mutex::new(RpcStatus::Inactive),
-            last_error: Mutex::new(None),
-            cfg: Mutex::new(None),
-            start_ts: Mutex::new(None),
-        }
-    }
+#[tauri::command]
+fn set_notifications(enabled: bool, notifications: tauri::State<'_, Notifications>) {
+    notifications.enabled.store(enabled, Ordering::SeqCst);
 }
 
-fn set_status(w: &Arc<RpcWorker>, st: RpcStatus) {
-    *w.status.lock().unwrap() = st;
-}
-fn set_error(w: &Arc<RpcWorker>, msg: Option<String>) {
-    *w.last_error.lock().unwrap() = msg;
+/// Keeps the calling window above other apps, so a presence can be tweaked while another app has
+/// focus.
+#[tauri::command]
+fn set_always_on_top(enabled: bool, window: tauri::WebviewWindow) -> Result<(), String> {
+    window.set_always_on_top(enabled).map_err(|e| e.to_string())
 }
 
-/// ----------------------------
-/// Poke / Signal: allow instant update
-/// ----------------------------
-struct RpcSignal {
-    cv: Condvar,
-    flag: Mutex<bool>,
+/// Status plus the IPC pipe in use, so users with several Discord installs can tell which one
+/// receives the presence.
+#[tauri::command]
+fn rpc_status(worker: tauri::State<'_, ManagedClient>) -> StatusSnapshot {
+    worker.snapshot()
 }
 
-impl Default for RpcSignal {
-    fn default() -> Self {
-        Self {
-            cv: Condvar::new(),
-            flag: Mutex::new(false),
-        }
-    }
+/// Everything the UI polls, in one round trip.
+#[derive(Serialize)]
+struct RpcSnapshot {
+    status: RpcStatus,
+    last_error: Option<String>,
+    /// Presence the worker is publishing (or will publish once connected).
+    presence: Option<PresenceCfg>,
+    /// Discord user from the current connection's handshake.
+    user: Option<UserProfile>,
+    pipe: Option<String>,
+    /// Seconds since the current connection was established.
+    uptime_secs: Option<u64>,
+    /// Whether any Discord IPC socket exists, so the UI can wait for Discord instead of erroring.
+    discord_found: bool,
+    /// Fields Discord dropped or changed in the last presence it accepted.
+    warnings: Vec<String>,
+    /// Enable/update calls not sent to Discord yet.
+    pending_updates: u32,
+    /// Seconds until the worker sends next.
+    next_send_secs: Option<u64>,
 }
 
-impl RpcSignal {
-    fn poke(&self) {
-        let mut f = self.flag.lock().unwrap();
-        *f = true;
-        self.cv.notify_all();
-    }
+/// How long a scan for Discord's sockets is reused; the UI polls far more often than that.
+const DISCORD_PROBE_EVERY: Duration = Duration::from_secs(2);
 
-    /// Wait until:
-    /// - someone calls poke()
-    /// - or timeout expires
-    fn wait_or_timeout(&self, dur: Duration) {
-        let mut f = self.flag.lock().unwrap();
+/// Last scan for Discord's sockets: when, and whether any was found.
+#[derive(Default)]
+struct DiscordProbe(Mutex<Option<(Instant, bool)>>);
 
-        // if already poked, consume immediately
-        if *f {
-            *f = false;
-            return;
+impl DiscordProbe {
+    /// Whether Discord is running. A connected worker answers that; otherwise the sockets are
+    /// rescanned at most every `DISCORD_PROBE_EVERY`.
+    fn found(&self, connected: bool) -> bool {
+        if connected {
+            return true;
+        }
+        let mut last = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        match *last {
+            Some((at, found)) if at.elapsed() < DISCORD_PROBE_EVERY => found,
+            _ => {
+                let found = !rpc_core::discover_ipc_paths().is_empty();
+                *last = Some((Instant::now(), found));
+                found
+            }
         }
+    }
+}
 
-        let (mut f2, _) = self.cv.wait_timeout(f, dur).unwrap();
-        *f2 = false; // consume poke if any
+#[tauri::command]
+fn rpc_snapshot(worker: tauri::State<'_, ManagedClient>, probe: tauri::State<'_, DiscordProbe>) -> RpcSnapshot {
+    let report = worker.report();
+    let discord_found = probe.found(report.pipe.is_some());
+    RpcSnapshot {
+        status: worker.status(),
+        last_error: worker.last_error(),
+        presence: worker.presence(),
+        user: report.ready.map(|r| r.user),
+        pipe: report.pipe,
+        uptime_secs: report.connected_at.map(|t| t.elapsed().as_secs()),
+        warnings: report.echo_mismatches,
+        pending_updates: worker.pending_updates(),
+        next_send_secs: report.next_send.map(|t| t.saturating_duration_since(Instant::now()).as_secs_f32().ceil() as u64),
+        discord_found,
     }
 }
 
-/// ----------------------------
-/// Tauri commands
-/// ----------------------------
+/// Same checks the native app applies, so the web form can show them inline.
+#[tauri::command]
+fn cfg_validate(cfg: PresenceCfg) -> Vec<FieldError> {
+    rpc_core::validate::validate(&cfg)
+}
 
 #[tauri::command]
-fn rpc_status(worker: tauri::State<'_, Arc<RpcWorker>>) -> String {
-    worker.status.lock().unwrap().as_str().to_string()
+fn rpc_last_error(worker: tauri::State<'_, ManagedClient>) -> Option<String> {
+    worker.last_error()
 }
 
+/// Distinct errors since the last clear, oldest first, so the root cause is not lost to reconnects.
 #[tauri::command]
-fn rpc_last_error(worker: tauri::State<'_, Arc<RpcWorker>>) -> Option<String> {
-    worker.last_error.lock().unwrap().clone()
+fn rpc_error_history(worker: tauri::State<'_, ManagedClient>) -> Vec<ErrorRecord> {
+    worker.error_history()
+}
+
+#[tauri::command]
+fn rpc_clear_errors(worker: tauri::State<'_, ManagedClient>) {
+    worker.clear_error_history();
 }
 
 #[tauri::command]
 fn get_user_profile(
     client_id: String,
-    rate: tauri::State<'_, Mutex<RateState>>,
+    rate: tauri::State<'_, RateLimiter>,
 ) -> Result<UserProfile, String> {
-    rate_check(&rate, Duration::from_millis(650))?;
+    rate.check(Action::Sync)?;
     rpc_core::get_user_profile_via_handshake(&client_id).map_err(|e| e.to_string())
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-struct AppMeta {
-    name: String,
-    icon_hash: Option<String>,
-    icon_url: Option<String>,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct RpcAppResp {
-    name: String,
-    icon: Option<String>,
+/// Banner and accent color; needs an OAuth2 token with the `identify` scope.
+#[tauri::command]
+async fn get_user_style(token: String) -> Result<UserStyle, String> {
+    tauri::async_runtime::spawn_blocking(move || rpc_core::http::fetch_user_style(&token))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{:#}", e))
 }
 
 #[tauri::command]
 async fn get_app_meta(
     client_id: String,
-    rate: tauri::State<'_, Mutex<RateState>>,
+    rate: tauri::State<'_, RateLimiter>,
 ) -> Result<AppMeta, String> {
-    rate_check(&rate, Duration::from_millis(650))?;
+    rate.check(Action::Sync)?;
 
-    let url = format!("https://discord.com/api/v10/oauth2/applications/{}/rpc", client_id);
-
-    let resp = reqwest::Client::new()
-        .get(url)
-        .send()
+    tauri::async_runtime::spawn_blocking(move || rpc_core::http::fetch_app_meta(&client_id))
         .await
         .map_err(|e| e.to_string())?
-        .error_for_status()
-        .map_err(|e| e.to_string())?
-        .json::<RpcAppResp>()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let icon_url = resp.icon.as_ref().map(|h| {
-        format!("https://cdn.discordapp.com/app-icons/{}/{}.png?size=256", client_id, h)
-    });
-
-    Ok(AppMeta { name: resp.name, icon_hash: resp.icon, icon_url })
+        .map_err(|e| e.to_string())
 }
 
 /// Enable worker (starts thread once).
@@ -191,170 +230,11 @@ async fn get_app_meta(
 #[tauri::command]
 async fn rpc_enable(
     cfg: PresenceCfg,
-    rate: tauri::State<'_, Mutex<RateState>>,
-    worker: tauri::State<'_, Arc<RpcWorker>>,
-    signal: tauri::State<'_, Arc<RpcSignal>>,
+    rate: tauri::State<'_, RateLimiter>,
+    worker: tauri::State<'_, ManagedClient>,
 ) -> Result<(), String> {
-    rate_check(&rate, Duration::from_millis(900))?;
-
-    // Store cfg
-    {
-        let mut lock = worker.cfg.lock().unwrap();
-        *lock = Some(cfg);
-    }
-
-    // Start timestamp: set ONCE per "enable session"
-    {
-        let mut st = worker.start_ts.lock().unwrap();
-        if st.is_none() {
-            *st = Some(rpc_core::now_unix_ts());
-        }
-    }
-
-    worker.running.store(true, Ordering::SeqCst);
-
-    // If thread already running: just poke to apply right now
-    if worker.thread_alive.load(Ordering::SeqCst) {
-        signal.poke();
-        return Ok(());
-    }
-
-    // Mark thread alive
-    worker.thread_alive.store(true, Ordering::SeqCst);
-
-    let w = worker.inner().clone();
-    let sig = signal.inner().clone();
-
-    thread::spawn(move || {
-        // Quick "burst" on start to stabilize
-        let fast_schedule = [
-            Duration::from_secs(0),
-            Duration::from_secs(1),
-            Duration::from_secs(2),
-            Duration::from_secs(4),
-            Duration::from_secs(8),
-        ];
-
-        // Keepalive interval (stable). Updates will also happen on poke().
-        let keepalive_tick = Duration::from_secs(10);
-
-        set_status(&w, RpcStatus::Connecting);
-        set_error(&w, None);
-
-        let mut client: Option<DiscordRpcClient> = None;
-
-        while w.running.load(Ordering::SeqCst) {
-            // Snapshot config
-            let cfg_opt = { w.cfg.lock().unwrap().clone() };
-            let cfg = match cfg_opt {
-                Some(c) => c,
-                None => {
-                    set_status(&w, RpcStatus::Inactive);
-                    break;
-                }
-            };
-
-            // Fixed start timestamp (do not change while running)
-            let start_ts = *w.start_ts.lock().unwrap().get_or_insert_with(rpc_core::now_unix_ts);
-
-            // Ensure persistent IPC client
-            if client.is_none() {
-                set_status(&w, RpcStatus::Connecting);
-
-                match DiscordRpcClient::connect_and_handshake(&cfg.client_id) {
-                    Ok((c, _hs)) => {
-                        client = Some(c);
-                        set_error(&w, None);
-                    }
-                    Err(e) => {
-                        set_status(&w, RpcStatus::Error);
-                        set_error(&w, Some(e.to_string()));
-                        // Wait a bit (or until poke) and retry
-                        sig.wait_or_timeout(Duration::from_secs(2));
-                        continue;
-                    }
-                }
-            }
-
-            // Burst apply (helps the Discord client "latch" onto the presence)
-            {
-                let mut ok_streak = 0u8;
-
-                for d in fast_schedule {
-                    if !w.running.load(Ordering::SeqCst) { break; }
-                    if d.as_secs() > 0 { thread::sleep(d); }
-
-                    // config may have changed during burst
-                    let cfg2 = { w.cfg.lock().unwrap().clone() }.unwrap_or_else(|| cfg.clone());
-
-                    let res = match client.as_mut() {
-                        Some(c) => c.set_activity(&cfg2, start_ts),
-                        None => Err(anyhow::anyhow!("client is None")),
-                    };
-
-                    match res {
-                        Ok(_) => {
-                            ok_streak = ok_streak.saturating_add(1);
-                            set_error(&w, None);
-                            if ok_streak >= 2 {
-                                set_status(&w, RpcStatus::Active);
-                                break;
-                            } else {
-                                set_status(&w, RpcStatus::Connecting);
-                            }
-                        }
-                        Err(e) => {
-                            set_status(&w, RpcStatus::Error);
-                            set_error(&w, Some(e.to_string()));
-                            client = None; // force reconnect
-                            break;
-                        }
-                    }
-                }
-            }
-
-            if !w.running.load(Ordering::SeqCst) { break; }
-
-            // Wait for keepalive OR an explicit "poke" (rpc_update)
-            sig.wait_or_timeout(keepalive_tick);
-
-            if !w.running.load(Ordering::SeqCst) { break; }
-
-            // Apply latest cfg immediately after wait (whether poke or timeout)
-            let cfg3 = { w.cfg.lock().unwrap().clone() }.unwrap_or_else(|| cfg.clone());
-
-            let res = match client.as_mut() {
-                Some(c) => c.set_activity(&cfg3, start_ts),
-                None => Err(anyhow::anyhow!("client is None")),
-            };
-
-            match res {
-                Ok(_) => {
-                    set_status(&w, RpcStatus::Active);
-                    set_error(&w, None);
-                }
-                Err(e) => {
-                    set_status(&w, RpcStatus::Error);
-                    set_error(&w, Some(e.to_string()));
-                    client = None; // reconnect next loop
-                    sig.wait_or_timeout(Duration::from_secs(2));
-                }
-            }
-        }
-
-        // On stop: clear activity (best effort)
-        if let Some(mut c) = client {
-            let _ = c.clear_activity();
-        }
-
-        // Reset start timestamp so next enable starts fresh
-        *w.start_ts.lock().unwrap() = None;
-
-        set_status(&w, RpcStatus::Inactive);
-        set_error(&w, None);
-        w.thread_alive.store(false, Ordering::SeqCst);
-    });
-
+    rate.check(Action::Toggle)?;
+    worker.enable(cfg);
     Ok(())
 }
 
@@ -363,21 +243,11 @@ async fn rpc_enable(
 #[tauri::command]
 async fn rpc_update(
     cfg: PresenceCfg,
-    rate: tauri::State<'_, Mutex<RateState>>,
-    worker: tauri::State<'_, Arc<RpcWorker>>,
-    signal: tauri::State<'_, Arc<RpcSignal>>,
+    rate: tauri::State<'_, RateLimiter>,
+    worker: tauri::State<'_, ManagedClient>,
 ) -> Result<(), String> {
-    rate_check(&rate, Duration::from_millis(350))?;
-
-    {
-        let mut lock = worker.cfg.lock().unwrap();
-        *lock = Some(cfg);
-    }
-
-    if worker.running.load(Ordering::SeqCst) {
-        signal.poke();
-    }
-
+    rate.check(Action::Update)?;
+    worker.update(cfg);
     Ok(())
 }
 
@@ -385,32 +255,64 @@ async fn rpc_update(
 #[tauri::command]
 async fn rpc_disable(
     _client_id: String,
-    rate: tauri::State<'_, Mutex<RateState>>,
-    worker: tauri::State<'_, Arc<RpcWorker>>,
-    signal: tauri::State<'_, Arc<RpcSignal>>,
+    rate: tauri::State<'_, RateLimiter>,
+    worker: tauri::State<'_, ManagedClient>,
 ) -> Result<(), String> {
-    rate_check(&rate, Duration::from_millis(900))?;
-    worker.running.store(false, Ordering::SeqCst);
-    signal.poke(); // wake worker so it exits quickly
+    rate.check(Action::Toggle)?;
+    worker.disable();
     Ok(())
 }
 
 fn main() {
+    let worker = ManagedClient::new();
+    let watched = worker.clone();
+    let exiting = worker.clone();
+    let mut shutdown = Shutdown::new();
+    let stop = shutdown.token();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_opener::init())
-        .manage(Mutex::new(RateState::default()))
-        .manage(Arc::new(RpcWorker::default()))
-        .manage(Arc::new(RpcSignal::default()))
+        .manage(RateLimiter::default())
+        .manage(worker)
+        .manage(Notifications { enabled: AtomicBool::new(true) })
+        .manage(DiscordProbe::default())
+        .setup(move |app| {
+            rpc_core::http::set_cache_dir(app.path().app_cache_dir().ok());
+            let handle = app.handle().clone();
+            thread::spawn(move || watch_connection(handle, watched, stop));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             rpc_enable,
             rpc_update,
             rpc_disable,
             rpc_status,
+            rpc_snapshot,
+            cfg_validate,
+            set_notifications,
+            set_always_on_top,
+            set_language,
             rpc_last_error,
+            rpc_error_history,
+            rpc_clear_errors,
             get_user_profile,
+            get_user_style,
             get_app_meta
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(move |_, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Notifications stop first, so quitting is not reported as a lost presence; then
+                // the worker clears the activity before the process ends.
+                let mut steps = std::mem::take(&mut shutdown);
+                let worker = exiting.clone();
+                steps.then("worker", move || {
+                    worker.shutdown(WORKER_STOP);
+                });
+                steps.run();
+            }
+        });
 }
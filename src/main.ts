@@ -3,6 +3,10 @@ import { open } from "@tauri-apps/plugin-dialog";
 
 let rpcEnabled = false;
 let busy = false;
+// Last probe result; assume Discord is there until the first snapshot says otherwise.
+let discordFound = true;
+// Enable was pressed while Discord was not running; connect as soon as it shows up.
+let pendingEnable = false;
 
 type ButtonCfg = { label: string; url: string };
 
@@ -24,6 +28,12 @@ type UserProfile = {
   global_name?: string | null;
   avatar_hash?: string | null;
   avatar_url?: string | null;
+  avatar_decoration?: { asset: string; sku_id?: string | null; url: string } | null;
+};
+
+type UserStyle = {
+  banner_url?: string | null;
+  accent_color?: number | null;
 };
 
 type AppMeta = {
@@ -32,7 +42,29 @@ type AppMeta = {
   icon_url?: string | null;
 };
 
-type RpcStatus = "inactive" | "connecting" | "active" | "error";
+type RpcStatus =
+  | "inactive"
+  | "connecting"
+  | "waiting_for_discord"
+  | "stabilizing"
+  | "active"
+  | "throttled"
+  | "degraded"
+  | "paused"
+  | "error";
+
+type RpcSnapshot = {
+  status: RpcStatus;
+  last_error: string | null;
+  presence: { client_id: string; buttons?: { label: string; url: string }[] } | null;
+  user: { username: string; global_name: string | null } | null;
+  pipe: string | null;
+  uptime_secs: number | null;
+  discord_found: boolean;
+  warnings: string[];
+  pending_updates: number;
+  next_send_secs: number | null;
+};
 
 const COOLDOWN_MS_UI = 1200;
 let lastActionAt = 0;
@@ -40,6 +72,11 @@ let startAt = Date.now();
 
 let cachedAppIconUrl: string | null = null;
 let cachedUserAvatarUrl: string | null = null;
+let cachedUserDecorationUrl: string | null = null;
+let cachedUserBannerUrl: string | null = null;
+let cachedUserAccent: number | null = null;
+/** The one-time note about Discord's activity privacy setting was shown. */
+let privacyReminderShown = false;
 
 // ===== Persistence (localStorage) =====
 const STORAGE_KEY = "customrp.config.v1";
@@ -61,6 +98,8 @@ type StoredConfig = {
   b2url: string;
 
   ts: boolean;
+  notify?: boolean;
+  onTop?: boolean;
 
   pvAvatarSrc: string;
   pvBannerSrc: string;
@@ -71,6 +110,11 @@ type StoredConfig = {
 
   cachedAppIconUrl?: string | null;
   cachedUserAvatarUrl?: string | null;
+  cachedUserDecorationUrl?: string | null;
+  cachedUserBannerUrl?: string | null;
+  cachedUserAccent?: number | null;
+  oauthToken?: string;
+  privacyReminderShown?: boolean;
   pvAppName?: string;
   pvNameText?: string;
   pvHandleText?: string;
@@ -154,6 +198,63 @@ function getCfg(): PresenceCfg {
   };
 }
 
+type FieldError = { field: string; message: string };
+
+/** Input element for each `FieldError.field` reported by `cfg_validate`. */
+const FIELD_INPUTS: Record<string, string> = {
+  client_id: "clientId",
+  details: "details",
+  state: "state",
+  large_image: "largeImage",
+  large_text: "largeText",
+  small_image: "smallImage",
+  small_text: "smallText",
+  "buttons[0].label": "b1label",
+  "buttons[0].url": "b1url",
+  "buttons[1].label": "b2label",
+  "buttons[1].url": "b2url",
+  timer: "ts",
+};
+
+/** The form as typed (no trimming of short texts), so validation sees what the user sees. */
+function getRawCfg(): PresenceCfg {
+  const buttons = [
+    { label: $("b1label").value, url: $("b1url").value },
+    { label: $("b2label").value, url: $("b2url").value },
+  ].filter(b => b.label.trim() || b.url.trim());
+
+  return {
+    ...getCfg(),
+    details: $("details").value,
+    state: $("state").value,
+    buttons,
+  };
+}
+
+/** Runs the shared backend validator and shows its errors under the matching inputs. */
+async function validateForm(): Promise<FieldError[]> {
+  let errors: FieldError[] = [];
+  try {
+    errors = await invoke<FieldError[]>("cfg_validate", { cfg: getRawCfg() });
+  } catch {
+    return [];
+  }
+
+  document.querySelectorAll(".fieldError").forEach(n => n.remove());
+  Object.values(FIELD_INPUTS).forEach(id => document.getElementById(id)?.removeAttribute("aria-invalid"));
+
+  for (const err of errors) {
+    const input = document.getElementById(FIELD_INPUTS[err.field] ?? "");
+    if (!input) continue;
+    input.setAttribute("aria-invalid", "true");
+    const note = document.createElement("small");
+    note.className = "fieldError";
+    note.textContent = err.message;
+    input.insertAdjacentElement("afterend", note);
+  }
+  return errors;
+}
+
 function setBusy(disabled: boolean) {
   busy = disabled;
 
@@ -249,8 +350,12 @@ function updatePreview() {
   const cardSrc = normalizeImgSrc($("pvCardImgSrc").value);
 
   const banner = el("pvBanner");
-  if (bannerSrc) {
-    banner.style.backgroundImage = `url("${bannerSrc}")`;
+  const bannerFinal = bannerSrc || cachedUserBannerUrl;
+  banner.style.backgroundColor = cachedUserAccent != null
+    ? `#${cachedUserAccent.toString(16).padStart(6, "0")}`
+    : "";
+  if (bannerFinal) {
+    banner.style.backgroundImage = `url("${bannerFinal}")`;
     banner.style.backgroundSize = "cover";
     banner.style.backgroundPosition = "center";
   } else {
@@ -286,6 +391,11 @@ function updatePreview() {
   ? `url("${avatarFinal}")`
   : "";
 
+  // The decoration belongs to the synced account, so a custom avatar image goes without it.
+  const deco = el("pvAvatarDeco");
+  const decoFinal = !avatarSrc && avatarFinal === cachedUserAvatarUrl ? cachedUserDecorationUrl : null;
+  deco.style.backgroundImage = decoFinal ? `url("${decoFinal}")` : "";
+
   const art = el("pvArt");
   const c = presenceImgUrl ? presenceImgUrl : cachedAppIconUrl;
   const artFinal = c || cardSrc || avatarFinal;
@@ -311,6 +421,8 @@ function snapshotToStore(): StoredConfig {
     b2url: $("b2url").value,
 
     ts: (document.getElementById("ts") as HTMLInputElement).checked,
+    notify: (document.getElementById("notify") as HTMLInputElement).checked,
+    onTop: (document.getElementById("onTop") as HTMLInputElement).checked,
 
     pvAvatarSrc: $("pvAvatarSrc").value,
     pvBannerSrc: $("pvBannerSrc").value,
@@ -321,6 +433,11 @@ function snapshotToStore(): StoredConfig {
 
     cachedAppIconUrl,
     cachedUserAvatarUrl,
+    cachedUserDecorationUrl,
+    cachedUserBannerUrl,
+    cachedUserAccent,
+    oauthToken: $("oauthToken").value,
+    privacyReminderShown,
     pvAppName: el("pvAppName")?.textContent ?? "App",
     pvNameText: el("pvNameText")?.textContent ?? "You",
     pvHandleText: el("pvHandleText")?.textContent ?? "@handle",
@@ -344,6 +461,8 @@ function applyFromStore(s: StoredConfig) {
   $("b2url").value = s.b2url ?? "";
 
   (document.getElementById("ts") as HTMLInputElement).checked = !!s.ts;
+  (document.getElementById("notify") as HTMLInputElement).checked = s.notify ?? true;
+  (document.getElementById("onTop") as HTMLInputElement).checked = !!s.onTop;
 
   $("pvAvatarSrc").value = s.pvAvatarSrc ?? "";
   $("pvBannerSrc").value = s.pvBannerSrc ?? "";
@@ -354,6 +473,11 @@ function applyFromStore(s: StoredConfig) {
 
   cachedAppIconUrl = s.cachedAppIconUrl ?? null;
   cachedUserAvatarUrl = s.cachedUserAvatarUrl ?? null;
+  cachedUserDecorationUrl = s.cachedUserDecorationUrl ?? null;
+  cachedUserBannerUrl = s.cachedUserBannerUrl ?? null;
+  cachedUserAccent = s.cachedUserAccent ?? null;
+  $("oauthToken").value = s.oauthToken ?? "";
+  privacyReminderShown = s.privacyReminderShown ?? false;
 
   if (s.pvAppName) el("pvAppName").textContent = s.pvAppName;
   if (s.pvNameText) el("pvNameText").textContent = s.pvNameText;
@@ -422,8 +546,26 @@ async function syncUserProfile() {
     if (!$("pvHandle").value.trim()) $("pvHandle").value = `@${prof.username ?? "handle"}`;
 
     cachedUserAvatarUrl = prof.avatar_url ?? null;
+    cachedUserDecorationUrl = prof.avatar_decoration?.url ?? null;
+
+    // The handshake has no banner; the users endpoint does, with a token.
+    const token = $("oauthToken").value.trim();
+    let bannerError: string | null = null;
+    if (token) {
+      try {
+        const style = await invoke<UserStyle>("get_user_style", { token });
+        cachedUserBannerUrl = style.banner_url ?? null;
+        cachedUserAccent = style.accent_color ?? null;
+      } catch (e: any) {
+        bannerError = String(e);
+      }
+    }
 
-    setStatus("ok", "User OK", "Preview updated with your username/avatar.");
+    if (bannerError) {
+      setStatus("warn", "User OK, no banner", bannerError);
+    } else {
+      setStatus("ok", "User OK", "Preview updated with your username/avatar.");
+    }
     updatePreview();
     saveNow();
   } catch (e: any) {
@@ -470,13 +612,22 @@ async function enableRpc() {
     return;
   }
 
-  const d = $("details").value.trim();
-  const s = $("state").value.trim();
-  if ((d.length > 0 && d.length < 2) || (s.length > 0 && s.length < 2)) {
-    setStatus("warn", "Invalid text", "Details/State must be >= 2 characters (or empty).");
+  const errors = await validateForm();
+  if (errors.length > 0) {
+    setStatus("warn", "Invalid presence", errors[0].message);
     return;
   }
 
+  if (!discordFound) {
+    // Starting the worker now would only cycle through connection errors.
+    pendingEnable = true;
+    rpcEnabled = true;
+    renderToggle();
+    setStatus("busy", "Waiting for Discord", "Discord not detected. The presence will connect once it starts.");
+    return;
+  }
+  pendingEnable = false;
+
   setBusy(true);
   setStatus("busy", "Enabling", "Starting the RPC worker...");
 
@@ -496,6 +647,14 @@ async function enableRpc() {
 }
 
 async function disableRpc() {
+  if (pendingEnable) {
+    pendingEnable = false;
+    rpcEnabled = false;
+    renderToggle();
+    setStatus("ready", "Inactive", "No longer waiting for Discord.");
+    return;
+  }
+
   const clientId = $("clientId").value.trim();
   if (!clientId) {
     setStatus("warn", "Client ID", "Client ID is required to disable.");
@@ -515,15 +674,48 @@ async function disableRpc() {
   }
 }
 
+/** " 2 updates pending, next try in 5s." when updates are waiting to be sent, else "". */
+function pendingNote(snap: RpcSnapshot): string {
+  const n = snap.pending_updates;
+  if (!n) return "";
+  const next = snap.next_send_secs != null ? `, next try in ${snap.next_send_secs}s` : "";
+  return ` ${n} update${n === 1 ? "" : "s"} pending${next}.`;
+}
+
 async function refreshRpcStatus() {
   try {
-    const st = (await invoke<string>("rpc_status")) as RpcStatus;
+    const snap = await invoke<RpcSnapshot>("rpc_snapshot");
+    const st = snap.status;
+    discordFound = snap.discord_found;
+
+    if (pendingEnable && discordFound && !busy) {
+      await enableRpc();
+      return;
+    }
 
     if (st === "active") {
       rpcEnabled = true;
       renderToggle();
+      if (!privacyReminderShown) {
+        // Once ever: "it says active but nobody sees it" is nearly always this setting.
+        privacyReminderShown = true;
+        saveNow();
+        (el("privacyReminder") as HTMLDialogElement | null)?.showModal();
+      }
       if (!busy) {
-        setStatus("ok", "Active", "Rich Presence displayed on Discord.");
+        const via = snap.pipe ? ` (via ${snap.pipe})` : "";
+        if (snap.warnings.length) {
+          setStatus("warn", "Active", `Rich Presence displayed on Discord${via}, but not as sent: ${snap.warnings.join(" ")}`);
+        } else if (snap.presence?.buttons?.some(b => b.label.trim() && b.url.trim())) {
+          // The most common "my buttons don't work" report: Discord hides them from their owner.
+          setStatus(
+            "ok",
+            "Active",
+            `Rich Presence displayed on Discord${via}. You won't see your own buttons; check from another account.`
+          );
+        } else {
+          setStatus("ok", "Active", `Rich Presence displayed on Discord${via}.`);
+        }
       }
 
     } else if (st === "connecting") {
@@ -533,13 +725,49 @@ async function refreshRpcStatus() {
         setStatus("busy", "Connecting", "Trying to apply presence...");
       }
 
+    } else if (st === "stabilizing") {
+      rpcEnabled = true;
+      renderToggle();
+      if (!busy) {
+        setStatus("busy", "Connected", "Sending the presence until Discord shows it...");
+      }
+
+    } else if (st === "waiting_for_discord") {
+      rpcEnabled = true;
+      renderToggle();
+      if (!busy) {
+        setStatus("busy", "Waiting for Discord", "Discord not detected — the presence will connect once it starts.");
+      }
+
+    } else if (st === "degraded") {
+      rpcEnabled = true;
+      renderToggle();
+      if (!busy) {
+        const err = snap.last_error;
+        setStatus("warn", "Update rejected", `Connected, but Discord rejected the presence${err ? `: ${err}` : "."} Fix it and press Update.`);
+      }
+
+    } else if (st === "paused") {
+      rpcEnabled = true;
+      renderToggle();
+      if (!busy) {
+        setStatus("ready", "Paused", `The presence is hidden until resumed.${pendingNote(snap)}`);
+      }
+
+    } else if (st === "throttled") {
+      rpcEnabled = true;
+      renderToggle();
+      if (!busy) {
+        setStatus("warn", "Throttled", `Discord is rate limiting updates; retrying more slowly.${pendingNote(snap)}`);
+      }
+
     } else if (st === "error") {
       rpcEnabled = false;
       renderToggle();
 
       if (!busy) {
         // Key point: show last RPC error from backend.
-        const err = await invoke<string | null>("rpc_last_error");
+        const err = snap.last_error;
         setStatus(
           "warn",
           "Error",
@@ -547,6 +775,14 @@ async function refreshRpcStatus() {
         );
       }
 
+    } else if (!discordFound) {
+      rpcEnabled = pendingEnable;
+      renderToggle();
+      if (!busy) {
+        const next = pendingEnable ? "The presence will connect once it starts." : "Start the Discord desktop app to connect.";
+        setStatus("busy", "Waiting for Discord", `Discord not detected — waiting. ${next}`);
+      }
+
     } else {
       rpcEnabled = false;
       renderToggle();
@@ -568,7 +804,7 @@ function bindLivePreviewAndSave() {
     "largeImage", "largeText", "smallImage", "smallText",
     "b1label", "b1url", "b2label", "b2url",
     "ts",
-    "pvAvatarSrc", "pvBannerSrc", "pvCardImgSrc",
+    "pvAvatarSrc", "pvBannerSrc", "pvCardImgSrc", "oauthToken",
     "pvDisplayName", "pvHandle", "pvPresenceLine",
   ];
 
@@ -580,18 +816,40 @@ function bindLivePreviewAndSave() {
       if (id === "ts") startAt = now();
       updatePreview();
       scheduleSave();
+      if (Object.values(FIELD_INPUTS).includes(id)) validateForm();
     };
 
     input.addEventListener("input", handler);
     input.addEventListener("change", handler);
   });
 
+  document.getElementById("notify")?.addEventListener("change", () => {
+    pushNotifySetting();
+    scheduleSave();
+  });
+
+  document.getElementById("onTop")?.addEventListener("change", () => {
+    pushOnTopSetting();
+    scheduleSave();
+  });
+
   setInterval(() => {
     const ts = (document.getElementById("ts") as HTMLInputElement).checked;
     if (ts) updatePreview();
   }, 500);
 }
 
+/** Connection notifications are raised by the backend; it only needs the user's choice. */
+function pushNotifySetting() {
+  const enabled = (document.getElementById("notify") as HTMLInputElement).checked;
+  invoke("set_notifications", { enabled }).catch(() => {});
+}
+
+function pushOnTopSetting() {
+  const enabled = (document.getElementById("onTop") as HTMLInputElement).checked;
+  invoke("set_always_on_top", { enabled }).catch(() => {});
+}
+
 function isHttpUrl(v: string | null | undefined): boolean {
   if (!v) return false;
   return /^https?:\/\//i.test(v.trim());
@@ -608,6 +866,69 @@ function pickPresenceImageUrl(cfg: PresenceCfg): string | null {
   return null;
 }
 
+const EMOJI = [
+  "😀", "😂", "😊", "😎", "🤔", "😴", "😭", "😡", "🥳", "🤯", "👀", "👍", "👏", "🙏", "💪", "❤️",
+  "⭐", "✨", "🔥", "💯", "✅", "⚠️", "🎉", "🎵", "🎧", "🎤", "🎸", "🎮", "🕹️", "👾", "🎲", "🏆",
+  "💻", "⌨️", "🖥️", "📱", "🛠️", "⚙️", "🐛", "🚀", "📚", "✏️", "🎨", "📷", "🎬", "☕", "🍕", "🌙",
+];
+const EMOJI_FIELDS = ["details", "state", "largeText", "smallText", "b1label", "b2label"];
+
+/** One shared popover; each text field gets a button that opens it and inserts at the caret. */
+function bindEmojiPicker() {
+  const pop = document.createElement("div");
+  pop.className = "emojiPop";
+  pop.hidden = true;
+  document.body.appendChild(pop);
+  let target: HTMLInputElement | null = null;
+
+  EMOJI.forEach(e => {
+    const b = document.createElement("button");
+    b.type = "button";
+    b.textContent = e;
+    b.addEventListener("click", () => {
+      if (!target) return;
+      const start = target.selectionStart ?? target.value.length;
+      const end = target.selectionEnd ?? start;
+      target.setRangeText(e, start, end, "end");
+      // Same path as typing: preview, autosave and validation.
+      target.dispatchEvent(new Event("input"));
+      target.focus();
+      pop.hidden = true;
+    });
+    pop.appendChild(b);
+  });
+
+  EMOJI_FIELDS.forEach(id => {
+    const input = document.getElementById(id) as HTMLInputElement | null;
+    if (!input) return;
+    const btn = document.createElement("button");
+    btn.type = "button";
+    btn.className = "emojiBtn";
+    btn.textContent = "😊";
+    btn.title = "Insert emoji";
+    btn.addEventListener("click", ev => {
+      ev.preventDefault();
+      const reopen = pop.hidden || target !== input;
+      target = input;
+      pop.hidden = !reopen;
+      if (reopen) {
+        const r = btn.getBoundingClientRect();
+        pop.style.top = `${r.bottom + window.scrollY + 4}px`;
+        pop.style.left = `${Math.max(8, r.right + window.scrollX - 260)}px`;
+      }
+    });
+    input.insertAdjacentElement("afterend", btn);
+  });
+
+  document.addEventListener("click", ev => {
+    const t = ev.target as HTMLElement;
+    if (!pop.hidden && !pop.contains(t) && !t.classList.contains("emojiBtn")) pop.hidden = true;
+  });
+  document.addEventListener("keydown", ev => {
+    if (ev.key === "Escape") pop.hidden = true;
+  });
+}
+
 function bindButtons() {
   el("toggleBtn")?.addEventListener("click", async () => {
     if (busy) return;
@@ -634,9 +955,13 @@ function bindButtons() {
 // init
 bindButtons();
 bindLivePreviewAndSave();
+bindEmojiPicker();
 
 const loaded = loadIfAny();
 updatePreview();
+pushNotifySetting();
+pushOnTopSetting();
+invoke("set_language", { tag: navigator.language }).catch(() => {});
 
 // Initial state comes from the backend.
 rpcEnabled = false;